@@ -1,18 +1,21 @@
 use std::{
-    collections::HashMap,
+    collections::{hash_map, HashMap},
     fmt::Debug,
     hash::{BuildHasher, Hash},
+    iter::{Copied, Map},
     ops::{Deref, DerefMut},
+    slice,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ChunkManager<D> {
     chunks: HashMap<ChunkKey, Chunk<D>, ahash::RandomState>,
 }
 
 pub type ChunkKey = (i32, i32);
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Chunk<D> {
     chunk_x: i32,
     chunk_y: i32,
@@ -42,20 +45,15 @@ pub struct PassThroughHasherI32I32(([i32; 2], usize));
 impl std::hash::Hasher for PassThroughHasherI32I32 {
     #[inline]
     fn finish(&self) -> u64 {
-        u64::from_le_bytes(unsafe {
-            // Safety: casting [i32; 2] to u64 is fine
-            *(self.0 .0.as_ptr() as *const () as *const _)
-        })
+        let [a, b] = self.0 .0;
+        ((a as u64) << 32) | (b as u64 & 0xFFFF_FFFF)
     }
 
     #[inline]
     fn write_i32(&mut self, i: i32) {
-        *unsafe { self.0 .0.get_unchecked_mut(self.0 .1) } = i;
-        self.0 .1 = if self.0 .1 == 0 {
-            1
-        } else {
-            panic!("cannot be called more than twice")
-        };
+        assert!(self.0 .1 < 2, "cannot be called more than twice");
+        self.0 .0[self.0 .1] = i;
+        self.0 .1 += 1;
     }
 
     #[inline]
@@ -95,33 +93,144 @@ impl<D> ChunkManager<D> {
         self.chunks.is_empty()
     }
 
+    /// Gets the chunk at `chunk_pos` for in-place insertion, hashing the key only once.
+    #[inline]
+    pub fn entry(&mut self, chunk_pos: (i32, i32)) -> Entry<'_, D> {
+        Entry { inner: self.chunks.entry(chunk_pos) }
+    }
+
+    #[inline]
+    pub fn contains(&self, chunk_pos: (i32, i32)) -> bool {
+        self.chunks.contains_key(&chunk_pos)
+    }
+
+    /// Removes the chunk at `chunk_pos`, returning its data if it was loaded.
+    #[inline]
+    pub fn remove(&mut self, chunk_pos: (i32, i32)) -> Option<D> {
+        self.chunks.remove(&chunk_pos).map(|ch| ch.data)
+    }
+
     #[inline]
     pub fn clear(&mut self) {
         self.chunks.clear();
     }
 
+    /// Mutably borrows the chunks at each of `keys` at once, [`None`] for ones that aren't loaded.
+    ///
+    /// # Panics
+    /// If `keys` contains the same key more than once.
     #[inline]
-    pub fn chunk_at_mut_with_surrounding(
+    pub fn get_disjoint_mut<const N: usize>(
         &mut self,
-        chunk_pos: (i32, i32),
-        cb: impl FnOnce(&mut Chunk<D>, [Option<&Chunk<D>>; 8]),
-    ) {
-        if let Some(mut this) = self.chunks.remove(&chunk_pos) {
-            let surrounding = [
-                self.chunk_at((chunk_pos.0 - 1, chunk_pos.1 - 1)),
-                self.chunk_at((chunk_pos.0, chunk_pos.1 - 1)),
-                self.chunk_at((chunk_pos.0 + 1, chunk_pos.1 - 1)),
-                self.chunk_at((chunk_pos.0 - 1, chunk_pos.1)),
-                self.chunk_at((chunk_pos.0 + 1, chunk_pos.1)),
-                self.chunk_at((chunk_pos.0 - 1, chunk_pos.1 + 1)),
-                self.chunk_at((chunk_pos.0, chunk_pos.1 + 1)),
-                self.chunk_at((chunk_pos.0 + 1, chunk_pos.1 + 1)),
-            ];
+        keys: [(i32, i32); N],
+    ) -> [Option<&mut Chunk<D>>; N] {
+        for (i, key) in keys.iter().enumerate() {
+            assert!(
+                !keys[..i].contains(key),
+                "get_disjoint_mut called with duplicate key {key:?}"
+            );
+        }
+
+        // Safety: the keys are unique so none of the borrows alias,
+        //   and the map can't be modified while they're alive since they borrow `self`
+        keys.map(|key| {
+            self.chunks
+                .get_mut(&key)
+                .map(|ch| unsafe { &mut *(ch as *mut Chunk<D>) })
+        })
+    }
+
+    /// Removes every chunk, yielding each key with its data.
+    ///
+    /// The manager is empty afterwards even if the iterator isn't fully consumed.
+    #[inline]
+    pub fn drain(&mut self) -> impl Iterator<Item = (ChunkKey, D)> + '_ {
+        self.chunks.drain().map(|(k, ch)| (k, ch.data))
+    }
+
+    /// Chunks inside the rectangle from `min` to `max`, both inclusive.
+    #[inline]
+    pub fn chunks_in_region(
+        &self,
+        min: (i32, i32),
+        max: (i32, i32),
+    ) -> impl Iterator<Item = &Chunk<D>> {
+        // TODO: could use a spatial index if this ends up being slow for large maps
+        self.chunks
+            .values()
+            .filter(move |ch| in_region(ch, min, max))
+    }
 
-            cb(&mut this, surrounding);
+    /// Mutable version of [`ChunkManager::chunks_in_region`].
+    #[inline]
+    pub fn chunks_in_region_mut(
+        &mut self,
+        min: (i32, i32),
+        max: (i32, i32),
+    ) -> impl Iterator<Item = &mut Chunk<D>> {
+        self.chunks
+            .values_mut()
+            .filter(move |ch| in_region(ch, min, max))
+    }
 
-            self.chunks.insert(chunk_pos, this);
+    /// Calls `cb` with the chunk at `chunk_pos` and its 8 neighbors, returning its result.
+    ///
+    /// Returns [`None`] without calling `cb` if there is no chunk at `chunk_pos`.
+    #[inline]
+    pub fn chunk_at_mut_with_surrounding<R>(
+        &mut self,
+        chunk_pos: (i32, i32),
+        cb: impl FnOnce(&mut Chunk<D>, [Option<&Chunk<D>>; 8]) -> R,
+    ) -> Option<R> {
+        let mut this = self.chunks.remove(&chunk_pos)?;
+        let surrounding = [
+            self.chunk_at((chunk_pos.0 - 1, chunk_pos.1 - 1)),
+            self.chunk_at((chunk_pos.0, chunk_pos.1 - 1)),
+            self.chunk_at((chunk_pos.0 + 1, chunk_pos.1 - 1)),
+            self.chunk_at((chunk_pos.0 - 1, chunk_pos.1)),
+            self.chunk_at((chunk_pos.0 + 1, chunk_pos.1)),
+            self.chunk_at((chunk_pos.0 - 1, chunk_pos.1 + 1)),
+            self.chunk_at((chunk_pos.0, chunk_pos.1 + 1)),
+            self.chunk_at((chunk_pos.0 + 1, chunk_pos.1 + 1)),
+        ];
+
+        let result = cb(&mut this, surrounding);
+
+        self.chunks.insert(chunk_pos, this);
+        Some(result)
+    }
+
+    /// Calls `cb` with the chunk at `chunk_pos` and every chunk within `R` chunks of it (Chebyshev
+    ///   distance), returning its result.
+    ///
+    /// The neighbors are in row-major order (top to bottom, left to right) with the center left out,
+    ///   so the chunk at offset `(dx, dy)` is at index `i - (i > center) as usize` where
+    ///   `i = (dy + R) * (2R + 1) + (dx + R)` and `center = R * (2R + 1) + R`.
+    /// With `R = 1` this is the same order as [`ChunkManager::chunk_at_mut_with_surrounding`].
+    ///
+    /// Returns [`None`] without calling `cb` if there is no chunk at `chunk_pos`.
+    #[inline]
+    pub fn chunk_at_mut_with_neighborhood<const R: usize, T>(
+        &mut self,
+        chunk_pos: (i32, i32),
+        cb: impl FnOnce(&mut Chunk<D>, &[Option<&Chunk<D>>]) -> T,
+    ) -> Option<T> {
+        let mut this = self.chunks.remove(&chunk_pos)?;
+
+        let r = R as i32;
+        let mut neighborhood = Vec::with_capacity((2 * R + 1) * (2 * R + 1) - 1);
+        for dy in -r..=r {
+            for dx in -r..=r {
+                if dx != 0 || dy != 0 {
+                    neighborhood.push(self.chunk_at((chunk_pos.0 + dx, chunk_pos.1 + dy)));
+                }
+            }
         }
+
+        let result = cb(&mut this, &neighborhood);
+
+        self.chunks.insert(chunk_pos, this);
+        Some(result)
     }
 
     #[profiling::function]
@@ -199,6 +308,86 @@ impl<D> ChunkManager<D> {
     }
 }
 
+#[cfg(feature = "rayon")]
+impl<D: Send + Sync> ChunkManager<D> {
+    /// Parallel version of [`ChunkManager::each_chunk_mut_with_surrounding`].
+    ///
+    /// Chunks are split into 4 phases by the parity of their coordinates (a 2x2 checkerboard, like
+    ///   `chunk_update_order` in fs_common), and the chunks of each phase are processed in parallel.
+    #[profiling::function]
+    pub fn par_each_chunk_mut_with_surrounding(
+        &mut self,
+        cb: impl Fn(&mut Chunk<D>, [Option<&Chunk<D>>; 8]) + Sync,
+    ) {
+        use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+
+        struct ChunkPtr<D>(*mut Chunk<D>);
+        // Safety: the pointers are only dereferenced following the phase rules below
+        unsafe impl<D: Send + Sync> Send for ChunkPtr<D> {}
+        unsafe impl<D: Send + Sync> Sync for ChunkPtr<D> {}
+
+        // every pointer comes from the same `iter_mut`, so they're all unique
+        let ptrs = self
+            .chunks
+            .iter_mut()
+            .map(|(k, ch)| (*k, ChunkPtr(ch as *mut _)))
+            .collect::<HashMap<_, _, ahash::RandomState>>();
+
+        let mut phases: [Vec<ChunkKey>; 4] = Default::default();
+        for k in ptrs.keys() {
+            phases[(k.1.rem_euclid(2) * 2 + k.0.rem_euclid(2)) as usize].push(*k);
+        }
+
+        for phase in &phases {
+            phase.par_iter().for_each(|k| {
+                let get = |x: i32, y: i32| ptrs.get(&(x, y)).map(|p| unsafe { &*p.0 });
+
+                // Safety: two chunks in the same phase are at least 2 apart on one axis, so no chunk
+                //   mutated in this phase is a neighbor of another one.
+                // That means each `&mut` here is unique, and the neighbor `&`s only point to chunks
+                //   that aren't being mutated until the next phase starts.
+                let this = unsafe { &mut *ptrs[k].0 };
+                let surrounding = [
+                    get(k.0 - 1, k.1 - 1),
+                    get(k.0, k.1 - 1),
+                    get(k.0 + 1, k.1 - 1),
+                    get(k.0 - 1, k.1),
+                    get(k.0 + 1, k.1),
+                    get(k.0 - 1, k.1 + 1),
+                    get(k.0, k.1 + 1),
+                    get(k.0 + 1, k.1 + 1),
+                ];
+
+                cb(this, surrounding);
+            });
+        }
+    }
+}
+
+/// Serialized as a list of [`Chunk`]s, so the hasher isn't part of the format.
+#[cfg(feature = "serde")]
+impl<D: serde::Serialize> serde::Serialize for ChunkManager<D> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.chunks.values())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, D: serde::Deserialize<'de>> serde::Deserialize<'de> for ChunkManager<D> {
+    fn deserialize<De: serde::Deserializer<'de>>(deserializer: De) -> Result<Self, De::Error> {
+        let chunks = Vec::<Chunk<D>>::deserialize(deserializer)?;
+        Ok(chunks
+            .into_iter()
+            .map(|ch| ((ch.chunk_x, ch.chunk_y), ch.data))
+            .collect())
+    }
+}
+
+#[inline]
+fn in_region<D>(ch: &Chunk<D>, min: (i32, i32), max: (i32, i32)) -> bool {
+    (min.0..=max.0).contains(&ch.chunk_x) && (min.1..=max.1).contains(&ch.chunk_y)
+}
+
 impl<D> Default for ChunkManager<D> {
     #[inline]
     fn default() -> Self {
@@ -206,6 +395,27 @@ impl<D> Default for ChunkManager<D> {
     }
 }
 
+impl<D> Extend<(ChunkKey, D)> for ChunkManager<D> {
+    /// Inserts each chunk from `iter`, replacing any already at the same key.
+    #[inline]
+    fn extend<T: IntoIterator<Item = (ChunkKey, D)>>(&mut self, iter: T) {
+        let iter = iter.into_iter();
+        self.chunks.reserve(iter.size_hint().0);
+        for (chunk_pos, data) in iter {
+            self.insert(chunk_pos, data);
+        }
+    }
+}
+
+impl<D> FromIterator<(ChunkKey, D)> for ChunkManager<D> {
+    #[inline]
+    fn from_iter<T: IntoIterator<Item = (ChunkKey, D)>>(iter: T) -> Self {
+        let mut manager = Self::new();
+        manager.extend(iter);
+        manager
+    }
+}
+
 impl<D> Chunk<D> {
     #[inline]
     pub fn chunk_x(&self) -> i32 {
@@ -218,10 +428,42 @@ impl<D> Chunk<D> {
     }
 }
 
+/// A chunk slot in a [`ChunkManager`], which may be empty. See [`ChunkManager::entry`].
+pub struct Entry<'a, D> {
+    inner: hash_map::Entry<'a, ChunkKey, Chunk<D>>,
+}
+
+impl<'a, D> Entry<'a, D> {
+    #[inline]
+    pub fn key(&self) -> ChunkKey {
+        *self.inner.key()
+    }
+
+    #[inline]
+    pub fn or_insert(self, data: D) -> &'a mut Chunk<D> {
+        self.or_insert_with(|| data)
+    }
+
+    /// Returns the chunk, inserting the result of `default` first if there isn't one.
+    ///
+    /// `default` is only called if the chunk is missing.
+    #[inline]
+    pub fn or_insert_with(self, default: impl FnOnce() -> D) -> &'a mut Chunk<D> {
+        self.inner.or_insert_with_key(|&(chunk_x, chunk_y)| Chunk {
+            chunk_x,
+            chunk_y,
+            data: default(),
+        })
+    }
+}
+
 pub type BoxedIterator<'a, I> = Box<dyn Iterator<Item = I> + 'a>;
 
 pub trait ChunkQuery {
     type D;
+    type KeysIter<'a>: Iterator<Item = ChunkKey> + 'a
+    where
+        Self: 'a;
 
     fn chunk_at(&self, chunk_pos: ChunkKey) -> Option<&Chunk<Self::D>>;
     fn chunk_at_mut(&mut self, chunk_pos: ChunkKey) -> Option<&mut Chunk<Self::D>>;
@@ -234,7 +476,15 @@ pub trait ChunkQuery {
     fn kv_iter(&self) -> BoxedIterator<(ChunkKey, &Chunk<Self::D>)>;
     fn kv_iter_mut(&mut self) -> BoxedIterator<(ChunkKey, &mut Chunk<Self::D>)>;
 
-    fn keys(&self) -> Vec<ChunkKey>;
+    /// Owned snapshot of the keys, for when chunks are added or removed while iterating.
+    #[inline]
+    fn keys(&self) -> Vec<ChunkKey> {
+        self.keys_iter().collect()
+    }
+
+    /// Iterates the keys without collecting them, see [`ChunkQuery::keys`] for an owned copy.
+    fn keys_iter(&self) -> Self::KeysIter<'_>;
+
     fn query_one(&mut self, chunk_pos: ChunkKey) -> Option<ChunkQueryOne<Self::D>>;
 
     #[inline]
@@ -372,6 +622,10 @@ impl<'a, D> ChunkQueryOne<'a, D> {
 
 impl<D, T: Deref<Target = Chunk<D>> + DerefMut> ChunkQuery for [T] {
     type D = D;
+    type KeysIter<'a>
+        = Map<slice::Iter<'a, T>, fn(&T) -> ChunkKey>
+    where
+        Self: 'a;
 
     #[inline]
     fn chunk_at(&self, chunk_pos: ChunkKey) -> Option<&Chunk<D>> {
@@ -416,11 +670,9 @@ impl<D, T: Deref<Target = Chunk<D>> + DerefMut> ChunkQuery for [T] {
     }
 
     #[inline]
-    fn keys(&self) -> Vec<ChunkKey> {
-        self.iter()
-            .map(|t| &**t)
-            .map(|ch| (ch.chunk_x(), ch.chunk_y()))
-            .collect()
+    fn keys_iter(&self) -> Self::KeysIter<'_> {
+        let key: fn(&T) -> ChunkKey = |t| (t.chunk_x(), t.chunk_y());
+        self.iter().map(key)
     }
 
     #[inline]
@@ -448,6 +700,10 @@ impl<D, T: Deref<Target = Chunk<D>> + DerefMut> ChunkQuery for [T] {
 
 impl<D> ChunkQuery for ChunkManager<D> {
     type D = D;
+    type KeysIter<'a>
+        = Copied<hash_map::Keys<'a, ChunkKey, Chunk<D>>>
+    where
+        Self: 'a;
 
     #[inline]
     fn chunk_at(&self, chunk_pos: ChunkKey) -> Option<&Chunk<D>> {
@@ -479,8 +735,8 @@ impl<D> ChunkQuery for ChunkManager<D> {
     }
 
     #[inline]
-    fn keys(&self) -> Vec<ChunkKey> {
-        self.chunks.keys().copied().collect()
+    fn keys_iter(&self) -> Self::KeysIter<'_> {
+        self.chunks.keys().copied()
     }
 
     #[inline]
@@ -502,6 +758,10 @@ impl<D> ChunkQuery for ChunkManager<D> {
 
 impl<D> ChunkQuery for ChunkQueryOne<'_, D> {
     type D = D;
+    type KeysIter<'a>
+        = Copied<BoxedIterator<'a, &'a ChunkKey>>
+    where
+        Self: 'a;
 
     #[inline]
     fn chunk_at(&self, chunk_pos: ChunkKey) -> Option<&Chunk<D>> {
@@ -534,8 +794,8 @@ impl<D> ChunkQuery for ChunkQueryOne<'_, D> {
     }
 
     #[inline]
-    fn keys(&self) -> Vec<ChunkKey> {
-        self.chunks.keys().copied().collect()
+    fn keys_iter(&self) -> Self::KeysIter<'_> {
+        self.chunks.keys().copied()
     }
 
     #[inline]
@@ -593,9 +853,13 @@ impl<T> Accessor<T> for Vec<T> {
 #[cfg(test)]
 #[allow(unused)]
 mod test {
-    use std::cell::Cell;
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        cell::Cell,
+        hash::{Hash, Hasher},
+    };
 
-    use crate::{Chunk, ChunkManager, ChunkQuery, ChunkQueryOne};
+    use crate::{Chunk, ChunkManager, ChunkQuery, ChunkQueryOne, PassThroughHasherI32I32};
 
     #[derive(Debug)]
     struct Data {
@@ -674,6 +938,334 @@ mod test {
         });
     }
 
+    #[test]
+    fn clone() {
+        let mut cm = ChunkManager::<i32>::new();
+        cm.insert((0, 0), 1);
+        cm.insert((-3, 7), 2);
+
+        let mut snapshot = cm.clone();
+        assert_eq!(snapshot.len(), 2);
+
+        let ch = snapshot.chunk_at((-3, 7)).unwrap();
+        assert_eq!((ch.chunk_x(), ch.chunk_y()), (-3, 7));
+        assert_eq!(ch.data, 2);
+
+        // the clone is independent of the original
+        snapshot.chunk_at_mut((0, 0)).unwrap().data = 5;
+        assert_eq!(cm.chunk_at((0, 0)).unwrap().data, 1);
+    }
+
+    #[test]
+    fn from_iter_and_extend() {
+        let mut cm: ChunkManager<i32> = [((0, 0), 1), ((2, -1), 2)].into_iter().collect();
+        assert_eq!(cm.len(), 2);
+
+        cm.extend([((2, -1), 3), ((-5, 4), 4)]);
+        assert_eq!(cm.len(), 3);
+
+        for (pos, data) in [((0, 0), 1), ((2, -1), 3), ((-5, 4), 4)] {
+            let ch = cm.chunk_at(pos).unwrap();
+            assert_eq!((ch.chunk_x(), ch.chunk_y()), pos);
+            assert_eq!(ch.data, data);
+        }
+    }
+
+    #[test]
+    fn collect() {
+        let entries = [((0, 0), 'a'), ((-1, 0), 'b'), ((0, 3), 'c'), ((7, -7), 'd')];
+        let cm = entries.into_iter().collect::<ChunkManager<_>>();
+        assert_eq!(cm.len(), 4);
+
+        for (pos, data) in entries {
+            let ch = cm.chunk_at(pos).unwrap();
+            assert_eq!((ch.chunk_x(), ch.chunk_y()), pos);
+            assert_eq!(ch.data, data);
+        }
+    }
+
+    #[test]
+    fn pass_through_hasher() {
+        let hash = |key: (i32, i32)| {
+            let mut hasher = PassThroughHasherI32I32::default();
+            key.hash(&mut hasher);
+            hasher.finish()
+        };
+
+        assert_eq!(hash((-1, 5)), 0xFFFF_FFFF_0000_0005);
+        assert_eq!(hash((5, -1)), 0x0000_0005_FFFF_FFFF);
+        assert_eq!(hash((i32::MIN, i32::MAX)), 0x8000_0000_7FFF_FFFF);
+        assert_eq!(hash((-1, 5)), hash((-1, 5)));
+    }
+
+    #[test]
+    fn drain() {
+        let mut cm: ChunkManager<i32> = [((0, 0), 1), ((2, -1), 2)].into_iter().collect();
+
+        let mut drained = cm.drain().collect::<Vec<_>>();
+        drained.sort_unstable();
+        assert_eq!(drained, vec![((0, 0), 1), ((2, -1), 2)]);
+        assert!(cm.is_empty());
+    }
+
+    #[test]
+    fn remove() {
+        let mut cm: ChunkManager<i32> = [((0, 0), 1), ((1, 0), 2), ((2, 0), 3)]
+            .into_iter()
+            .collect();
+
+        assert_eq!(cm.remove((1, 0)), Some(2));
+        assert_eq!(cm.len(), 2);
+        assert!(!cm.contains((1, 0)));
+        assert!(cm.contains((0, 0)) && cm.contains((2, 0)));
+
+        assert_eq!(cm.remove((1, 0)), None);
+        assert_eq!(cm.len(), 2);
+    }
+
+    #[test]
+    fn chunks_in_region() {
+        let mut cm: ChunkManager<i32> = (-2..=2)
+            .flat_map(|x| (-2..=2).map(move |y| ((x, y), 0)))
+            .collect();
+        assert_eq!(cm.len(), 25);
+
+        let mut found = cm
+            .chunks_in_region((0, -1), (1, 0))
+            .map(|ch| (ch.chunk_x(), ch.chunk_y()))
+            .collect::<Vec<_>>();
+        found.sort_unstable();
+        assert_eq!(found, vec![(0, -1), (0, 0), (1, -1), (1, 0)]);
+
+        for ch in cm.chunks_in_region_mut((0, -1), (1, 0)) {
+            ch.data = 1;
+        }
+        assert_eq!(cm.chunks_iter().filter(|ch| ch.data == 1).count(), 4);
+    }
+
+    #[test]
+    fn entry() {
+        let mut cm = ChunkManager::<i32>::new();
+        cm.insert((0, 0), 1);
+
+        let mut calls = 0;
+        let ch = cm.entry((0, 0)).or_insert_with(|| {
+            calls += 1;
+            2
+        });
+        assert_eq!(ch.data, 1);
+        assert_eq!(calls, 0);
+
+        let ch = cm.entry((-4, 3)).or_insert_with(|| {
+            calls += 1;
+            3
+        });
+        assert_eq!((ch.chunk_x(), ch.chunk_y(), ch.data), (-4, 3, 3));
+        assert_eq!(calls, 1);
+
+        cm.entry((-4, 3)).or_insert(4).data += 1;
+        assert_eq!(cm.chunk_at((-4, 3)).unwrap().data, 4);
+        assert_eq!(cm.len(), 2);
+    }
+
+    #[test]
+    fn chunk_at_mut_with_surrounding() {
+        let mut cm: ChunkManager<i32> = (-1..=1)
+            .flat_map(|x| (-1..=1).map(move |y| ((x, y), x + y * 3 + 4)))
+            .collect();
+
+        let sum = cm.chunk_at_mut_with_surrounding((0, 0), |this, surrounding| {
+            this.data = 100;
+            surrounding.iter().flatten().map(|ch| ch.data).sum::<i32>()
+        });
+        // 0..=8 without the center chunk's 4
+        assert_eq!(sum, Some(32));
+        assert_eq!(cm.chunk_at((0, 0)).unwrap().data, 100);
+        assert_eq!(cm.len(), 9);
+
+        // edge chunks only see the neighbors that exist
+        let count = cm.chunk_at_mut_with_surrounding((1, 1), |_, surrounding| {
+            surrounding.iter().flatten().count()
+        });
+        assert_eq!(count, Some(3));
+
+        assert_eq!(cm.chunk_at_mut_with_surrounding((5, 5), |_, _| 0), None);
+    }
+
+    #[test]
+    fn chunk_at_mut_with_neighborhood() {
+        let mut cm: ChunkManager<(i32, i32)> = (-3..=3)
+            .flat_map(|x| (-3..=3).map(move |y| ((x, y), (x, y))))
+            .collect();
+
+        let surrounding = cm
+            .chunk_at_mut_with_surrounding((1, 1), |_, surrounding| {
+                surrounding.map(|ch| ch.map(|ch| ch.data))
+            })
+            .unwrap();
+        let neighborhood = cm
+            .chunk_at_mut_with_neighborhood::<1, _>((1, 1), |_, neighborhood| {
+                neighborhood
+                    .iter()
+                    .map(|ch| ch.map(|ch| ch.data))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap();
+        assert_eq!(neighborhood, surrounding);
+
+        let neighborhood = cm
+            .chunk_at_mut_with_neighborhood::<2, _>((0, 0), |_, neighborhood| {
+                neighborhood
+                    .iter()
+                    .map(|ch| ch.unwrap().data)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap();
+        assert_eq!(neighborhood.len(), 24);
+        assert_eq!(neighborhood[0], (-2, -2));
+        assert_eq!(neighborhood[4], (2, -2));
+        assert_eq!(neighborhood[5], (-2, -1));
+        // the center is skipped
+        assert_eq!(neighborhood[11], (-1, 0));
+        assert_eq!(neighborhood[12], (1, 0));
+        assert_eq!(neighborhood[23], (2, 2));
+
+        // chunks outside the map show up as `None`
+        let missing = cm.chunk_at_mut_with_neighborhood::<2, _>((3, 3), |_, neighborhood| {
+            neighborhood.iter().filter(|ch| ch.is_none()).count()
+        });
+        assert_eq!(missing, Some(24 - 8));
+    }
+
+    /// Counts allocations per thread, so tests running in parallel don't affect each other.
+    struct CountingAlloc;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAlloc {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout);
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAlloc = CountingAlloc;
+
+    fn count_allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+        let before = ALLOCATIONS.with(Cell::get);
+        let result = f();
+        (result, ALLOCATIONS.with(Cell::get) - before)
+    }
+
+    #[test]
+    fn keys_iter() {
+        let cm: ChunkManager<()> = (0..100)
+            .flat_map(|x| (0..100).map(move |y| ((x, y), ())))
+            .collect();
+
+        let (sum, allocations) =
+            count_allocations(|| cm.keys_iter().map(|(x, y)| i64::from(x + y)).sum::<i64>());
+        assert_eq!(sum, 2 * 100 * (0..100).sum::<i64>());
+        assert_eq!(allocations, 0);
+
+        let (keys, allocations) = count_allocations(|| cm.keys());
+        assert_eq!(keys.len(), 10_000);
+        assert_eq!(allocations, 1);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_each_chunk_mut_with_surrounding() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let mut cm: ChunkManager<(u32, usize)> = (-5..5)
+            .flat_map(|x| (-5..5).map(move |y| ((x, y), (0, 0))))
+            .collect();
+
+        let visits = AtomicUsize::new(0);
+        cm.par_each_chunk_mut_with_surrounding(|this, surrounding| {
+            visits.fetch_add(1, Ordering::Relaxed);
+            this.data.0 += 1;
+            this.data.1 = surrounding.iter().flatten().count();
+        });
+
+        assert_eq!(visits.load(Ordering::Relaxed), 100);
+        for ch in cm.chunks_iter() {
+            assert_eq!(ch.data.0, 1);
+            let edges = [ch.chunk_x(), ch.chunk_y()]
+                .iter()
+                .filter(|&&c| c == -5 || c == 4)
+                .count();
+            assert_eq!(ch.data.1, [8, 5, 3][edges]);
+        }
+    }
+
+    #[test]
+    fn get_disjoint_mut() {
+        let mut cm: ChunkManager<i32> = (-2..=2)
+            .flat_map(|x| (-2..=2).map(move |y| ((x, y), 0)))
+            .collect();
+
+        let mut keys = [(0, 0); 9];
+        for (i, key) in keys.iter_mut().enumerate() {
+            *key = (i as i32 % 3 - 1, i as i32 / 3 - 1);
+        }
+
+        let chunks = cm.get_disjoint_mut(keys);
+        for (i, ch) in chunks.into_iter().enumerate() {
+            ch.unwrap().data = i as i32 + 1;
+        }
+
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(cm.chunk_at(*key).unwrap().data, i as i32 + 1);
+        }
+        assert_eq!(cm.chunks_iter().filter(|ch| ch.data == 0).count(), 25 - 9);
+
+        let [a, b] = cm.get_disjoint_mut([(2, 2), (3, 3)]);
+        assert!(a.is_some());
+        assert!(b.is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn get_disjoint_mut_duplicate() {
+        let mut cm = ChunkManager::<i32>::new();
+        cm.insert((0, 0), 1);
+        cm.get_disjoint_mut([(0, 0), (1, 0), (0, 0)]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let cm: ChunkManager<Vec<u8>> =
+            [((0, 0), vec![1, 2]), ((-3, 7), vec![]), ((5, -1), vec![3])]
+                .into_iter()
+                .collect();
+
+        let json = serde_json::to_string(&cm).unwrap();
+        let loaded: ChunkManager<Vec<u8>> = serde_json::from_str(&json).unwrap();
+
+        let mut keys = loaded.keys();
+        keys.sort_unstable();
+        assert_eq!(keys, vec![(-3, 7), (0, 0), (5, -1)]);
+
+        for ch in cm.chunks_iter() {
+            let other = loaded.chunk_at((ch.chunk_x(), ch.chunk_y())).unwrap();
+            assert_eq!(
+                (other.chunk_x(), other.chunk_y()),
+                (ch.chunk_x(), ch.chunk_y())
+            );
+            assert_eq!(other.data, ch.data);
+        }
+    }
+
     fn test2<D>(cm: &mut ChunkManager<D>) {
         for ch in cm.chunks_iter_mut() {}
 
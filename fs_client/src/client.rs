@@ -14,10 +14,10 @@ use fs_common::game::common::world::{
     Camera, Position, Velocity, World,
 };
 
-use crate::{render::Renderer, ui::DebugUIs};
+use crate::{history::EditHistory, network::ServerConnection, render::Renderer, ui::DebugUIs};
 
 use super::{
-    input::{Controls, InputEvent},
+    input::{Controls, GamepadInput, InputEvent},
     ui::MainMenu,
     world::{ClientChunk, ClientWorld},
 };
@@ -25,10 +25,17 @@ use super::{
 pub struct Client {
     pub world: Option<ClientWorld>,
     pub controls: Controls,
+    pub gamepad: GamepadInput,
     pub camera_scale: f64,
     pub mouse_joint: Option<(RigidBodyHandle, Vector2<f32>)>,
     pub main_menu: MainMenu,
     pub debug_ui: Option<DebugUIs>,
+    /// Undo/redo history for edits made with the draw UI.
+    pub edit_history: EditHistory,
+    /// The server we're playing on, if connected to one.
+    pub connection: Option<ServerConnection>,
+    /// Pixels changed with the draw UI that haven't been sent to the server yet.
+    pub pending_edits: Vec<(i64, i64, MaterialInstance)>,
 }
 
 impl Client {
@@ -36,6 +43,7 @@ impl Client {
         Self {
             world: None,
             controls: Controls::default(),
+            gamepad: GamepadInput::new(),
             camera_scale: 2.0,
             mouse_joint: None,
             main_menu: MainMenu {
@@ -43,6 +51,9 @@ impl Client {
                 action_queue: Vec::new(),
             },
             debug_ui: None,
+            edit_history: EditHistory::default(),
+            connection: None,
+            pending_edits: Vec::new(),
         }
     }
 
@@ -56,10 +67,31 @@ impl Client {
 
             tick_player(world, cw, renderer, &mut self.controls, self.camera_scale);
 
+            let changes = if self.controls.undo.get() {
+                self.edit_history
+                    .undo(&mut world.chunk_handler)
+                    .map_err(|e| log::error!("Undo failed: {e}"))
+            } else if self.controls.redo.get() {
+                self.edit_history
+                    .redo(&mut world.chunk_handler)
+                    .map_err(|e| log::error!("Redo failed: {e}"))
+            } else {
+                Ok(Vec::new())
+            };
+            // the server needs to see undo/redo like any other edit
+            if let (Ok(changes), Some(_)) = (changes, &self.connection) {
+                self.pending_edits.extend(changes);
+            }
+
             world.ecs.maintain();
         }
     }
 
+    /// Feeds input from gamepads to the controls, since it doesn't come through [`Client::on_event`].
+    pub fn poll_gamepads(&mut self) {
+        self.gamepad.poll(&mut self.controls);
+    }
+
     pub fn on_event(&mut self, event: &WindowEvent) -> bool {
         self.controls.process(&InputEvent::GlutinEvent(event));
         false
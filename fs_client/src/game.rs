@@ -1,8 +1,4 @@
-use std::{
-    io::{BufReader, Read},
-    net::TcpStream,
-    time::Instant,
-};
+use std::time::Instant;
 
 use glutin::{
     dpi::PhysicalPosition,
@@ -24,10 +20,10 @@ use sysinfo::{ProcessExt, SystemExt};
 use fs_common::game::{
     common::{
         cli::CLArgs,
-        networking::{Packet, PacketType},
+        networking::{check_handshake, send_packet, Packet, PacketReader, PacketType},
         world::{
-            chunk_access::FSChunkAccess, entity::Player, material::placer::MaterialPlacerSampler,
-            physics::PHYSICS_SCALE, Camera, Position, Target, World, WorldNetworkMode,
+            chunk_access::FSChunkAccess, entity::Player, physics::PHYSICS_SCALE, Camera, Position,
+            Target, World, WorldNetworkMode,
         },
         FileHelper, Settings,
     },
@@ -35,7 +31,8 @@ use fs_common::game::{
 };
 
 use crate::{
-    ui::MainMenuAction,
+    network::ServerConnection,
+    ui::{draw::DrawTool, MainMenuAction},
     world::{ClientChunkHandlerExt, ClientWorld, ClientWorldExt},
 };
 
@@ -47,9 +44,9 @@ pub struct ClientGame {
 }
 
 impl ClientGame {
-    pub fn new(file_helper: FileHelper, build_data: BuildData) -> Self {
+    pub fn new(file_helper: FileHelper, build_data: BuildData, seed: Option<i32>) -> Self {
         Self {
-            data: GameData::new(file_helper, build_data),
+            data: GameData::new(file_helper, build_data, seed),
             client: Client::new(),
         }
     }
@@ -67,23 +64,9 @@ impl ClientGame {
             self.data.settings.tick_physics = false;
         }
 
-        let mut network = None;
-
         if let Some(addr) = args.connect {
-            info!("Connecting to {addr}...");
-            match TcpStream::connect(addr.to_string()).map(BufReader::new) {
-                Ok(mut r) => {
-                    info!("[CLIENT] Connected to server");
-
-                    r.get_mut().set_nonblocking(true).unwrap();
-                    self.data.world.as_mut().unwrap().net_mode = WorldNetworkMode::Remote;
-
-                    network = Some(r);
-                },
-                Err(e) => {
-                    error!("[CLIENT] Failed to connect to server: {}", e);
-                },
-            }
+            self.client.connection =
+                Some(ServerConnection::new(addr.to_string(), args.name.clone()));
         }
 
         // TODO: updating settings like this should be a fn
@@ -114,8 +97,7 @@ impl ClientGame {
         let mut do_tick_next = false;
         let mut do_tick_physics_next = false;
 
-        let mut bytes_to_read: Option<u32> = None;
-        let mut read_buffer: Option<Vec<u8>> = None;
+        let mut packet_reader = PacketReader::new();
 
         let mut cursor_pos: PhysicalPosition<f64> = PhysicalPosition::new(0.0, 0.0);
 
@@ -187,7 +169,7 @@ impl ClientGame {
                                         }
                                     }
                                 } else if middle_mouse_down {
-                                    if let Some(debug_ui) = &self.client.debug_ui {
+                                    if let Some(debug_ui) = self.client.debug_ui.as_ref().filter(|d| !d.draw.eyedropper && d.draw.tool == DrawTool::Brush) {
                                         if let Some(w) = &mut self.data.world {
                                             let (
                                                 position_storage,
@@ -210,14 +192,28 @@ impl ClientGame {
                                                         - f64::from(renderer.display.gl_window().window().inner_size().height) / 2.0)
                                                         / self.client.camera_scale;
 
-                                                for xx in -3..=3 {
-                                                    for yy in -3..=3 {
-                                                        let x = world_x as i64 + xx;
-                                                        let y = world_y as i64 + yy;
-                                                        let _ = w.chunk_handler.set_pixel(x, y,
-                                                            self.data.registries.material_placers.get(&debug_ui.draw.selected).unwrap().pixel(x, y),
-                                                        );
-                                                    }
+                                                let placer = self.data.registries.material_placers.get(&debug_ui.draw.selected).unwrap();
+                                                let offsets = debug_ui.draw.brush_offsets();
+                                                let min_x = offsets.iter().map(|o| o.0).min().unwrap_or(0);
+                                                let min_y = offsets.iter().map(|o| o.1).min().unwrap_or(0);
+                                                let size = debug_ui.draw.brush_size.max(1);
+
+                                                let edit = self.client.edit_history.edit(
+                                                    &mut w.chunk_handler,
+                                                    world_x as i64 + min_x,
+                                                    world_y as i64 + min_y,
+                                                    size,
+                                                    size,
+                                                    |chunk_handler| {
+                                                        for (xx, yy) in offsets {
+                                                            let x = world_x as i64 + xx;
+                                                            let y = world_y as i64 + yy;
+                                                            let _ = debug_ui.draw.paint(chunk_handler, x, y, placer);
+                                                        }
+                                                    },
+                                                );
+                                                if let (Some(edit), Some(_)) = (edit, &self.client.connection) {
+                                                    self.client.pending_edits.extend(edit.changes());
                                                 }
                                             }
                                         }
@@ -296,6 +292,65 @@ impl ClientGame {
                                     _ => {},
                                 };
 
+                                if *button == MouseButton::Middle {
+                                    if let (Some(debug_ui), Some(w)) = (&mut self.client.debug_ui, &mut self.data.world) {
+                                        let (position_storage, camera_storage) = w.ecs.system_data::<(
+                                            ReadStorage<Position>,
+                                            ReadStorage<Camera>,
+                                        )>(
+                                        );
+
+                                        let camera_pos = (&position_storage, &camera_storage)
+                                            .join().map(|(p, _c)| p).next();
+
+                                        if let Some(camera_pos) = camera_pos {
+                                            let world_x = (camera_pos.x
+                                                + (cursor_pos.x - f64::from(renderer.display.gl_window().window().inner_size().width) / 2.0)
+                                                    / self.client.camera_scale) as i64;
+                                            let world_y = (camera_pos.y
+                                                + (cursor_pos.y - f64::from(renderer.display.gl_window().window().inner_size().height) / 2.0)
+                                                    / self.client.camera_scale) as i64;
+
+                                            if *state == ElementState::Pressed {
+                                                if debug_ui.draw.eyedropper {
+                                                    let mat = if debug_ui.draw.background {
+                                                        w.chunk_handler.background(world_x, world_y)
+                                                    } else {
+                                                        w.chunk_handler.pixel(world_x, world_y)
+                                                    };
+                                                    if let Ok(mat) = mat {
+                                                        debug_ui.draw.pick_from_world(mat, world_x, world_y, &self.data.registries);
+                                                    }
+                                                } else if debug_ui.draw.tool != DrawTool::Brush {
+                                                    debug_ui.draw.drag_start = Some((world_x, world_y));
+                                                }
+                                            } else if let Some(start) = debug_ui.draw.drag_start.take() {
+                                                let end = (world_x, world_y);
+                                                let bounds = debug_ui.draw.shape_bounds(start, end);
+                                                let placer = self.data.registries.material_placers.get(&debug_ui.draw.selected).unwrap();
+                                                let draw = &debug_ui.draw;
+
+                                                let edit = self.client.edit_history.edit(
+                                                    &mut w.chunk_handler,
+                                                    bounds.left(),
+                                                    bounds.top(),
+                                                    u16::try_from(bounds.width()).unwrap_or(u16::MAX),
+                                                    u16::try_from(bounds.height()).unwrap_or(u16::MAX),
+                                                    |chunk_handler| draw.draw_shape(chunk_handler, start, end, placer),
+                                                );
+                                                if let (Some(edit), Some(_)) = (edit, &self.client.connection) {
+                                                    self.client.pending_edits.extend(edit.changes());
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+
+                                if *button == MouseButton::Middle && *state == ElementState::Released {
+                                    // one brush stroke or shape is one undo step
+                                    self.client.edit_history.commit();
+                                }
+
                                 if *button == MouseButton::Right && *state == ElementState::Pressed {
                                     if let Some(w) = &mut self.data.world {
                                         let (position_storage, camera_storage) = w.ecs.system_data::<(
@@ -395,6 +450,8 @@ impl ClientGame {
                 },
                 glutin::event::Event::MainEventsCleared => {
                     // println!("{event:?}");
+                    self.client.poll_gamepads();
+
                     let now = std::time::Instant::now();
                     let delta = now.saturating_duration_since(last_frame);
                     last_frame = now;
@@ -429,12 +486,17 @@ impl ClientGame {
 
                     // tick
 
-                    let mut can_tick = self.data.settings.tick;
+                    // while paused, only tick if a step was requested
+                    let stepping = !self.data.settings.tick && self.data.settings.tick_steps > 0;
+                    let mut can_tick = self.data.settings.tick || stepping;
 
                     let has_focus = true; // TODO
                     can_tick = can_tick && (has_focus || !self.data.settings.pause_on_lost_focus);
 
                     if do_tick_next && can_tick {
+                        if stepping {
+                            self.data.settings.tick_steps -= 1;
+                        }
                         prev_tick_time = now;
                         let st = Instant::now();
                         self.tick(&mut renderer);
@@ -459,14 +521,15 @@ impl ClientGame {
                                             .expect("World meta file has no parent directory ??")
                                             .to_path_buf(),
                                         ),
-                                        Some(3), // TODO: save/load seed from file
+                                        // worlds saved without a seed were generated with 3
+                                        Some(world_meta.seed.unwrap_or(3)),
                                     ));
 
                                     if let Some(w) = &mut self.data.world {
                                         let player = Player::create_and_add(w);
 
                                         self.client.world =
-                                            Some(ClientWorld { local_entity: Some(player) });
+                                            Some(ClientWorld::new(Some(player)));
                                     };
                                 },
                                 MainMenuAction::LoadRandomSeed => {
@@ -486,177 +549,211 @@ impl ClientGame {
                                         Camera::create_and_add(w, Target::Entity(player));
 
                                         self.client.world =
-                                            Some(ClientWorld { local_entity: Some(player) });
+                                            Some(ClientWorld::new(Some(player)));
                                     };
                                 }
                             }
                         }
 
-                        if let Some(stream) = &mut network {
-                            let start = Instant::now();
+                        if let Some(connection) = &mut self.client.connection {
+                            if connection.poll() {
+                                if let Some(w) = &mut self.data.world {
+                                    w.net_mode = WorldNetworkMode::Remote;
+
+                                    // the new connection doesn't know what the old one was sending us
+                                    if let Some(cw) = &mut self.client.world {
+                                        cw.requested_chunks.clear();
+                                        cw.chunks_to_resync.clear();
+                                        cw.sent_position = None;
+                                        cw.clear_remote_entities(w);
+                                    }
+                                }
+                                packet_reader = PacketReader::new();
+                            }
+                        }
 
-                            // let mut n = 0;
-                            while Instant::now().saturating_duration_since(start).as_nanos() < 5_000_000 {
-                                if bytes_to_read.is_none() {
-                                    let mut buf = [0; 4];
-                                    if stream.read_exact(&mut buf).is_ok() {
-                                        let size: u32 = bincode::deserialize(&buf).unwrap();
-                                        // println!("[CLIENT] Incoming packet, size = {}.", size);
-
-                                        bytes_to_read = Some(size);
-                                        read_buffer = Some(Vec::with_capacity(size as usize));
+                        let mut disconnect = false;
+                        // whether a dropped connection should be retried
+                        let mut reconnect = true;
+                        // the tick speed from the server's WelcomePacket, if we got it this tick
+                        let mut welcome = None;
+                        if let Some(stream) = self.client.connection.as_mut().and_then(|c| c.stream.as_mut()) {
+                            // tell the server where we are, and ask it for chunks entering our load zone
+                            if let (Some(w), Some(cw)) = (&self.data.world, &mut self.client.world) {
+                                let position = cw.position_to_send(w).map(|Position { x, y }| Packet {
+                                    packet_type: PacketType::ClientPositionPacket { x, y },
+                                });
+                                let requests = cw.chunks_to_request(w).into_iter().map(|(chunk_x, chunk_y)| Packet {
+                                    packet_type: PacketType::RequestChunkPacket { chunk_x, chunk_y },
+                                });
+                                let resyncs = cw.chunks_to_resync.drain().map(|(chunk_x, chunk_y)| Packet {
+                                    packet_type: PacketType::ResyncChunkPacket { chunk_x, chunk_y },
+                                });
+                                let packets: Vec<Packet> = position.into_iter().chain(requests).chain(resyncs).collect();
+                                if !packets.is_empty() {
+                                    stream.get_mut().set_nonblocking(false).unwrap();
+                                    for packet in packets {
+                                        if let Err(e) = send_packet(stream.get_mut(), &packet) {
+                                            error!("[CLIENT] Failed to send position or chunk request: {}", e);
+                                            disconnect = true;
+                                            break;
+                                        }
                                     }
+                                    stream.get_mut().set_nonblocking(true).unwrap();
                                 }
+                            }
 
-                                if let (Some(size), Some(buf)) = (bytes_to_read, &mut read_buffer) {
-                                    // println!("[CLIENT] size = {}", size);
-                                    if size == 0 {
-                                        // trying_to_read = None;
-                                        panic!("[CLIENT] Zero length packet.");
-                                    } else {
-                                        assert!(size <= 2_000_000, "[CLIENT] Almost tried to read packet that is too big ({size} bytes)");
-
-                                        // let mut buf = vec![0; size as usize];
-
-                                        // println!("[CLIENT] read_to_end...");
-                                        let prev_size = buf.len();
-                                        match std::io::Read::by_ref(stream)
-                                            .take(u64::from(size))
-                                            .read_to_end(buf)
-                                        {
-                                            // match stream.read_exact(&mut buf) {
-                                            Ok(read) => {
-                                                if read != size as usize {
-                                                    warn!(
-                                                        "[CLIENT] Couldn't read enough bytes! Read {}/{}.",
-                                                        read, size
-                                                    );
-                                                }
+                            // send what we drew since last tick
+                            if !disconnect && !self.client.pending_edits.is_empty() {
+                                let packet = Packet {
+                                    packet_type: PacketType::SetPixelsPacket {
+                                        edits: std::mem::take(&mut self.client.pending_edits),
+                                    },
+                                };
+                                stream.get_mut().set_nonblocking(false).unwrap();
+                                if let Err(e) = send_packet(stream.get_mut(), &packet) {
+                                    error!("[CLIENT] Failed to send edits: {}", e);
+                                    disconnect = true;
+                                }
+                                stream.get_mut().set_nonblocking(true).unwrap();
+                            }
 
-                                                // println!("[CLIENT] Read {}/{} bytes", read, buf.len());
-
-                                                bytes_to_read = None;
-
-                                                // println!("[CLIENT] Read {} bytes.", buf.len());
-                                                match bincode::deserialize::<Packet>(buf) {
-                                                    // match serde_json::from_slice::<Packet>(&buf) {
-                                                    Ok(p) => {
-                                                        // n += 1;
-                                                        #[allow(unreachable_patterns)]
-                                                        #[allow(clippy::match_same_arms)]
-                                                        match p.packet_type {
-                                                            PacketType::SyncChunkPacket {
-                                                                chunk_x,
-                                                                chunk_y,
-                                                                pixels,
-                                                                colors,
-                                                            } => {
-                                                                if let Some(w) = &mut self.data.world {
-                                                                    if let Err(e) = w.sync_chunk(
-                                                                        chunk_x, chunk_y, pixels, colors,
-                                                                    ) {
-                                                                        warn!("[CLIENT] sync_chunk failed: {}", e);
-                                                                    }
-                                                                }
-                                                            },
-                                                            PacketType::SyncLiquidFunPacket {
-                                                                positions: _,
-                                                                velocities: _,
-                                                            } => {
-                                                                // TODO: reimplement for rapier/salva
-                                                                // println!("[CLIENT] Got SyncLiquidFunPacket");
-                                                                // if let Some(w) = &mut self.data.world {
-                                                                //     let mut particle_system = w
-                                                                //         .lqf_world
-                                                                //         .get_particle_system_list()
-                                                                //         .unwrap();
-
-                                                                //     let particle_count = particle_system
-                                                                //         .get_particle_count()
-                                                                //         as usize;
-                                                                //     // let particle_colors: &[b2ParticleColor] = particle_system.get_color_buffer();
-                                                                //     let particle_positions: &mut [Vec2] =
-                                                                //         particle_system
-                                                                //             .get_position_buffer_mut();
-                                                                //     for i in 0..particle_count
-                                                                //         .min(positions.len())
-                                                                //     {
-                                                                //         let dx = positions[i].x
-                                                                //             - particle_positions[i].x;
-                                                                //         let dy = positions[i].y
-                                                                //             - particle_positions[i].y;
-
-                                                                //         if dx.abs() > 1.0 || dy.abs() > 1.0
-                                                                //         {
-                                                                //             particle_positions[i].x += dx;
-                                                                //             particle_positions[i].y += dy;
-                                                                //         } else {
-                                                                //             particle_positions[i].x +=
-                                                                //                 dx / 2.0;
-                                                                //             particle_positions[i].y +=
-                                                                //                 dy / 2.0;
-                                                                //         }
-                                                                //     }
-
-                                                                //     let particle_velocities: &mut [Vec2] =
-                                                                //         particle_system
-                                                                //             .get_velocity_buffer_mut();
-                                                                //     for i in 0..particle_count
-                                                                //         .min(positions.len())
-                                                                //     {
-                                                                //         particle_velocities[i].x =
-                                                                //             velocities[i].x;
-                                                                //         particle_velocities[i].y =
-                                                                //             velocities[i].y;
-                                                                //     }
-                                                                // }
-                                                            },
-                                                            _ => {},
-                                                        }
-                                                    },
-                                                    Err(e) => {
-                                                        warn!(
-                                                            "[CLIENT] Failed to deserialize packet: {}",
-                                                            e
-                                                        );
-                                                        // println!("[CLIENT]     Raw: {:?}", buf);
-                                                        // let s = String::from_utf8(buf);
-                                                        // match s {
-                                                        //     Ok(st) => {
-                                                        //         // println!("[CLIENT]     Raw: {} <- raw", st)
-                                                        //         let mut file = std::fs::File::create("data.dat").expect("create failed");
-                                                        //         file.write_all(&st.into_bytes()).expect("write failed");
-                                                        //         panic!("[CLIENT] See data.dat");
-                                                        //     },
-                                                        //     Err(e) => {
-                                                        //         let index = e.utf8_error().valid_up_to();
-                                                        //         let len = e.utf8_error().error_len().unwrap();
-                                                        //         let sl = &e.as_bytes()[index .. index + len];
-
-                                                        //         let mut file = std::fs::File::create("data.dat").expect("create failed");
-                                                        //         file.write_all(e.as_bytes()).expect("write failed");
-
-                                                        //         panic!("[CLIENT] See data.dat: {} : {:?}", e, sl);
-                                                        //     },
-                                                        // }
-                                                    },
-                                                };
-                                                // println!("[CLIENT] Recieved packet : {:?}", match p.packet_type {
-                                                //     PacketType::SyncChunkPacket{..} => "SyncChunkPacket",
-                                                //     _ => "???",
-                                                // });
-                                            },
-                                            Err(_e) => {
-                                                let read = buf.len() - prev_size;
-                                                // println!("[CLIENT] read_to_end failed (but read {} bytes): {}", read, e);
-                                                bytes_to_read = Some(size - read as u32);
-                                            },
+                            let start = Instant::now();
+
+                            // let mut n = 0;
+                            while !disconnect && Instant::now().saturating_duration_since(start).as_nanos() < 5_000_000 {
+                                let p = match packet_reader.read(stream) {
+                                    Ok(Some(p)) => p,
+                                    Ok(None) => break,
+                                    Err(e) => {
+                                        error!("[CLIENT] Failed to read from server: {}", e);
+                                        disconnect = true;
+                                        break;
+                                    },
+                                };
+
+                                #[allow(unreachable_patterns)]
+                                #[allow(clippy::match_same_arms)]
+                                match p.packet_type {
+                                    PacketType::WelcomePacket {
+                                        protocol_version,
+                                        chunk_size,
+                                        server_tick_speed,
+                                    } => {
+                                        if let Err(e) = check_handshake(protocol_version, chunk_size) {
+                                            error!("[CLIENT] Server handshake rejected: {}", e);
+                                            disconnect = true;
+                                            reconnect = false;
+                                        } else {
+                                            welcome = Some(server_tick_speed);
                                         }
-                                    }
+                                    },
+                                    PacketType::DisconnectPacket { reason } => {
+                                        error!("[CLIENT] Disconnected by server: {}", reason);
+                                        disconnect = true;
+                                        reconnect = false;
+                                    },
+                                    PacketType::SyncChunkPacket {
+                                        chunk_x,
+                                        chunk_y,
+                                        pixels,
+                                        colors,
+                                    } => {
+                                        if let Some(w) = &mut self.data.world {
+                                            if let Err(e) = w.sync_chunk(
+                                                chunk_x, chunk_y, pixels, colors,
+                                            ) {
+                                                warn!("[CLIENT] sync_chunk failed: {}", e);
+                                            }
+                                        }
+                                    },
+                                    PacketType::ChunkDeltaPacket {
+                                        chunk_x,
+                                        chunk_y,
+                                        changes,
+                                    } => {
+                                        if let Some(w) = &mut self.data.world {
+                                            if let Err(e) = w.apply_chunk_delta(
+                                                chunk_x, chunk_y, &changes,
+                                            ) {
+                                                warn!("[CLIENT] apply_chunk_delta failed: {}", e);
+                                                if let Some(cw) = &mut self.client.world {
+                                                    cw.missed_delta((chunk_x, chunk_y));
+                                                }
+                                            }
+                                        }
+                                    },
+                                    PacketType::EntitySyncPacket { entities } => {
+                                        if let (Some(w), Some(cw)) = (&mut self.data.world, &mut self.client.world) {
+                                            cw.apply_entity_sync(w, entities);
+                                        }
+                                    },
+                                    PacketType::SyncLiquidFunPacket {
+                                        positions: _,
+                                        velocities: _,
+                                    } => {
+                                        // TODO: reimplement for rapier/salva
+                                        // println!("[CLIENT] Got SyncLiquidFunPacket");
+                                        // if let Some(w) = &mut self.data.world {
+                                        //     let mut particle_system = w
+                                        //         .lqf_world
+                                        //         .get_particle_system_list()
+                                        //         .unwrap();
+
+                                        //     let particle_count = particle_system
+                                        //         .get_particle_count()
+                                        //         as usize;
+                                        //     // let particle_colors: &[b2ParticleColor] = particle_system.get_color_buffer();
+                                        //     let particle_positions: &mut [Vec2] =
+                                        //         particle_system
+                                        //             .get_position_buffer_mut();
+                                        //     for i in 0..particle_count
+                                        //         .min(positions.len())
+                                        //     {
+                                        //         let dx = positions[i].x
+                                        //             - particle_positions[i].x;
+                                        //         let dy = positions[i].y
+                                        //             - particle_positions[i].y;
+
+                                        //         if dx.abs() > 1.0 || dy.abs() > 1.0
+                                        //         {
+                                        //             particle_positions[i].x += dx;
+                                        //             particle_positions[i].y += dy;
+                                        //         } else {
+                                        //             particle_positions[i].x +=
+                                        //                 dx / 2.0;
+                                        //             particle_positions[i].y +=
+                                        //                 dy / 2.0;
+                                        //         }
+                                        //     }
+
+                                        //     let particle_velocities: &mut [Vec2] =
+                                        //         particle_system
+                                        //             .get_velocity_buffer_mut();
+                                        //     for i in 0..particle_count
+                                        //         .min(positions.len())
+                                        //     {
+                                        //         particle_velocities[i].x =
+                                        //             velocities[i].x;
+                                        //         particle_velocities[i].y =
+                                        //             velocities[i].y;
+                                        //     }
+                                        // }
+                                    },
+                                    _ => {},
                                 }
                             }
                             // println!("[CLIENT] Handled {} packets.", n);
                         }
+                        if let (Some(tick_speed), Some(connection)) = (welcome, &mut self.client.connection) {
+                            connection.welcomed(tick_speed);
+                        }
+                        if disconnect {
+                            if let Some(connection) = &mut self.client.connection {
+                                connection.disconnected(reconnect);
+                            }
+                            packet_reader = PacketReader::new();
+                        }
 
                         self.data.fps_counter.tick_times.rotate_left(1);
                         self.data.fps_counter.tick_times[self.data.fps_counter.tick_times.len() - 1] =
@@ -744,6 +841,8 @@ impl ClientGame {
                     self.data.fps_counter.frame_times.rotate_left(1);
                     self.data.fps_counter.frame_times[self.data.fps_counter.frame_times.len() - 1] = time_nano as f32;
 
+                    #[cfg(feature = "profile-puffin")]
+                    puffin::set_scopes_on(self.data.settings.profiling);
                     profiling::finish_frame!();
 
                     // sleep a bit if we aren't going to tick next frame
@@ -782,6 +881,13 @@ impl ClientGame {
                 self.data.registries.clone(),
                 &self.data.file_helper,
             );
+            renderer.shaders.set_light_scale(
+                &renderer.display,
+                &self.data.file_helper,
+                self.data.settings.valid_light_scale(),
+            );
+            w.chunk_handler
+                .apply_lighting_policy(self.data.settings.lighting_active_chunks_only);
             w.chunk_handler.update_chunk_graphics(&renderer.shaders);
         }
     }
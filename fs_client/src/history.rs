@@ -0,0 +1,286 @@
+use std::collections::VecDeque;
+
+use fs_common::game::common::world::{
+    chunk_access::FSChunkAccess,
+    chunk_handler::ChunkHandler,
+    material::{buf::MaterialBuf, MaterialInstance},
+};
+
+use crate::world::ClientChunk;
+
+/// A rectangular region of the world before and after an edit.
+pub struct WorldEdit {
+    pub x: i64,
+    pub y: i64,
+    pub before: MaterialBuf,
+    pub after: MaterialBuf,
+}
+
+impl WorldEdit {
+    /// The pixels that differ between `before` and `after`, in world coordinates, as they are
+    ///   after the edit.
+    pub fn changes(&self) -> Vec<(i64, i64, MaterialInstance)> {
+        self.diff(&self.before, &self.after)
+    }
+
+    /// Like [`WorldEdit::changes`], but as they were before the edit (ie. what undoing it sets).
+    pub fn reverted(&self) -> Vec<(i64, i64, MaterialInstance)> {
+        self.diff(&self.after, &self.before)
+    }
+
+    /// Whether every pixel of the edited region is loaded.
+    pub fn is_loaded(&self, chunks: &dyn FSChunkAccess) -> bool {
+        (0..i64::from(self.before.height)).all(|dy| {
+            (0..i64::from(self.before.width))
+                .all(|dx| chunks.is_pixel_loaded(self.x + dx, self.y + dy))
+        })
+    }
+
+    fn diff(&self, from: &MaterialBuf, to: &MaterialBuf) -> Vec<(i64, i64, MaterialInstance)> {
+        let width = usize::from(to.width);
+        from.materials
+            .iter()
+            .zip(&to.materials)
+            .enumerate()
+            .filter(|(_, (from, to))| from != to)
+            .map(|(i, (_, to))| {
+                (
+                    self.x + (i % width) as i64,
+                    self.y + (i / width) as i64,
+                    to.clone(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Undo/redo history for world edits made with the draw tools.
+///
+/// Edits are recorded into the current step with [`EditHistory::record`] and grouped into one
+///   undoable step by [`EditHistory::commit`] (eg. one step per brush stroke).
+pub struct EditHistory {
+    undo_stack: VecDeque<Vec<WorldEdit>>,
+    redo_stack: Vec<Vec<WorldEdit>>,
+    current: Vec<WorldEdit>,
+    /// Maximum number of steps kept; the oldest steps are dropped past this.
+    pub max_steps: usize,
+}
+
+impl EditHistory {
+    pub fn new(max_steps: usize) -> Self {
+        Self {
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            current: Vec::new(),
+            max_steps,
+        }
+    }
+
+    /// Runs `edit` on the world, recording the `width`x`height` region at (`x`, `y`) before and after.
+    ///
+    /// If the region isn't fully loaded, `edit` is still run but nothing is recorded.
+    /// Returns the recorded edit, if any.
+    pub fn edit(
+        &mut self,
+        chunk_handler: &mut ChunkHandler<ClientChunk>,
+        x: i64,
+        y: i64,
+        width: u16,
+        height: u16,
+        edit: impl FnOnce(&mut ChunkHandler<ClientChunk>),
+    ) -> Option<&WorldEdit> {
+        let Ok(before) = MaterialBuf::copy(chunk_handler, x, y, width, height) else {
+            edit(chunk_handler);
+            return None;
+        };
+
+        edit(chunk_handler);
+
+        let after = MaterialBuf::copy(chunk_handler, x, y, width, height).ok()?;
+        if before == after {
+            return None;
+        }
+
+        self.record(WorldEdit { x, y, before, after });
+        self.current.last()
+    }
+
+    /// Adds an edit to the current step.
+    pub fn record(&mut self, edit: WorldEdit) {
+        self.current.push(edit);
+    }
+
+    /// Finishes the current step, making it undoable.
+    pub fn commit(&mut self) {
+        if self.current.is_empty() {
+            return;
+        }
+
+        self.redo_stack.clear();
+        self.undo_stack.push_back(std::mem::take(&mut self.current));
+        while self.undo_stack.len() > self.max_steps {
+            self.undo_stack.pop_front();
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Reverts the most recent step.
+    ///
+    /// Returns the pixels it changed, in the order they were set (see [`WorldEdit::reverted`]),
+    ///   which is empty if there was nothing to undo.
+    pub fn undo(
+        &mut self,
+        chunks: &mut dyn FSChunkAccess,
+    ) -> Result<Vec<(i64, i64, MaterialInstance)>, String> {
+        self.commit();
+
+        let Some(step) = self.undo_stack.pop_back() else {
+            return Ok(Vec::new());
+        };
+
+        // apply in reverse so overlapping edits end up in the original state
+        let applied = apply_step(chunks, step.iter().rev(), |edit, chunks| {
+            edit.before.paste(chunks, edit.x, edit.y)?;
+            Ok(edit.reverted())
+        });
+        match applied {
+            Ok(changes) => {
+                self.redo_stack.push(step);
+                Ok(changes)
+            },
+            Err(e) => {
+                self.undo_stack.push_back(step);
+                Err(e)
+            },
+        }
+    }
+
+    /// Reapplies the most recently undone step.
+    ///
+    /// Returns the pixels it changed, in the order they were set (see [`WorldEdit::changes`]),
+    ///   which is empty if there was nothing to redo.
+    pub fn redo(
+        &mut self,
+        chunks: &mut dyn FSChunkAccess,
+    ) -> Result<Vec<(i64, i64, MaterialInstance)>, String> {
+        let Some(step) = self.redo_stack.pop() else {
+            return Ok(Vec::new());
+        };
+
+        let applied = apply_step(chunks, step.iter(), |edit, chunks| {
+            edit.after.paste(chunks, edit.x, edit.y)?;
+            Ok(edit.changes())
+        });
+        match applied {
+            Ok(changes) => {
+                self.undo_stack.push_back(step);
+                Ok(changes)
+            },
+            Err(e) => {
+                self.redo_stack.push(step);
+                Err(e)
+            },
+        }
+    }
+}
+
+/// Runs `apply` on each of `edits`, collecting the pixels they changed.
+///
+/// Errors before applying anything if part of an edit isn't loaded, so a step is never left half
+///   applied.
+fn apply_step<'a>(
+    chunks: &mut dyn FSChunkAccess,
+    edits: impl Iterator<Item = &'a WorldEdit> + Clone,
+    mut apply: impl FnMut(
+        &WorldEdit,
+        &mut dyn FSChunkAccess,
+    ) -> Result<Vec<(i64, i64, MaterialInstance)>, String>,
+) -> Result<Vec<(i64, i64, MaterialInstance)>, String> {
+    if let Some(edit) = edits.clone().find(|edit| !edit.is_loaded(&*chunks)) {
+        return Err(format!(
+            "Edit at {}, {} is not fully loaded",
+            edit.x, edit.y
+        ));
+    }
+
+    let mut changes = Vec::new();
+    for edit in edits {
+        changes.extend(apply(edit, chunks)?);
+    }
+    Ok(changes)
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        Self::new(64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fs_common::game::common::world::{
+        chunk_access::FSChunkAccess,
+        chunk_handler::ChunkHandler,
+        gen::TestGenerator,
+        material::{self, color::Color, MaterialInstance, PhysicsType},
+        Chunk, CHUNK_AREA,
+    };
+
+    use super::EditHistory;
+    use crate::world::ClientChunk;
+
+    fn loaded_chunk(x: i32, y: i32) -> ClientChunk {
+        let mut chunk = ClientChunk::new_empty(x, y);
+        chunk.data.pixels = Some(
+            vec![MaterialInstance::air(); CHUNK_AREA]
+                .try_into()
+                .unwrap(),
+        );
+        chunk
+    }
+
+    #[test]
+    fn undo_keeps_unloaded_steps() {
+        let mut ch: ChunkHandler<ClientChunk> = ChunkHandler::new(TestGenerator::new(), None);
+        ch.manager.insert((-1, 0), loaded_chunk(-1, 0));
+        ch.manager.insert((0, 0), loaded_chunk(0, 0));
+
+        // a line across the chunk border
+        let mat = material::TEST.instance(PhysicsType::Solid, Color::rgb(1, 2, 3));
+        let mut history = EditHistory::default();
+        history.edit(&mut ch, -2, 5, 4, 1, |ch| {
+            for x in -2..2 {
+                ch.set_pixel(x, 5, mat.clone()).unwrap();
+            }
+        });
+        history.commit();
+
+        // part of the step is unloaded, so nothing is undone and it can be tried again
+        let unloaded = ch.manager.remove((-1, 0)).unwrap();
+        assert!(history.undo(&mut ch).is_err());
+        assert!(history.can_undo());
+        assert_eq!(ch.pixel(1, 5), Ok(&mat));
+
+        ch.manager.insert((-1, 0), unloaded);
+        assert_eq!(history.undo(&mut ch).unwrap().len(), 4);
+        assert_eq!(ch.pixel(-2, 5), Ok(&MaterialInstance::air()));
+        assert_eq!(ch.pixel(1, 5), Ok(&MaterialInstance::air()));
+
+        // same for redo
+        let unloaded = ch.manager.remove((-1, 0)).unwrap();
+        assert!(history.redo(&mut ch).is_err());
+        assert!(history.can_redo());
+        assert_eq!(ch.pixel(1, 5), Ok(&MaterialInstance::air()));
+
+        ch.manager.insert((-1, 0), unloaded);
+        assert_eq!(history.redo(&mut ch).unwrap().len(), 4);
+        assert_eq!(ch.pixel(-2, 5), Ok(&mat));
+    }
+}
@@ -8,6 +8,52 @@ use glutin::{
 #[derive(Debug)]
 pub enum InputEvent<'a> {
     GlutinEvent(&'a WindowEvent<'a>),
+    /// Read separately by [`GamepadInput`], since glutin doesn't report gamepads.
+    ///
+    /// [`GamepadInput`]: super::GamepadInput
+    Gamepad(GamepadEvent),
+}
+
+/// Buttons on a standard gamepad, named by position (eg. [`GamepadButton::South`] is A on an Xbox
+///   controller).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GamepadAxis {
+    LeftStickX,
+    /// Positive is up.
+    LeftStickY,
+    RightStickX,
+    /// Positive is up.
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GamepadEvent {
+    Button {
+        button: GamepadButton,
+        pressed: bool,
+    },
+    /// `value` is in `-1.0..=1.0` for sticks and `0.0..=1.0` for triggers.
+    Axis { axis: GamepadAxis, value: f32 },
 }
 
 pub struct Controls {
@@ -29,6 +75,9 @@ pub struct Controls {
     pub cut: Box<dyn Control<bool>>,
     pub paste: Box<dyn Control<bool>>,
     pub clipboard_action: Box<dyn Control<bool>>,
+
+    pub undo: Box<dyn Control<bool>>,
+    pub redo: Box<dyn Control<bool>>,
 }
 
 impl Controls {
@@ -60,6 +109,9 @@ impl Controls {
         self.cut.process(event, &self.cur_modifiers);
         self.paste.process(event, &self.cur_modifiers);
         self.clipboard_action.process(event, &self.cur_modifiers);
+
+        self.undo.process(event, &self.cur_modifiers);
+        self.redo.process(event, &self.cur_modifiers);
     }
 }
 
@@ -81,6 +133,11 @@ impl Default for Controls {
                         KeyControlMode::Momentary,
                         ModifiersState::empty(),
                     )),
+                    Box::new(GamepadButtonControl::new(
+                        GamepadButton::DPadUp,
+                        GamepadButtonControlMode::Momentary,
+                    )),
+                    Box::new(GamepadAxisControl::new(GamepadAxis::LeftStickY, true, 0.5)),
                 ],
             )),
             down: Box::new(MultiControl::new(
@@ -96,6 +153,11 @@ impl Default for Controls {
                         KeyControlMode::Momentary,
                         ModifiersState::empty(),
                     )),
+                    Box::new(GamepadButtonControl::new(
+                        GamepadButton::DPadDown,
+                        GamepadButtonControlMode::Momentary,
+                    )),
+                    Box::new(GamepadAxisControl::new(GamepadAxis::LeftStickY, false, 0.5)),
                 ],
             )),
             left: Box::new(MultiControl::new(
@@ -111,6 +173,11 @@ impl Default for Controls {
                         KeyControlMode::Momentary,
                         ModifiersState::empty(),
                     )),
+                    Box::new(GamepadButtonControl::new(
+                        GamepadButton::DPadLeft,
+                        GamepadButtonControlMode::Momentary,
+                    )),
+                    Box::new(GamepadAxisControl::new(GamepadAxis::LeftStickX, false, 0.5)),
                 ],
             )),
             right: Box::new(MultiControl::new(
@@ -126,6 +193,11 @@ impl Default for Controls {
                         KeyControlMode::Momentary,
                         ModifiersState::empty(),
                     )),
+                    Box::new(GamepadButtonControl::new(
+                        GamepadButton::DPadRight,
+                        GamepadButtonControlMode::Momentary,
+                    )),
+                    Box::new(GamepadAxisControl::new(GamepadAxis::LeftStickX, true, 0.5)),
                 ],
             )),
             jump: Box::new(MultiControl::new(
@@ -141,6 +213,10 @@ impl Default for Controls {
                         KeyControlMode::Momentary,
                         ModifiersState::empty(),
                     )),
+                    Box::new(GamepadButtonControl::new(
+                        GamepadButton::South,
+                        GamepadButtonControlMode::Momentary,
+                    )),
                 ],
             )),
             launch: Box::new(MultiControl::new(
@@ -156,15 +232,25 @@ impl Default for Controls {
                         KeyControlMode::Momentary,
                         ModifiersState::empty(),
                     )),
+                    Box::new(GamepadButtonControl::new(
+                        GamepadButton::East,
+                        GamepadButtonControlMode::Momentary,
+                    )),
                 ],
             )),
             grapple: Box::new(MultiControl::new(
                 MultiControlMode::Or,
-                vec![Box::new(KeyControl::new(
-                    VirtualKeyCode::Z,
-                    KeyControlMode::Momentary,
-                    ModifiersState::empty(),
-                ))],
+                vec![
+                    Box::new(KeyControl::new(
+                        VirtualKeyCode::Z,
+                        KeyControlMode::Momentary,
+                        ModifiersState::empty(),
+                    )),
+                    Box::new(GamepadButtonControl::new(
+                        GamepadButton::West,
+                        GamepadButtonControlMode::Momentary,
+                    )),
+                ],
             )),
             free_fly: Box::new(KeyControl::new(
                 VirtualKeyCode::Numpad1,
@@ -200,7 +286,54 @@ impl Default for Controls {
                 MouseButtonControlMode::Momentary,
                 ModifiersState::CTRL,
             )),
+            undo: Box::new(MultiControl::new(
+                MultiControlMode::Or,
+                vec![Box::new(KeyControl::new(
+                    VirtualKeyCode::Z,
+                    KeyControlMode::Rising,
+                    ModifiersState::CTRL,
+                ))],
+            )),
+            redo: Box::new(MultiControl::new(
+                MultiControlMode::Or,
+                vec![Box::new(KeyControl::new(
+                    VirtualKeyCode::Y,
+                    KeyControlMode::Rising,
+                    ModifiersState::CTRL,
+                ))],
+            )),
+        }
+    }
+}
+
+/// Edge and toggle tracking shared by the button-like controls.
+#[derive(Debug, Default)]
+struct ButtonState {
+    raw: bool,
+    last_raw: bool,
+    toggled: bool,
+}
+
+impl ButtonState {
+    fn rising(&self) -> bool {
+        self.raw && !self.last_raw
+    }
+
+    fn falling(&self) -> bool {
+        !self.raw && self.last_raw
+    }
+
+    /// Flips on every press.
+    fn toggle(&mut self) -> bool {
+        if self.rising() {
+            self.toggled = !self.toggled;
         }
+        self.toggled
+    }
+
+    /// Must be called at the end of each [`Control::get`], after the edges have been checked.
+    fn advance(&mut self) {
+        self.last_raw = self.raw;
     }
 }
 
@@ -238,9 +371,7 @@ pub struct KeyControl {
     pub mode: KeyControlMode,
     pub modifiers: ModifiersState,
 
-    raw: bool,
-    last_raw: bool,
-    last_state: bool,
+    state: ButtonState,
 }
 
 impl KeyControl {
@@ -249,9 +380,7 @@ impl KeyControl {
             key,
             mode,
             modifiers,
-            raw: false,
-            last_raw: false,
-            last_state: false,
+            state: ButtonState::default(),
         }
     }
 }
@@ -259,23 +388,18 @@ impl KeyControl {
 impl Control<bool> for KeyControl {
     fn get(&mut self) -> bool {
         let ret = match self.mode {
-            KeyControlMode::Momentary => self.raw,
-            KeyControlMode::Rising => self.raw && !self.last_raw,
-            KeyControlMode::Falling => !self.raw && self.last_raw,
-            KeyControlMode::Toggle => {
-                if self.raw && self.last_raw {
-                    self.last_state = !self.last_state;
-                }
-                self.last_state
-            },
+            KeyControlMode::Momentary => self.state.raw,
+            KeyControlMode::Rising => self.state.rising(),
+            KeyControlMode::Falling => self.state.falling(),
+            KeyControlMode::Toggle => self.state.toggle(),
             KeyControlMode::Type => {
-                let r = self.raw;
-                self.raw = false;
+                let r = self.state.raw;
+                self.state.raw = false;
                 r
             },
         };
 
-        self.last_raw = self.raw;
+        self.state.advance();
 
         ret
     }
@@ -289,7 +413,8 @@ impl Control<bool> for KeyControl {
                 ..
             }) if *k == self.key => {
                 // if !repeat || self.mode == KeyControlMode::Type {
-                self.raw = *state == ElementState::Pressed && modifiers.contains(self.modifiers);
+                self.state.raw =
+                    *state == ElementState::Pressed && modifiers.contains(self.modifiers);
                 // }
             },
             _ => {},
@@ -311,9 +436,7 @@ pub struct MouseButtonControl {
     pub mode: MouseButtonControlMode,
     pub modifiers: ModifiersState,
 
-    raw: bool,
-    last_raw: bool,
-    last_state: bool,
+    state: ButtonState,
 }
 
 impl MouseButtonControl {
@@ -326,9 +449,7 @@ impl MouseButtonControl {
             button,
             mode,
             modifiers,
-            raw: false,
-            last_raw: false,
-            last_state: false,
+            state: ButtonState::default(),
         }
     }
 }
@@ -336,18 +457,13 @@ impl MouseButtonControl {
 impl Control<bool> for MouseButtonControl {
     fn get(&mut self) -> bool {
         let ret = match self.mode {
-            MouseButtonControlMode::Momentary => self.raw,
-            MouseButtonControlMode::Rising => self.raw && !self.last_raw,
-            MouseButtonControlMode::Falling => !self.raw && self.last_raw,
-            MouseButtonControlMode::Toggle => {
-                if self.raw && self.last_raw {
-                    self.last_state = !self.last_state;
-                }
-                self.last_state
-            },
+            MouseButtonControlMode::Momentary => self.state.raw,
+            MouseButtonControlMode::Rising => self.state.rising(),
+            MouseButtonControlMode::Falling => self.state.falling(),
+            MouseButtonControlMode::Toggle => self.state.toggle(),
         };
 
-        self.last_raw = self.raw;
+        self.state.advance();
 
         ret
     }
@@ -359,7 +475,124 @@ impl Control<bool> for MouseButtonControl {
                 button,
                 ..
             }) if *button == self.button => {
-                self.raw = *state == ElementState::Pressed && modifiers.contains(self.modifiers);
+                self.state.raw =
+                    *state == ElementState::Pressed && modifiers.contains(self.modifiers);
+            },
+            _ => {},
+        }
+    }
+}
+
+/// Latest cursor position in window coordinates, rounded to whole pixels.
+#[derive(Debug, Default)]
+pub struct MousePositionControl {
+    pos: (i32, i32),
+}
+
+impl MousePositionControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Control<(i32, i32)> for MousePositionControl {
+    fn get(&mut self) -> (i32, i32) {
+        self.pos
+    }
+
+    fn process(&mut self, event: &InputEvent, _modifiers: &ModifiersState) {
+        if let InputEvent::GlutinEvent(glutin::event::WindowEvent::CursorMoved {
+            position, ..
+        }) = event
+        {
+            self.pos = (position.x.round() as i32, position.y.round() as i32);
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum GamepadButtonControlMode {
+    Momentary,
+    Rising,
+    Falling,
+    Toggle,
+}
+
+pub struct GamepadButtonControl {
+    pub button: GamepadButton,
+    pub mode: GamepadButtonControlMode,
+
+    state: ButtonState,
+}
+
+impl GamepadButtonControl {
+    pub fn new(button: GamepadButton, mode: GamepadButtonControlMode) -> Self {
+        Self { button, mode, state: ButtonState::default() }
+    }
+}
+
+impl Control<bool> for GamepadButtonControl {
+    fn get(&mut self) -> bool {
+        let ret = match self.mode {
+            GamepadButtonControlMode::Momentary => self.state.raw,
+            GamepadButtonControlMode::Rising => self.state.rising(),
+            GamepadButtonControlMode::Falling => self.state.falling(),
+            GamepadButtonControlMode::Toggle => self.state.toggle(),
+        };
+
+        self.state.advance();
+
+        ret
+    }
+
+    fn process(&mut self, event: &InputEvent, _modifiers: &ModifiersState) {
+        match event {
+            InputEvent::Gamepad(GamepadEvent::Button { button, pressed })
+                if *button == self.button =>
+            {
+                self.state.raw = *pressed;
+            },
+            _ => {},
+        }
+    }
+}
+
+/// Pressed while a gamepad axis is pushed past `deadzone` in one direction.
+///
+/// [`GamepadAxisControl::value`] gives the analog value, rescaled so it starts from zero at the
+///   edge of the deadzone.
+pub struct GamepadAxisControl {
+    pub axis: GamepadAxis,
+    /// Whether this is pressed by positive values of the axis instead of negative ones.
+    pub positive: bool,
+    /// Values closer to zero than this are ignored, in `0.0..1.0`.
+    pub deadzone: f32,
+
+    raw: f32,
+}
+
+impl GamepadAxisControl {
+    pub fn new(axis: GamepadAxis, positive: bool, deadzone: f32) -> Self {
+        Self { axis, positive, deadzone, raw: 0.0 }
+    }
+
+    /// How far the axis is pushed in this control's direction, from `0.0` inside the deadzone to
+    ///   `1.0` all the way.
+    pub fn value(&self) -> f32 {
+        let v = if self.positive { self.raw } else { -self.raw };
+        ((v - self.deadzone) / (1.0 - self.deadzone)).clamp(0.0, 1.0)
+    }
+}
+
+impl Control<bool> for GamepadAxisControl {
+    fn get(&mut self) -> bool {
+        self.value() > 0.0
+    }
+
+    fn process(&mut self, event: &InputEvent, _modifiers: &ModifiersState) {
+        match event {
+            InputEvent::Gamepad(GamepadEvent::Axis { axis, value }) if *axis == self.axis => {
+                self.raw = *value;
             },
             _ => {},
         }
@@ -397,3 +630,220 @@ impl Control<bool> for MultiControl {
             .for_each(|c| c.process(event, modifiers));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use glutin::{
+        dpi::PhysicalPosition,
+        event::{DeviceId, ElementState, ModifiersState, MouseButton, WindowEvent},
+    };
+
+    use super::{
+        Control, GamepadAxis, GamepadAxisControl, GamepadButton, GamepadButtonControl,
+        GamepadButtonControlMode, GamepadEvent, InputEvent, MouseButtonControl,
+        MouseButtonControlMode, MousePositionControl,
+    };
+
+    fn send(control: &mut impl Control<bool>, event: GamepadEvent) {
+        Control::<bool>::process(
+            control,
+            &InputEvent::Gamepad(event),
+            &ModifiersState::empty(),
+        );
+    }
+
+    #[test]
+    fn gamepad_button() {
+        let mut jump =
+            GamepadButtonControl::new(GamepadButton::South, GamepadButtonControlMode::Momentary);
+        assert!(!Control::<bool>::get(&mut jump));
+
+        send(
+            &mut jump,
+            GamepadEvent::Button { button: GamepadButton::South, pressed: true },
+        );
+        assert!(Control::<bool>::get(&mut jump));
+
+        // other buttons and axes are ignored
+        send(
+            &mut jump,
+            GamepadEvent::Button { button: GamepadButton::East, pressed: false },
+        );
+        send(
+            &mut jump,
+            GamepadEvent::Axis { axis: GamepadAxis::LeftTrigger, value: 0.0 },
+        );
+        assert!(Control::<bool>::get(&mut jump));
+
+        send(
+            &mut jump,
+            GamepadEvent::Button { button: GamepadButton::South, pressed: false },
+        );
+        assert!(!Control::<bool>::get(&mut jump));
+
+        let mut rising =
+            GamepadButtonControl::new(GamepadButton::Start, GamepadButtonControlMode::Rising);
+        send(
+            &mut rising,
+            GamepadEvent::Button { button: GamepadButton::Start, pressed: true },
+        );
+        assert!(Control::<bool>::get(&mut rising));
+        assert!(!Control::<bool>::get(&mut rising));
+    }
+
+    #[test]
+    fn toggle() {
+        let mut toggle =
+            GamepadButtonControl::new(GamepadButton::Select, GamepadButtonControlMode::Toggle);
+        let press = |control: &mut GamepadButtonControl, pressed| {
+            send(
+                control,
+                GamepadEvent::Button { button: GamepadButton::Select, pressed },
+            );
+        };
+        assert!(!Control::<bool>::get(&mut toggle));
+
+        // flips once per press, however long it's held
+        press(&mut toggle, true);
+        for _ in 0..3 {
+            assert!(Control::<bool>::get(&mut toggle));
+        }
+        press(&mut toggle, false);
+        assert!(Control::<bool>::get(&mut toggle));
+
+        press(&mut toggle, true);
+        for _ in 0..3 {
+            assert!(!Control::<bool>::get(&mut toggle));
+        }
+        press(&mut toggle, false);
+        assert!(!Control::<bool>::get(&mut toggle));
+    }
+
+    #[test]
+    fn gamepad_axis_deadzone() {
+        let mut left = GamepadAxisControl::new(GamepadAxis::LeftStickX, false, 0.2);
+        let mut right = GamepadAxisControl::new(GamepadAxis::LeftStickX, true, 0.2);
+
+        // inside the deadzone
+        for control in [&mut left, &mut right] {
+            send(
+                control,
+                GamepadEvent::Axis { axis: GamepadAxis::LeftStickX, value: -0.1 },
+            );
+        }
+        assert!(!Control::<bool>::get(&mut left));
+        assert!(!Control::<bool>::get(&mut right));
+        assert_eq!(left.value(), 0.0);
+
+        for control in [&mut left, &mut right] {
+            send(
+                control,
+                GamepadEvent::Axis { axis: GamepadAxis::LeftStickX, value: -0.6 },
+            );
+        }
+        assert!(Control::<bool>::get(&mut left));
+        assert!(!Control::<bool>::get(&mut right));
+        assert!((left.value() - 0.5).abs() < 0.001, "{}", left.value());
+
+        // other axes are ignored
+        send(
+            &mut left,
+            GamepadEvent::Axis { axis: GamepadAxis::LeftStickY, value: 0.0 },
+        );
+        assert!(Control::<bool>::get(&mut left));
+
+        send(
+            &mut left,
+            GamepadEvent::Axis { axis: GamepadAxis::LeftStickX, value: -1.0 },
+        );
+        assert_eq!(left.value(), 1.0);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn mouse() {
+        // Safety: only used to build events, never passed to the windowing system
+        let device_id = unsafe { DeviceId::dummy() };
+        let press = |state, button| WindowEvent::MouseInput {
+            device_id,
+            state,
+            button,
+            modifiers: ModifiersState::empty(),
+        };
+
+        let mut draw = MouseButtonControl::new(
+            MouseButton::Left,
+            MouseButtonControlMode::Momentary,
+            ModifiersState::empty(),
+        );
+        let mut ctrl_draw = MouseButtonControl::new(
+            MouseButton::Left,
+            MouseButtonControlMode::Momentary,
+            ModifiersState::CTRL,
+        );
+
+        let event = press(ElementState::Pressed, MouseButton::Left);
+        for control in [&mut draw, &mut ctrl_draw] {
+            Control::<bool>::process(
+                control,
+                &InputEvent::GlutinEvent(&event),
+                &ModifiersState::empty(),
+            );
+        }
+        assert!(Control::<bool>::get(&mut draw));
+        assert!(!Control::<bool>::get(&mut ctrl_draw));
+
+        let event = press(ElementState::Released, MouseButton::Right);
+        Control::<bool>::process(
+            &mut draw,
+            &InputEvent::GlutinEvent(&event),
+            &ModifiersState::empty(),
+        );
+        assert!(Control::<bool>::get(&mut draw));
+
+        let event = press(ElementState::Released, MouseButton::Left);
+        Control::<bool>::process(
+            &mut draw,
+            &InputEvent::GlutinEvent(&event),
+            &ModifiersState::empty(),
+        );
+        assert!(!Control::<bool>::get(&mut draw));
+
+        // toggles flip once per click, however many ticks it's held for
+        let mut toggle = MouseButtonControl::new(
+            MouseButton::Middle,
+            MouseButtonControlMode::Toggle,
+            ModifiersState::empty(),
+        );
+        for (state, expected) in [
+            (ElementState::Pressed, true),
+            (ElementState::Released, true),
+            (ElementState::Pressed, false),
+            (ElementState::Released, false),
+        ] {
+            let event = press(state, MouseButton::Middle);
+            Control::<bool>::process(
+                &mut toggle,
+                &InputEvent::GlutinEvent(&event),
+                &ModifiersState::empty(),
+            );
+            for _ in 0..3 {
+                assert_eq!(Control::<bool>::get(&mut toggle), expected);
+            }
+        }
+
+        let mut cursor = MousePositionControl::new();
+        assert_eq!(Control::<(i32, i32)>::get(&mut cursor), (0, 0));
+        let event = WindowEvent::CursorMoved {
+            device_id,
+            position: PhysicalPosition::new(12.4, 30.6),
+            modifiers: ModifiersState::empty(),
+        };
+        Control::<(i32, i32)>::process(
+            &mut cursor,
+            &InputEvent::GlutinEvent(&event),
+            &ModifiersState::empty(),
+        );
+        assert_eq!(Control::<(i32, i32)>::get(&mut cursor), (12, 31));
+    }
+}
@@ -0,0 +1,153 @@
+use gilrs::{Axis, Button, EventType, Gilrs};
+
+use super::{Controls, GamepadAxis, GamepadButton, GamepadEvent, InputEvent};
+
+/// Reads connected gamepads with gilrs, since glutin doesn't report them.
+pub struct GamepadInput {
+    /// [`None`] if gilrs couldn't be initialized, in which case gamepads are ignored.
+    gilrs: Option<Gilrs>,
+}
+
+impl GamepadInput {
+    pub fn new() -> Self {
+        let gilrs = match Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(e) => {
+                log::warn!("Gamepads are unavailable: {e}");
+                None
+            },
+        };
+
+        Self { gilrs }
+    }
+
+    /// Passes the gamepad events since the last call to `controls`.
+    pub fn poll(&mut self, controls: &mut Controls) {
+        let Some(gilrs) = &mut self.gilrs else {
+            return;
+        };
+
+        while let Some(event) = gilrs.next_event() {
+            if let Some(event) = convert(event.event) {
+                controls.process(&InputEvent::Gamepad(event));
+            }
+        }
+    }
+}
+
+impl Default for GamepadInput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Translates a gilrs event, or returns [`None`] if it's not one the controls use.
+///
+/// The analog triggers come through as [`GamepadAxis::LeftTrigger`] and
+///   [`GamepadAxis::RightTrigger`], while the bumpers above them are buttons.
+fn convert(event: EventType) -> Option<GamepadEvent> {
+    match event {
+        EventType::ButtonPressed(button, _) => {
+            Some(GamepadEvent::Button { button: convert_button(button)?, pressed: true })
+        },
+        EventType::ButtonReleased(button, _) => {
+            Some(GamepadEvent::Button { button: convert_button(button)?, pressed: false })
+        },
+        EventType::ButtonChanged(Button::LeftTrigger2, value, _) => {
+            Some(GamepadEvent::Axis { axis: GamepadAxis::LeftTrigger, value })
+        },
+        EventType::ButtonChanged(Button::RightTrigger2, value, _) => {
+            Some(GamepadEvent::Axis { axis: GamepadAxis::RightTrigger, value })
+        },
+        EventType::AxisChanged(axis, value, _) => {
+            let axis = match axis {
+                Axis::LeftStickX => GamepadAxis::LeftStickX,
+                Axis::LeftStickY => GamepadAxis::LeftStickY,
+                Axis::RightStickX => GamepadAxis::RightStickX,
+                Axis::RightStickY => GamepadAxis::RightStickY,
+                _ => return None,
+            };
+            Some(GamepadEvent::Axis { axis, value })
+        },
+        _ => None,
+    }
+}
+
+fn convert_button(button: Button) -> Option<GamepadButton> {
+    Some(match button {
+        Button::South => GamepadButton::South,
+        Button::East => GamepadButton::East,
+        Button::West => GamepadButton::West,
+        Button::North => GamepadButton::North,
+        Button::LeftTrigger => GamepadButton::LeftBumper,
+        Button::RightTrigger => GamepadButton::RightBumper,
+        Button::Select => GamepadButton::Select,
+        Button::Start => GamepadButton::Start,
+        Button::LeftThumb => GamepadButton::LeftStick,
+        Button::RightThumb => GamepadButton::RightStick,
+        Button::DPadUp => GamepadButton::DPadUp,
+        Button::DPadDown => GamepadButton::DPadDown,
+        Button::DPadLeft => GamepadButton::DPadLeft,
+        Button::DPadRight => GamepadButton::DPadRight,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use gilrs::{Axis, Button, EventType};
+
+    use super::convert;
+    use crate::input::{Control, Controls, GamepadAxis, GamepadButton, GamepadEvent, InputEvent};
+
+    #[test]
+    fn events() {
+        // the native code isn't used, and gilrs only exposes it for buttons
+        let code = Button::South.to_nec().unwrap();
+        assert_eq!(
+            convert(EventType::ButtonPressed(Button::South, code)),
+            Some(GamepadEvent::Button { button: GamepadButton::South, pressed: true })
+        );
+        assert_eq!(
+            convert(EventType::ButtonReleased(Button::LeftTrigger, code)),
+            Some(GamepadEvent::Button { button: GamepadButton::LeftBumper, pressed: false })
+        );
+        assert_eq!(
+            convert(EventType::ButtonChanged(Button::RightTrigger2, 0.5, code)),
+            Some(GamepadEvent::Axis { axis: GamepadAxis::RightTrigger, value: 0.5 })
+        );
+        assert_eq!(
+            convert(EventType::AxisChanged(Axis::LeftStickY, -0.75, code)),
+            Some(GamepadEvent::Axis { axis: GamepadAxis::LeftStickY, value: -0.75 })
+        );
+
+        // the triggers' digital presses and unmapped inputs are dropped
+        assert_eq!(
+            convert(EventType::ButtonPressed(Button::LeftTrigger2, code)),
+            None
+        );
+        assert_eq!(convert(EventType::ButtonPressed(Button::Mode, code)), None);
+        assert_eq!(
+            convert(EventType::AxisChanged(Axis::LeftZ, 1.0, code)),
+            None
+        );
+        assert_eq!(convert(EventType::Connected), None);
+    }
+
+    #[test]
+    fn default_controls() {
+        let code = Button::South.to_nec().unwrap();
+        let mut controls = Controls::default();
+        for event in [
+            EventType::ButtonPressed(Button::South, code),
+            EventType::AxisChanged(Axis::LeftStickX, -0.9, code),
+        ] {
+            controls.process(&InputEvent::Gamepad(convert(event).unwrap()));
+        }
+
+        assert!(controls.jump.get());
+        assert!(controls.left.get());
+        assert!(!controls.right.get());
+        assert!(!controls.launch.get());
+    }
+}
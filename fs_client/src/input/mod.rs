@@ -1,2 +1,4 @@
 mod controls;
+mod gamepad;
 pub use controls::*;
+pub use gamepad::*;
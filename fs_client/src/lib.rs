@@ -1,6 +1,8 @@
 mod client;
 mod game;
+pub mod history;
 pub mod input;
+pub mod network;
 pub mod render;
 pub mod ui;
 pub mod world;
@@ -0,0 +1,285 @@
+use std::{
+    io::BufReader,
+    net::{TcpStream, ToSocketAddrs},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    time::{Duration, Instant},
+};
+
+use fs_common::game::common::networking::{send_packet, Packet};
+use log::{error, info, warn};
+
+/// Delay before the first reconnection attempt; doubled after every failed attempt.
+pub const RECONNECT_BASE_DELAY: Duration = Duration::from_millis(500);
+/// Upper bound for the delay between reconnection attempts.
+pub const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+/// Number of failed reconnection attempts before giving up.
+pub const MAX_RECONNECT_ATTEMPTS: u32 = 10;
+/// Time limit for a single connection attempt.
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// Waiting for the first connection to succeed.
+    Connecting,
+    Connected,
+    /// The connection dropped and we're retrying with backoff.
+    Reconnecting,
+    /// Disconnected for good, either by the server or after running out of attempts.
+    Disconnected,
+}
+
+/// A connection to a server that reconnects with exponential backoff when it drops.
+pub struct ServerConnection {
+    pub addr: String,
+    /// Name sent to the server in our hello packet.
+    pub client_name: String,
+    pub stream: Option<BufReader<TcpStream>>,
+    /// The attempt in progress on its own thread, so connecting doesn't block the game.
+    connecting: Option<Receiver<Result<BufReader<TcpStream>, String>>>,
+    state: ConnectionState,
+    /// Failed attempts since the last successful connection.
+    attempts: u32,
+    next_attempt: Instant,
+    /// Set once the server accepts us with a welcome packet.
+    server_tick_speed: Option<u16>,
+}
+
+impl ServerConnection {
+    pub fn new(addr: impl Into<String>, client_name: impl Into<String>) -> Self {
+        Self {
+            addr: addr.into(),
+            client_name: client_name.into(),
+            stream: None,
+            connecting: None,
+            state: ConnectionState::Connecting,
+            attempts: 0,
+            next_attempt: Instant::now(),
+            server_tick_speed: None,
+        }
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        self.state
+    }
+
+    /// Number of failed attempts since the last successful connection.
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// The server's tick speed, known once it has accepted our handshake.
+    pub fn server_tick_speed(&self) -> Option<u16> {
+        self.server_tick_speed
+    }
+
+    /// Records the server's welcome packet.
+    pub fn welcomed(&mut self, server_tick_speed: u16) {
+        info!("[CLIENT] Server accepted handshake, tick speed {server_tick_speed}");
+        self.server_tick_speed = Some(server_tick_speed);
+    }
+
+    /// Starts a connection attempt if we aren't connected and the backoff delay has passed, and
+    ///   checks on the one in progress.
+    ///
+    /// Attempts run on a separate thread, which connects and sends our hello packet. Returns `true`
+    ///   once one succeeds, so the caller can re-request anything it had asked the previous
+    ///   connection for.
+    pub fn poll(&mut self) -> bool {
+        if let Some(connecting) = &self.connecting {
+            let result = match connecting.try_recv() {
+                Ok(result) => result,
+                Err(TryRecvError::Empty) => return false,
+                Err(TryRecvError::Disconnected) => Err("Connection thread stopped".to_owned()),
+            };
+            self.connecting = None;
+            return self.connect_finished(result);
+        }
+
+        if self.stream.is_some()
+            || self.state == ConnectionState::Disconnected
+            || Instant::now() < self.next_attempt
+        {
+            return false;
+        }
+
+        info!("[CLIENT] Connecting to {}...", self.addr);
+        let (tx, rx) = mpsc::channel();
+        let addr = self.addr.clone();
+        let client_name = self.client_name.clone();
+        let spawned = std::thread::Builder::new()
+            .name("connect".to_owned())
+            .spawn(move || {
+                // the receiver is gone if we disconnected in the meantime
+                let _ = tx.send(Self::connect(&addr, &client_name));
+            });
+
+        match spawned {
+            Ok(_) => {
+                self.connecting = Some(rx);
+                false
+            },
+            Err(e) => self.connect_finished(Err(e.to_string())),
+        }
+    }
+
+    /// Connects to `addr` and sends our hello packet, blocking for up to [`CONNECT_TIMEOUT`].
+    fn connect(addr: &str, client_name: &str) -> Result<BufReader<TcpStream>, String> {
+        let socket_addr = addr
+            .to_socket_addrs()
+            .map_err(|e| e.to_string())?
+            .next()
+            .ok_or_else(|| format!("{addr} didn't resolve to any address"))?;
+
+        let stream =
+            TcpStream::connect_timeout(&socket_addr, CONNECT_TIMEOUT).map_err(|e| e.to_string())?;
+        let mut r = BufReader::new(stream);
+        send_packet(r.get_mut(), &Packet::hello(client_name)).map_err(|e| e.to_string())?;
+        r.get_mut()
+            .set_nonblocking(true)
+            .map_err(|e| e.to_string())?;
+        Ok(r)
+    }
+
+    fn connect_finished(&mut self, result: Result<BufReader<TcpStream>, String>) -> bool {
+        match result {
+            Ok(r) => {
+                info!("[CLIENT] Connected to server");
+                self.stream = Some(r);
+                self.state = ConnectionState::Connected;
+                self.attempts = 0;
+                true
+            },
+            Err(e) => {
+                error!("[CLIENT] Failed to connect to server: {}", e);
+                self.schedule_retry();
+                false
+            },
+        }
+    }
+
+    /// Drops the current stream.
+    ///
+    /// If `reconnect` is `false` (eg. the server kicked us), no further attempts are made.
+    pub fn disconnected(&mut self, reconnect: bool) {
+        self.stream = None;
+        self.connecting = None;
+        self.server_tick_speed = None;
+
+        if reconnect {
+            warn!("[CLIENT] Lost connection to server, reconnecting...");
+            self.schedule_retry();
+        } else {
+            self.state = ConnectionState::Disconnected;
+        }
+    }
+
+    fn schedule_retry(&mut self) {
+        if self.attempts >= MAX_RECONNECT_ATTEMPTS {
+            error!(
+                "[CLIENT] Giving up on {} after {} attempts",
+                self.addr, self.attempts
+            );
+            self.state = ConnectionState::Disconnected;
+            return;
+        }
+
+        if self.state != ConnectionState::Connecting {
+            self.state = ConnectionState::Reconnecting;
+        }
+        self.next_attempt = Instant::now() + Self::backoff(self.attempts);
+        self.attempts += 1;
+    }
+
+    /// Delay before attempt number `attempts + 1`.
+    pub fn backoff(attempts: u32) -> Duration {
+        RECONNECT_BASE_DELAY
+            .saturating_mul(1 << attempts.min(16))
+            .min(RECONNECT_MAX_DELAY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, time::Duration};
+
+    use fs_common::game::common::networking::{read_packet, PacketType};
+
+    use super::{
+        ConnectionState, ServerConnection, MAX_RECONNECT_ATTEMPTS, RECONNECT_BASE_DELAY,
+        RECONNECT_MAX_DELAY,
+    };
+
+    /// Polls `conn` until an attempt finishes, returning whether it succeeded.
+    fn poll_attempt(conn: &mut ServerConnection) -> bool {
+        let attempts = conn.attempts();
+        for _ in 0..500 {
+            if conn.poll() {
+                return true;
+            }
+            if conn.attempts() != attempts {
+                return false;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("connection attempt didn't finish");
+    }
+
+    #[test]
+    fn backoff() {
+        assert_eq!(ServerConnection::backoff(0), RECONNECT_BASE_DELAY);
+        assert_eq!(ServerConnection::backoff(1), RECONNECT_BASE_DELAY * 2);
+        assert_eq!(ServerConnection::backoff(3), RECONNECT_BASE_DELAY * 8);
+        assert_eq!(ServerConnection::backoff(6), RECONNECT_MAX_DELAY);
+        assert_eq!(ServerConnection::backoff(u32::MAX), RECONNECT_MAX_DELAY);
+
+        for attempts in 0..MAX_RECONNECT_ATTEMPTS {
+            assert!(ServerConnection::backoff(attempts) <= ServerConnection::backoff(attempts + 1));
+        }
+    }
+
+    #[test]
+    fn retries() {
+        // nothing listens on a port that was just freed
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let mut conn = ServerConnection::new(addr.to_string(), "test");
+
+        // the first attempt is immediate and doesn't block
+        assert!(!conn.poll());
+        assert!(!poll_attempt(&mut conn));
+        assert_eq!(conn.attempts(), 1);
+        assert_eq!(conn.state(), ConnectionState::Connecting);
+
+        // the next one waits for the backoff
+        assert!(!conn.poll());
+        assert_eq!(conn.attempts(), 1);
+
+        for _ in 1..MAX_RECONNECT_ATTEMPTS {
+            conn.schedule_retry();
+        }
+        assert_eq!(conn.attempts(), MAX_RECONNECT_ATTEMPTS);
+        conn.schedule_retry();
+        assert_eq!(conn.state(), ConnectionState::Disconnected);
+        assert!(!conn.poll());
+    }
+
+    #[test]
+    fn connects() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut conn = ServerConnection::new(listener.local_addr().unwrap().to_string(), "test");
+
+        assert!(poll_attempt(&mut conn));
+        assert_eq!(conn.state(), ConnectionState::Connected);
+
+        let (mut stream, _) = listener.accept().unwrap();
+        let packet = read_packet(&mut stream).unwrap();
+        assert!(matches!(packet.packet_type, PacketType::HelloPacket { .. }));
+
+        // dropping the connection schedules a retry
+        conn.disconnected(true);
+        assert_eq!(conn.state(), ConnectionState::Reconnecting);
+        assert_eq!(conn.attempts(), 1);
+    }
+}
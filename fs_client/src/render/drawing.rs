@@ -66,6 +66,65 @@ impl Vertices for Rect<f32> {
     }
 }
 
+/// Corners of `dest` rotated by `rotation` radians around its center, in the same order as
+///   [`Vertices::vertices`].
+pub fn rotated_quad(dest: Rect<f32>, rotation: f32) -> [Vertex2; 4] {
+    let cx = (dest.left() + dest.right()) / 2.0;
+    let cy = (dest.top() + dest.bottom()) / 2.0;
+    let (sin, cos) = rotation.sin_cos();
+
+    let verts = dest.vertices();
+    std::array::from_fn(|i| {
+        let [x, y] = verts[i].position;
+        let (dx, dy) = (x - cx, y - cy);
+        (cx + dx * cos - dy * sin, cy + dx * sin + dy * cos).into()
+    })
+}
+
+/// Indices for filling a convex polygon with `points` corners as a
+///   [`TrianglesList`](glium::index::PrimitiveType::TrianglesList) fanning out from the first one.
+pub fn convex_fan_indices(points: usize) -> Vec<u16> {
+    (1..points.saturating_sub(1))
+        .flat_map(|i| [0, i as u16, i as u16 + 1])
+        .collect()
+}
+
+/// Vertices for drawing `segments` as a [`LinesList`](glium::index::PrimitiveType::LinesList), two
+///   per segment.
+pub fn colored_line_vertices(segments: &[(Vertex2, Vertex2, Color)]) -> Vec<Vertex2C> {
+    segments
+        .iter()
+        .flat_map(|&(a, b, color)| {
+            [
+                Vertex2C { position: a.position, color: color.into() },
+                Vertex2C { position: b.position, color: color.into() },
+            ]
+        })
+        .collect()
+}
+
+/// Vertices around a circle, starting at the right and going counterclockwise.
+///
+/// If `segments` is [`None`], it's picked with [`circle_segments`].
+pub fn circle_vertices(center: Vertex2, radius: f32, segments: Option<u16>) -> Vec<Vertex2> {
+    let segments = segments.unwrap_or_else(|| circle_segments(radius)).max(3);
+    let [cx, cy] = center.position;
+    (0..segments)
+        .map(|i| {
+            let angle = f32::from(i) / f32::from(segments) * std::f32::consts::TAU;
+            (cx + angle.cos() * radius, cy + angle.sin() * radius).into()
+        })
+        .collect()
+}
+
+/// Number of segments for a circle of `radius` to look round, about one per 4 units of
+///   circumference.
+pub fn circle_segments(radius: f32) -> u16 {
+    (radius.abs() * std::f32::consts::TAU / 4.0)
+        .ceil()
+        .clamp(8.0, 128.0) as u16
+}
+
 impl<'a, 'b> RenderTarget<'a, 'b> {
     #[must_use]
     pub fn new(
@@ -107,6 +166,9 @@ impl<'a, 'b> RenderTarget<'a, 'b> {
         self.frame.finish()
     }
 
+    /// Draws a single line segment.
+    ///
+    /// Line width is set with [`DrawParameters::line_width`].
     pub fn line(
         &mut self,
         p1: impl Into<Vertex2>,
@@ -128,22 +190,24 @@ impl<'a, 'b> RenderTarget<'a, 'b> {
         self.frame.draw(&vertex_buffer, indices, &self.shaders.common, &uniform! { matrix: view, col: [color.r_f32(), color.g_f32(), color.b_f32(), color.a_f32()] }, &param).unwrap();
     }
 
+    /// Draws a batch of separate line segments, each with its own color, in a single draw call.
+    ///
+    /// Line width is set with [`DrawParameters::line_width`].
     pub fn lines(
         &mut self,
         lines: Vec<(impl Into<Vertex2>, impl Into<Vertex2>, Color)>,
         param: DrawParameters,
     ) {
-        let shape = lines
+        let segments = lines
             .into_iter()
-            .flat_map(|l| {
-                let a = l.0.into();
-                let b = l.1.into();
-                [
-                    Vertex2C { position: a.position, color: l.2.into() },
-                    Vertex2C { position: b.position, color: l.2.into() },
-                ]
-            })
+            .map(|(a, b, color)| (a.into(), b.into(), color))
             .collect::<Vec<_>>();
+        self.lines_colored(&segments, param);
+    }
+
+    /// Like [`RenderTarget::lines`], for segments that are already [`Vertex2`]s.
+    pub fn lines_colored(&mut self, segments: &[(Vertex2, Vertex2, Color)], param: DrawParameters) {
+        let shape = colored_line_vertices(segments);
 
         let model_view =
             *self.base_transform.stack.last().unwrap() * *self.transform.stack.last().unwrap();
@@ -163,6 +227,7 @@ impl<'a, 'b> RenderTarget<'a, 'b> {
             .unwrap();
     }
 
+    /// Draws connected line segments through `points`, blending colors between them.
     pub fn line_strip(&mut self, points: Vec<(impl Into<Vertex2>, Color)>, param: DrawParameters) {
         let shape = points
             .into_iter()
@@ -281,6 +346,84 @@ impl<'a, 'b> RenderTarget<'a, 'b> {
         }
     }
 
+    /// Draws the outline of a circle.
+    ///
+    /// If `segments` is [`None`], it's picked based on `radius` (see [`circle_segments`]).
+    pub fn circle(
+        &mut self,
+        center: impl Into<Vertex2>,
+        radius: f32,
+        segments: Option<u16>,
+        color: Color,
+        param: DrawParameters,
+    ) {
+        let shape = circle_vertices(center.into(), radius, segments);
+
+        let model_view =
+            *self.base_transform.stack.last().unwrap() * *self.transform.stack.last().unwrap();
+        let view: [[f32; 4]; 4] = model_view.into();
+
+        let vertex_buffer = glium::VertexBuffer::immutable(&self.display, &shape).unwrap();
+        let indices = NoIndices(glium::index::PrimitiveType::LineLoop);
+
+        self.frame.draw(&vertex_buffer, indices, &self.shaders.common, &uniform! { matrix: view, col: [color.r_f32(), color.g_f32(), color.b_f32(), color.a_f32()] }, &param).unwrap();
+    }
+
+    /// Draws a filled circle, or its outline if `param` uses [`PolygonMode::Line`].
+    ///
+    /// If `segments` is [`None`], it's picked based on `radius` (see [`circle_segments`]).
+    pub fn circle_filled(
+        &mut self,
+        center: impl Into<Vertex2>,
+        radius: f32,
+        segments: Option<u16>,
+        color: Color,
+        param: DrawParameters,
+    ) {
+        if param.polygon_mode == PolygonMode::Line {
+            self.circle(center, radius, segments, color, param);
+            return;
+        }
+
+        let shape = circle_vertices(center.into(), radius, segments);
+
+        let model_view =
+            *self.base_transform.stack.last().unwrap() * *self.transform.stack.last().unwrap();
+        let view: [[f32; 4]; 4] = model_view.into();
+
+        let vertex_buffer = glium::VertexBuffer::immutable(&self.display, &shape).unwrap();
+        let indices = NoIndices(glium::index::PrimitiveType::TriangleFan);
+
+        self.frame.draw(&vertex_buffer, indices, &self.shaders.common, &uniform! { matrix: view, col: [color.r_f32(), color.g_f32(), color.b_f32(), color.a_f32()] }, &param).unwrap();
+    }
+
+    /// Draws a convex polygon, or its outline if `param` uses [`PolygonMode::Line`].
+    pub fn polygon(&mut self, points: &[Vertex2], color: Color, param: DrawParameters) {
+        if points.len() < 3 {
+            return;
+        }
+
+        let model_view =
+            *self.base_transform.stack.last().unwrap() * *self.transform.stack.last().unwrap();
+        let view: [[f32; 4]; 4] = model_view.into();
+
+        let vertex_buffer = glium::VertexBuffer::immutable(&self.display, points).unwrap();
+        if param.polygon_mode == PolygonMode::Line {
+            let indices = NoIndices(glium::index::PrimitiveType::LineLoop);
+
+            self.frame.draw(&vertex_buffer, indices, &self.shaders.common, &uniform! { matrix: view, col: [color.r_f32(), color.g_f32(), color.b_f32(), color.a_f32()] }, &param).unwrap();
+        } else {
+            let indices = IndexBuffer::new(
+                &self.display,
+                glium::index::PrimitiveType::TrianglesList,
+                &convex_fan_indices(points.len()),
+            )
+            .unwrap();
+
+            self.frame.draw(&vertex_buffer, &indices, &self.shaders.common, &uniform! { matrix: view, col: [color.r_f32(), color.g_f32(), color.b_f32(), color.a_f32()] }, &param).unwrap();
+        }
+    }
+
     pub fn rectangles(&mut self, rects: &[Rect<f32>], color: Color, param: DrawParameters) {
         let model_view =
             *self.base_transform.stack.last().unwrap() * *self.transform.stack.last().unwrap();
@@ -446,6 +589,41 @@ impl<'a, 'b> RenderTarget<'a, 'b> {
         }
     }
 
+    /// Draws `tex` stretched over `dest` rotated by `rotation` radians around its center, with
+    ///   its colors multiplied by `color_mul`.
+    #[profiling::function]
+    pub fn texture(
+        &mut self,
+        tex: &Texture2d,
+        dest: Rect<f32>,
+        rotation: f32,
+        color_mul: Color,
+        param: DrawParameters,
+    ) {
+        let shape = rotated_quad(dest, rotation)
+            .into_iter()
+            .zip([[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]])
+            .map(|(v, t)| Vertex2T::from(((v.position[0], v.position[1]), (t[0], t[1]))))
+            .collect::<Vec<_>>();
+
+        let model_view =
+            *self.base_transform.stack.last().unwrap() * *self.transform.stack.last().unwrap();
+        let view: [[f32; 4]; 4] = model_view.into();
+
+        let vertex_buffer = glium::VertexBuffer::immutable(&self.display, &shape).unwrap();
+        let indices = IndexBuffer::new(
+            &self.display,
+            glium::index::PrimitiveType::TriangleStrip,
+            &[1_u16, 2, 0, 3],
+        )
+        .unwrap();
+
+        {
+            profiling::scope!("draw");
+            self.frame.draw(&vertex_buffer, &indices, &self.shaders.texture_tinted, &uniform! { matrix: view, col: [color_mul.r_f32(), color_mul.g_f32(), color_mul.b_f32(), color_mul.a_f32()], tex: tex.sampled().magnify_filter(glium::uniforms::MagnifySamplerFilter::Nearest) }, &param).unwrap();
+        }
+    }
+
     #[profiling::function]
     pub fn draw_texture_flipped(
         &mut self,
@@ -599,7 +777,45 @@ impl<'a, 'b> RenderTarget<'a, 'b> {
                 matrix: view,
                 c_pos: *p,
                 tex: data.texture.sampled().magnify_filter(glium::uniforms::MagnifySamplerFilter::Nearest),
-                tex_bg: data.background_texture.sampled().magnify_filter(glium::uniforms::MagnifySamplerFilter::Nearest),
+            }, &params).unwrap();
+        }
+    }
+
+    /// Draws chunk background layer textures at the given world positions, in order.
+    ///
+    /// Should be drawn before [`RenderTarget::draw_chunks`] so the foreground covers it.
+    pub fn draw_chunks_background(&mut self, layers: &[((f32, f32), Arc<Texture2d>)]) {
+        profiling::scope!("RenderTarget::draw_chunks_background");
+
+        let model_view =
+            *self.base_transform.stack.last().unwrap() * *self.transform.stack.last().unwrap();
+        let view: [[f32; 4]; 4] = model_view.into();
+
+        let shape = Rect::<f32>::new(0.0, 0.0, CHUNK_SIZE as f32, CHUNK_SIZE as f32)
+            .vertices()
+            .into_iter()
+            .zip([[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]])
+            .map(Vertex2T::from)
+            .collect::<Vec<_>>();
+        let vertex_buffer = glium::VertexBuffer::immutable(&self.display, &shape).unwrap();
+        let indices = IndexBuffer::new(
+            &self.display,
+            glium::index::PrimitiveType::TriangleStrip,
+            &[1_u16, 2, 0, 3],
+        )
+        .unwrap();
+
+        let params = DrawParameters {
+            blend: Blend::alpha_blending(),
+            ..DrawParameters::default()
+        };
+
+        for (p, texture) in layers {
+            profiling::scope!("draw chunk background");
+            self.frame.draw(&vertex_buffer, &indices, &self.shaders.chunk_background, &uniform! {
+                matrix: view,
+                c_pos: *p,
+                tex_bg: texture.sampled().magnify_filter(glium::uniforms::MagnifySamplerFilter::Nearest),
             }, &params).unwrap();
         }
     }
@@ -667,3 +883,90 @@ impl<'a, 'b> RenderTarget<'a, 'b> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use fs_common::game::common::world::material::color::Color;
+
+    use fs_common::game::common::Rect;
+
+    use super::{
+        circle_segments, circle_vertices, colored_line_vertices, convex_fan_indices, rotated_quad,
+        Vertex2,
+    };
+
+    #[test]
+    fn circle_vertex_count() {
+        let verts = circle_vertices((10.0_f32, 20.0_f32).into(), 2.0, Some(12));
+        assert_eq!(verts.len(), 12);
+        for v in &verts {
+            let [x, y] = v.position;
+            let dist = ((x - 10.0).powi(2) + (y - 20.0).powi(2)).sqrt();
+            assert!((dist - 2.0).abs() < 0.001, "{dist}");
+        }
+
+        assert_eq!(
+            circle_vertices((0.0_f32, 0.0_f32).into(), 2.0, None).len(),
+            8
+        );
+        assert_eq!(
+            circle_vertices((0.0_f32, 0.0_f32).into(), 100.0, None).len(),
+            usize::from(circle_segments(100.0))
+        );
+        assert!(circle_segments(100.0) > circle_segments(20.0));
+    }
+
+    #[test]
+    fn colored_line_vertex_count() {
+        let segments = [
+            (
+                (0.0_f32, 0.0_f32).into(),
+                (1.0_f32, 0.0_f32).into(),
+                Color::RED,
+            ),
+            (
+                (1.0_f32, 0.0_f32).into(),
+                (1.0_f32, 1.0_f32).into(),
+                Color::GREEN,
+            ),
+            (
+                (1.0_f32, 1.0_f32).into(),
+                (0.0_f32, 0.0_f32).into(),
+                Color::BLUE,
+            ),
+        ];
+        let verts = colored_line_vertices(&segments);
+        assert_eq!(verts.len(), 2 * segments.len());
+        assert_eq!(verts[2].position, [1.0, 0.0]);
+        assert_eq!(verts[3].position, [1.0, 1.0]);
+        assert!(colored_line_vertices(&[]).is_empty());
+    }
+
+    #[test]
+    fn rotated_quad_corners() {
+        let dest = Rect::new_wh(0.0_f32, 0.0_f32, 4.0_f32, 2.0_f32);
+        let close = |v: Vertex2, x: f32, y: f32| {
+            (v.position[0] - x).abs() < 0.001 && (v.position[1] - y).abs() < 0.001
+        };
+
+        let quad = rotated_quad(dest, 0.0);
+        assert!(close(quad[0], 0.0, 2.0));
+        assert!(close(quad[2], 4.0, 0.0));
+
+        // a quarter turn around (2, 1)
+        let quad = rotated_quad(dest, std::f32::consts::FRAC_PI_2);
+        assert!(close(quad[0], 1.0, -1.0), "{:?}", quad[0].position);
+        assert!(close(quad[2], 3.0, 3.0), "{:?}", quad[2].position);
+    }
+
+    #[test]
+    fn convex_fan() {
+        let indices = convex_fan_indices(5);
+        assert_eq!(indices.len(), 9);
+        assert_eq!(indices, vec![0, 1, 2, 0, 2, 3, 0, 3, 4]);
+
+        assert_eq!(convex_fan_indices(3), vec![0, 1, 2]);
+        assert!(convex_fan_indices(2).is_empty());
+        assert!(convex_fan_indices(0).is_empty());
+    }
+}
@@ -1,6 +1,9 @@
 use std::{borrow::Borrow, sync::Arc};
 
-use fs_common::game::common::{ChunkCollisionOverlay, Registries, Settings};
+use fs_common::game::common::{
+    world::{light_scales, particle::ParticleOverflowPolicy},
+    ChunkCollisionOverlay, GraphicsPreset, Registries, Settings, MAX_SIMULATION_SUBSTEPS,
+};
 
 pub trait DebugUI {
     fn debug_ui(&mut self, ui: &mut egui::Ui, registries: Arc<Registries>);
@@ -10,6 +13,22 @@ impl DebugUI for Settings {
     #[profiling::function]
     fn debug_ui(&mut self, ui: &mut egui::Ui, registries: Arc<Registries>) {
         ui.collapsing("rendering", |ui| {
+            egui::ComboBox::from_label("graphics_preset")
+                .selected_text(format!("{:?}", self.graphics_preset))
+                .show_ui(ui, |ui| {
+                    for v in GraphicsPreset::values() {
+                        if ui
+                            .selectable_label(self.graphics_preset == *v, format!("{v:?}"))
+                            .clicked()
+                        {
+                            self.apply_graphics_preset(*v);
+                        }
+                    }
+                });
+
+            // changing anything covered by the presets by hand makes it a custom preset
+            let mut preset_changed = false;
+
             ui.checkbox(
                 &mut self.draw_chunk_state_overlay,
                 "draw_chunk_state_overlay",
@@ -51,13 +70,73 @@ impl DebugUI for Settings {
                     }
                 });
 
-            ui.checkbox(&mut self.draw_lighting, "draw_lighting");
-            ui.checkbox(&mut self.lighting_smooth, "lighting_smooth");
-            ui.checkbox(&mut self.lighting_dithering, "lighting_dithering");
+            preset_changed |= ui
+                .checkbox(&mut self.draw_lighting, "draw_lighting")
+                .changed();
+            preset_changed |= ui
+                .checkbox(&mut self.lighting_smooth, "lighting_smooth")
+                .changed();
+            preset_changed |= ui
+                .checkbox(&mut self.lighting_dithering, "lighting_dithering")
+                .changed();
             ui.checkbox(&mut self.lighting_overlay, "lighting_overlay");
             ui.checkbox(&mut self.lighting_linear_blend, "lighting_linear_blend");
+            preset_changed |= ui
+                .checkbox(
+                    &mut self.lighting_active_chunks_only,
+                    "lighting_active_chunks_only",
+                )
+                .changed();
+            egui::ComboBox::from_label("light_scale")
+                .selected_text(format!("{}", self.light_scale))
+                .show_ui(ui, |ui| {
+                    for v in light_scales() {
+                        preset_changed |= ui
+                            .selectable_value(&mut self.light_scale, v, format!("{v}"))
+                            .changed();
+                    }
+                });
+
+            ui.add(
+                egui::Slider::new(&mut self.background_parallax, 0.0..=1.0)
+                    .text("background_parallax")
+                    .clamp_to_range(true),
+            );
+
+            ui.checkbox(&mut self.draw_minimap, "draw_minimap");
+            ui.add(egui::Slider::new(&mut self.minimap_radius, 1..=64).text("minimap_radius"));
+
+            preset_changed |= ui.checkbox(&mut self.cull_chunks, "cull_chunks").changed();
+            preset_changed |= ui
+                .add(
+                    egui::Slider::new(&mut self.mesh_simplify_epsilon, 0.0..=4.0)
+                        .text("mesh_simplify_epsilon")
+                        .clamp_to_range(true),
+                )
+                .changed();
+            preset_changed |= ui
+                .add(
+                    egui::Slider::new(&mut self.max_particles, 1_000..=500_000)
+                        .text("max_particles")
+                        .logarithmic(true)
+                        .clamp_to_range(true),
+                )
+                .changed();
+            egui::ComboBox::from_label("particle_overflow_policy")
+                .selected_text(format!("{:?}", self.particle_overflow_policy))
+                .show_ui(ui, |ui| {
+                    for v in ParticleOverflowPolicy::values() {
+                        ui.selectable_value(
+                            &mut self.particle_overflow_policy,
+                            *v,
+                            format!("{v:?}"),
+                        );
+                    }
+                });
 
-            ui.checkbox(&mut self.cull_chunks, "cull_chunks");
+            if preset_changed {
+                self.graphics_preset = GraphicsPreset::Custom;
+            }
 
             egui::ComboBox::from_label("draw_chunk_collision")
                 .selected_text(format!("{:?}", self.draw_chunk_collision))
@@ -108,6 +187,12 @@ impl DebugUI for Settings {
                 if ui.button("reset##tick_speed").clicked() {
                     self.tick_speed = 30;
                 }
+
+                ui.add_enabled_ui(!self.tick, |ui| {
+                    if ui.button("step").clicked() {
+                        self.tick_steps += 1;
+                    }
+                });
             });
 
             ui.checkbox(&mut self.tick_physics, "tick_physics");
@@ -134,8 +219,31 @@ impl DebugUI for Settings {
 
             ui.checkbox(&mut self.load_chunks, "load_chunks");
             ui.checkbox(&mut self.simulate_chunks, "simulate_chunks");
+            ui.indent("simulate_chunks#indent", |ui| {
+                ui.add(
+                    egui::Slider::new(&mut self.simulation_substeps, 1..=MAX_SIMULATION_SUBSTEPS)
+                        .text("simulation_substeps")
+                        .clamp_to_range(true),
+                );
+            });
             ui.checkbox(&mut self.simulate_particles, "simulate_particles");
+            ui.checkbox(&mut self.simulate_heat, "simulate_heat");
             ui.checkbox(&mut self.pause_on_lost_focus, "pause_on_lost_focus");
+
+            ui.checkbox(&mut self.autosave, "autosave");
+            ui.indent("autosave#indent", |ui| {
+                ui.add(
+                    egui::Slider::new(&mut self.autosave_interval, 5..=600)
+                        .text("autosave_interval")
+                        .clamp_to_range(true),
+                );
+            });
         });
+
+        ui.add_enabled(
+            cfg!(feature = "profile-puffin"),
+            egui::Checkbox::new(&mut self.profiling, "profiling"),
+        )
+        .on_disabled_hover_text("requires building with the profile-puffin feature");
     }
 }
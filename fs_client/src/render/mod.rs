@@ -1,4 +1,6 @@
 pub mod drawing;
+#[cfg(feature = "profile-puffin")]
+pub mod profiler;
 mod renderer;
 pub mod rigidbody;
 pub mod shaders;
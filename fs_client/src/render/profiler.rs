@@ -0,0 +1,131 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use puffin::{FrameData, FrameSinkId, GlobalProfiler, Reader, Stream};
+
+/// Time spent in one profiler scope over a frame, summed over every call on every thread.
+#[derive(Debug, Clone, Default)]
+pub struct ScopeTime {
+    pub id: String,
+    /// Time spent in the scope itself, not counting its child scopes.
+    pub self_ns: i64,
+    pub calls: usize,
+}
+
+/// Keeps the most recent frame recorded by puffin and summarizes its scopes for the debug UI.
+///
+/// Scopes are only recorded while [`puffin::are_scopes_on`], which the game loop syncs with
+///   [`fs_common::game::common::Settings::profiling`].
+pub struct ProfilerSummary {
+    latest: Arc<Mutex<Option<Arc<FrameData>>>>,
+    sink: FrameSinkId,
+}
+
+impl ProfilerSummary {
+    pub fn new() -> Self {
+        let latest = Arc::new(Mutex::new(None));
+
+        let sink_latest = latest.clone();
+        let sink = GlobalProfiler::lock().add_sink(Box::new(move |frame| {
+            *sink_latest.lock().unwrap() = Some(frame);
+        }));
+
+        Self { latest, sink }
+    }
+
+    /// The `n` scopes with the most self time in the last finished frame, most expensive first.
+    pub fn top_scopes(&self, n: usize) -> Vec<ScopeTime> {
+        let Some(frame) = self.latest.lock().unwrap().clone() else {
+            return vec![];
+        };
+
+        let unpacked = match frame.unpacked() {
+            Ok(unpacked) => unpacked,
+            Err(e) => {
+                log::warn!("Failed to unpack profiler frame: {e}");
+                return vec![];
+            },
+        };
+
+        let mut totals = HashMap::new();
+        for stream_info in unpacked.thread_streams.values() {
+            if let Err(e) = collect_self_times(&stream_info.stream, 0, &mut totals) {
+                log::warn!("Failed to read profiler stream: {e}");
+            }
+        }
+
+        let mut scopes: Vec<ScopeTime> = totals.into_values().collect();
+        scopes.sort_by(|a, b| b.self_ns.cmp(&a.self_ns));
+        scopes.truncate(n);
+        scopes
+    }
+
+    pub fn ui(&self, ui: &mut egui::Ui) {
+        let scopes = self.top_scopes(20);
+        if scopes.is_empty() {
+            ui.label("no frames recorded yet");
+            return;
+        }
+
+        egui::Grid::new("profiler_scopes")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("scope");
+                ui.label("self ms");
+                ui.label("calls");
+                ui.end_row();
+
+                for scope in scopes {
+                    ui.label(scope.id);
+                    ui.label(format!("{:.3}", scope.self_ns as f64 / 1_000_000.0));
+                    ui.label(format!("{}", scope.calls));
+                    ui.end_row();
+                }
+            });
+    }
+}
+
+impl Default for ProfilerSummary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for ProfilerSummary {
+    fn drop(&mut self) {
+        GlobalProfiler::lock().remove_sink(self.sink);
+    }
+}
+
+/// Adds the self time of every scope starting at `offset` (and their children) to `totals`.
+///
+/// Returns the total duration of the scopes at this level, so the caller can subtract it from its
+///   own duration.
+fn collect_self_times(
+    stream: &Stream,
+    offset: u64,
+    totals: &mut HashMap<String, ScopeTime>,
+) -> puffin::Result<i64> {
+    let mut total_ns = 0;
+
+    for scope in Reader::with_offset(stream, offset)? {
+        let scope = scope?;
+        let children_ns = collect_self_times(stream, scope.child_begin_position, totals)?;
+
+        let time = totals
+            .entry(scope.record.id.to_owned())
+            .or_insert_with(|| ScopeTime {
+                id: scope.record.id.to_owned(),
+                ..ScopeTime::default()
+            });
+        time.self_ns += scope.record.duration_ns - children_ns;
+        time.calls += 1;
+
+        total_ns += scope.record.duration_ns;
+    }
+
+    Ok(total_ns)
+}
@@ -3,7 +3,9 @@ use std::fs;
 use egui::{plot::HLine, Align2, RichText, WidgetText};
 use fs_common::game::{
     common::{
-        world::{entity::Player, material::color::Color, Position, Velocity},
+        world::{
+            entity::Player, material::color::Color, particle::ParticleSystem, Position, Velocity,
+        },
         FileHelper, Rect,
     },
     GameData,
@@ -14,7 +16,7 @@ use glium_glyph::{
     GlyphBrush, GlyphBrushBuilder,
 };
 use glutin::{dpi::LogicalSize, event_loop::EventLoop};
-use specs::{ReadStorage, WriteStorage};
+use specs::{ReadStorage, WorldExt, WriteStorage};
 
 use crate::{
     render::egui::DebugUI,
@@ -32,6 +34,8 @@ pub struct Renderer<'a> {
     pub display: Display,
     pub world_renderer: WorldRenderer,
     pub egui_glium: egui_glium::EguiGlium,
+    #[cfg(feature = "profile-puffin")]
+    pub profiler: super::profiler::ProfilerSummary,
     // pub version_info_cache_1: Option<(u32, u32, GPUImage)>,
     // pub version_info_cache_2: Option<(u32, u32, GPUImage)>,
 }
@@ -79,6 +83,8 @@ impl<'a> Renderer<'a> {
             display,
             world_renderer: WorldRenderer::new(),
             egui_glium,
+            #[cfg(feature = "profile-puffin")]
+            profiler: super::profiler::ProfilerSummary::new(),
             // version_info_cache_1: None,
             // version_info_cache_2: None,
         })
@@ -180,6 +186,13 @@ impl<'a> Renderer<'a> {
                             game.settings.debug_ui(ui, game.registries.clone());
                         });
 
+                    #[cfg(feature = "profile-puffin")]
+                    if game.settings.profiling {
+                        egui::Window::new("Profiler")
+                            .default_width(300.0)
+                            .show(egui_ctx, |ui| self.profiler.ui(ui));
+                    }
+
                     // TODO: this should be somewhere better
                     // maybe clone the Settings before each frame and at the end compare it?
 
@@ -219,6 +232,32 @@ impl<'a> Renderer<'a> {
 
                         ui.label(text);
 
+                        if let Some(connection) = &client.connection {
+                            ui.label(format!(
+                                "server: {} ({:?})",
+                                connection.addr,
+                                connection.state(),
+                            ));
+                        }
+
+                        if let Some(world) = &game.world {
+                            let chunk_handler = &world.chunk_handler;
+                            let active = chunk_handler.active_chunks().count();
+                            ui.label(format!(
+                                "chunks: {} loaded, {} active, {} queued",
+                                chunk_handler.manager.len(),
+                                active,
+                                chunk_handler.load_queue.len(),
+                            ));
+
+                            let particles = world.ecs.read_resource::<ParticleSystem>();
+                            ui.label(format!(
+                                "particles: {} active, {} sleeping",
+                                particles.active.len(),
+                                particles.sleeping.len(),
+                            ));
+                        }
+
                         let nums: Vec<f32> = game
                             .fps_counter
                             .frame_times
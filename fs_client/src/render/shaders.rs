@@ -1,6 +1,9 @@
 use std::fs;
 
-use fs_common::game::common::FileHelper;
+use fs_common::game::common::{
+    world::{light_size, LIGHT_SCALE},
+    FileHelper,
+};
 use glium::{
     program::{ComputeShader, ProgramChooserCreationError},
     Display, ProgramCreationError,
@@ -10,12 +13,17 @@ pub struct Shaders {
     pub common: glium::Program,
     pub vertex_colors: glium::Program,
     pub texture: glium::Program,
+    /// Like [`Shaders::texture`], but multiplied by the `col` uniform.
+    pub texture_tinted: glium::Program,
     pub texture_array: glium::Program,
     pub particle: glium::Program,
     pub chunk: glium::Program,
+    pub chunk_background: glium::Program,
     pub chunk_light: glium::Program,
     pub lighting_compute_propagate: ComputeShader,
     pub lighting_compute_prep: ComputeShader,
+    /// The light scale the lighting compute shaders were built for, see [`Shaders::set_light_scale`].
+    pub light_scale: u8,
 }
 
 impl Shaders {
@@ -41,6 +49,13 @@ impl Shaders {
                     "data/shaders/textured.frag",
                 )
                 .unwrap(),
+            texture_tinted: helper
+                .load_from_files(
+                    140,
+                    "data/shaders/textured.vert",
+                    "data/shaders/textured_tinted.frag",
+                )
+                .unwrap(),
             texture_array: helper
                 .load_from_files(
                     140,
@@ -58,6 +73,13 @@ impl Shaders {
             chunk: helper
                 .load_from_files(140, "data/shaders/chunk.vert", "data/shaders/chunk.frag")
                 .unwrap(),
+            chunk_background: helper
+                .load_from_files(
+                    140,
+                    "data/shaders/chunk.vert",
+                    "data/shaders/chunk_background.frag",
+                )
+                .unwrap(),
             chunk_light: helper
                 .load_from_files(
                     140,
@@ -66,12 +88,41 @@ impl Shaders {
                 )
                 .unwrap(),
             lighting_compute_propagate: helper
-                .load_compute_from_files("data/shaders/lighting_propagate.comp")
+                .load_lighting_compute_from_files(
+                    "data/shaders/lighting_propagate.comp",
+                    LIGHT_SCALE,
+                )
                 .unwrap(),
             lighting_compute_prep: helper
-                .load_compute_from_files("data/shaders/lighting_prep.comp")
+                .load_lighting_compute_from_files("data/shaders/lighting_prep.comp", LIGHT_SCALE)
                 .unwrap(),
+            light_scale: LIGHT_SCALE,
+        }
+    }
+
+    /// Rebuilds the lighting compute shaders if they weren't built for `light_scale`.
+    ///
+    /// `light_scale` must be accepted by [`light_size`].
+    pub fn set_light_scale(
+        &mut self,
+        display: &Display,
+        file_helper: &FileHelper,
+        light_scale: u8,
+    ) {
+        if self.light_scale == light_scale {
+            return;
         }
+
+        profiling::scope!("Shaders::set_light_scale");
+        let helper = ShaderFileHelper { file_helper, display };
+
+        self.lighting_compute_propagate = helper
+            .load_lighting_compute_from_files("data/shaders/lighting_propagate.comp", light_scale)
+            .unwrap();
+        self.lighting_compute_prep = helper
+            .load_lighting_compute_from_files("data/shaders/lighting_prep.comp", light_scale)
+            .unwrap();
+        self.light_scale = light_scale;
     }
 }
 
@@ -110,4 +161,23 @@ impl ShaderFileHelper<'_> {
 
         ComputeShader::from_source(self.display, &src)
     }
+
+    /// Like [`ShaderFileHelper::load_compute_from_files`], but with `LIGHT_SIZE` defined as the
+    ///   lighting grid size for `light_scale`.
+    #[profiling::function]
+    pub fn load_lighting_compute_from_files(
+        &self,
+        src: &str,
+        light_scale: u8,
+    ) -> Result<glium::program::ComputeShader, ProgramCreationError> {
+        let light_size =
+            light_size(light_scale).unwrap_or_else(|| panic!("Invalid light scale: {light_scale}"));
+
+        let src = fs::read_to_string(self.file_helper.asset_path(src)).unwrap();
+        // defines have to come after #version
+        let (version, rest) = src.split_once('\n').unwrap_or((&src, ""));
+        let src = format!("{version}\n#define LIGHT_SIZE {light_size}\n{rest}");
+
+        ComputeShader::from_source(self.display, &src)
+    }
 }
@@ -1,16 +1,56 @@
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, time::Instant};
 
 use egui::TextureOptions;
 use fs_common::game::common::{
     registry::RegistryID,
-    world::material::placer::{self, MaterialPlacer, MaterialPlacerSampler},
+    world::{
+        chunk_access::FSChunkAccess,
+        chunk_handler::ChunkHandler,
+        material::{
+            placer::{self, MaterialPlacer, MaterialPlacerSampler},
+            MaterialInstance,
+        },
+    },
+    Rect, Registries,
 };
 
+use crate::world::ClientChunk;
+
 use super::DebugUIsContext;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrushShape {
+    Square,
+    Circle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawTool {
+    /// Paint continuously while dragging.
+    Brush,
+    /// Drag to define opposite corners of a rectangle.
+    Rectangle,
+    /// Drag to define the start and end of a line, `brush_size` pixels thick.
+    Line,
+}
+
 pub struct DrawUI {
     textures: BTreeMap<RegistryID<MaterialPlacer>, egui::TextureHandle>,
     pub selected: RegistryID<MaterialPlacer>,
+    /// Width of the brush in pixels.
+    pub brush_size: u16,
+    pub brush_shape: BrushShape,
+    pub tool: DrawTool,
+    /// If true, the rectangle tool only draws the border.
+    pub rect_outline: bool,
+    /// World position where the current rectangle/line drag started.
+    pub drag_start: Option<(i64, i64)>,
+    /// If true, clicking picks the placer for the material under the cursor instead of drawing.
+    pub eyedropper: bool,
+    /// If true, tools draw on (and the eyedropper picks from) the background layer.
+    pub background: bool,
+    message: Option<(String, Instant)>,
+    search: String,
 }
 
 impl DrawUI {
@@ -19,6 +59,124 @@ impl DrawUI {
         Self {
             textures: BTreeMap::new(),
             selected: placer::AIR_PLACER.clone(),
+            brush_size: 7,
+            brush_shape: BrushShape::Square,
+            tool: DrawTool::Brush,
+            rect_outline: false,
+            drag_start: None,
+            eyedropper: false,
+            background: false,
+            message: None,
+            search: String::new(),
+        }
+    }
+
+    /// Selects the placer that produces `mat` at world position (`x`, `y`), if there is one.
+    ///
+    /// Returns `false` (and leaves the selection unchanged) if no placer matches.
+    pub fn pick_from_world(
+        &mut self,
+        mat: &MaterialInstance,
+        x: i64,
+        y: i64,
+        registries: &Registries,
+    ) -> bool {
+        let found = registries
+            .material_placers
+            .into_iter()
+            .find(|(_, placer)| {
+                let sample = placer.pixel(x, y);
+                sample.material_id == mat.material_id && sample.physics == mat.physics
+            })
+            .map(|(id, _)| id.clone());
+
+        if let Some(id) = found {
+            self.selected = id;
+            true
+        } else {
+            self.message = Some((
+                format!("No placer for material {:?}", mat.material_id),
+                Instant::now(),
+            ));
+            false
+        }
+    }
+
+    /// Offsets (relative to the cursor) of every pixel covered by the current brush.
+    pub fn brush_offsets(&self) -> Vec<(i64, i64)> {
+        let size = i64::from(self.brush_size.max(1));
+        let start = -(size - 1) / 2;
+        let end = start + size - 1;
+        let radius_sq = (size as f64 / 2.0).powi(2);
+
+        let mut offsets = Vec::with_capacity((size * size) as usize);
+        for dy in start..=end {
+            for dx in start..=end {
+                let inside = match self.brush_shape {
+                    BrushShape::Square => true,
+                    BrushShape::Circle => (dx * dx + dy * dy) as f64 <= radius_sq,
+                };
+                if inside {
+                    offsets.push((dx, dy));
+                }
+            }
+        }
+        offsets
+    }
+
+    /// Sets the pixel at (`x`, `y`) on the targeted layer using `placer`.
+    pub fn paint(
+        &self,
+        chunk_handler: &mut ChunkHandler<ClientChunk>,
+        x: i64,
+        y: i64,
+        placer: &dyn MaterialPlacerSampler,
+    ) -> Result<(), String> {
+        if self.background {
+            chunk_handler.set_background(x, y, placer.pixel(x, y))
+        } else {
+            chunk_handler.set_pixel(x, y, placer.pixel(x, y))
+        }
+    }
+
+    /// Region that [`DrawUI::draw_shape`] may modify for a drag from `start` to `end`.
+    pub fn shape_bounds(&self, start: (i64, i64), end: (i64, i64)) -> Rect<i64> {
+        let rect = Rect::new(
+            start.0.min(end.0),
+            start.1.min(end.1),
+            start.0.max(end.0) + 1,
+            start.1.max(end.1) + 1,
+        );
+
+        match self.tool {
+            DrawTool::Line => rect.inflated(i64::from(self.brush_size)),
+            DrawTool::Brush | DrawTool::Rectangle => rect,
+        }
+    }
+
+    /// Draws the current tool's shape for a drag from `start` to `end`.
+    ///
+    /// Does nothing for [`DrawTool::Brush`], which paints while dragging instead.
+    pub fn draw_shape(
+        &self,
+        chunk_handler: &mut ChunkHandler<ClientChunk>,
+        start: (i64, i64),
+        end: (i64, i64),
+        placer: &dyn MaterialPlacerSampler,
+    ) {
+        match self.tool {
+            DrawTool::Brush => {},
+            DrawTool::Rectangle => {
+                chunk_handler.fill_rect(
+                    self.shape_bounds(start, end),
+                    placer,
+                    self.rect_outline,
+                    self.background,
+                );
+            },
+            DrawTool::Line => {
+                chunk_handler.draw_line(start, end, self.brush_size, placer, self.background);
+            },
         }
     }
 
@@ -36,33 +194,75 @@ impl DrawUI {
         egui::Window::new("Draw")
             .resizable(false)
             .show(egui_ctx, |ui| {
-                ui.with_layout(
-                    egui::Layout::left_to_right(egui::Align::Min)
-                        .with_cross_align(egui::Align::Min)
-                        .with_main_wrap(true),
-                    |ui| {
-                        for (id, tex) in &self.textures {
-                            if ui
-                                .add(
-                                    egui::ImageButton::new(tex, (40.0, 40.0))
-                                        .selected(*id == self.selected),
-                                )
-                                .on_hover_text(
-                                    ctx.registries
-                                        .material_placers
-                                        .get(id)
-                                        .unwrap()
-                                        .meta
-                                        .display_name
-                                        .to_string(),
-                                )
-                                .clicked()
-                            {
-                                self.selected = id.clone();
-                            };
-                        }
-                    },
-                );
+                ui.horizontal(|ui| {
+                    ui.add(egui::Slider::new(&mut self.brush_size, 1..=64).text("Size"));
+                    ui.selectable_value(&mut self.brush_shape, BrushShape::Square, "Square");
+                    ui.selectable_value(&mut self.brush_shape, BrushShape::Circle, "Circle");
+                    ui.toggle_value(&mut self.eyedropper, "Eyedropper");
+                    ui.toggle_value(&mut self.background, "Background");
+                });
+
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.tool, DrawTool::Brush, "Brush");
+                    ui.selectable_value(&mut self.tool, DrawTool::Rectangle, "Rectangle");
+                    ui.selectable_value(&mut self.tool, DrawTool::Line, "Line");
+                    if self.tool == DrawTool::Rectangle {
+                        ui.checkbox(&mut self.rect_outline, "Outline");
+                    }
+                });
+
+                if let Some((msg, time)) = &self.message {
+                    if time.elapsed().as_secs() < 3 {
+                        ui.colored_label(egui::Color32::YELLOW, msg);
+                    } else {
+                        self.message = None;
+                    }
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("Search:");
+                    ui.text_edit_singleline(&mut self.search);
+                });
+
+                // group placers by category, skipping ones that don't match the search
+                let search = self.search.to_lowercase();
+                let mut categories: BTreeMap<&str, Vec<_>> = BTreeMap::new();
+                for (id, tex) in &self.textures {
+                    let meta = &ctx.registries.material_placers.get(id).unwrap().meta;
+                    if meta.display_name.to_lowercase().contains(&search) {
+                        categories
+                            .entry(meta.category.as_str())
+                            .or_default()
+                            .push((id, tex, meta));
+                    }
+                }
+
+                for (category, placers) in categories {
+                    egui::CollapsingHeader::new(category)
+                        .default_open(true)
+                        .open((!search.is_empty()).then_some(true))
+                        .show(ui, |ui| {
+                            ui.with_layout(
+                                egui::Layout::left_to_right(egui::Align::Min)
+                                    .with_cross_align(egui::Align::Min)
+                                    .with_main_wrap(true),
+                                |ui| {
+                                    for (id, tex, meta) in placers {
+                                        if ui
+                                            .add(
+                                                egui::ImageButton::new(tex, (40.0, 40.0))
+                                                    .selected(*id == self.selected),
+                                            )
+                                            .on_hover_text(meta.display_name.to_string())
+                                            .clicked()
+                                        {
+                                            self.selected = id.clone();
+                                        };
+                                    }
+                                },
+                            );
+                        });
+                }
             });
     }
 }
@@ -101,3 +301,87 @@ fn gen_material_preview(placer: &dyn MaterialPlacerSampler) -> egui::ColorImage
     }
     img
 }
+
+#[cfg(test)]
+mod tests {
+    use fs_common::game::common::{
+        world::material::{
+            self,
+            color::Color,
+            placer::{self, MaterialPlacer, MaterialPlacerMeta},
+            PhysicsType,
+        },
+        Registries,
+    };
+
+    use super::{BrushShape, DrawUI};
+
+    #[test]
+    fn brush_offsets() {
+        let mut draw = DrawUI::new();
+
+        draw.brush_size = 1;
+        assert_eq!(draw.brush_offsets(), vec![(0, 0)]);
+
+        draw.brush_size = 3;
+        draw.brush_shape = BrushShape::Square;
+        let offsets = draw.brush_offsets();
+        assert_eq!(offsets.len(), 9);
+        assert!(offsets.contains(&(-1, -1)) && offsets.contains(&(1, 1)));
+
+        // radius 2 disc: a 5x5 square without its corners
+        draw.brush_size = 5;
+        draw.brush_shape = BrushShape::Circle;
+        let offsets = draw.brush_offsets();
+        assert_eq!(offsets.len(), 21);
+        for corner in [(-2, -2), (2, -2), (-2, 2), (2, 2)] {
+            assert!(!offsets.contains(&corner), "{corner:?}");
+        }
+        for edge in [(0, -2), (2, 0), (2, 1), (-1, 2)] {
+            assert!(offsets.contains(&edge), "{edge:?}");
+        }
+    }
+
+    #[test]
+    fn pick_from_world() {
+        let mut registries = Registries::empty();
+        for (id, material_id, physics) in [
+            (
+                placer::TEST_PLACER_1.clone(),
+                &material::TEST,
+                PhysicsType::Solid,
+            ),
+            (
+                placer::TEST_PLACER_2.clone(),
+                &material::TEST,
+                PhysicsType::Sand,
+            ),
+            (placer::GLASS.clone(), &material::GLASS, PhysicsType::Solid),
+        ] {
+            let display_name = format!("{id:?}");
+            registries.material_placers.register(
+                id,
+                MaterialPlacer {
+                    meta: MaterialPlacerMeta { display_name, category: "test".to_owned() },
+                    sampler: Box::new(material_id.instance(physics, Color::WHITE)),
+                },
+            );
+        }
+
+        let mut draw = DrawUI::new();
+
+        // color doesn't need to match
+        let sand = material::TEST.instance(PhysicsType::Sand, Color::RED);
+        assert!(draw.pick_from_world(&sand, 3, 4, &registries));
+        assert_eq!(draw.selected, *placer::TEST_PLACER_2);
+
+        let glass = material::GLASS.instance(PhysicsType::Solid, Color::BLUE);
+        assert!(draw.pick_from_world(&glass, 0, 0, &registries));
+        assert_eq!(draw.selected, *placer::GLASS);
+
+        // no placer for this, so the selection stays
+        let acid = material::ACID.instance(PhysicsType::Liquid, Color::GREEN);
+        assert!(!draw.pick_from_world(&acid, 0, 0, &registries));
+        assert_eq!(draw.selected, *placer::GLASS);
+    }
+}
@@ -72,6 +72,7 @@ impl RegistriesUI {
                         for (id, placer) in &ctx.registries.material_placers {
                             ui.collapsing(format!("{id}"), |ui| {
                                 ui.label(format!("display_name = {}", placer.meta.display_name));
+                                ui.label(format!("category = {}", placer.meta.category));
                             });
                         }
                     },
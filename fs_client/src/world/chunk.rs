@@ -4,27 +4,33 @@ use fs_common::game::common::{
     world::{
         chunk_handler::ChunkHandler,
         chunk_index::{ChunkLocalIndex, ChunkLocalPosition},
+        chunk_pool::ChunkBuffers,
         material::PhysicsType,
         Chunk, CHUNK_AREA,
     },
     ChunkCollisionOverlay,
 };
-use std::{borrow::Cow, convert::TryInto, sync::Arc};
+use std::{borrow::Cow, convert::TryInto, path::Path, sync::Arc};
 
 use fs_common::game::common::{
     world::{
         chunk_data::{CommonChunkData, SidedChunkData},
+        light_size,
         material::{color::Color, MaterialInstance},
         mesh::{self, Mesh},
         tile_entity::{TileEntity, TileEntityCommon},
-        ChunkRigidBodyState, ChunkState, SidedChunk, CHUNK_SIZE, LIGHT_SCALE,
+        ChunkRigidBodyState, ChunkState, SidedChunk, CHUNK_SIZE,
     },
     FileHelper, Rect, Settings,
 };
 use glium::{
-    pixel_buffer::PixelBuffer, texture::Texture2d, uniform, uniforms::ImageUnit, Blend, Display,
-    DrawParameters, PolygonMode,
+    pixel_buffer::PixelBuffer,
+    texture::{RawImage2d, Texture2d},
+    uniform,
+    uniforms::ImageUnit,
+    Blend, Display, DrawParameters, PolygonMode,
 };
+use image::RgbaImage;
 
 use crate::render::{drawing::RenderTarget, shaders::Shaders};
 
@@ -33,8 +39,11 @@ use super::chunk_data::tile_entity::TileEntityClient;
 pub struct ClientChunk {
     pub data: CommonChunkData<Self>,
     pub graphics: Box<ChunkGraphics>,
-    pub mesh: Option<Mesh>,
     pub tris: Option<Vec<Vec<mesh::Tri>>>,
+    /// If `false`, lighting isn't recomputed for this chunk (but stays dirty until it is enabled).
+    ///
+    /// See [`ClientChunkHandlerExt::apply_lighting_policy`].
+    pub lighting_enabled: bool,
 }
 
 impl SidedChunkData for ClientChunk {
@@ -42,26 +51,41 @@ impl SidedChunkData for ClientChunk {
 }
 
 impl Chunk for ClientChunk {
-    fn new_empty(chunk_x: i32, chunk_y: i32) -> Self {
+    fn new_with_buffers(chunk_x: i32, chunk_y: i32, buffers: ChunkBuffers) -> Self {
         Self {
             data: CommonChunkData::new(chunk_x, chunk_y),
             graphics: Box::new(ChunkGraphics {
                 data: None,
-                pixel_data: Box::new([Color::TRANSPARENT; CHUNK_AREA]),
-                lighting_data: Box::new([[0.0; 4]; CHUNK_AREA]),
-                background_data: Box::new([Color::TRANSPARENT; CHUNK_AREA]),
+                pixel_data: buffers.colors,
+                lighting_data: buffers.lights,
+                background_layers: vec![BackgroundLayer::new(buffers.background_colors, 1.0)],
                 dirty: true,
+                dirty_rect: None,
                 was_dirty: true,
+                average_color: None,
                 lighting_dirty: true,
                 was_lighting_dirty: true,
-                background_dirty: true,
                 pixels_updated_last_update: true,
                 lighting_updated_last_update: true,
                 dist_to_nearest_dirty_light: None,
                 prev_dist_to_nearest_dirty_light: None,
             }),
-            mesh: None,
             tris: None,
+            lighting_enabled: true,
+        }
+    }
+
+    fn into_buffers(self) -> ChunkBuffers {
+        let graphics = *self.graphics;
+        ChunkBuffers {
+            colors: graphics.pixel_data,
+            lights: graphics.lighting_data,
+            background_colors: graphics
+                .background_layers
+                .into_iter()
+                .next()
+                .unwrap()
+                .colors,
         }
     }
 
@@ -89,6 +113,22 @@ impl Chunk for ClientChunk {
         self.data.dirty_rect = rect;
     }
 
+    fn needs_save(&self) -> bool {
+        self.data.needs_save
+    }
+
+    fn set_needs_save(&mut self, needs_save: bool) {
+        self.data.needs_save = needs_save;
+    }
+
+    fn frozen(&self) -> bool {
+        self.data.frozen
+    }
+
+    fn set_frozen(&mut self, frozen: bool) {
+        self.data.frozen = frozen;
+    }
+
     fn refresh(&mut self) {
         for pos in ChunkLocalPosition::iter() {
             let i: ChunkLocalIndex = pos.into();
@@ -186,8 +226,8 @@ impl Chunk for ClientChunk {
     }
 
     #[profiling::function]
-    fn set_pixel_colors(&mut self, colors: Box<[Color; CHUNK_AREA]>) {
-        self.graphics.replace(colors);
+    fn set_pixel_colors(&mut self, colors: Box<[Color; CHUNK_AREA]>) -> Box<[Color; CHUNK_AREA]> {
+        self.graphics.replace(colors)
     }
 
     fn colors_mut(&mut self) -> &mut [Color; CHUNK_AREA] {
@@ -212,45 +252,43 @@ impl Chunk for ClientChunk {
     }
 
     #[profiling::function]
-    fn set_background_pixel_colors(&mut self, colors: Box<[Color; CHUNK_AREA]>) {
-        self.graphics.replace_background(colors);
+    fn set_background_pixel_colors(
+        &mut self,
+        colors: Box<[Color; CHUNK_AREA]>,
+    ) -> Box<[Color; CHUNK_AREA]> {
+        self.graphics.replace_background(colors)
     }
 
     fn background_colors_mut(&mut self) -> &mut [Color; CHUNK_AREA] {
-        &mut self.graphics.background_data
+        &mut self.graphics.background_layers[0].colors
     }
 
     fn background_colors(&self) -> &[Color; CHUNK_AREA] {
-        &self.graphics.background_data
+        &self.graphics.background_layers[0].colors
     }
 
     fn mark_dirty(&mut self) {
-        self.graphics.dirty = true;
-        self.graphics.background_dirty = true;
+        self.graphics.mark_all_dirty();
+        for layer in &mut self.graphics.background_layers {
+            layer.dirty = true;
+        }
         self.graphics.lighting_dirty = true;
     }
 
-    fn generate_mesh(&mut self) -> Result<(), String> {
-        if self.data.pixels.is_none() {
-            return Err("generate_mesh failed: self.data.pixels is None".to_owned());
-        }
-
-        let vs: Vec<f64> = mesh::pixels_to_valuemap(self.data.pixels.as_ref().unwrap().as_ref());
+    fn generate_mesh_with_epsilon(&mut self, epsilon: f64) -> Result<(), String> {
+        self.data.generate_mesh(epsilon)?;
+        self.tris = self.data.mesh_simplified.as_ref().map(mesh::triangulate);
 
-        let generated =
-            mesh::generate_mesh_with_simplified(&vs, u32::from(CHUNK_SIZE), u32::from(CHUNK_SIZE));
+        Ok(())
+    }
 
-        if let Ok(r) = generated {
-            self.mesh = Some(r.0);
-            self.data.mesh_simplified = Some(r.1);
-        } else {
-            self.mesh = None;
-            self.data.mesh_simplified = None;
+    fn update_mesh_with_epsilon(&mut self, epsilon: f64) -> Result<bool, String> {
+        let updated = self.data.update_mesh(epsilon)?;
+        if updated {
+            self.tris = self.data.mesh_simplified.as_ref().map(mesh::triangulate);
         }
 
-        self.tris = self.data.mesh_simplified.as_ref().map(mesh::triangulate);
-
-        Ok(())
+        Ok(updated)
     }
 
     fn mesh_loops(&self) -> &Option<Mesh> {
@@ -337,24 +375,49 @@ impl SidedChunk for ClientChunk {
 pub struct ChunkGraphicsData {
     pub display: Display,
     pub texture: Texture2d,
-    pub background_texture: Texture2d,
     pub lighting_src_buf: PixelBuffer<(f32, f32, f32, f32)>,
     pub lighting_src: Texture2d,
     pub lighting_dst: Texture2d,
     pub lighting_neighbors: Texture2d,
     pub lighting_constant_black: Texture2d,
+    /// The light scale the lighting textures were allocated for.
+    pub light_scale: u8,
+}
+
+/// A layer of background colors drawn behind a chunk, shifted with the camera so it appears
+///   further away.
+pub struct BackgroundLayer {
+    pub colors: Box<[Color; CHUNK_AREA]>,
+    /// How far away the layer appears, as a multiple of [`Settings::background_parallax`].
+    pub parallax: f32,
+    pub dirty: bool,
+    /// [`None`] until it's created in [`ChunkGraphics::prep_render`].
+    pub texture: Option<Arc<Texture2d>>,
+}
+
+impl BackgroundLayer {
+    pub fn new(colors: Box<[Color; CHUNK_AREA]>, parallax: f32) -> Self {
+        Self { colors, parallax, dirty: true, texture: None }
+    }
 }
 
 pub struct ChunkGraphics {
     pub data: Option<Arc<ChunkGraphicsData>>,
     pub pixel_data: Box<[Color; CHUNK_AREA]>,
     pub lighting_data: Box<[[f32; 4]; CHUNK_AREA]>,
-    pub background_data: Box<[Color; CHUNK_AREA]>,
+    /// Background layers from front to back. The first one always exists and holds the colors of
+    ///   the chunk's background pixels.
+    pub background_layers: Vec<BackgroundLayer>,
     pub dirty: bool,
+    /// Part of `pixel_data` changed since the last upload, or [`None`] if all of it needs uploading.
+    ///
+    /// Only meaningful while `dirty` is set.
+    pub dirty_rect: Option<Rect<u16>>,
     pub was_dirty: bool,
+    /// Cached [`ChunkGraphics::average_color`], cleared whenever `pixel_data` is marked dirty.
+    pub average_color: Option<Color>,
     pub lighting_dirty: bool,
     pub was_lighting_dirty: bool,
-    pub background_dirty: bool,
 
     pub pixels_updated_last_update: bool,
     pub lighting_updated_last_update: bool,
@@ -375,10 +438,90 @@ impl ChunkGraphics {
                 self.lighting_dirty = true;
             }
             self.pixel_data[i] = color;
+
+            let pos: ChunkLocalPosition = i.into();
+            let px = Rect::new_wh(pos.x(), pos.y(), 1_u16, 1_u16);
+            self.dirty_rect = match self.dirty_rect {
+                Some(rect) => Some(rect.union(px)),
+                // already waiting on a full upload
+                None if self.dirty => None,
+                None => Some(px),
+            };
             self.dirty = true;
+            self.average_color = None;
+        }
+    }
+
+    /// Marks all of `pixel_data` as needing to be uploaded.
+    pub fn mark_all_dirty(&mut self) {
+        self.dirty = true;
+        self.dirty_rect = None;
+        self.average_color = None;
+    }
+
+    /// Clears `dirty`, returning the part of `pixel_data` that needs to be uploaded.
+    fn take_dirty_rect(&mut self) -> Rect<u16> {
+        self.dirty = false;
+        self.dirty_rect
+            .take()
+            .unwrap_or_else(|| Rect::new_wh(0, 0, CHUNK_SIZE, CHUNK_SIZE))
+    }
+
+    /// The pixels of `colors` inside `rect`, for uploading into that part of a texture.
+    fn sub_image(colors: &[Color; CHUNK_AREA], rect: Rect<u16>) -> RawImage2d<'_, u8> {
+        let color_sl = colors.as_slice();
+        let bytes = unsafe {
+            // Safety: Color is statically guaranteed to be equivalent to four u8s
+            core::slice::from_raw_parts(color_sl.as_ptr().cast::<u8>(), color_sl.len() * 4)
+        };
+
+        let data = if rect.width() == CHUNK_SIZE && rect.height() == CHUNK_SIZE {
+            Cow::Borrowed(bytes)
+        } else {
+            let row_len = CHUNK_SIZE as usize * 4;
+            let (start, end) = (rect.left() as usize * 4, rect.right() as usize * 4);
+            Cow::Owned(
+                rect.range_tb()
+                    .flat_map(|y| {
+                        let row = y as usize * row_len;
+                        &bytes[row + start..row + end]
+                    })
+                    .copied()
+                    .collect(),
+            )
+        };
+
+        RawImage2d {
+            data,
+            width: rect.width().into(),
+            height: rect.height().into(),
+            format: glium::texture::ClientFormat::U8U8U8U8,
         }
     }
 
+    /// Average of the chunk's pixel colors, used to draw it as a single pixel on the minimap.
+    ///
+    /// Only recomputed after `pixel_data` changes.
+    pub fn average_color(&mut self) -> Color {
+        if let Some(color) = self.average_color {
+            return color;
+        }
+
+        let sums = self.pixel_data.iter().fold([0_u32; 4], |mut sums, c| {
+            sums[0] += u32::from(c.r);
+            sums[1] += u32::from(c.g);
+            sums[2] += u32::from(c.b);
+            sums[3] += u32::from(c.a);
+            sums
+        });
+
+        let n = CHUNK_AREA as u32;
+        let [r, g, b, a] = sums.map(|sum| ((sum + n / 2) / n) as u8);
+        let color = Color::rgba(r, g, b, a);
+        self.average_color = Some(color);
+        color
+    }
+
     // #[profiling::function] // huge performance impact
     pub fn set_light(&mut self, pos: impl Into<ChunkLocalIndex>, color: [f32; 3]) {
         let i: ChunkLocalIndex = pos.into();
@@ -394,96 +537,86 @@ impl ChunkGraphics {
         self.pixel_data[i]
     }
 
+    /// Sets a pixel of the first background layer.
     pub fn set_background(&mut self, pos: impl Into<ChunkLocalIndex>, color: Color) {
+        self.set_background_layer(0, pos, color);
+    }
+
+    /// Sets a pixel of background layer `layer`, doing nothing if it doesn't exist.
+    pub fn set_background_layer(
+        &mut self,
+        layer: usize,
+        pos: impl Into<ChunkLocalIndex>,
+        color: Color,
+    ) {
         let i: ChunkLocalIndex = pos.into();
-        if self.background_data[i] != color {
-            self.background_data[i] = color;
-            self.background_dirty = true;
+        if let Some(layer) = self.background_layers.get_mut(layer) {
+            if layer.colors[i] != color {
+                layer.colors[i] = color;
+                layer.dirty = true;
+            }
         }
     }
 
+    /// Adds a background layer behind the existing ones, returning its index.
+    pub fn add_background_layer(
+        &mut self,
+        colors: Box<[Color; CHUNK_AREA]>,
+        parallax: f32,
+    ) -> usize {
+        self.background_layers
+            .push(BackgroundLayer::new(colors, parallax));
+        self.background_layers.len() - 1
+    }
+
+    pub fn background_layer(&self, layer: usize) -> Option<&BackgroundLayer> {
+        self.background_layers.get(layer)
+    }
+
     // #[profiling::function]
     pub fn update_texture(&mut self) {
         self.pixels_updated_last_update = false;
-        if self.dirty {
-            if let Some(data) = &mut self.data {
-                profiling::scope!("dirty");
-
-                let image = {
-                    profiling::scope!("RawImage2d");
-
-                    glium::texture::RawImage2d {
-                        data: Cow::Borrowed({
-                            let color_sl = self.pixel_data.as_slice();
-                            unsafe {
-                                // Safety: Color is statically guaranteed to be equivalent to four u8s
-                                core::slice::from_raw_parts(
-                                    color_sl.as_ptr().cast::<u8>(),
-                                    color_sl.len() * 4,
-                                )
-                            }
-                        }),
-                        width: CHUNK_SIZE.into(),
-                        height: CHUNK_SIZE.into(),
-                        format: glium::texture::ClientFormat::U8U8U8U8,
-                    }
-                };
+        if self.dirty && self.data.is_some() {
+            profiling::scope!("dirty");
 
-                {
-                    profiling::scope!("write");
-                    data.texture.write(
-                        glium::Rect {
-                            left: 0,
-                            bottom: 0,
-                            width: CHUNK_SIZE.into(),
-                            height: CHUNK_SIZE.into(),
-                        },
-                        image,
-                    );
-                }
+            let rect = self.take_dirty_rect();
+            let image = {
+                profiling::scope!("RawImage2d");
+                Self::sub_image(&self.pixel_data, rect)
+            };
 
-                self.pixels_updated_last_update = true;
-                self.dirty = false;
+            {
+                profiling::scope!("write");
+                self.data.as_ref().unwrap().texture.write(
+                    glium::Rect {
+                        left: rect.left().into(),
+                        bottom: rect.top().into(),
+                        width: rect.width().into(),
+                        height: rect.height().into(),
+                    },
+                    image,
+                );
             }
-        }
 
-        if self.background_dirty {
-            if let Some(data) = &mut self.data {
-                profiling::scope!("background_dirty");
-                let image = {
-                    profiling::scope!("RawImage2d");
+            self.pixels_updated_last_update = true;
+        }
 
-                    glium::texture::RawImage2d {
-                        data: Cow::Borrowed({
-                            let color_sl = self.background_data.as_slice();
-                            unsafe {
-                                // Safety: Color is statically guaranteed to be equivalent to four u8s
-                                core::slice::from_raw_parts(
-                                    color_sl.as_ptr().cast::<u8>(),
-                                    color_sl.len() * 4,
-                                )
-                            }
-                        }),
-                        width: CHUNK_SIZE.into(),
-                        height: CHUNK_SIZE.into(),
-                        format: glium::texture::ClientFormat::U8U8U8U8,
-                    }
-                };
+        for layer in &mut self.background_layers {
+            let Some(texture) = layer.texture.as_ref().filter(|_| layer.dirty) else {
+                continue;
+            };
 
-                {
-                    profiling::scope!("write");
-                    data.background_texture.write(
-                        glium::Rect {
-                            left: 0,
-                            bottom: 0,
-                            width: CHUNK_SIZE.into(),
-                            height: CHUNK_SIZE.into(),
-                        },
-                        image,
-                    );
-                }
-                self.background_dirty = false;
-            }
+            profiling::scope!("background_dirty");
+            texture.write(
+                glium::Rect {
+                    left: 0,
+                    bottom: 0,
+                    width: CHUNK_SIZE.into(),
+                    height: CHUNK_SIZE.into(),
+                },
+                Self::sub_image(&layer.colors, Rect::new_wh(0, 0, CHUNK_SIZE, CHUNK_SIZE)),
+            );
+            layer.dirty = false;
         }
     }
 
@@ -495,7 +628,12 @@ impl ChunkGraphics {
     ) {
         self.lighting_updated_last_update = false;
         if self.lighting_dirty || self.dist_to_nearest_dirty_light.is_some() {
-            if let Some(data) = &mut self.data {
+            // textures for an old light scale are reallocated in prep_render first
+            let data = self
+                .data
+                .as_ref()
+                .filter(|d| d.light_scale == shaders.light_scale);
+            if let Some(data) = data {
                 profiling::scope!("lighting update");
 
                 let src_image = {
@@ -555,37 +693,20 @@ impl ChunkGraphics {
                     .unwrap()
                     .set_access(glium::uniforms::ImageUnitAccess::ReadWrite);
 
-                let t_light_n = r32f_read(
-                    neighbors
-                        .and_then(|ch| {
-                            ch[0].and_then(|c| c.graphics.data.as_ref().map(|d| &d.lighting_dst))
-                        })
-                        .unwrap_or(&data.lighting_constant_black),
-                );
-                let t_light_w = r32f_read(
-                    neighbors
-                        .and_then(|ch| {
-                            ch[1].and_then(|c| c.graphics.data.as_ref().map(|d| &d.lighting_dst))
-                        })
-                        .unwrap_or(&data.lighting_constant_black),
-                );
-                let t_light_e = r32f_read(
+                // neighbors that haven't been reallocated for a new light scale yet count as dark
+                let neighbor_light = |i: usize| {
                     neighbors
-                        .and_then(|ch| {
-                            ch[2].and_then(|c| c.graphics.data.as_ref().map(|d| &d.lighting_dst))
-                        })
-                        .unwrap_or(&data.lighting_constant_black),
-                );
-                let t_light_s = r32f_read(
-                    neighbors
-                        .and_then(|ch| {
-                            ch[3].and_then(|c| c.graphics.data.as_ref().map(|d| &d.lighting_dst))
-                        })
-                        .unwrap_or(&data.lighting_constant_black),
-                );
+                        .and_then(|ch| ch[i].and_then(|c| c.graphics.data.as_ref()))
+                        .filter(|d| d.light_scale == data.light_scale)
+                        .map_or(&data.lighting_constant_black, |d| &d.lighting_dst)
+                };
+                let t_light_n = r32f_read(neighbor_light(0));
+                let t_light_w = r32f_read(neighbor_light(1));
+                let t_light_e = r32f_read(neighbor_light(2));
+                let t_light_s = r32f_read(neighbor_light(3));
 
                 let uni = uniform! {
-                    light_scale: LIGHT_SCALE as i32,
+                    light_scale: i32::from(data.light_scale),
                     t_src: t_src,
                     t_light_n: t_light_n,
                     t_light_e: t_light_e,
@@ -606,7 +727,7 @@ impl ChunkGraphics {
                     .set_access(glium::uniforms::ImageUnitAccess::ReadWrite);
 
                 let uni = uniform! {
-                    light_scale: LIGHT_SCALE as i32,
+                    light_scale: i32::from(data.light_scale),
                     t_px: t_px,
                     t_dst: t_dst,
                     t_work: t_work,
@@ -629,16 +750,20 @@ impl ChunkGraphics {
 
     #[profiling::function]
     #[allow(clippy::cast_lossless)]
-    pub fn replace(&mut self, colors: Box<[Color; CHUNK_AREA]>) {
-        self.pixel_data = colors;
-        self.dirty = true;
+    pub fn replace(&mut self, colors: Box<[Color; CHUNK_AREA]>) -> Box<[Color; CHUNK_AREA]> {
+        self.mark_all_dirty();
+        std::mem::replace(&mut self.pixel_data, colors)
     }
 
     #[profiling::function]
     #[allow(clippy::cast_lossless)]
-    pub fn replace_background(&mut self, colors: Box<[Color; CHUNK_AREA]>) {
-        self.background_data = colors;
-        self.background_dirty = true;
+    pub fn replace_background(
+        &mut self,
+        colors: Box<[Color; CHUNK_AREA]>,
+    ) -> Box<[Color; CHUNK_AREA]> {
+        let layer = &mut self.background_layers[0];
+        layer.dirty = true;
+        std::mem::replace(&mut layer.colors, colors)
     }
 }
 
@@ -650,7 +775,11 @@ impl ClientChunk {
         shaders: &Shaders,
     ) -> Result<(), String> {
         self.graphics.update_texture();
-        self.graphics.update_lighting(surrounding, shaders);
+        if self.lighting_enabled {
+            self.graphics.update_lighting(surrounding, shaders);
+        } else {
+            self.graphics.lighting_updated_last_update = false;
+        }
 
         Ok(())
     }
@@ -664,10 +793,35 @@ impl ClientChunk {
         self.graphics.prep_render(target, settings, file_helper);
     }
 
+    /// Triangulation of the chunk's collision mesh, one list per mesh loop.
+    ///
+    /// [`None`] until the mesh has been generated, see [`Chunk::generate_mesh`].
+    pub fn get_tris(&self) -> Option<&Vec<Vec<mesh::Tri>>> {
+        self.tris.as_ref()
+    }
+
+    /// The chunk's rendered colors as a `CHUNK_SIZE x CHUNK_SIZE` image.
+    pub fn to_image(&self) -> RgbaImage {
+        let data = self
+            .graphics
+            .pixel_data
+            .iter()
+            .flat_map(|c| [c.r, c.g, c.b, c.a])
+            .collect();
+        RgbaImage::from_raw(u32::from(CHUNK_SIZE), u32::from(CHUNK_SIZE), data).unwrap()
+    }
+
+    /// Saves the chunk's rendered colors as a PNG.
+    pub fn export_png(&self, path: &Path) -> Result<(), String> {
+        self.to_image()
+            .save_with_format(path, image::ImageFormat::Png)
+            .map_err(|e| format!("Failed to save image @ {path:?}: {e}"))
+    }
+
     pub fn render(&mut self, target: &mut RenderTarget, settings: &Settings) {
         if settings.debug && settings.draw_chunk_collision == ChunkCollisionOverlay::MarchingSquares
         {
-            if let Some(f) = &self.mesh {
+            if let Some(f) = &self.data.mesh {
                 let colors = vec![
                     Color::rgb(32, 255, 32),
                     Color::rgb(255, 32, 32),
@@ -791,10 +945,17 @@ impl ChunkGraphics {
     pub fn prep_render(
         &mut self,
         target: &mut RenderTarget,
-        _settings: &Settings,
+        settings: &Settings,
         _file_helper: &FileHelper,
     ) {
+        let light_scale = settings.valid_light_scale();
+        if matches!(&self.data, Some(d) if d.light_scale != light_scale) {
+            // the light scale changed, so everything gets reallocated
+            self.data = None;
+        }
+
         if self.data.is_none() {
+            let light_size = light_size(light_scale).unwrap();
             let image = {
                 glium::texture::RawImage2d {
                     data: Cow::Borrowed({
@@ -820,31 +981,6 @@ impl ChunkGraphics {
             )
             .unwrap();
 
-            let background_image = {
-                glium::texture::RawImage2d {
-                    data: Cow::Borrowed({
-                        let color_sl = self.background_data.as_slice();
-                        unsafe {
-                            // Safety: Color is statically guaranteed to be equivalent to four u8s
-                            core::slice::from_raw_parts(
-                                color_sl.as_ptr().cast::<u8>(),
-                                color_sl.len() * 4,
-                            )
-                        }
-                    }),
-                    width: CHUNK_SIZE.into(),
-                    height: CHUNK_SIZE.into(),
-                    format: glium::texture::ClientFormat::U8U8U8U8,
-                }
-            };
-            let background_texture = Texture2d::with_format(
-                &target.display,
-                background_image,
-                glium::texture::UncompressedFloatFormat::U8U8U8U8,
-                glium::texture::MipmapsOption::NoMipmap,
-            )
-            .unwrap();
-
             let default_src = glium::texture::RawImage2d {
                 data: Cow::Owned(vec![0.0; CHUNK_AREA * 4]),
                 width: CHUNK_SIZE.into(),
@@ -861,14 +997,9 @@ impl ChunkGraphics {
             .unwrap();
 
             let default_dst = glium::texture::RawImage2d {
-                data: Cow::Owned(vec![
-                    0.0;
-                    ((CHUNK_SIZE / (LIGHT_SCALE as u16)) * (CHUNK_SIZE / (LIGHT_SCALE as u16)))
-                        as usize
-                        * 4
-                ]),
-                width: (CHUNK_SIZE / (LIGHT_SCALE as u16)).into(),
-                height: (CHUNK_SIZE / (LIGHT_SCALE as u16)).into(),
+                data: Cow::Owned(vec![0.0; light_size as usize * light_size as usize * 4]),
+                width: light_size.into(),
+                height: light_size.into(),
                 format: glium::texture::ClientFormat::F32F32F32F32,
             };
 
@@ -880,16 +1011,12 @@ impl ChunkGraphics {
             )
             .unwrap();
 
+            // one extra cell on each side for the neighboring chunks' light
+            let work_size = light_size + 2;
             let default_neighbors = glium::texture::RawImage2d {
-                data: Cow::Owned(vec![
-                    0.0;
-                    ((CHUNK_SIZE / (LIGHT_SCALE as u16) + 2)
-                        * (CHUNK_SIZE / (LIGHT_SCALE as u16) + 2))
-                        as usize
-                        * 4
-                ]),
-                width: (CHUNK_SIZE / (LIGHT_SCALE as u16) + 2).into(),
-                height: (CHUNK_SIZE / (LIGHT_SCALE as u16) + 2).into(),
+                data: Cow::Owned(vec![0.0; work_size as usize * work_size as usize * 4]),
+                width: work_size.into(),
+                height: work_size.into(),
                 format: glium::texture::ClientFormat::F32F32F32F32,
             };
 
@@ -922,14 +1049,29 @@ impl ChunkGraphics {
             self.data = Some(Arc::new(ChunkGraphicsData {
                 display: target.display.clone(),
                 texture,
-                background_texture,
                 lighting_src_buf: PixelBuffer::new_empty(&target.display, CHUNK_AREA),
                 lighting_src,
                 lighting_dst,
                 lighting_neighbors,
                 lighting_constant_black,
+                light_scale,
             }));
-            self.dirty = true;
+            self.mark_all_dirty();
+            self.lighting_dirty = true;
+        }
+
+        for layer in &mut self.background_layers {
+            if layer.texture.is_none() {
+                let texture = Texture2d::with_format(
+                    &target.display,
+                    Self::sub_image(&layer.colors, Rect::new_wh(0, 0, CHUNK_SIZE, CHUNK_SIZE)),
+                    glium::texture::UncompressedFloatFormat::U8U8U8U8,
+                    glium::texture::MipmapsOption::NoMipmap,
+                )
+                .unwrap();
+                layer.texture = Some(Arc::new(texture));
+                layer.dirty = false;
+            }
         }
     }
 }
@@ -943,7 +1085,32 @@ pub trait ClientChunkHandlerExt {
         colors: Vec<Color>,
     ) -> Result<(), String>;
 
+    /// Applies the changes from a [`PacketType::ChunkDeltaPacket`] to an already synced chunk.
+    ///
+    /// [`PacketType::ChunkDeltaPacket`]: fs_common::game::common::networking::PacketType::ChunkDeltaPacket
+    fn apply_chunk_delta(
+        &mut self,
+        chunk_x: i32,
+        chunk_y: i32,
+        changes: &[(u16, u16, MaterialInstance)],
+    ) -> Result<(), String>;
+
     fn update_chunk_graphics(&mut self, shaders: &Shaders);
+
+    /// Enables lighting updates for every chunk, or only for [`ChunkState::Active`] ones if
+    ///   `active_only` is set.
+    fn apply_lighting_policy(&mut self, active_only: bool);
+
+    /// Saves the rendered colors of the `width x height` chunks starting at `chunk_x, chunk_y` as
+    ///   one PNG. Chunks that aren't loaded are left transparent.
+    fn export_region_png(
+        &self,
+        chunk_x: i32,
+        chunk_y: i32,
+        width: u16,
+        height: u16,
+        path: &Path,
+    ) -> Result<(), String>;
 }
 
 impl ClientChunkHandlerExt for ChunkHandler<ClientChunk> {
@@ -987,6 +1154,21 @@ impl ClientChunkHandlerExt for ChunkHandler<ClientChunk> {
         Ok(())
     }
 
+    fn apply_chunk_delta(
+        &mut self,
+        chunk_x: i32,
+        chunk_y: i32,
+        changes: &[(u16, u16, MaterialInstance)],
+    ) -> Result<(), String> {
+        match self.manager.chunk_at_mut((chunk_x, chunk_y)) {
+            Some(chunk) if chunk.pixels().is_some() => {
+                chunk.apply_diff(changes);
+                Ok(())
+            },
+            _ => Err(format!("chunk {chunk_x},{chunk_y} hasn't been synced")),
+        }
+    }
+
     #[profiling::function]
     fn update_chunk_graphics(&mut self, shaders: &Shaders) {
         for ch in self.manager.chunks_iter_mut() {
@@ -1016,4 +1198,292 @@ impl ClientChunkHandlerExt for ChunkHandler<ClientChunk> {
                 }
             });
     }
+
+    fn apply_lighting_policy(&mut self, active_only: bool) {
+        for ch in self.manager.chunks_iter_mut() {
+            ch.lighting_enabled = !active_only || ch.state() == ChunkState::Active;
+        }
+    }
+
+    fn export_region_png(
+        &self,
+        chunk_x: i32,
+        chunk_y: i32,
+        width: u16,
+        height: u16,
+        path: &Path,
+    ) -> Result<(), String> {
+        let size = u32::from(CHUNK_SIZE);
+        let mut img = RgbaImage::new(u32::from(width) * size, u32::from(height) * size);
+
+        for dy in 0..height {
+            for dx in 0..width {
+                let key = (chunk_x + i32::from(dx), chunk_y + i32::from(dy));
+                if let Some(chunk) = self.chunk_at(key) {
+                    image::imageops::replace(
+                        &mut img,
+                        &chunk.to_image(),
+                        i64::from(dx) * i64::from(size),
+                        i64::from(dy) * i64::from(size),
+                    );
+                }
+            }
+        }
+
+        img.save_with_format(path, image::ImageFormat::Png)
+            .map_err(|e| format!("Failed to save image @ {path:?}: {e}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chunksystem::ChunkQuery;
+    use fs_common::game::common::networking::{read_packet, send_packet, Packet, PacketType};
+    use fs_common::game::common::world::{
+        chunk_handler::ChunkHandler,
+        chunk_index::ChunkLocalPosition,
+        gen::TestGenerator,
+        material::{self, color::Color, MaterialInstance, PhysicsType},
+        Chunk, ChunkState, CHUNK_AREA, CHUNK_SIZE,
+    };
+    use fs_common::game::common::Rect;
+
+    use super::{ChunkGraphics, ClientChunk, ClientChunkHandlerExt};
+
+    fn pos(x: u16, y: u16) -> ChunkLocalPosition {
+        (x, y).try_into().unwrap()
+    }
+
+    fn rect_of(graphics: &ChunkGraphics) -> Option<(u16, u16, u16, u16)> {
+        graphics.dirty_rect.map(|r| (r.x1, r.y1, r.x2, r.y2))
+    }
+
+    #[test]
+    fn average_color() {
+        let mut chunk = ClientChunk::new_empty(0, 0);
+        let graphics = &mut chunk.graphics;
+
+        for (i, c) in graphics.pixel_data.iter_mut().enumerate() {
+            *c = if i % 2 == 0 {
+                Color::rgba(0, 0, 0, 255)
+            } else {
+                Color::rgba(200, 100, 51, 0)
+            };
+        }
+        // 25.5 and 127.5 round up
+        assert_eq!(graphics.average_color(), Color::rgba(100, 50, 26, 128));
+
+        graphics.pixel_data.fill(Color::rgb(7, 8, 9));
+        // cached until the pixels are marked dirty
+        assert_eq!(graphics.average_color(), Color::rgba(100, 50, 26, 128));
+        graphics.mark_all_dirty();
+        assert_eq!(graphics.average_color(), Color::rgb(7, 8, 9));
+    }
+
+    #[test]
+    fn dirty_rect() {
+        let mut chunk = ClientChunk::new_empty(0, 0);
+        let graphics = &mut chunk.graphics;
+
+        // new chunks need a full upload
+        assert!(graphics.dirty);
+        assert!(graphics.dirty_rect.is_none());
+        let rect = graphics.take_dirty_rect();
+        assert_eq!((rect.width(), rect.height()), (CHUNK_SIZE, CHUNK_SIZE));
+        assert!(!graphics.dirty);
+
+        graphics.set(pos(3, 4), Color::rgb(1, 2, 3));
+        assert!(graphics.dirty);
+        assert_eq!(rect_of(graphics), Some((3, 4, 4, 5)));
+
+        graphics.set(pos(10, 2), Color::rgb(1, 2, 3));
+        graphics.set(pos(5, 8), Color::rgb(1, 2, 3));
+        assert_eq!(rect_of(graphics), Some((3, 2, 11, 9)));
+
+        // setting the same color doesn't grow it
+        let color = graphics.get(pos(50, 50));
+        graphics.set(pos(50, 50), color);
+        assert_eq!(rect_of(graphics), Some((3, 2, 11, 9)));
+
+        let rect = graphics.take_dirty_rect();
+        let image = ChunkGraphics::sub_image(&graphics.pixel_data, rect);
+        assert_eq!((image.width, image.height), (8, 7));
+        assert_eq!(image.data.len(), 8 * 7 * 4);
+        // (10, 2) is the top right corner
+        assert_eq!(&image.data[7 * 4..8 * 4], &[1, 2, 3, 255]);
+
+        assert!(!graphics.dirty);
+        assert!(graphics.dirty_rect.is_none());
+
+        // after a full invalidation, sets don't shrink the upload
+        graphics.mark_all_dirty();
+        graphics.set(pos(1, 1), Color::rgb(4, 5, 6));
+        assert!(graphics.dirty_rect.is_none());
+    }
+
+    #[test]
+    fn export_png() {
+        let path = std::env::temp_dir().join(format!("fs_client_chunk_{}.png", std::process::id()));
+
+        let mut chunk = ClientChunk::new_empty(0, 0);
+        for y in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                chunk
+                    .graphics
+                    .set(pos(x, y), Color::rgb(x as u8 * 2, y as u8 * 2, 7));
+            }
+        }
+
+        chunk.export_png(&path).unwrap();
+        let img = image::open(&path).map(|img| img.into_rgba8());
+        let _ignore = std::fs::remove_file(&path);
+        let img = img.unwrap();
+
+        assert_eq!(
+            img.dimensions(),
+            (u32::from(CHUNK_SIZE), u32::from(CHUNK_SIZE))
+        );
+        assert_eq!(img.get_pixel(0, 0).0, [0, 0, 7, 255]);
+        assert_eq!(img.get_pixel(10, 40).0, [20, 80, 7, 255]);
+        assert_eq!(img.get_pixel(99, 99).0, [198, 198, 7, 255]);
+
+        // unloaded chunks are transparent
+        let mut handler: ChunkHandler<ClientChunk> = ChunkHandler::new(TestGenerator::new(), None);
+        handler.manager.insert((0, 0), chunk);
+        handler.export_region_png(0, 0, 2, 1, &path).unwrap();
+        let img = image::open(&path).map(|img| img.into_rgba8());
+        let _ignore = std::fs::remove_file(&path);
+        let img = img.unwrap();
+
+        assert_eq!(
+            img.dimensions(),
+            (u32::from(CHUNK_SIZE) * 2, u32::from(CHUNK_SIZE))
+        );
+        assert_eq!(img.get_pixel(10, 40).0, [20, 80, 7, 255]);
+        assert_eq!(img.get_pixel(110, 40).0, [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn get_tris() {
+        let mut chunk = ClientChunk::new_empty(0, 0);
+        assert!(chunk.get_tris().is_none());
+
+        let mut pixels = vec![MaterialInstance::air(); CHUNK_AREA];
+        let solid = material::TEST.instance(PhysicsType::Solid, Color::rgb(1, 2, 3));
+        for y in 20..40 {
+            for x in 30..60 {
+                pixels[x + y * CHUNK_SIZE as usize] = solid.clone();
+            }
+        }
+        chunk.data.pixels = Some(pixels.try_into().unwrap());
+
+        chunk.generate_mesh().unwrap();
+        let tris = chunk.get_tris().unwrap();
+        assert!(tris.iter().map(Vec::len).sum::<usize>() > 0);
+    }
+
+    #[test]
+    fn lighting_policy() {
+        let mut handler: ChunkHandler<ClientChunk> = ChunkHandler::new(TestGenerator::new(), None);
+        let mut active = ClientChunk::new_empty(0, 0);
+        active.set_state(ChunkState::Active);
+        let mut cached = ClientChunk::new_empty(1, 0);
+        cached.set_state(ChunkState::Cached);
+        handler.manager.insert((0, 0), active);
+        handler.manager.insert((1, 0), cached);
+
+        handler.apply_lighting_policy(true);
+        assert!(handler.manager.chunk_at((0, 0)).unwrap().lighting_enabled);
+        let cached = handler.manager.chunk_at((1, 0)).unwrap();
+        assert!(!cached.lighting_enabled);
+        // still dirty, so it's updated once it's enabled again
+        assert!(cached.graphics.lighting_dirty);
+
+        handler.apply_lighting_policy(false);
+        assert!(handler.manager.chunk_at((1, 0)).unwrap().lighting_enabled);
+    }
+
+    #[test]
+    fn background_layers() {
+        let mut chunk = ClientChunk::new_empty(0, 0);
+        let graphics = &mut chunk.graphics;
+        assert_eq!(graphics.background_layers.len(), 1);
+
+        let far = graphics.add_background_layer(Box::new([Color::TRANSPARENT; CHUNK_AREA]), 2.5);
+        assert_eq!(far, 1);
+
+        graphics.set_background(pos(1, 2), Color::RED);
+        graphics.set_background_layer(far, pos(3, 4), Color::BLUE);
+        // missing layers are ignored
+        graphics.set_background_layer(5, pos(3, 4), Color::GREEN);
+
+        let near = graphics.background_layer(0).unwrap();
+        assert_eq!(near.colors[pos(1, 2)], Color::RED);
+        assert_eq!(near.colors[pos(3, 4)], Color::TRANSPARENT);
+        assert!((near.parallax - 1.0).abs() < f32::EPSILON);
+
+        let far = graphics.background_layer(far).unwrap();
+        assert_eq!(far.colors[pos(3, 4)], Color::BLUE);
+        assert_eq!(far.colors[pos(1, 2)], Color::TRANSPARENT);
+        assert!((far.parallax - 2.5).abs() < f32::EPSILON);
+        assert!(far.dirty);
+
+        assert!(graphics.background_layer(2).is_none());
+        assert_eq!(chunk.background_colors()[pos(1, 2)], Color::RED);
+    }
+
+    #[test]
+    fn chunk_delta() {
+        let solid = material::TEST.instance(PhysicsType::Solid, Color::rgb(1, 2, 3));
+
+        let mut server_pixels = vec![MaterialInstance::air(); CHUNK_AREA];
+        server_pixels[4 + 6 * CHUNK_SIZE as usize] = solid.clone();
+        server_pixels[7 + 8 * CHUNK_SIZE as usize] = solid;
+        let server_pixels: Box<[MaterialInstance; CHUNK_AREA]> = server_pixels.try_into().unwrap();
+
+        // a delta covering the whole chunk isn't worth it
+        assert!(PacketType::chunk_delta(
+            0,
+            0,
+            &server_pixels,
+            Rect::new_wh(0, 0, CHUNK_SIZE, CHUNK_SIZE)
+        )
+        .is_none());
+
+        let packet = Packet {
+            packet_type: PacketType::chunk_delta(
+                0,
+                0,
+                &server_pixels,
+                Rect::new_wh(4_u16, 6_u16, 4_u16, 3_u16),
+            )
+            .unwrap(),
+        };
+        let mut buf = vec![];
+        send_packet(&mut buf, &packet).unwrap();
+        let packet = read_packet(&mut buf.as_slice()).unwrap();
+
+        let PacketType::ChunkDeltaPacket { chunk_x, chunk_y, changes } = packet.packet_type else {
+            panic!("expected a ChunkDeltaPacket");
+        };
+        assert_eq!((chunk_x, chunk_y), (0, 0));
+        assert_eq!(changes.len(), 4 * 3);
+
+        let mut handler: ChunkHandler<ClientChunk> = ChunkHandler::new(TestGenerator::new(), None);
+        // not synced yet
+        assert!(handler.apply_chunk_delta(0, 0, &changes).is_err());
+
+        let mut chunk = ClientChunk::new_empty(0, 0);
+        chunk.data.pixels = Some(
+            vec![MaterialInstance::air(); CHUNK_AREA]
+                .try_into()
+                .unwrap(),
+        );
+        handler.manager.insert((0, 0), chunk);
+        handler.apply_chunk_delta(0, 0, &changes).unwrap();
+
+        let chunk = handler.manager.chunk_at((0, 0)).unwrap();
+        assert_eq!(chunk.pixels().as_deref(), Some(&*server_pixels));
+        assert_eq!(chunk.graphics.get(pos(7, 8)), Color::rgb(1, 2, 3));
+    }
 }
@@ -1,17 +1,165 @@
-use fs_common::game::common::world::{
-    material::{color::Color, MaterialInstance},
-    World,
+use std::collections::{HashMap, HashSet};
+
+use chunksystem::ChunkKey;
+use fs_common::game::common::{
+    networking::{EntityKind, EntitySync},
+    world::{
+        entity::GameEntity,
+        material::{color::Color, MaterialInstance},
+        pixel_to_chunk_pos, Position, Velocity, World, CHUNK_SIZE,
+    },
+    Rect,
 };
+use specs::{Builder, WorldExt};
 
 use super::{ClientChunk, ClientChunkHandlerExt};
 
 pub struct ClientWorld {
     pub local_entity: Option<specs::Entity>,
+    /// Chunks we've asked the server for (in remote mode).
+    pub requested_chunks: HashSet<ChunkKey>,
+    /// Requested chunks the server sent a delta for that we don't have, to ask for in full.
+    pub chunks_to_resync: HashSet<ChunkKey>,
+    /// The local entity's position as last sent to the server (in remote mode).
+    pub sent_position: Option<Position>,
+    /// Local entities mirroring the server's, keyed by the server's entity id (in remote mode).
+    pub remote_entities: HashMap<u32, (EntityKind, specs::Entity)>,
 }
 
 impl ClientWorld {
+    pub fn new(local_entity: Option<specs::Entity>) -> Self {
+        Self {
+            local_entity,
+            requested_chunks: HashSet::new(),
+            chunks_to_resync: HashSet::new(),
+            sent_position: None,
+            remote_entities: HashMap::new(),
+        }
+    }
+
     #[allow(clippy::unused_self)]
     pub fn tick(&mut self, _world: &mut World<ClientChunk>) {}
+
+    /// Returns the chunks in the local entity's load zone that haven't been requested from the server yet,
+    ///   marking them as requested.
+    ///
+    /// Chunks that leave the unload zone are forgotten, so they will be requested again if they come back into range.
+    pub fn chunks_to_request(&mut self, world: &World<ClientChunk>) -> Vec<ChunkKey> {
+        let Some(pos) = self
+            .local_entity
+            .and_then(|e| world.ecs.read_storage::<Position>().get(e).cloned())
+        else {
+            return vec![];
+        };
+
+        let load = world.chunk_handler.get_load_zone((pos.x, pos.y));
+        let unload = world.chunk_handler.get_unload_zone((pos.x, pos.y));
+
+        self.requested_chunks.retain(|&(chunk_x, chunk_y)| {
+            Rect::new_wh(
+                chunk_x * i32::from(CHUNK_SIZE),
+                chunk_y * i32::from(CHUNK_SIZE),
+                CHUNK_SIZE,
+                CHUNK_SIZE,
+            )
+            .intersects(&unload)
+        });
+
+        let mut out = vec![];
+        for px in load.range_lr().step_by(CHUNK_SIZE.into()) {
+            for py in load.range_tb().step_by(CHUNK_SIZE.into()) {
+                let chunk_pos = pixel_to_chunk_pos(px.into(), py.into());
+                if self.requested_chunks.insert(chunk_pos) {
+                    out.push(chunk_pos);
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Handles a [`PacketType::ChunkDeltaPacket`] we couldn't apply because we don't have the chunk.
+    ///
+    /// If we still want the chunk, it's queued to be asked for in full with a
+    ///   [`PacketType::ResyncChunkPacket`].
+    ///
+    /// [`PacketType::ChunkDeltaPacket`]: fs_common::game::common::networking::PacketType::ChunkDeltaPacket
+    /// [`PacketType::ResyncChunkPacket`]: fs_common::game::common::networking::PacketType::ResyncChunkPacket
+    pub fn missed_delta(&mut self, key: ChunkKey) {
+        if self.requested_chunks.contains(&key) {
+            self.chunks_to_resync.insert(key);
+        }
+    }
+
+    /// Returns the local entity's position if it moved at least a pixel since it was last sent to
+    ///   the server, marking it as sent.
+    pub fn position_to_send(&mut self, world: &World<ClientChunk>) -> Option<Position> {
+        let pos = self
+            .local_entity
+            .and_then(|e| world.ecs.read_storage::<Position>().get(e).cloned())?;
+
+        if let Some(sent) = &self.sent_position {
+            if (sent.x - pos.x).abs() < 1.0 && (sent.y - pos.y).abs() < 1.0 {
+                return None;
+            }
+        }
+
+        self.sent_position = Some(pos.clone());
+        Some(pos)
+    }
+
+    /// Spawns, updates or deletes the local mirrors of the server's entities.
+    pub fn apply_entity_sync(&mut self, world: &mut World<ClientChunk>, entities: Vec<EntitySync>) {
+        for sync in entities {
+            match sync {
+                EntitySync::Update { id, kind, position, velocity } => {
+                    match self.remote_entities.get(&id) {
+                        Some(&(old_kind, entity))
+                            if old_kind == kind && world.ecs.is_alive(entity) =>
+                        {
+                            world
+                                .ecs
+                                .write_storage::<Position>()
+                                .insert(entity, position)
+                                .expect("Failed to update Position of remote entity");
+                            world
+                                .ecs
+                                .write_storage::<Velocity>()
+                                .insert(entity, velocity)
+                                .expect("Failed to update Velocity of remote entity");
+                        },
+                        old => {
+                            // the server reused the id for something else
+                            if let Some(&(_, entity)) = old {
+                                let _ = world.ecs.delete_entity(entity);
+                            }
+
+                            let entity = world
+                                .ecs
+                                .create_entity()
+                                .with(GameEntity)
+                                .with(position)
+                                .with(velocity)
+                                .build();
+                            self.remote_entities.insert(id, (kind, entity));
+                        },
+                    }
+                },
+                EntitySync::Remove { id } => {
+                    if let Some((_, entity)) = self.remote_entities.remove(&id) {
+                        let _ = world.ecs.delete_entity(entity);
+                    }
+                },
+            }
+        }
+    }
+
+    /// Deletes every entity created by [`ClientWorld::apply_entity_sync`], eg. after reconnecting.
+    pub fn clear_remote_entities(&mut self, world: &mut World<ClientChunk>) {
+        for (_, (_, entity)) in self.remote_entities.drain() {
+            let _ = world.ecs.delete_entity(entity);
+        }
+    }
 }
 
 pub trait ClientWorldExt {
@@ -22,6 +170,13 @@ pub trait ClientWorldExt {
         pixels: Vec<MaterialInstance>,
         colors: Vec<Color>,
     ) -> Result<(), String>;
+
+    fn apply_chunk_delta(
+        &mut self,
+        chunk_x: i32,
+        chunk_y: i32,
+        changes: &[(u16, u16, MaterialInstance)],
+    ) -> Result<(), String>;
 }
 
 impl ClientWorldExt for World<ClientChunk> {
@@ -35,4 +190,14 @@ impl ClientWorldExt for World<ClientChunk> {
         self.chunk_handler
             .sync_chunk(chunk_x, chunk_y, pixels, colors)
     }
+
+    fn apply_chunk_delta(
+        &mut self,
+        chunk_x: i32,
+        chunk_y: i32,
+        changes: &[(u16, u16, MaterialInstance)],
+    ) -> Result<(), String> {
+        self.chunk_handler
+            .apply_chunk_delta(chunk_x, chunk_y, changes)
+    }
 }
@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use chunksystem::ChunkQuery;
-use glium::{Blend, DrawParameters, PolygonMode};
+use glium::{texture::RawImage2d, Blend, DrawParameters, PolygonMode, Texture2d};
 use rapier2d::prelude::Shape;
 use specs::{Join, ReadStorage, WorldExt};
 
@@ -20,17 +20,21 @@ use fs_common::game::common::{
 };
 
 use crate::{
-    render::{drawing::RenderTarget, rigidbody::FSRigidBodyExt},
+    render::{drawing::RenderTarget, rigidbody::FSRigidBodyExt, vertex::Vertex2},
+    ui::draw::DrawTool,
     Client,
 };
 
 use super::{chunk_data::tile_entity::ClientTileEntityExt, ClientChunk, ClientWorld};
 
-pub struct WorldRenderer {}
+pub struct WorldRenderer {
+    /// Reused by [`WorldRenderer::draw_minimap`] until `minimap_radius` changes.
+    minimap_texture: Option<Texture2d>,
+}
 
 impl WorldRenderer {
     pub fn new() -> Self {
-        Self {}
+        Self { minimap_texture: None }
     }
 
     #[allow(clippy::unused_self)]
@@ -61,7 +65,7 @@ impl WorldRenderer {
             .expect("No Camera in world!");
 
         let loader_pos = match ctx.client {
-            Client { world: Some(ClientWorld { local_entity }), .. } => local_entity
+            Client { world: Some(ClientWorld { local_entity, .. }), .. } => local_entity
                 .and_then(|local| position_storage.get(local))
                 .or(Some(&camera_pos))
                 .map(|pos| (pos.x, pos.y))
@@ -87,6 +91,16 @@ impl WorldRenderer {
             .chunk_handler
             .get_screen_zone((camera_pos.x, camera_pos.y)); // note we always use the camera for the screen zone
 
+        // backgrounds are shifted along with the camera so they appear further away
+        let background_offset = |parallax: f32| {
+            let parallax = f64::from(ctx.settings.background_parallax * parallax);
+            (
+                (camera_pos.x * parallax) as f32,
+                (camera_pos.y * parallax) as f32,
+            )
+        };
+
+        let mut background_layers = vec![];
         let chunk_tex_data = {
             profiling::scope!("build chunk_tex_data");
             unsafe { world.chunk_handler.manager.raw_mut().iter_mut() }
@@ -97,9 +111,20 @@ impl WorldRenderer {
                         CHUNK_SIZE,
                         CHUNK_SIZE,
                     );
+                    let background_visible = ch.graphics.background_layers.iter().any(|layer| {
+                        let offset = background_offset(layer.parallax);
+                        Rect::new_wh(
+                            rc.left() + offset.0 as i32,
+                            rc.top() + offset.1 as i32,
+                            CHUNK_SIZE,
+                            CHUNK_SIZE,
+                        )
+                        .intersects(&screen_zone)
+                    });
 
                     if (ctx.settings.debug && !ctx.settings.cull_chunks)
                         || rc.intersects(&screen_zone)
+                        || background_visible
                     {
                         target.transform.push();
                         target.transform.translate(
@@ -111,6 +136,17 @@ impl WorldRenderer {
 
                         target.transform.pop();
 
+                        for (i, layer) in ch.graphics.background_layers.iter().enumerate() {
+                            if let Some(texture) = &layer.texture {
+                                let offset = background_offset(layer.parallax);
+                                background_layers.push((
+                                    i,
+                                    (rc.left() as f32 + offset.0, rc.top() as f32 + offset.1),
+                                    texture.clone(),
+                                ));
+                            }
+                        }
+
                         // ch.render(target, settings);
                         // ch.graphics.texture
                         // let image = glium::texture::RawImage2d::from_raw_rgba((&ch.graphics.pixel_data).to_vec(), (CHUNK_SIZE.into(), CHUNK_SIZE.into()));
@@ -131,6 +167,13 @@ impl WorldRenderer {
                 .collect::<Vec<_>>()
         };
 
+        // back to front
+        background_layers.sort_by_key(|(i, _, _)| std::cmp::Reverse(*i));
+        let background_layers: Vec<_> = background_layers
+            .into_iter()
+            .map(|(_, pos, texture)| (pos, texture))
+            .collect();
+        target.draw_chunks_background(&background_layers);
         target.draw_chunks(&chunk_tex_data);
 
         // draw tile entities
@@ -185,7 +228,186 @@ impl WorldRenderer {
             self.draw_load_zones(loader_pos, Some(camera_pos.into()), world, target);
         }
 
+        self.draw_brush_preview(&camera_pos, ctx.client, target);
+
         target.transform.pop();
+
+        if ctx.settings.draw_minimap {
+            self.draw_minimap(&camera_pos, world, target, &ctx);
+        }
+    }
+
+    /// Draws the chunks around the camera as one pixel each in the top right corner of the screen.
+    ///
+    /// Each chunk is drawn with [`ChunkGraphics::average_color`], and unloaded chunks are left
+    ///   transparent.
+    ///
+    /// [`ChunkGraphics::average_color`]: super::chunk::ChunkGraphics::average_color
+    fn draw_minimap(
+        &mut self,
+        camera_pos: &Position,
+        world: &mut World<ClientChunk>,
+        target: &mut RenderTarget,
+        ctx: &RenderContext,
+    ) {
+        profiling::scope!("draw_minimap");
+        const CHUNK_PIXELS: f32 = 4.0;
+        const MARGIN: f32 = 10.0;
+
+        let radius = i32::from(ctx.settings.minimap_radius);
+        let size = radius * 2 + 1;
+        let camera_chunk_x = (camera_pos.x / f64::from(CHUNK_SIZE)).floor() as i32;
+        let camera_chunk_y = (camera_pos.y / f64::from(CHUNK_SIZE)).floor() as i32;
+
+        let mut data = Vec::with_capacity((size * size * 4) as usize);
+        for y in -radius..=radius {
+            for x in -radius..=radius {
+                let color = world
+                    .chunk_handler
+                    .manager
+                    .chunk_at_mut((camera_chunk_x + x, camera_chunk_y + y))
+                    .map_or(Color::rgba(0, 0, 0, 0), |ch| ch.graphics.average_color());
+                data.extend_from_slice(&[color.r, color.g, color.b, color.a]);
+            }
+        }
+
+        let size = size as u32;
+        let image = RawImage2d::from_raw_rgba(data, (size, size));
+        let texture: &Texture2d = match &mut self.minimap_texture {
+            Some(texture) if texture.width() == size => {
+                texture.write(
+                    glium::Rect { left: 0, bottom: 0, width: size, height: size },
+                    image,
+                );
+                texture
+            },
+            texture => match Texture2d::new(&target.display, image) {
+                Ok(new) => texture.insert(new),
+                Err(e) => {
+                    log::error!("Failed to create minimap texture: {e}");
+                    *texture = None;
+                    return;
+                },
+            },
+        };
+
+        let map_size = size as f32 * CHUNK_PIXELS;
+        let map_rect = Rect::new_wh(
+            target.width() as f32 - map_size - MARGIN,
+            MARGIN,
+            map_size,
+            map_size,
+        );
+        let param = DrawParameters {
+            blend: Blend::alpha_blending(),
+            ..Default::default()
+        };
+
+        target.rectangle(map_rect, Color::BLACK.with_a(0.5), param.clone());
+        target.draw_texture_flipped(map_rect, texture, param.clone());
+        target.rectangle(
+            map_rect,
+            Color::rgba(255, 255, 255, 127),
+            DrawParameters {
+                polygon_mode: PolygonMode::Line,
+                line_width: Some(1.0),
+                ..param.clone()
+            },
+        );
+
+        // where the camera is
+        let marker_x = map_rect.left()
+            + ((camera_pos.x / f64::from(CHUNK_SIZE)) as f32 - (camera_chunk_x - radius) as f32)
+                * CHUNK_PIXELS;
+        let marker_y = map_rect.top()
+            + ((camera_pos.y / f64::from(CHUNK_SIZE)) as f32 - (camera_chunk_y - radius) as f32)
+                * CHUNK_PIXELS;
+        target.rectangle(
+            Rect::new_wh(marker_x - 1.5, marker_y - 1.5, 3.0, 3.0),
+            Color::RED,
+            param,
+        );
+    }
+
+    fn draw_brush_preview(
+        &mut self,
+        camera_pos: &Position,
+        client: &Client,
+        target: &mut RenderTarget,
+    ) {
+        profiling::scope!("draw_brush_preview");
+        let Some(debug_ui) = &client.debug_ui else {
+            return;
+        };
+
+        let cursor_pos = client.controls.cursor_pos;
+        let world_x =
+            camera_pos.x + (cursor_pos.x - f64::from(target.width()) / 2.0) / client.camera_scale;
+        let world_y =
+            camera_pos.y + (cursor_pos.y - f64::from(target.height()) / 2.0) / client.camera_scale;
+
+        let cursor = (world_x as i64, world_y as i64);
+        let preview_color = Color::rgba(255, 255, 255, 64);
+        let param = DrawParameters {
+            blend: Blend::alpha_blending(),
+            ..Default::default()
+        };
+
+        if let Some(start) = debug_ui.draw.drag_start {
+            match debug_ui.draw.tool {
+                DrawTool::Brush => {},
+                DrawTool::Rectangle => {
+                    let bounds = debug_ui.draw.shape_bounds(start, cursor);
+                    target.rectangle(
+                        Rect::new(
+                            bounds.left() as f32,
+                            bounds.top() as f32,
+                            bounds.right() as f32,
+                            bounds.bottom() as f32,
+                        ),
+                        preview_color,
+                        DrawParameters {
+                            polygon_mode: if debug_ui.draw.rect_outline {
+                                PolygonMode::Line
+                            } else {
+                                PolygonMode::Fill
+                            },
+                            ..param
+                        },
+                    );
+                },
+                DrawTool::Line => {
+                    target.line(
+                        (start.0 as f32 + 0.5, start.1 as f32 + 0.5),
+                        (cursor.0 as f32 + 0.5, cursor.1 as f32 + 0.5),
+                        preview_color,
+                        DrawParameters {
+                            line_width: Some(
+                                f32::from(debug_ui.draw.brush_size) * client.camera_scale as f32,
+                            ),
+                            ..param
+                        },
+                    );
+                },
+            }
+            return;
+        }
+
+        let rects = debug_ui
+            .draw
+            .brush_offsets()
+            .into_iter()
+            .map(|(dx, dy)| {
+                Rect::new_wh(
+                    (world_x as i64 + dx) as f32,
+                    (world_y as i64 + dy) as f32,
+                    1.0,
+                    1.0,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        target.rectangles(&rects, preview_color, param);
     }
 
     fn draw_ecs_debug(
@@ -824,17 +1046,22 @@ impl WorldRenderer {
                         },
                     );
                 }
-            } else if let Some(_poly) = shape.as_convex_polygon() {
-                // target.polygon(
-                //     poly.points()
-                //         .iter()
-                //         .flat_map(|v| {
-                //             let (x, y) = target.transform.transform((v[0], v[1]));
-                //             [x as f32, y as f32]
-                //         })
-                //         .collect(),
-                //     color.into_sdl(),
-                // );
+            } else if let Some(poly) = shape.as_convex_polygon() {
+                let points = poly
+                    .points()
+                    .iter()
+                    .map(|v| Vertex2::from((v[0], v[1])))
+                    .collect::<Vec<_>>();
+                target.polygon(
+                    &points,
+                    color,
+                    DrawParameters {
+                        polygon_mode: PolygonMode::Line,
+                        line_width: Some(1.0),
+                        blend: Blend::alpha_blending(),
+                        ..Default::default()
+                    },
+                );
             } else if let Some(trimesh) = shape.as_trimesh() {
                 for tri in trimesh.triangles() {
                     let (x1, y1) = (tri.a[0], tri.a[1]);
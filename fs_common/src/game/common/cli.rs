@@ -1,4 +1,7 @@
-use std::path::PathBuf;
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+};
 
 use clap::{
     builder::{StringValueParser, TypedValueParser, ValueParserFactory},
@@ -25,6 +28,15 @@ pub struct CLArgs {
     )]
     pub connect: Option<IPPort>,
 
+    #[arg(
+        long,
+        value_name = "NAME",
+        action,
+        default_value = "Player",
+        help = "Set the name sent to servers"
+    )]
+    pub name: String,
+
     #[arg(
         long = "game-dir",
         value_name = "PATH",
@@ -43,6 +55,14 @@ pub struct CLArgs {
     )]
     pub assets_dir: PathBuf,
 
+    #[arg(
+        long,
+        value_name = "SEED",
+        action,
+        help = "Set the world generation seed (random if not set)"
+    )]
+    pub seed: Option<i32>,
+
     #[command(subcommand)]
     pub subcommand: Option<CLSubcommand>,
 }
@@ -58,9 +78,28 @@ pub enum CLSubcommand {
             help = "The port to run the server on"
         )]
         port: u16,
+
+        #[arg(
+            long,
+            alias = "bind",
+            value_name = "IP",
+            action,
+            default_value = "0.0.0.0",
+            help = "The address to listen on"
+        )]
+        host: IpAddr,
     },
 }
 
+impl CLSubcommand {
+    /// The address the server should listen on.
+    pub fn bind_addr(&self) -> SocketAddr {
+        match self {
+            Self::Server { port, host } => SocketAddr::new(*host, *port),
+        }
+    }
+}
+
 impl CLArgs {
     pub fn parse_args() -> Self {
         Self::parse()
@@ -120,3 +159,32 @@ impl TypedValueParser for IPPortParser {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::CLArgs;
+
+    #[test]
+    fn server_bind_addr() {
+        let args = CLArgs::try_parse_from(["fs", "server", "--host", "192.168.1.5", "-p", "7000"])
+            .unwrap();
+        let addr = args.subcommand.unwrap().bind_addr();
+        assert_eq!(addr.to_string(), "192.168.1.5:7000");
+
+        let args = CLArgs::try_parse_from(["fs", "server", "--bind", "::1"]).unwrap();
+        assert_eq!(
+            args.subcommand.unwrap().bind_addr().to_string(),
+            "[::1]:6673"
+        );
+
+        let args = CLArgs::try_parse_from(["fs", "server"]).unwrap();
+        assert_eq!(
+            args.subcommand.unwrap().bind_addr().to_string(),
+            "0.0.0.0:6673"
+        );
+
+        assert!(CLArgs::try_parse_from(["fs", "server", "--host", "not an ip"]).is_err());
+    }
+}
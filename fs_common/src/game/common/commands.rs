@@ -1,4 +1,4 @@
-use clap::{ArgMatches, Command};
+use clap::{value_parser, Arg, ArgMatches, Command};
 
 pub struct CommandHandler {
     commands: Command,
@@ -18,7 +18,18 @@ impl CommandHandler {
                         .aliases(["exit", "quit", "stop"])
                         .about("Exit the game"),
                 )
-                .subcommand(Command::new("save").about("Save the game")),
+                .subcommand(Command::new("save").about("Save the game"))
+                .subcommand(Command::new("pause").about("Pause the simulation"))
+                .subcommand(Command::new("resume").about("Resume the simulation"))
+                .subcommand(
+                    Command::new("step")
+                        .about("Run ticks while the simulation is paused")
+                        .arg(
+                            Arg::new("ticks")
+                                .value_parser(value_parser!(u32))
+                                .default_value("1"),
+                        ),
+                ),
         }
     }
 
@@ -1,29 +1,580 @@
-use super::world::material::{color::Color, MaterialInstance};
+use std::{
+    collections::HashSet,
+    io::{self, Read, Write},
+};
+
+use super::{
+    world::{
+        entity::{GameEntity, Player},
+        material::{color::Color, MaterialInstance},
+        Position, Velocity, CHUNK_AREA, CHUNK_SIZE,
+    },
+    Rect,
+};
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
 use serde::{Deserialize, Serialize};
+use specs::{Join, ReadStorage};
+
+/// Bump this whenever the wire format of [`Packet`] changes.
+pub const PROTOCOL_VERSION: u32 = 9;
+
+/// Encoded packets at least this big are compressed, if that makes them smaller.
+pub const COMPRESSION_THRESHOLD: usize = 1024;
+
+/// Packets longer than this (before or after decompression) are treated as a corrupt stream.
+pub const MAX_PACKET_SIZE: usize = 2_000_000;
+
+/// Flag byte for a packet body that's stored as is.
+const FLAG_RAW: u8 = 0;
+/// Flag byte for a deflate compressed packet body.
+const FLAG_DEFLATE: u8 = 1;
 
 #[derive(Serialize, Deserialize)]
 pub struct Packet {
     pub packet_type: PacketType,
 }
 
+/// Writes `packet` to `w`, returning the number of bytes written.
+///
+/// The wire format is a 4 byte little endian length, followed by that many bytes from
+///   [`Packet::encode`].
+pub fn send_packet<W: Write>(w: &mut W, packet: &Packet) -> io::Result<usize> {
+    let frame = packet.encode()?;
+    let size = u32::try_from(frame.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "packet is too big"))?;
+
+    w.write_all(&size.to_le_bytes())?;
+    w.write_all(&frame)?;
+    w.flush()?;
+    Ok(frame.len() + 4)
+}
+
+/// Reads a single packet written by [`send_packet`] from `r`, blocking until it is complete.
+pub fn read_packet<R: Read>(r: &mut R) -> io::Result<Packet> {
+    let mut size_buf = [0; 4];
+    r.read_exact(&mut size_buf)?;
+
+    let size = u32::from_le_bytes(size_buf) as usize;
+    check_packet_size(size)?;
+
+    let mut frame = vec![0; size];
+    r.read_exact(&mut frame)?;
+    Packet::decode(&frame)
+}
+
+fn invalid_data(e: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// Errors if a length prefix is bigger than [`MAX_PACKET_SIZE`], so it's rejected before
+///   anything is allocated for it.
+fn check_packet_size(size: usize) -> io::Result<()> {
+    if size > MAX_PACKET_SIZE {
+        return Err(invalid_data(format!("Packet is too big ({size} bytes)")));
+    }
+    Ok(())
+}
+
+/// Reads packets written by [`send_packet`] from a nonblocking stream, which may deliver them in
+///   pieces over several reads.
+#[derive(Debug, Default)]
+pub struct PacketReader {
+    /// Bytes received but not decoded yet, starting with the current packet's length prefix.
+    buf: Vec<u8>,
+}
+
+impl PacketReader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads what's available from `r`, returning the next packet once all of it has arrived.
+    ///
+    /// Returns [`None`] when `r` would block before that, keeping what was read for the next call.
+    /// Errors if `r` fails or closes, or the data isn't a valid packet; the stream can't be read
+    ///   from after that.
+    pub fn read<R: Read>(&mut self, r: &mut R) -> io::Result<Option<Packet>> {
+        loop {
+            if let Some(size) = self.packet_size()? {
+                if self.buf.len() >= 4 + size {
+                    let packet = Packet::decode(&self.buf[4..4 + size]);
+                    self.buf.drain(..4 + size);
+                    return packet.map(Some);
+                }
+            }
+
+            let mut chunk = [0; 4096];
+            match r.read(&mut chunk) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => self.buf.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(None),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Bytes still missing from the current packet, or [`None`] if its length hasn't arrived yet.
+    pub fn remaining(&self) -> Option<usize> {
+        let size = self.packet_size().ok()??;
+        Some((4 + size).saturating_sub(self.buf.len()))
+    }
+
+    fn packet_size(&self) -> io::Result<Option<usize>> {
+        let Some(prefix) = self.buf.get(..4) else {
+            return Ok(None);
+        };
+
+        let size = u32::from_le_bytes(prefix.try_into().unwrap()) as usize;
+        check_packet_size(size)?;
+        Ok(Some(size))
+    }
+}
+
+impl Packet {
+    /// Encodes this packet as a flag byte followed by the bincode encoded packet.
+    ///
+    /// Packets of at least [`COMPRESSION_THRESHOLD`] bytes are deflate compressed (and flagged as
+    ///   such) if that makes them smaller, which it usually does for chunk colors.
+    pub fn encode(&self) -> io::Result<Vec<u8>> {
+        let body = bincode::serialize(self).map_err(invalid_data)?;
+
+        if body.len() >= COMPRESSION_THRESHOLD {
+            let mut encoder = DeflateEncoder::new(vec![FLAG_DEFLATE], Compression::fast());
+            encoder.write_all(&body)?;
+            let compressed = encoder.finish()?;
+            if compressed.len() <= body.len() {
+                return Ok(compressed);
+            }
+        }
+
+        let mut frame = Vec::with_capacity(body.len() + 1);
+        frame.push(FLAG_RAW);
+        frame.extend(body);
+        Ok(frame)
+    }
+
+    /// Decodes a packet encoded by [`Packet::encode`].
+    ///
+    /// Compressed bodies that inflate past [`MAX_PACKET_SIZE`] are rejected.
+    pub fn decode(frame: &[u8]) -> io::Result<Self> {
+        match frame.split_first() {
+            Some((&FLAG_RAW, body)) => bincode::deserialize(body).map_err(invalid_data),
+            Some((&FLAG_DEFLATE, body)) => {
+                let inflated = DeflateDecoder::new(body).take(MAX_PACKET_SIZE as u64);
+                bincode::deserialize_from(inflated).map_err(invalid_data)
+            },
+            Some((flag, _)) => Err(invalid_data(format!("Unknown packet flag {flag}"))),
+            None => Err(invalid_data("Empty packet")),
+        }
+    }
+
+    /// The first packet the client sends.
+    pub fn hello(client_name: impl Into<String>) -> Self {
+        Self {
+            packet_type: PacketType::HelloPacket {
+                protocol_version: PROTOCOL_VERSION,
+                chunk_size: CHUNK_SIZE,
+                client_name: client_name.into(),
+            },
+        }
+    }
+
+    /// The server's answer to an accepted [`PacketType::HelloPacket`].
+    pub fn welcome(server_tick_speed: u16) -> Self {
+        Self {
+            packet_type: PacketType::WelcomePacket {
+                protocol_version: PROTOCOL_VERSION,
+                chunk_size: CHUNK_SIZE,
+                server_tick_speed,
+            },
+        }
+    }
+}
+
+/// Checks a remote's handshake against our own constants.
+///
+/// Returns a human readable reason if the two sides can't talk to each other.
+pub fn check_handshake(protocol_version: u32, chunk_size: u16) -> Result<(), String> {
+    if protocol_version != PROTOCOL_VERSION {
+        return Err(format!(
+            "Protocol version mismatch: remote is {protocol_version}, local is {PROTOCOL_VERSION}"
+        ));
+    }
+
+    if chunk_size != CHUNK_SIZE {
+        return Err(format!(
+            "CHUNK_SIZE mismatch: remote is {chunk_size}, local is {CHUNK_SIZE}"
+        ));
+    }
+
+    Ok(())
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct PVec2 {
     pub x: f32,
     pub y: f32,
 }
 
+/// What a synced entity is, so the receiving side knows how to represent it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntityKind {
+    Player,
+    Other,
+}
+
+/// One entity's change in a [`PacketType::EntitySyncPacket`].
+///
+/// `id` is the sender's [`specs::Entity::id`], it has no meaning on the receiving side's ECS.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum EntitySync {
+    Update {
+        id: u32,
+        kind: EntityKind,
+        position: Position,
+        velocity: Velocity,
+    },
+    Remove {
+        id: u32,
+    },
+}
+
+impl EntitySync {
+    /// Snapshots every [`GameEntity`] with a [`Position`] and [`Velocity`] in `ecs`.
+    ///
+    /// `synced` holds the ids sent by the previous call and is updated to the current ones, so
+    ///   entities that disappeared since then get an [`EntitySync::Remove`].
+    pub fn collect(ecs: &specs::World, synced: &mut HashSet<u32>) -> Vec<Self> {
+        let (entities, game_entity_storage, position_storage, velocity_storage, player_storage) =
+            ecs.system_data::<(
+                specs::Entities,
+                ReadStorage<GameEntity>,
+                ReadStorage<Position>,
+                ReadStorage<Velocity>,
+                ReadStorage<Player>,
+            )>();
+
+        let mut out = vec![];
+        let mut current = HashSet::new();
+        for (entity, _, position, velocity, player) in (
+            &entities,
+            &game_entity_storage,
+            &position_storage,
+            &velocity_storage,
+            player_storage.maybe(),
+        )
+            .join()
+        {
+            current.insert(entity.id());
+            out.push(Self::Update {
+                id: entity.id(),
+                kind: if player.is_some() {
+                    EntityKind::Player
+                } else {
+                    EntityKind::Other
+                },
+                position: position.clone(),
+                velocity: velocity.clone(),
+            });
+        }
+
+        out.extend(synced.difference(&current).map(|&id| Self::Remove { id }));
+        *synced = current;
+
+        out
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub enum PacketType {
+    /// Must be the first packet sent by the client.
+    ///
+    /// The server answers with a [`PacketType::WelcomePacket`], or a [`PacketType::DisconnectPacket`]
+    ///   if it refuses the client (see [`check_handshake`]).
+    HelloPacket {
+        protocol_version: u32,
+        chunk_size: u16,
+        client_name: String,
+    },
+    /// Sent by the server once it has accepted a [`PacketType::HelloPacket`].
+    WelcomePacket {
+        protocol_version: u32,
+        chunk_size: u16,
+        server_tick_speed: u16,
+    },
+    /// Sent right before the sender closes the connection.
+    DisconnectPacket { reason: String },
+    /// Sent by the client when its local entity moves.
+    ///
+    /// The server keeps the chunks around this position loaded for the client, so it can answer
+    ///   its [`PacketType::RequestChunkPacket`]s.
+    ClientPositionPacket { x: f64, y: f64 },
+    /// Sent by the client for chunks entering its load zone.
+    ///
+    /// The server answers with a [`PacketType::SyncChunkPacket`] once it has the chunk. Requests
+    ///   for chunks that don't load in time (eg. far from the last [`PacketType::ClientPositionPacket`])
+    ///   are dropped.
+    RequestChunkPacket { chunk_x: i32, chunk_y: i32 },
+    /// Sent by the client when it gets a [`PacketType::ChunkDeltaPacket`] for a chunk it wants but
+    ///   doesn't have (eg. it dropped it).
+    ///
+    /// The server stops sending deltas for the chunk and answers like a
+    ///   [`PacketType::RequestChunkPacket`], with the whole chunk.
+    ResyncChunkPacket { chunk_x: i32, chunk_y: i32 },
     SyncChunkPacket {
         chunk_x: i32,
         chunk_y: i32,
         pixels: Vec<MaterialInstance>,
         colors: Vec<Color>,
     },
+    /// Sent by the server for chunks the client already has, with the pixels that changed.
+    ///
+    /// See [`PacketType::chunk_delta`].
+    ChunkDeltaPacket {
+        chunk_x: i32,
+        chunk_y: i32,
+        changes: Vec<(u16, u16, MaterialInstance)>,
+    },
     SyncLiquidFunPacket {
         //TODO: this assumes the number of particles never changes
         //      should probably send the entire state of rapier/salva (or only nearby?)
         positions: Vec<PVec2>,
         velocities: Vec<PVec2>,
     },
+    /// Sent periodically by the server with the state of its entities.
+    EntitySyncPacket { entities: Vec<EntitySync> },
+    /// Sent by the client for pixels it changed (eg. with the draw tools), in world coordinates.
+    ///
+    /// Edits outside of chunks loaded on the server are ignored. The rest reach other clients
+    ///   through the usual chunk syncing.
+    SetPixelsPacket {
+        edits: Vec<(i64, i64, MaterialInstance)>,
+    },
+}
+
+impl PacketType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PacketType::HelloPacket { .. } => "HelloPacket",
+            PacketType::WelcomePacket { .. } => "WelcomePacket",
+            PacketType::DisconnectPacket { .. } => "DisconnectPacket",
+            PacketType::ClientPositionPacket { .. } => "ClientPositionPacket",
+            PacketType::RequestChunkPacket { .. } => "RequestChunkPacket",
+            PacketType::ResyncChunkPacket { .. } => "ResyncChunkPacket",
+            PacketType::SyncChunkPacket { .. } => "SyncChunkPacket",
+            PacketType::ChunkDeltaPacket { .. } => "ChunkDeltaPacket",
+            PacketType::SyncLiquidFunPacket { .. } => "SyncLiquidFunPacket",
+            PacketType::EntitySyncPacket { .. } => "EntitySyncPacket",
+            PacketType::SetPixelsPacket { .. } => "SetPixelsPacket",
+        }
+    }
+
+    /// A [`PacketType::ChunkDeltaPacket`] with the pixels of `pixels` inside `rect`.
+    ///
+    /// Returns [`None`] if `rect` covers the whole chunk, since each change takes about as much
+    ///   space as a pixel and its color in a [`PacketType::SyncChunkPacket`], so the full sync
+    ///   wouldn't be any larger.
+    pub fn chunk_delta(
+        chunk_x: i32,
+        chunk_y: i32,
+        pixels: &[MaterialInstance; CHUNK_AREA],
+        rect: Rect<u16>,
+    ) -> Option<Self> {
+        if usize::from(rect.width()) * usize::from(rect.height()) >= CHUNK_AREA {
+            return None;
+        }
+
+        let changes = rect
+            .range_tb()
+            .flat_map(|y| rect.range_lr().map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let i = usize::from(x) + usize::from(y) * usize::from(CHUNK_SIZE);
+                (x, y, pixels[i].clone())
+            })
+            .collect();
+
+        Some(Self::ChunkDeltaPacket { chunk_x, chunk_y, changes })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use specs::{Builder, WorldExt};
+
+    use crate::game::common::world::{
+        ecs,
+        entity::GameEntity,
+        material::{self, color::Color, MaterialInstance, PhysicsType},
+        Position, Velocity, CHUNK_AREA,
+    };
+
+    use super::{
+        read_packet, send_packet, EntitySync, Packet, PacketReader, PacketType, FLAG_DEFLATE,
+        MAX_PACKET_SIZE,
+    };
+
+    #[test]
+    fn entity_sync() {
+        let mut ecs = ecs();
+        let entity = ecs
+            .create_entity()
+            .with(GameEntity)
+            .with(Position { x: 1.0, y: 2.0 })
+            .with(Velocity { x: 0.0, y: 0.0 })
+            .build();
+        // not a GameEntity, so not synced
+        ecs.create_entity()
+            .with(Position { x: 0.0, y: 0.0 })
+            .with(Velocity { x: 0.0, y: 0.0 })
+            .build();
+
+        let mut synced = HashSet::new();
+        let sync = EntitySync::collect(&ecs, &mut synced);
+        assert_eq!(sync.len(), 1);
+        assert!(matches!(
+            &sync[0],
+            EntitySync::Update { id, position, .. }
+                if *id == entity.id() && *position == Position { x: 1.0, y: 2.0 }
+        ));
+
+        ecs.delete_entity(entity).unwrap();
+        ecs.maintain();
+
+        let sync = EntitySync::collect(&ecs, &mut synced);
+        assert_eq!(sync.len(), 1);
+        assert!(matches!(sync[0], EntitySync::Remove { id } if id == entity.id()));
+        assert!(synced.is_empty());
+    }
+
+    #[test]
+    fn compressed_round_trip() {
+        let mut pixels = vec![MaterialInstance::air(); CHUNK_AREA];
+        pixels[12] = material::TEST.instance(PhysicsType::Solid, Color::rgb(1, 2, 3));
+        let colors = vec![Color::rgb(4, 5, 6); CHUNK_AREA];
+        let packet = Packet {
+            packet_type: PacketType::SyncChunkPacket {
+                chunk_x: -3,
+                chunk_y: 7,
+                pixels: pixels.clone(),
+                colors: colors.clone(),
+            },
+        };
+
+        let mut buf = std::io::Cursor::new(vec![]);
+        let written = send_packet(&mut buf, &packet).unwrap();
+        let buf = buf.into_inner();
+        assert_eq!(written, buf.len());
+        // chunk data compresses well
+        assert_eq!(buf[4], FLAG_DEFLATE);
+        assert!(buf.len() < CHUNK_AREA);
+
+        // small packets aren't compressed, and several can be read back to back
+        let mut buf = std::io::Cursor::new(buf);
+        buf.set_position(buf.get_ref().len() as u64);
+        send_packet(&mut buf, &Packet::hello("test")).unwrap();
+        buf.set_position(0);
+
+        let PacketType::SyncChunkPacket {
+            chunk_x,
+            chunk_y,
+            pixels: read_pixels,
+            colors: read_colors,
+        } = read_packet(&mut buf).unwrap().packet_type
+        else {
+            panic!("expected a SyncChunkPacket");
+        };
+        assert_eq!((chunk_x, chunk_y), (-3, 7));
+        assert_eq!(read_pixels, pixels);
+        assert_eq!(read_colors, colors);
+
+        assert!(matches!(
+            read_packet(&mut buf).unwrap().packet_type,
+            PacketType::HelloPacket { .. }
+        ));
+        assert!(read_packet(&mut buf).is_err());
+    }
+
+    /// Hands out its pieces one read at a time, blocking (with [`std::io::ErrorKind::WouldBlock`])
+    ///   between them.
+    struct Trickle(Vec<Option<Vec<u8>>>);
+
+    impl std::io::Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.0.is_empty() {
+                return Ok(0);
+            }
+            match self.0.remove(0) {
+                Some(piece) => {
+                    buf[..piece.len()].copy_from_slice(&piece);
+                    Ok(piece.len())
+                },
+                None => Err(std::io::ErrorKind::WouldBlock.into()),
+            }
+        }
+    }
+
+    #[test]
+    fn split_packet() {
+        let mut bytes = vec![];
+        send_packet(&mut bytes, &Packet::hello("test")).unwrap();
+        send_packet(
+            &mut bytes,
+            &Packet {
+                packet_type: PacketType::RequestChunkPacket { chunk_x: 1, chunk_y: -2 },
+            },
+        )
+        .unwrap();
+
+        // the first packet is split inside its body, the second inside its length prefix
+        let first_len = bytes.len() - 4 - 1 - 12;
+        let (a, rest) = bytes.split_at(5);
+        let (b, rest) = rest.split_at(first_len - 5 + 2);
+        let mut stream = Trickle(vec![
+            Some(a.to_vec()),
+            None,
+            Some(b.to_vec()),
+            None,
+            Some(rest.to_vec()),
+        ]);
+
+        let mut reader = PacketReader::new();
+        assert!(reader.read(&mut stream).unwrap().is_none());
+        assert!(reader.remaining().unwrap() > 0);
+
+        let packet = reader.read(&mut stream).unwrap().unwrap();
+        assert!(matches!(packet.packet_type, PacketType::HelloPacket { .. }));
+        assert!(reader.read(&mut stream).unwrap().is_none());
+        assert_eq!(reader.remaining(), None);
+
+        let packet = reader.read(&mut stream).unwrap().unwrap();
+        assert!(matches!(
+            packet.packet_type,
+            PacketType::RequestChunkPacket { chunk_x: 1, chunk_y: -2 }
+        ));
+
+        // the stream closed
+        assert!(reader.read(&mut stream).is_err());
+    }
+
+    #[test]
+    fn oversized_packets() {
+        // a huge length prefix is refused without waiting for (or allocating) the body
+        let mut bytes = std::io::Cursor::new(u32::MAX.to_le_bytes().to_vec());
+        let err = read_packet(&mut bytes).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        // a small compressed body can't inflate into something bigger than a packet can be
+        let edits = (0..200_000)
+            .map(|i| (i, 0, MaterialInstance::air()))
+            .collect();
+        let bomb = Packet { packet_type: PacketType::SetPixelsPacket { edits } }
+            .encode()
+            .unwrap();
+        assert_eq!(bomb[0], FLAG_DEFLATE);
+        assert!(bomb.len() < MAX_PACKET_SIZE);
+        assert!(Packet::decode(&bomb).is_err());
+    }
 }
@@ -11,6 +11,7 @@ use super::{
         material::{
             self,
             placer::{self, MaterialPlacerRegistry},
+            properties::{self, MaterialPropertiesRegistry},
             MaterialRegistry,
         },
     },
@@ -20,6 +21,7 @@ use super::{
 pub struct Registries {
     pub materials: MaterialRegistry,
     pub material_placers: MaterialPlacerRegistry,
+    pub material_properties: MaterialPropertiesRegistry,
     pub structure_pieces: StructurePieceRegistry,
     pub structure_pools: StructurePoolRegistry,
     pub configured_structures: ConfiguredStructureRegistry,
@@ -32,6 +34,7 @@ impl Registries {
         Self {
             materials: material::init_material_types(),
             material_placers: placer::init_material_placers(file_helper),
+            material_properties: properties::init_material_properties(),
             structure_pieces: structure::piece::init_structure_pieces(file_helper),
             structure_pools: structure::pool::init_structure_pools(file_helper),
             configured_structures: structure::configured_structure::init_configured_structures(
@@ -46,6 +49,7 @@ impl Registries {
         Self {
             materials: MaterialRegistry::new(),
             material_placers: MaterialPlacerRegistry::new(),
+            material_properties: MaterialPropertiesRegistry::new(),
             structure_pieces: StructurePieceRegistry::new(),
             structure_pools: StructurePoolRegistry::new(),
             configured_structures: ConfiguredStructureRegistry::new(),
@@ -1,9 +1,27 @@
-use super::{registry::RegistryID, world::gen::structure::set::StructureSet};
+use super::{
+    registry::RegistryID,
+    world::{
+        gen::structure::set::StructureSet,
+        light_size, mesh,
+        particle::{ParticleOverflowPolicy, DEFAULT_MAX_PARTICLES},
+        LIGHT_SCALE,
+    },
+};
+
+/// Upper bound for [`Settings::simulation_substeps`].
+pub const MAX_SIMULATION_SUBSTEPS: u8 = 8;
 
 pub struct Settings {
     pub debug: bool,
+    /// Whether profiler scopes are recorded and summarized in the debug UI.
+    ///
+    /// Only has an effect when built with the `profile-puffin` feature.
+    pub profiling: bool,
 
     // rendering
+    /// The last preset applied with [`Settings::apply_graphics_preset`], or [`GraphicsPreset::Custom`]
+    ///   if any of the fields it covers were changed afterwards.
+    pub graphics_preset: GraphicsPreset,
     pub draw_chunk_state_overlay: bool,
     pub draw_chunk_state_overlay_alpha: f32,
     pub draw_chunk_dirty_rects: bool,
@@ -18,7 +36,25 @@ pub struct Settings {
     pub lighting_dithering: bool,
     pub lighting_overlay: bool,
     pub lighting_linear_blend: bool,
+    /// Chunk pixels per lighting cell along each axis; bigger is coarser but cheaper.
+    ///
+    /// Must be accepted by [`light_size`], see [`Settings::valid_light_scale`].
+    pub light_scale: u8,
+    /// Only recompute lighting for active chunks, leaving cached ones with their last lighting.
+    pub lighting_active_chunks_only: bool,
+    /// How much the background layer lags behind the foreground as the camera moves.
+    ///
+    /// `0.0` keeps it locked to the foreground, `1.0` keeps it fixed on screen.
+    pub background_parallax: f32,
     pub cull_chunks: bool,
+    pub draw_minimap: bool,
+    /// Number of chunks shown on the minimap in each direction from the camera.
+    pub minimap_radius: u8,
+    /// Tolerance used when simplifying chunk collision meshes; higher is coarser but cheaper.
+    pub mesh_simplify_epsilon: f64,
+    /// Maximum number of particles, see [`super::world::particle::ParticleSystem::max_particles`].
+    pub max_particles: usize,
+    pub particle_overflow_policy: ParticleOverflowPolicy,
     pub physics_dbg_draw: bool,
     pub physics_dbg_draw_shape: bool,
     pub physics_dbg_draw_joint: bool,
@@ -35,14 +71,37 @@ pub struct Settings {
 
     // simulation
     pub tick: bool,
+    /// Ticks still to run while [`Settings::tick`] is off; each one taken decrements it.
+    ///
+    /// Lets a paused simulation be stepped one tick at a time.
+    pub tick_steps: u32,
     pub tick_speed: u16,
     pub tick_physics: bool,
     pub tick_physics_speed: u16,
     pub tick_physics_timestep: f32,
     pub load_chunks: bool,
     pub simulate_chunks: bool,
+    /// Number of chunk simulation passes per tick (clamped to `1..=`[`MAX_SIMULATION_SUBSTEPS`]).
+    ///
+    /// More substeps make falling and reacting materials update in finer steps, but each one is a
+    ///   full simulation pass, so the simulation cost of a tick grows linearly with it.
+    pub simulation_substeps: u8,
     pub simulate_particles: bool,
+    /// Diffuse heat between pixels and apply the registered phase transitions (eg. water boiling).
+    ///
+    /// Off by default, since it keeps chunks with temperature differences dirty until they settle.
+    /// Only has an effect when built with the `heat` feature.
+    pub simulate_heat: bool,
     pub pause_on_lost_focus: bool,
+    pub autosave: bool,
+    /// Seconds between autosaves.
+    pub autosave_interval: u16,
+
+    // networking
+    /// Bytes of chunk updates the server sends to each client per tick.
+    ///
+    /// A single chunk can go over this, so at least one dirty chunk is sent per tick.
+    pub chunk_sync_budget: usize,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -64,10 +123,81 @@ impl ChunkCollisionOverlay {
     }
 }
 
+/// Bundles of the performance-relevant [`Settings`], see [`Settings::apply_graphics_preset`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphicsPreset {
+    Low,
+    Medium,
+    High,
+    /// Set when a field covered by the presets is changed by hand.
+    Custom,
+}
+
+impl GraphicsPreset {
+    /// The presets that can be applied (everything but [`GraphicsPreset::Custom`]).
+    pub fn values() -> &'static [Self] {
+        &[Self::Low, Self::Medium, Self::High]
+    }
+}
+
+impl Settings {
+    /// [`Settings::light_scale`], or [`LIGHT_SCALE`] if it isn't valid.
+    pub fn valid_light_scale(&self) -> u8 {
+        if light_size(self.light_scale).is_some() {
+            self.light_scale
+        } else {
+            LIGHT_SCALE
+        }
+    }
+
+    /// Sets the lighting, light scale, culling, mesh simplification and particle cap fields to the
+    ///   values for `preset`.
+    ///
+    /// Applying [`GraphicsPreset::Custom`] leaves the fields as they are.
+    pub fn apply_graphics_preset(&mut self, preset: GraphicsPreset) {
+        self.graphics_preset = preset;
+        match preset {
+            GraphicsPreset::Low => {
+                self.draw_lighting = false;
+                self.lighting_smooth = false;
+                self.lighting_dithering = false;
+                self.lighting_active_chunks_only = true;
+                self.light_scale = 10;
+                self.cull_chunks = true;
+                self.mesh_simplify_epsilon = 2.0;
+                self.max_particles = 20_000;
+            },
+            GraphicsPreset::Medium => {
+                self.draw_lighting = true;
+                self.lighting_smooth = true;
+                self.lighting_dithering = false;
+                self.lighting_active_chunks_only = true;
+                self.light_scale = 5;
+                self.cull_chunks = true;
+                self.mesh_simplify_epsilon = 1.5;
+                self.max_particles = 50_000;
+            },
+            GraphicsPreset::High => {
+                self.draw_lighting = true;
+                self.lighting_smooth = true;
+                self.lighting_dithering = true;
+                self.lighting_active_chunks_only = false;
+                self.light_scale = LIGHT_SCALE;
+                self.cull_chunks = true;
+                self.mesh_simplify_epsilon = mesh::DEFAULT_SIMPLIFY_EPSILON;
+                self.max_particles = DEFAULT_MAX_PARTICLES;
+            },
+            GraphicsPreset::Custom => {},
+        }
+    }
+}
+
 impl Default for Settings {
     fn default() -> Self {
         Self {
             debug: false,
+            profiling: false,
+            graphics_preset: GraphicsPreset::High,
             draw_chunk_state_overlay: false,
             draw_chunk_state_overlay_alpha: 0.5,
             draw_chunk_dirty_rects: false,
@@ -83,8 +213,16 @@ impl Default for Settings {
             lighting_dithering: true,
             lighting_overlay: false,
             lighting_linear_blend: true,
+            light_scale: LIGHT_SCALE,
+            lighting_active_chunks_only: false,
+            background_parallax: 0.1,
 
             cull_chunks: true,
+            draw_minimap: false,
+            minimap_radius: 16,
+            mesh_simplify_epsilon: mesh::DEFAULT_SIMPLIFY_EPSILON,
+            max_particles: DEFAULT_MAX_PARTICLES,
+            particle_overflow_policy: ParticleOverflowPolicy::RejectNew,
             physics_dbg_draw: false,
             physics_dbg_draw_shape: true,
             physics_dbg_draw_joint: true,
@@ -99,14 +237,43 @@ impl Default for Settings {
             minimize_on_lost_focus: false,
 
             tick: true,
+            tick_steps: 0,
             tick_speed: 30,
             tick_physics: true,
             tick_physics_speed: 60,
             tick_physics_timestep: 1.0 / 45.0,
             load_chunks: true,
             simulate_chunks: true,
+            simulation_substeps: 1,
             simulate_particles: true,
+            simulate_heat: false,
             pause_on_lost_focus: false,
+            autosave: true,
+            autosave_interval: 60,
+
+            chunk_sync_budget: 1_000_000,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::common::world::light_scales;
+
+    use super::{GraphicsPreset, Settings};
+
+    #[test]
+    fn presets_use_valid_light_scales() {
+        let mut settings = Settings::default();
+        for preset in GraphicsPreset::values() {
+            settings.apply_graphics_preset(*preset);
+            assert!(
+                light_scales().any(|s| s == settings.light_scale),
+                "{preset:?} has an invalid light scale"
+            );
         }
+
+        // the default settings are the high preset
+        assert_eq!(settings.light_scale, Settings::default().light_scale);
     }
 }
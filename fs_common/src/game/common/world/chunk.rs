@@ -8,19 +8,59 @@ use std::fmt::Debug;
 
 use super::chunk_data::SidedChunkData;
 use super::chunk_index::ChunkLocalPosition;
+use super::chunk_pool::ChunkBuffers;
 use super::material::color::Color;
-use super::mesh::Mesh;
+use super::mesh::{self, Mesh};
 use super::tile_entity::{TileEntity, TileEntityCommon};
 use crate::game::common::world::material::MaterialInstance;
 
 pub const CHUNK_SIZE: u16 = 100;
 pub const CHUNK_AREA: usize = CHUNK_SIZE as usize * CHUNK_SIZE as usize;
-// must be a factor of CHUNK_SIZE
-// also (CHUNK_SIZE / LIGHT_SCALE)^2 must be <= 1024 for compute shader (and local_size needs to be set to CHUNK_SIZE / LIGHT_SCALE in the shader)
+/// Default light scale, see [`light_size`].
 pub const LIGHT_SCALE: u8 = 4;
+/// Max number of cells in a chunk's lighting grid.
+///
+/// Each chunk's lighting is computed by a single compute shader work group, and 1024 is the
+///   smallest invocation limit OpenGL allows.
+pub const MAX_LIGHT_CELLS: usize = 1024;
+
+/// Width and height of a chunk's lighting grid when each cell covers `scale`x`scale` pixels.
+///
+/// Returns [`None`] if `scale` doesn't divide [`CHUNK_SIZE`] or the grid would have more than
+///   [`MAX_LIGHT_CELLS`] cells.
+pub const fn light_size(scale: u8) -> Option<u16> {
+    if scale == 0 || CHUNK_SIZE % scale as u16 != 0 {
+        return None;
+    }
+
+    let size = CHUNK_SIZE / scale as u16;
+    if size as usize * size as usize > MAX_LIGHT_CELLS {
+        return None;
+    }
+
+    Some(size)
+}
+
+/// Every scale accepted by [`light_size`], smallest (finest) first.
+pub fn light_scales() -> impl Iterator<Item = u8> {
+    (1..=u8::MAX).filter(|&scale| light_size(scale).is_some())
+}
 
 pub trait Chunk {
     fn new_empty(chunk_x: i32, chunk_y: i32) -> Self
+    where
+        Self: Sized,
+    {
+        Self::new_with_buffers(chunk_x, chunk_y, ChunkBuffers::default())
+    }
+    /// Like [`Chunk::new_empty`], but reusing existing buffers (eg. from a [`ChunkBufferPool`]).
+    ///
+    /// [`ChunkBufferPool`]: super::chunk_pool::ChunkBufferPool
+    fn new_with_buffers(chunk_x: i32, chunk_y: i32, buffers: ChunkBuffers) -> Self
+    where
+        Self: Sized;
+    /// Consumes the chunk, returning its buffers so they can be reused.
+    fn into_buffers(self) -> ChunkBuffers
     where
         Self: Sized;
 
@@ -33,10 +73,19 @@ pub trait Chunk {
     fn dirty_rect(&self) -> Option<Rect<i32>>;
     fn set_dirty_rect(&mut self, rect: Option<Rect<i32>>);
 
+    /// Whether the chunk changed since it was last saved.
+    fn needs_save(&self) -> bool;
+    fn set_needs_save(&mut self, needs_save: bool);
+
+    /// Whether the chunk is excluded from simulation. See [`super::chunk_handler::ChunkHandler::set_frozen`].
+    fn frozen(&self) -> bool;
+    fn set_frozen(&mut self, frozen: bool);
+
     fn set_pixels(&mut self, pixels: Box<[MaterialInstance; CHUNK_AREA]>);
     fn pixels_mut(&mut self) -> &mut Option<Box<[MaterialInstance; CHUNK_AREA]>>;
     fn pixels(&self) -> &Option<Box<[MaterialInstance; CHUNK_AREA]>>;
-    fn set_pixel_colors(&mut self, colors: Box<[Color; CHUNK_AREA]>);
+    /// Replaces the color buffer, returning the previous one.
+    fn set_pixel_colors(&mut self, colors: Box<[Color; CHUNK_AREA]>) -> Box<[Color; CHUNK_AREA]>;
     fn colors_mut(&mut self) -> &mut [Color; CHUNK_AREA];
     fn colors(&self) -> &[Color; CHUNK_AREA];
     fn lights_mut(&mut self) -> &mut [[f32; 4]; CHUNK_AREA];
@@ -44,18 +93,34 @@ pub trait Chunk {
     fn set_background_pixels(&mut self, pixels: Box<[MaterialInstance; CHUNK_AREA]>);
     fn background_pixels_mut(&mut self) -> &mut Option<Box<[MaterialInstance; CHUNK_AREA]>>;
     fn background_pixels(&self) -> &Option<Box<[MaterialInstance; CHUNK_AREA]>>;
-    fn set_background_pixel_colors(&mut self, colors: Box<[Color; CHUNK_AREA]>);
+    /// Replaces the background color buffer, returning the previous one.
+    fn set_background_pixel_colors(
+        &mut self,
+        colors: Box<[Color; CHUNK_AREA]>,
+    ) -> Box<[Color; CHUNK_AREA]>;
     fn background_colors_mut(&mut self) -> &mut [Color; CHUNK_AREA];
     fn background_colors(&self) -> &[Color; CHUNK_AREA];
 
-    fn generate_mesh(&mut self) -> Result<(), String>;
-    // fn get_tris(&self) -> &Option<Vec<Vec<((f64, f64), (f64, f64), (f64, f64))>>>;
+    fn generate_mesh(&mut self) -> Result<(), String> {
+        self.generate_mesh_with_epsilon(mesh::DEFAULT_SIMPLIFY_EPSILON)
+    }
+    /// Like [`Chunk::generate_mesh`], but with a custom simplification tolerance (see [`mesh::generate_mesh_with_simplified`]).
+    fn generate_mesh_with_epsilon(&mut self, epsilon: f64) -> Result<(), String>;
+    /// Regenerates the part of the mesh around pixels that became solid or stopped being solid since
+    ///   it was last generated (see [`mesh::update_mesh_with_simplified`]).
+    ///
+    /// Returns `false` if there were none.
+    fn update_mesh_with_epsilon(&mut self, epsilon: f64) -> Result<bool, String>;
     fn mesh_loops(&self) -> &Option<Mesh>;
     fn rigidbody(&self) -> &Option<ChunkRigidBodyState>;
     fn rigidbody_mut(&mut self) -> &mut Option<ChunkRigidBodyState>;
     fn set_rigidbody(&mut self, body: Option<ChunkRigidBodyState>);
 
     fn mark_dirty(&mut self);
+    /// Like [`Chunk::mark_dirty`], but only the pixels in `rect` (within the chunk) changed.
+    fn mark_dirty_rect(&mut self, _rect: Rect<i32>) {
+        self.mark_dirty();
+    }
 
     fn refresh(&mut self);
 
@@ -113,6 +178,44 @@ pub trait Chunk {
                 .unwrap(); // TODO: handle this Err
         }
     }
+
+    /// Sets every pixel in `rect` (in chunk local pixels, clipped to the chunk) to `mat`, updating
+    ///   colors and lights like [`Chunk::set_pixel`].
+    ///
+    /// Unlike setting each pixel, this only adds `rect` to the dirty rect instead of the whole chunk.
+    #[profiling::function]
+    fn fill_rect(&mut self, rect: Rect<i32>, mat: &MaterialInstance) -> Result<(), String> {
+        if self.pixels().is_none() {
+            return Err("Chunk is not ready yet.".to_string());
+        }
+
+        let size = i32::from(CHUNK_SIZE);
+        let rect = Rect::new(
+            rect.x1.max(0),
+            rect.y1.max(0),
+            rect.x2.min(size),
+            rect.y2.min(size),
+        );
+        if rect.x1 >= rect.x2 || rect.y1 >= rect.y2 {
+            return Ok(());
+        }
+
+        let dirty_rect = self.dirty_rect();
+        for y in rect.range_tb() {
+            for x in rect.range_lr() {
+                // Safety: the pixels are loaded and `rect` is clipped to the chunk
+                unsafe {
+                    self.set_pixel_unchecked(
+                        ChunkLocalPosition::new_unchecked(x as u16, y as u16),
+                        mat.clone(),
+                    );
+                }
+            }
+        }
+        self.set_dirty_rect(Some(dirty_rect.map_or(rect, |r| r.union(rect))));
+
+        Ok(())
+    }
 }
 
 pub trait SidedChunk: Chunk {
@@ -167,42 +270,55 @@ mod tests {
     #[test]
     fn chunk_index_correct() {
         // center
-        assert_eq!(chunk_index(0, 0), 0);
-        assert_eq!(chunk_index(1, 0), 3);
-        assert_eq!(chunk_index(0, 1), 5);
-        assert_eq!(chunk_index(1, 1), 12);
-        assert_eq!(chunk_index(-1, 0), 1);
-        assert_eq!(chunk_index(0, -1), 2);
-        assert_eq!(chunk_index(-1, -1), 4);
-        assert_eq!(chunk_index(1, -1), 7);
-        assert_eq!(chunk_index(-1, 1), 8);
+        assert_eq!(chunk_index(0, 0), Ok(0));
+        assert_eq!(chunk_index(1, 0), Ok(3));
+        assert_eq!(chunk_index(0, 1), Ok(5));
+        assert_eq!(chunk_index(1, 1), Ok(12));
+        assert_eq!(chunk_index(-1, 0), Ok(1));
+        assert_eq!(chunk_index(0, -1), Ok(2));
+        assert_eq!(chunk_index(-1, -1), Ok(4));
+        assert_eq!(chunk_index(1, -1), Ok(7));
+        assert_eq!(chunk_index(-1, 1), Ok(8));
 
         // some random nearby ones
-        assert_eq!(chunk_index(207, 432), 818_145);
-        assert_eq!(chunk_index(285, -65), 244_779);
-        assert_eq!(chunk_index(958, 345), 3_397_611);
-        assert_eq!(chunk_index(632, 255), 1_574_935);
-        assert_eq!(chunk_index(-942, 555), 4_481_631);
-        assert_eq!(chunk_index(696, 589), 3_304_913);
-        assert_eq!(chunk_index(-201, -623), 1_356_726);
-        assert_eq!(chunk_index(741, 283), 2_098_742);
-        assert_eq!(chunk_index(-302, 718), 2_081_216);
-        assert_eq!(chunk_index(493, 116), 742_603);
+        assert_eq!(chunk_index(207, 432), Ok(818_145));
+        assert_eq!(chunk_index(285, -65), Ok(244_779));
+        assert_eq!(chunk_index(958, 345), Ok(3_397_611));
+        assert_eq!(chunk_index(632, 255), Ok(1_574_935));
+        assert_eq!(chunk_index(-942, 555), Ok(4_481_631));
+        assert_eq!(chunk_index(696, 589), Ok(3_304_913));
+        assert_eq!(chunk_index(-201, -623), Ok(1_356_726));
+        assert_eq!(chunk_index(741, 283), Ok(2_098_742));
+        assert_eq!(chunk_index(-302, 718), Ok(2_081_216));
+        assert_eq!(chunk_index(493, 116), Ok(742_603));
 
         // some random far ones
-        assert_eq!(chunk_index(1258, 7620), 157_661_886);
-        assert_eq!(chunk_index(9438, 4645), 396_685_151);
-        assert_eq!(chunk_index(6852, -7129), 390_936_998);
-        assert_eq!(chunk_index(-7692, -912), 148_033_644);
-        assert_eq!(chunk_index(-4803, -131), 48_674_172);
-        assert_eq!(chunk_index(-4565, 8366), 334_425_323);
-        assert_eq!(chunk_index(248, -126), 279_629);
-        assert_eq!(chunk_index(-1125, 3179), 37_050_886);
-        assert_eq!(chunk_index(4315, -4044), 139_745_490);
-        assert_eq!(chunk_index(-3126, 9730), 330_560_076);
+        assert_eq!(chunk_index(1258, 7620), Ok(157_661_886));
+        assert_eq!(chunk_index(9438, 4645), Ok(396_685_151));
+        assert_eq!(chunk_index(6852, -7129), Ok(390_936_998));
+        assert_eq!(chunk_index(-7692, -912), Ok(148_033_644));
+        assert_eq!(chunk_index(-4803, -131), Ok(48_674_172));
+        assert_eq!(chunk_index(-4565, 8366), Ok(334_425_323));
+        assert_eq!(chunk_index(248, -126), Ok(279_629));
+        assert_eq!(chunk_index(-1125, 3179), Ok(37_050_886));
+        assert_eq!(chunk_index(4315, -4044), Ok(139_745_490));
+        assert_eq!(chunk_index(-3126, 9730), Ok(330_560_076));
 
         // maximum
-        assert_eq!(chunk_index(-27804, 18537), u32::MAX);
+        assert_eq!(chunk_index(-27804, 18537), Ok(u32::MAX));
+    }
+
+    #[test]
+    fn chunk_index_overflow() {
+        // the index after u32::MAX would wrap around to chunk_index(0, 0)
+        assert_eq!(
+            chunk_index(27803, -18538),
+            Err(ChunkIndexError { chunk_x: 27803, chunk_y: -18538 })
+        );
+        assert!(chunk_index(40000, 0).is_err());
+        assert!(chunk_index(0, -40000).is_err());
+        assert!(chunk_index(i32::MAX, i32::MAX).is_err());
+        assert!(chunk_index(i32::MIN, i32::MIN).is_err());
     }
 
     #[test]
@@ -253,13 +369,28 @@ mod tests {
             let y: i32 = rand::thread_rng().gen_range(-10000..10000);
 
             println!("Testing ({x}, {y})...");
-            let index = chunk_index(x, y);
+            let index = chunk_index(x, y).unwrap();
             let result = chunk_index_inv(index);
 
             assert_eq!(result, (x, y));
         }
     }
 
+    #[test]
+    fn light_size() {
+        assert_eq!(super::light_size(LIGHT_SCALE), Some(25));
+        assert_eq!(super::light_size(5), Some(20));
+        assert_eq!(super::light_size(100), Some(1));
+
+        // doesn't divide CHUNK_SIZE
+        assert_eq!(super::light_size(0), None);
+        assert_eq!(super::light_size(3), None);
+        // too many cells for one work group
+        assert_eq!(super::light_size(2), None);
+
+        assert_eq!(light_scales().next(), Some(LIGHT_SCALE));
+    }
+
     #[test]
     fn chunk_update_order() {
         for _ in 0..100 {
@@ -327,22 +458,47 @@ pub const fn pixel_to_chunk(world_x: i64, world_y: i64) -> (ChunkKey, ChunkLocal
     })
 }
 
+/// Returned by [`chunk_index`] for chunks too far out to have a `u32` index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkIndexError {
+    pub chunk_x: i32,
+    pub chunk_y: i32,
+}
+
+impl std::fmt::Display for ChunkIndexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Chunk {},{} is too far out to have a chunk index",
+            self.chunk_x, self.chunk_y
+        )
+    }
+}
+
+impl std::error::Error for ChunkIndexError {}
+
+/// Unique index of a chunk, inverted by [`chunk_index_inv`].
+///
+/// Indices grow with the distance from the origin, and run out around 32768 chunks
+///   (`2^16 / 2`) out, past which this returns an error instead of reusing an index.
 #[inline]
-pub fn chunk_index(chunk_x: i32, chunk_y: i32) -> u32 {
+pub fn chunk_index(chunk_x: i32, chunk_y: i32) -> Result<u32, ChunkIndexError> {
     #[inline]
-    const fn int_to_nat(i: i32) -> u32 {
+    const fn int_to_nat(i: i32) -> u64 {
         if i >= 0 {
-            (2 * i) as u32
+            2 * i as u64
         } else {
-            (-2 * i - 1) as u32
+            2 * i.unsigned_abs() as u64 - 1
         }
     }
-    let xx: u32 = int_to_nat(chunk_x);
-    let yy: u32 = int_to_nat(chunk_y);
-
-    // TODO: this multiply is the first thing to overflow if you go out too far
-    //          (though you need to go out ~32768 chunks (2^16 / 2)
-    ((u64::from(xx + yy) * u64::from(xx + yy + 1)) / 2 + u64::from(yy)) as u32
+    let xx = int_to_nat(chunk_x);
+    let yy = int_to_nat(chunk_y);
+
+    let sum = xx + yy;
+    sum.checked_mul(sum + 1)
+        .map(|n| n / 2 + yy)
+        .and_then(|index| u32::try_from(index).ok())
+        .ok_or(ChunkIndexError { chunk_x, chunk_y })
 }
 
 #[inline]
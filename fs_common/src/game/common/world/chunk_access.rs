@@ -1,10 +1,17 @@
+use std::collections::{HashSet, VecDeque};
+
 use chunksystem::{ChunkKey, ChunkQuery};
 
+use crate::game::common::Rect;
+
 use super::{
     material::{MaterialInstance, PhysicsType},
-    pixel_to_chunk, pixel_to_chunk_pos, pixel_to_pos_in_chunk, Chunk,
+    pixel_to_chunk, pixel_to_chunk_pos, pixel_to_pos_in_chunk, Chunk, CHUNK_SIZE,
 };
 
+/// Max number of pixels changed by [`FSChunkAccess::flood_fill`].
+pub const FLOOD_FILL_MAX_PIXELS: usize = 1 << 16;
+
 pub trait FSChunkAccess {
     fn pixel(&self, world_x: i64, world_y: i64) -> Result<&MaterialInstance, String>;
     fn set_pixel(
@@ -19,12 +26,70 @@ pub trait FSChunkAccess {
         Self: Sized,
         F: FnOnce(&MaterialInstance) -> Option<MaterialInstance>;
 
+    fn background(&self, world_x: i64, world_y: i64) -> Result<&MaterialInstance, String>;
+    fn set_background(
+        &mut self,
+        world_x: i64,
+        world_y: i64,
+        mat: MaterialInstance,
+    ) -> Result<(), String>;
+
     fn displace_pixel(&mut self, world_x: i64, world_y: i64, material: MaterialInstance) -> bool;
 
+    /// Fills the `w`x`h` rectangle at `(x, y)` with `mat`, one chunk at a time.
+    ///
+    /// Returns an error if part of the rectangle isn't loaded, in which case the loaded parts are
+    ///   still filled.
+    fn set_rect(
+        &mut self,
+        x: i64,
+        y: i64,
+        w: u16,
+        h: u16,
+        mat: MaterialInstance,
+    ) -> Result<(), String>;
+
     fn chunk_at_dyn(&self, chunk_pos: ChunkKey) -> Option<&dyn Chunk>;
     fn chunk_at_mut_dyn(&mut self, chunk_pos: ChunkKey) -> Option<&mut dyn Chunk>;
 
     fn is_pixel_loaded(&self, world_x: i64, world_y: i64) -> bool;
+
+    /// Walks the line from `(x0, y0)` to `(x1, y1)` (both inclusive) and returns the first pixel
+    ///   that isn't [`PhysicsType::Air`], along with its position.
+    ///
+    /// Returns [`None`] if the whole line is air, or as soon as the line reaches a chunk that isn't
+    ///   loaded, even if there would be a hit further along.
+    fn raycast(&self, x0: i64, y0: i64, x1: i64, y1: i64) -> Option<(i64, i64, &MaterialInstance)>;
+
+    /// Replaces the 4-connected region of pixels matching `match_fn` around `(x, y)` with
+    ///   `replacement`, returning the number of pixels changed.
+    ///
+    /// Crosses chunk boundaries, but doesn't spread into unloaded chunks.
+    /// Stops after [`FLOOD_FILL_MAX_PIXELS`], see [`FSChunkAccess::flood_fill_max`].
+    fn flood_fill(
+        &mut self,
+        x: i64,
+        y: i64,
+        replacement: MaterialInstance,
+        match_fn: impl Fn(&MaterialInstance) -> bool,
+    ) -> usize
+    where
+        Self: Sized,
+    {
+        self.flood_fill_max(x, y, replacement, FLOOD_FILL_MAX_PIXELS, match_fn)
+    }
+
+    /// [`FSChunkAccess::flood_fill`], changing at most `max_pixels` pixels.
+    fn flood_fill_max(
+        &mut self,
+        x: i64,
+        y: i64,
+        replacement: MaterialInstance,
+        max_pixels: usize,
+        match_fn: impl Fn(&MaterialInstance) -> bool,
+    ) -> usize
+    where
+        Self: Sized;
 }
 
 impl<Q: ChunkQuery> FSChunkAccess for Q
@@ -70,6 +135,79 @@ where
         ch.replace_pixel(local, cb)
     }
 
+    #[inline]
+    fn background(&self, world_x: i64, world_y: i64) -> Result<&MaterialInstance, String> {
+        let (chunk_pos, local) = pixel_to_chunk(world_x, world_y);
+        let Some(ch) = self.chunk_at(chunk_pos) else {
+            return Err("Position is not loaded".into());
+        };
+        ch.background(local)
+    }
+
+    #[inline]
+    fn set_background(
+        &mut self,
+        world_x: i64,
+        world_y: i64,
+        mat: MaterialInstance,
+    ) -> Result<(), String> {
+        let (chunk_pos, local) = pixel_to_chunk(world_x, world_y);
+        let Some(ch) = self.chunk_at_mut(chunk_pos) else {
+            return Err("Position is not loaded".into());
+        };
+        ch.set_background(local, mat)
+    }
+
+    #[profiling::function]
+    fn set_rect(
+        &mut self,
+        x: i64,
+        y: i64,
+        w: u16,
+        h: u16,
+        mat: MaterialInstance,
+    ) -> Result<(), String> {
+        if w == 0 || h == 0 {
+            return Ok(());
+        }
+
+        let size = i64::from(CHUNK_SIZE);
+        let x2 = x + i64::from(w);
+        let y2 = y + i64::from(h);
+        let (cx1, cy1) = pixel_to_chunk_pos(x, y);
+        let (cx2, cy2) = pixel_to_chunk_pos(x2 - 1, y2 - 1);
+
+        let mut unloaded = 0;
+        for cy in cy1..=cy2 {
+            for cx in cx1..=cx2 {
+                let Some(ch) = self.chunk_at_mut((cx, cy)) else {
+                    unloaded += 1;
+                    continue;
+                };
+
+                // intersection of the rect with this chunk, in chunk local pixels
+                let ox = i64::from(cx) * size;
+                let oy = i64::from(cy) * size;
+                let local = Rect::new(
+                    (x - ox).max(0) as i32,
+                    (y - oy).max(0) as i32,
+                    (x2 - ox).min(size) as i32,
+                    (y2 - oy).min(size) as i32,
+                );
+
+                if ch.fill_rect(local, &mat).is_err() {
+                    unloaded += 1;
+                }
+            }
+        }
+
+        if unloaded > 0 {
+            return Err(format!("{unloaded} chunks in the rect are not loaded"));
+        }
+
+        Ok(())
+    }
+
     #[inline]
     fn chunk_at_dyn(&self, chunk_pos: ChunkKey) -> Option<&dyn Chunk> {
         self.chunk_at(chunk_pos).map(|ch| &ch.data as &dyn Chunk)
@@ -86,6 +224,73 @@ where
         self.is_chunk_loaded(pixel_to_chunk_pos(world_x, world_y))
     }
 
+    fn raycast(&self, x0: i64, y0: i64, x1: i64, y1: i64) -> Option<(i64, i64, &MaterialInstance)> {
+        // bresenham
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let sx = (x1 - x0).signum();
+        let sy = (y1 - y0).signum();
+        let mut err = dx + dy;
+
+        let (mut x, mut y) = (x0, y0);
+        loop {
+            let mat = self.pixel(x, y).ok()?;
+            if mat.physics != PhysicsType::Air {
+                return Some((x, y, mat));
+            }
+
+            if x == x1 && y == y1 {
+                return None;
+            }
+
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    #[profiling::function]
+    fn flood_fill_max(
+        &mut self,
+        x: i64,
+        y: i64,
+        replacement: MaterialInstance,
+        max_pixels: usize,
+        match_fn: impl Fn(&MaterialInstance) -> bool,
+    ) -> usize {
+        let mut changed = 0;
+        let mut visited = HashSet::from([(x, y)]);
+        let mut queue = VecDeque::from([(x, y)]);
+
+        while changed < max_pixels {
+            let Some((px, py)) = queue.pop_front() else {
+                break;
+            };
+
+            // errors mean the pixel isn't loaded
+            let replaced =
+                self.replace_pixel(px, py, |mat| match_fn(mat).then(|| replacement.clone()));
+            if replaced != Ok(true) {
+                continue;
+            }
+            changed += 1;
+
+            for next in [(px + 1, py), (px - 1, py), (px, py + 1), (px, py - 1)] {
+                if visited.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        changed
+    }
+
     #[profiling::function]
     fn displace_pixel(&mut self, world_x: i64, world_y: i64, material: MaterialInstance) -> bool {
         let mut succeeded = false;
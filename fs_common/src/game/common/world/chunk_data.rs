@@ -1,7 +1,10 @@
 use crate::game::common::Rect;
 
 use super::{
-    chunk_index::ChunkLocalIndex, material::MaterialInstance, mesh::Mesh, tile_entity::TileEntity,
+    chunk_index::{ChunkLocalIndex, ChunkLocalPosition},
+    material::{MaterialInstance, PhysicsType},
+    mesh::{self, Mesh},
+    tile_entity::TileEntity,
     ChunkRigidBodyState, ChunkState, CHUNK_AREA, CHUNK_SIZE,
 };
 
@@ -13,8 +16,15 @@ pub struct CommonChunkData<S: SidedChunkData> {
     pub light: Option<Box<[[f32; 3]; CHUNK_AREA]>>,
     pub background: Option<Box<[MaterialInstance; CHUNK_AREA]>>,
     pub dirty_rect: Option<Rect<i32>>,
+    /// Whether the chunk changed since it was last saved.
+    pub needs_save: bool,
+    /// Frozen chunks are skipped by the simulation but can still be edited directly.
+    pub frozen: bool,
     pub rigidbody: Option<ChunkRigidBodyState>,
+    pub mesh: Option<Mesh>,
     pub mesh_simplified: Option<Mesh>,
+    /// Pixels that became solid or stopped being solid since the mesh was generated.
+    pub mesh_dirty_rect: Option<Rect<i32>>,
     pub tile_entities: Vec<TileEntity<S::TileEntityData>>,
 }
 
@@ -22,6 +32,11 @@ pub trait SidedChunkData {
     type TileEntityData;
 }
 
+/// Whether `mat` is part of the chunk's mesh, see [`mesh::pixels_to_valuemap`].
+fn is_solid(mat: &MaterialInstance) -> bool {
+    mat.physics == PhysicsType::Solid
+}
+
 impl<S: SidedChunkData> CommonChunkData<S> {
     pub fn new(chunk_x: i32, chunk_y: i32) -> Self {
         Self {
@@ -32,12 +47,79 @@ impl<S: SidedChunkData> CommonChunkData<S> {
             light: None,
             background: None,
             dirty_rect: None,
+            needs_save: false,
+            frozen: false,
             rigidbody: None,
+            mesh: None,
             mesh_simplified: None,
+            mesh_dirty_rect: None,
             tile_entities: vec![],
         }
     }
 
+    /// Regenerates [`Self::mesh`] and [`Self::mesh_simplified`] from the pixels.
+    pub fn generate_mesh(&mut self, epsilon: f64) -> Result<(), String> {
+        let Some(pixels) = &self.pixels else {
+            return Err("generate_mesh failed: self.pixels is None".to_owned());
+        };
+
+        let values = mesh::pixels_to_valuemap(pixels.as_ref());
+        let generated = mesh::generate_mesh_with_simplified(
+            &values,
+            CHUNK_SIZE.into(),
+            CHUNK_SIZE.into(),
+            epsilon,
+        );
+
+        (self.mesh, self.mesh_simplified) = match generated {
+            Ok((mesh, simplified)) => (Some(mesh), Some(simplified)),
+            Err(_) => (None, None),
+        };
+        self.mesh_dirty_rect = None;
+
+        Ok(())
+    }
+
+    /// Regenerates the part of the mesh around [`Self::mesh_dirty_rect`], see [`mesh::update_mesh_with_simplified`].
+    ///
+    /// Falls back to [`Self::generate_mesh`] if most of the mesh needs regenerating anyway.
+    /// Returns `false` if nothing changed, or if there's no mesh yet (eg. while generating).
+    pub fn update_mesh(&mut self, epsilon: f64) -> Result<bool, String> {
+        let Some(dirty) = self.mesh_dirty_rect.take() else {
+            return Ok(false);
+        };
+
+        let (Some(pixels), Some(old), Some(old_simplified)) =
+            (&self.pixels, &self.mesh, &self.mesh_simplified)
+        else {
+            return Ok(false);
+        };
+
+        let values = mesh::pixels_to_valuemap(pixels.as_ref());
+        match mesh::update_mesh_with_simplified(
+            (old, old_simplified),
+            &values,
+            CHUNK_SIZE.into(),
+            CHUNK_SIZE.into(),
+            dirty,
+            epsilon,
+        )? {
+            Some((mesh, simplified)) => {
+                self.mesh = Some(mesh);
+                self.mesh_simplified = Some(simplified);
+            },
+            None => self.generate_mesh(epsilon)?,
+        }
+
+        Ok(true)
+    }
+
+    fn mark_mesh_dirty(&mut self, i: ChunkLocalIndex) {
+        let pos = ChunkLocalPosition::from(i);
+        let px = Rect::new_wh(i32::from(pos.x()), i32::from(pos.y()), 1, 1);
+        self.mesh_dirty_rect = Some(self.mesh_dirty_rect.map_or(px, |r| r.union(px)));
+    }
+
     pub fn set(
         &mut self,
         pos: impl Into<ChunkLocalIndex>,
@@ -47,9 +129,15 @@ impl<S: SidedChunkData> CommonChunkData<S> {
         if let Some(px) = &mut self.pixels {
             (cb)(&mat)?;
 
-            px[pos.into()] = mat;
+            let i = pos.into();
+            let remesh = is_solid(&px[i]) != is_solid(&mat);
+            px[i] = mat;
+            if remesh {
+                self.mark_mesh_dirty(i);
+            }
 
             self.dirty_rect = Some(Rect::new_wh(0, 0, CHUNK_SIZE, CHUNK_SIZE));
+            self.needs_save = true;
 
             return Ok(());
         }
@@ -60,9 +148,16 @@ impl<S: SidedChunkData> CommonChunkData<S> {
     /// # Safety
     /// Assumes the chunk is loaded (unchecked). Use [`Self::set`] if this is not known.
     pub unsafe fn set_unchecked(&mut self, pos: impl Into<ChunkLocalIndex>, mat: MaterialInstance) {
-        self.pixels.as_mut().unwrap_unchecked()[pos.into()] = mat;
+        let i = pos.into();
+        let px = &mut self.pixels.as_mut().unwrap_unchecked()[i];
+        let remesh = is_solid(px) != is_solid(&mat);
+        *px = mat;
+        if remesh {
+            self.mark_mesh_dirty(i);
+        }
 
         self.dirty_rect = Some(Rect::new_wh(0, 0, CHUNK_SIZE, CHUNK_SIZE));
+        self.needs_save = true;
     }
 
     pub fn pixel(&self, pos: impl Into<ChunkLocalIndex>) -> Result<&MaterialInstance, String> {
@@ -94,9 +189,14 @@ impl<S: SidedChunkData> CommonChunkData<S> {
             let px = unsafe { px.get_unchecked_mut(*i) };
             if let Some(mat) = (cb)(px) {
                 (chunk_cb)(&mat)?;
+                let remesh = is_solid(px) != is_solid(&mat);
                 *px = mat;
+                if remesh {
+                    self.mark_mesh_dirty(i);
+                }
 
                 self.dirty_rect = Some(Rect::new_wh(0, 0, CHUNK_SIZE, CHUNK_SIZE));
+                self.needs_save = true;
 
                 return Ok(true);
             }
@@ -1,17 +1,15 @@
-use std::{cell::UnsafeCell, fmt::Debug, path::PathBuf, sync::Arc};
+use std::{cell::UnsafeCell, cmp::Reverse, fmt::Debug, path::PathBuf, sync::Arc};
 
 use asefile::AsepriteFile;
 use chunksystem::{ChunkKey, ChunkManager, ChunkQuery};
 use futures::channel::oneshot::Receiver;
-use rand::{rngs::StdRng, SeedableRng};
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
-use serde::{Deserialize, Serialize};
 use specs::{Join, ReadStorage, RunNow, WorldExt};
 
 use crate::game::common::{
     hashmap_ext::HashMapExt,
     world::{
-        chunk_index, chunk_update_order,
+        chunk_update_order,
         gen::{populator::ChunkContext, structure::UpdateStructureNodes, GenBuffers, GenContext},
         material::buf::MaterialRect,
         particle::{Particle, ParticleSystem},
@@ -20,13 +18,16 @@ use crate::game::common::{
         tile_entity::{TileEntityCommon, TileEntityTickContext},
         ChunkState, Loader, Position, CHUNK_SIZE,
     },
-    FileHelper, Rect, Registries, Settings,
+    FileHelper, Rect, Registries, Settings, MAX_SIMULATION_SUBSTEPS,
 };
 
 use super::{
+    chunk_access::FSChunkAccess,
     chunk_data::SidedChunkData,
+    chunk_io::{ChunkIoWorker, ChunkLoadStatus, ChunkSaveFormat},
+    chunk_pool::ChunkBufferPool,
     gen::WorldGenerator,
-    material::{color::Color, MaterialInstance},
+    material::{color::Color, placer::MaterialPlacerSampler, MaterialInstance},
     physics::Physics,
     tile_entity::TileEntitySided,
     Chunk, ChunkRigidBodyState, SidedChunk, CHUNK_AREA,
@@ -41,8 +42,16 @@ pub struct ChunkHandler<C: Chunk> {
     pub screen_size: (u16, u16),
     pub generator: Arc<dyn WorldGenerator<C>>,
     pub path: Option<PathBuf>,
+    /// Buffers from unloaded chunks, reused by newly loaded and generated ones.
+    pub buffer_pool: ChunkBufferPool,
+    /// Saves and loads chunk files in the background; [`None`] if the world has no `path`.
+    pub io: Option<ChunkIoWorker>,
+    /// Called with `(chunk_x, chunk_y, old, new)` when a tick changes a chunk's state.
+    pub on_state_change: Option<StateChangeCallback>,
 }
 
+pub type StateChangeCallback = Box<dyn FnMut(i32, i32, ChunkState, ChunkState) + Send + Sync>;
+
 impl<C: Chunk> Debug for ChunkHandler<C> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("ChunkHandler")
@@ -64,12 +73,6 @@ pub type ChunkGenOutput = (
     Box<[Color; CHUNK_AREA]>,
 );
 
-#[derive(Serialize, Deserialize)]
-struct ChunkSaveFormat {
-    pixels: Vec<MaterialInstance>,
-    colors: Vec<Color>,
-}
-
 pub struct ChunkTickContext<'a> {
     pub tick_time: u32,
     pub settings: &'a Settings,
@@ -108,7 +111,7 @@ where
 
         if ctx.settings.load_chunks {
             self.queue_chunk_loading(&loader_zones);
-            self.load_chunks(&ctx);
+            self.load_chunks(&ctx, &loader_zones);
         }
 
         // switch chunks between cached and active
@@ -117,11 +120,7 @@ where
         }
 
         if ctx.settings.load_chunks && ctx.tick_time % 2 == 0 {
-            let num_active = self
-                .manager
-                .chunks_iter()
-                .filter(|c| c.state() == ChunkState::Active)
-                .count();
+            let num_active = self.active_chunks().count();
             let num_cached = self
                 .manager
                 .chunks_iter()
@@ -137,7 +136,20 @@ where
         }
 
         if ctx.settings.simulate_chunks {
-            self.simulate_chunks(&mut ctx);
+            let substeps = ctx
+                .settings
+                .simulation_substeps
+                .clamp(1, MAX_SIMULATION_SUBSTEPS);
+            for _ in 0..substeps {
+                self.simulate_chunks(&mut ctx);
+
+                // anything still dirty after simulating has moved this substep
+                for ch in self.manager.chunks_iter_mut() {
+                    if ch.dirty_rect().is_some() {
+                        ch.set_needs_save(true);
+                    }
+                }
+            }
         }
 
         self.tick_tile_entities(&mut ctx);
@@ -170,55 +182,58 @@ where
         }
     }
 
-    fn load_chunks(&mut self, ctx: &ChunkTickContext) {
+    fn load_chunks(&mut self, ctx: &ChunkTickContext, loader_zones: &[Zones]) {
         profiling::scope!("chunk loading");
 
         let (loaders, positions) = ctx
             .world
             .system_data::<(ReadStorage<Loader>, ReadStorage<Position>)>();
 
-        self.load_queue.sort_by(|(a_x, a_y), (b_x, b_y)| {
-            let c1_x = a_x * i32::from(CHUNK_SIZE);
-            let c1_y = a_y * i32::from(CHUNK_SIZE);
-            let c2_x = b_x * i32::from(CHUNK_SIZE);
-            let c2_y = b_y * i32::from(CHUNK_SIZE);
-
-            let d1 = (&loaders, &positions)
-                .join()
-                .map(|(_, p)| {
-                    let x = (p.x as i32 - c1_x).abs();
-                    let y = (p.y as i32 - c1_y).abs();
-                    x + y
-                })
-                .min()
-                .unwrap();
+        let loader_positions: Vec<_> = (&loaders, &positions)
+            .join()
+            .map(|(_, p)| (p.x as i32, p.y as i32))
+            .collect();
 
-            let d2 = (&loaders, &positions)
-                .join()
-                .map(|(_, p)| {
-                    let x = (p.x as i32 - c2_x).abs();
-                    let y = (p.y as i32 - c2_y).abs();
-                    x + y
-                })
-                .min()
-                .unwrap();
+        // nearest chunks last since they're popped off the end
+        self.load_queue.sort_by_cached_key(|&(chunk_x, chunk_y)| {
+            let c_x = chunk_x * i32::from(CHUNK_SIZE);
+            let c_y = chunk_y * i32::from(CHUNK_SIZE);
 
-            d2.cmp(&d1)
+            let dist = loader_positions
+                .iter()
+                .map(|(x, y)| (x - c_x).abs() + (y - c_y).abs())
+                .min()
+                .unwrap_or(i32::MAX);
+            Reverse(dist)
         });
 
-        for _ in 0..Self::MAX_LOAD_PER_TICK {
-            // TODO: don't load queued chunks if they are no longer in range
-            if let Some(to_load) = self.load_queue.pop() {
-                let c = self.load_chunk(to_load.0, to_load.1);
-                if to_load == (0, 0) {
-                    let ase = AsepriteFile::read_file(
-                        &ctx.file_helper.asset_path("data/tile_entity/test/test.ase"),
-                    )
-                    .unwrap();
-                    c.add_tile_entity(TileEntityCommon {
-                        material_rect: MaterialRect::load_from_ase(&ase, (-40, -40)),
-                    });
-                }
+        let mut loaded = 0;
+        while loaded < Self::MAX_LOAD_PER_TICK {
+            let Some(to_load) = self.load_queue.pop() else {
+                break;
+            };
+
+            // the loaders may have moved away since it was queued
+            let rect = Rect::new_wh(
+                to_load.0 * i32::from(CHUNK_SIZE),
+                to_load.1 * i32::from(CHUNK_SIZE),
+                CHUNK_SIZE,
+                CHUNK_SIZE,
+            );
+            if !loader_zones.iter().any(|z| rect.intersects(&z.load)) {
+                continue;
+            }
+
+            loaded += 1;
+            let c = self.load_chunk(to_load.0, to_load.1);
+            if to_load == (0, 0) {
+                let ase = AsepriteFile::read_file(
+                    &ctx.file_helper.asset_path("data/tile_entity/test/test.ase"),
+                )
+                .unwrap();
+                c.add_tile_entity(TileEntityCommon {
+                    material_rect: MaterialRect::load_from_ase(&ase, (-40, -40)),
+                });
             }
         }
     }
@@ -242,28 +257,13 @@ where
             match state {
                 ChunkState::Cached => {
                     if !loader_zones.iter().any(|z| rect.intersects(&z.unload)) {
-                        if let Err(e) = self.save_chunk(key) {
-                            log::error!("Chunk @ {}, {} failed to save: {:?}", key.0, key.1, e);
-                        }
                         if let Err(e) = self.unload_chunk(key, ctx.physics) {
                             log::error!("Chunk @ {}, {} failed to unload: {:?}", key.0, key.1, e);
                         }
                         keep_map[i] = false;
                     } else if loader_zones.iter().any(|z| rect.intersects(&z.active)) {
                         let (chunk_x, chunk_y) = key;
-                        if [
-                            self.chunk_at((chunk_x - 1, chunk_y - 1)),
-                            self.chunk_at((chunk_x, chunk_y - 1)),
-                            self.chunk_at((chunk_x + 1, chunk_y - 1)),
-                            self.chunk_at((chunk_x - 1, chunk_y)),
-                            self.chunk_at((chunk_x, chunk_y)),
-                            self.chunk_at((chunk_x + 1, chunk_y)),
-                            self.chunk_at((chunk_x - 1, chunk_y + 1)),
-                            self.chunk_at((chunk_x, chunk_y + 1)),
-                            self.chunk_at((chunk_x + 1, chunk_y + 1)),
-                        ]
-                        .iter()
-                        .all(|ch| {
+                        if self.get_neighbors(chunk_x, chunk_y).iter().all(|ch| {
                             if ch.is_none() {
                                 return false;
                             }
@@ -272,10 +272,7 @@ where
 
                             matches!(state, ChunkState::Cached | ChunkState::Active)
                         }) {
-                            self.manager
-                                .chunk_at_mut(key)
-                                .unwrap()
-                                .set_state(ChunkState::Active);
+                            self.set_chunk_state(key, ChunkState::Active);
                             self.manager
                                 .chunk_at_mut(key)
                                 .unwrap()
@@ -285,10 +282,7 @@ where
                 },
                 ChunkState::Active => {
                     if !loader_zones.iter().any(|z| rect.intersects(&z.active)) {
-                        self.manager
-                            .chunk_at_mut(key)
-                            .unwrap()
-                            .set_state(ChunkState::Cached);
+                        self.set_chunk_state(key, ChunkState::Cached);
                     }
                 },
                 _ => {},
@@ -296,8 +290,7 @@ where
         }
 
         if ctx.settings.load_chunks {
-            let mut iter = keep_map.iter();
-            unsafe { self.manager.raw_mut() }.retain(|_, _| *iter.next().unwrap());
+            self.remove_chunks(&keys, &keep_map);
         }
     }
 
@@ -311,6 +304,8 @@ where
     ) {
         profiling::scope!("generate_chunks");
 
+        self.receive_loaded_chunks(ctx);
+
         let (loaders, positions) = ctx
             .world
             .system_data::<(ReadStorage<Loader>, ReadStorage<Position>)>();
@@ -387,80 +382,15 @@ where
                     should_generate = false;
                 }
 
-                // try to load from file
-                if let Some(path) = &self.path {
-                    let chunk_path_root = path.join("chunks/");
-                    if !chunk_path_root.exists() {
-                        std::fs::create_dir_all(&chunk_path_root).expect(
-                            format!(
-                                "Failed to create chunk directory @ {chunk_path_root:?}"
-                            )
-                            .as_str(),
-                        );
-                    }
-                    let chunk_path =
-                        chunk_path_root.join(format!("{chunk_x}_{chunk_y}.chunk"));
-                    if chunk_path.exists() {
-                        if let Ok(data) = std::fs::read(&chunk_path) {
-                            match bincode::deserialize(&data) {
-                                Ok(res) => {
-                                    let save: ChunkSaveFormat = res;
-
-                                    if save.pixels.len()
-                                        == (CHUNK_AREA)
-                                    {
-                                        let chunk =  self.manager.chunk_at_mut(*key).unwrap();
-                                        chunk.set_state(ChunkState::Cached);
-                                        chunk.set_pixels(save.pixels.try_into().unwrap());
-                                        chunk.mark_dirty();
-                                        let _: Result<(), _> = chunk.generate_mesh();
-
-                                        if save.colors.len()
-                                            == (CHUNK_SIZE as usize
-                                                * CHUNK_SIZE as usize
-                                                * 4)
-                                        {
-                                            chunk.set_pixel_colors(save.colors.try_into().unwrap());
-                                        } else {
-                                            log::error!("colors Vec is the wrong size: {} (expected {})", save.colors.len(), CHUNK_AREA * 4);
-                                            chunk.refresh();
-                                        }
-
-                                        should_generate = false;
-                                    } else {
-                                        log::error!("pixels Vec is the wrong size: {} (expected {})", save.pixels.len(), CHUNK_AREA);
-                                        self.manager
-                                            .chunk_at_mut(*key)
-                                            .unwrap()
-                                            .set_state(ChunkState::Cached);
-                                    }
-                                },
-                                Err(e) => {
-                                    log::error!(
-                                        "Chunk parse failed @ {},{} -> {:?}: {:?}",
-                                        chunk_x,
-                                        chunk_y,
-                                        chunk_path,
-                                        e
-                                    );
-                                    self.manager
-                                        .chunk_at_mut(*key)
-                                        .unwrap()
-                                        .set_state(ChunkState::Cached);
-                                },
-                            }
-                        } else {
-                            log::error!(
-                                "Chunk load failed @ {},{} -> {:?}",
-                                chunk_x,
-                                chunk_y,
-                                chunk_path
-                            );
-                            self.manager
-                                .chunk_at_mut(*key)
-                                .unwrap()
-                                .set_state(ChunkState::Cached);
-                        }
+                // try to load from file, generating only once the io worker has checked there's no save
+                if let Some(io) = &mut self.io {
+                    match io.status(*key) {
+                        ChunkLoadStatus::Unknown => {
+                            io.queue_load(*key);
+                            should_generate = false;
+                        },
+                        ChunkLoadStatus::Pending => should_generate = false,
+                        ChunkLoadStatus::Missing => {},
                     }
                 }
 
@@ -511,11 +441,19 @@ where
                 profiling::scope!("finish chunk");
 
                 self.manager.chunk_at_mut(key).map(|chunk| {
+                    let old = chunk.state();
                     chunk.set_state(ChunkState::Generating(0));
+                    Self::notify_state_change(
+                        &mut self.on_state_change,
+                        key,
+                        old,
+                        ChunkState::Generating(0),
+                    );
                     chunk.set_pixels(pixels);
-                    chunk.set_pixel_colors(colors);
+                    self.buffer_pool.give_colors(chunk.set_pixel_colors(colors));
                     chunk.set_background_pixels(background);
-                    chunk.set_background_pixel_colors(background_colors);
+                    self.buffer_pool
+                        .give_colors(chunk.set_background_pixel_colors(background_colors));
                     key
                 })
             })
@@ -535,6 +473,66 @@ where
         }
     }
 
+    /// Puts chunks loaded by [`ChunkHandler::io`] into the chunks still waiting for them.
+    fn receive_loaded_chunks(&mut self, ctx: &ChunkTickContext) {
+        profiling::scope!("receive_loaded_chunks");
+
+        let Some(io) = &mut self.io else {
+            return;
+        };
+
+        for (key, result) in io.poll_loads() {
+            // it may have been unloaded while loading
+            let Some(chunk) = self.manager.chunk_at_mut(key) else {
+                continue;
+            };
+            if chunk.state() != ChunkState::NotGenerated {
+                continue;
+            }
+
+            match result {
+                Ok(save) if save.pixels.len() == CHUNK_AREA => {
+                    chunk.set_state(ChunkState::Cached);
+                    chunk.set_pixels(save.pixels.try_into().unwrap());
+                    chunk.mark_dirty();
+                    let _: Result<(), _> =
+                        chunk.generate_mesh_with_epsilon(ctx.settings.mesh_simplify_epsilon);
+
+                    if save.colors.len() == CHUNK_AREA {
+                        let old = chunk.set_pixel_colors(save.colors.try_into().unwrap());
+                        self.buffer_pool.give_colors(old);
+                    } else {
+                        log::error!(
+                            "colors Vec is the wrong size: {} (expected {})",
+                            save.colors.len(),
+                            CHUNK_AREA
+                        );
+                        chunk.refresh();
+                    }
+                },
+                Ok(save) => {
+                    log::error!(
+                        "pixels Vec is the wrong size: {} (expected {})",
+                        save.pixels.len(),
+                        CHUNK_AREA
+                    );
+                    chunk.set_state(ChunkState::Cached);
+                },
+                Err(e) => {
+                    log::error!("Chunk load failed @ {},{}: {}", key.0, key.1, e);
+                    chunk.set_state(ChunkState::Cached);
+                },
+            }
+
+            Self::notify_state_change(
+                &mut self.on_state_change,
+                key,
+                ChunkState::NotGenerated,
+                chunk.state(),
+            );
+        }
+    }
+
     fn spawn_chunk_generation(
         &mut self,
         ctx: &ChunkTickContext,
@@ -547,22 +545,17 @@ where
         let reg = ctx.registries.clone();
         let (tx, rx) = futures::channel::oneshot::channel();
         let seed = ctx.seed;
+
+        // these arrays are too large for the stack
+        let mut pixels = self.buffer_pool.take_pixels();
+        let mut colors = self.buffer_pool.take_colors();
+        let mut background = self.buffer_pool.take_pixels();
+        let mut background_colors = self.buffer_pool.take_colors();
+
         self.gen_pool.spawn_fifo(move || {
             profiling::register_thread!("Generation thread");
             profiling::scope!("chunk");
 
-            // these arrays are too large for the stack
-
-            let mut pixels = Box::new([(); CHUNK_AREA].map(|_| MaterialInstance::air()));
-
-            #[allow(clippy::cast_lossless)]
-            let mut colors = Box::new([Color::TRANSPARENT; CHUNK_AREA]);
-
-            let mut background = Box::new([(); CHUNK_AREA].map(|_| MaterialInstance::air()));
-
-            #[allow(clippy::cast_lossless)]
-            let mut background_colors = Box::new([Color::TRANSPARENT; CHUNK_AREA]);
-
             generator.generate(
                 (chunk_x, chunk_y),
                 GenBuffers::new(
@@ -609,9 +602,6 @@ where
                 ChunkState::NotGenerated => {
                     profiling::scope!("NotGenerated");
                     if !loader_zones.iter().any(|z| rect.intersects(&z.unload)) {
-                        if let Err(e) = self.save_chunk(key) {
-                            log::error!("Chunk @ {}, {} failed to save: {:?}", key.0, key.1, e);
-                        };
                         if let Err(e) = self.unload_chunk(key, ctx.physics) {
                             log::error!("Chunk @ {}, {} failed to unload: {:?}", key.0, key.1, e);
                         };
@@ -627,13 +617,13 @@ where
 
                     if cur_stage >= max_stage {
                         profiling::scope!("finish");
-                        let _: Result<(), _> =
-                            self.manager.chunk_at_mut(key).unwrap().generate_mesh();
-
-                        self.manager
+                        let _: Result<(), _> = self
+                            .manager
                             .chunk_at_mut(key)
                             .unwrap()
-                            .set_state(ChunkState::Cached);
+                            .generate_mesh_with_epsilon(ctx.settings.mesh_simplify_epsilon);
+
+                        self.set_chunk_state(key, ChunkState::Cached);
                     } else {
                         if populated_num
                             < if num_active < 16 {
@@ -645,19 +635,7 @@ where
                             }
                             && {
                                 profiling::scope!("check neighbors");
-                                [
-                                    self.chunk_at((chunk_x - 1, chunk_y - 1)),
-                                    self.chunk_at((chunk_x, chunk_y - 1)),
-                                    self.chunk_at((chunk_x + 1, chunk_y - 1)),
-                                    self.chunk_at((chunk_x - 1, chunk_y)),
-                                    self.chunk_at((chunk_x, chunk_y)),
-                                    self.chunk_at((chunk_x + 1, chunk_y)),
-                                    self.chunk_at((chunk_x - 1, chunk_y + 1)),
-                                    self.chunk_at((chunk_x, chunk_y + 1)),
-                                    self.chunk_at((chunk_x + 1, chunk_y + 1)),
-                                ]
-                                .iter()
-                                .all(|ch| {
+                                self.get_neighbors(chunk_x, chunk_y).iter().all(|ch| {
                                     let Some(chunk) = ch else {
                                         return false;
                                     };
@@ -701,18 +679,10 @@ where
                                 if cur_stage + 1 == 1 {
                                     let mut chunk_ctx =
                                         ChunkContext::<1, C>::new(&mut chunks_data).unwrap();
-                                    let mut rng = StdRng::seed_from_u64(
-                                        ctx.seed as u64
-                                            + u64::from(chunk_index(
-                                                chunk_ctx.center_chunk().0,
-                                                chunk_ctx.center_chunk().1,
-                                            )),
-                                    );
                                     for feat in self.generator.features() {
                                         feat.generate(
                                             &mut chunk_ctx,
                                             ctx.seed,
-                                            &mut rng,
                                             ctx.registries,
                                             ctx.world,
                                         );
@@ -727,10 +697,7 @@ where
                                     ctx.registries,
                                 );
 
-                                self.manager
-                                    .chunk_at_mut(key)
-                                    .unwrap()
-                                    .set_state(ChunkState::Generating(cur_stage + 1));
+                                self.set_chunk_state(key, ChunkState::Generating(cur_stage + 1));
 
                                 populated_num += 1;
                             }
@@ -739,14 +706,6 @@ where
                         {
                             profiling::scope!("check unload");
                             if !loader_zones.iter().any(|z| rect.intersects(&z.unload)) {
-                                if let Err(e) = self.save_chunk(key) {
-                                    log::error!(
-                                        "Chunk @ {}, {} failed to save: {:?}",
-                                        key.0,
-                                        key.1,
-                                        e
-                                    );
-                                };
                                 if let Err(e) = self.unload_chunk(key, ctx.physics) {
                                     log::error!(
                                         "Chunk @ {}, {} failed to unload: {:?}",
@@ -772,8 +731,7 @@ where
         update_structures.run_now(ctx.world);
         ctx.world.maintain();
 
-        let mut iter = keep_map.iter();
-        unsafe { self.manager.raw_mut() }.retain(|_, _| *iter.next().unwrap());
+        self.remove_chunks(&keys, &keep_map);
     }
 
     #[allow(clippy::too_many_lines)]
@@ -794,7 +752,7 @@ where
                 let rect = ch.dirty_rect();
                 ch.set_dirty_rect(None);
                 old_dirty_rects.insert(*key, rect);
-                if ch.state() == ChunkState::Active {
+                if ch.state() == ChunkState::Active && !ch.frozen() {
                     keys_for_phases[chunk_update_order(key.0, key.1) as usize].push(*key);
                 }
             }
@@ -881,6 +839,7 @@ where
                 )> = {
                     profiling::scope!("par_iter");
                     let reg = ctx.registries.clone();
+                    let simulate_heat = ctx.settings.simulate_heat;
                     to_exec
                         .into_par_iter()
                         .map(move |(ch_pos, mut chunk_data)| {
@@ -894,6 +853,7 @@ where
                                 &mut chunk_data,
                                 &mut particles,
                                 reg.clone(),
+                                simulate_heat,
                             );
 
                             let dirty_info = chunk_data.map(|d| (d.dirty, d.dirty_rect));
@@ -926,7 +886,11 @@ where
                         // TODO: clean up this dirty rect code
 
                         if dirty_info[i as usize].0 {
-                            ch.mark_dirty();
+                            // every pixel the simulation wrote is inside its dirty rect
+                            match dirty_info[i as usize].1 {
+                                Some(rect) => ch.mark_dirty_rect(rect),
+                                None => ch.mark_dirty(),
+                            }
                         }
 
                         if i != 4 && dirty_info[4].1.is_some() {
@@ -995,6 +959,10 @@ where
 
 impl<C: Chunk> ChunkQuery for ChunkHandler<C> {
     type D = C;
+    type KeysIter<'a>
+        = <ChunkManager<C> as ChunkQuery>::KeysIter<'a>
+    where
+        Self: 'a;
 
     #[inline]
     fn chunk_at(&self, chunk_pos: ChunkKey) -> Option<&chunksystem::Chunk<Self::D>> {
@@ -1029,8 +997,8 @@ impl<C: Chunk> ChunkQuery for ChunkHandler<C> {
     }
 
     #[inline]
-    fn keys(&self) -> Vec<ChunkKey> {
-        self.manager.keys()
+    fn keys_iter(&self) -> Self::KeysIter<'_> {
+        self.manager.keys_iter()
     }
 
     #[inline]
@@ -1045,59 +1013,124 @@ impl<C: Chunk> ChunkQuery for ChunkHandler<C> {
 }
 
 impl<C: Chunk> ChunkHandler<C> {
+    /// Max number of each kind of buffer kept in [`ChunkHandler::buffer_pool`].
+    const BUFFER_POOL_CAPACITY: usize = 64;
+
+    /// Number of [`ChunkHandler::gen_pool`] threads used by [`ChunkHandler::new`].
+    pub const DEFAULT_GEN_THREADS: usize = 2;
+
     // #[profiling::function]
     pub fn new(generator: impl WorldGenerator<C> + 'static, path: Option<PathBuf>) -> Self {
+        Self::new_with_gen_threads(generator, path, Self::DEFAULT_GEN_THREADS)
+    }
+
+    /// Like [`ChunkHandler::new`], but with `gen_threads` threads generating chunks.
+    pub fn new_with_gen_threads(
+        generator: impl WorldGenerator<C> + 'static,
+        path: Option<PathBuf>,
+        gen_threads: usize,
+    ) -> Self {
         ChunkHandler {
             manager: ChunkManager::new_with_capacity(1000),
             load_queue: vec![],
             gen_pool: rayon::ThreadPoolBuilder::new()
-                .num_threads(2)
+                .num_threads(gen_threads)
+                .thread_name(|i| format!("chunk gen {i}"))
                 .build()
                 .expect("Failed to build gen_poool"),
             gen_threads: vec![],
             screen_size: (1920 / 2, 1080 / 2),
             generator: Arc::new(generator),
+            io: path
+                .as_ref()
+                .map(|path| ChunkIoWorker::new(path.join("chunks/"))),
             path,
+            buffer_pool: ChunkBufferPool::new(Self::BUFFER_POOL_CAPACITY),
+            on_state_change: None,
+        }
+    }
+
+    /// Sets [`ChunkHandler::on_state_change`].
+    pub fn set_state_change_callback(
+        &mut self,
+        callback: impl FnMut(i32, i32, ChunkState, ChunkState) + Send + Sync + 'static,
+    ) {
+        self.on_state_change = Some(Box::new(callback));
+    }
+
+    /// Sets the state of the chunk at `key`, calling [`ChunkHandler::on_state_change`] if it changed.
+    fn set_chunk_state(&mut self, key: ChunkKey, state: ChunkState) {
+        if let Some(chunk) = self.manager.chunk_at_mut(key) {
+            let old = chunk.state();
+            chunk.set_state(state);
+            Self::notify_state_change(&mut self.on_state_change, key, old, state);
         }
     }
 
+    /// Takes the callback by itself so it can be called while a chunk is borrowed.
+    fn notify_state_change(
+        callback: &mut Option<StateChangeCallback>,
+        (chunk_x, chunk_y): ChunkKey,
+        old: ChunkState,
+        new: ChunkState,
+    ) {
+        if old != new {
+            if let Some(callback) = callback {
+                callback(chunk_x, chunk_y, old, new);
+            }
+        }
+    }
+
+    /// Saves and loads chunks in `dir` from now on, instead of the `chunks` folder of the `path`
+    ///   passed to [`ChunkHandler::new`].
+    ///
+    /// Without either, chunks aren't saved and are regenerated every time they're loaded.
+    pub fn set_save_dir(&mut self, dir: PathBuf) {
+        // dropping the old worker waits for its queued saves
+        self.io = Some(ChunkIoWorker::new(dir));
+    }
+
+    /// Queues saving the chunk on [`ChunkHandler::io`], if the world has a `path`.
+    ///
+    /// The file is written in the background; use [`ChunkHandler::save_all_chunks`] to wait for it.
     #[profiling::function]
     pub fn save_chunk(&mut self, index: ChunkKey) -> Result<(), Box<dyn std::error::Error>> {
         let chunk = self.manager.chunk_at_mut(index).ok_or("Chunk not loaded")?;
-        if let Some(path) = &self.path {
+        if let Some(io) = &mut self.io {
             if let Some(pixels) = chunk.pixels() {
-                let chunk_path_root = path.join("chunks/");
-                if !chunk_path_root.exists() {
-                    std::fs::create_dir_all(&chunk_path_root).expect(
-                        format!("Failed to create chunk directory @ {chunk_path_root:?}").as_str(),
-                    );
-                }
-                let chunk_path =
-                    chunk_path_root.join(format!("{}_{}.chunk", chunk.chunk_x(), chunk.chunk_y()));
-                let mut contents = Vec::new();
+                io.save(
+                    index,
+                    ChunkSaveFormat {
+                        pixels: pixels.to_vec(),
+                        colors: chunk.colors().to_vec(),
+                    },
+                );
+
+                chunk.set_needs_save(false);
+            }
+        }
 
-                let save = ChunkSaveFormat {
-                    pixels: pixels.to_vec(),
-                    colors: chunk.colors().to_vec(),
-                };
+        Ok(())
+    }
 
-                let pixel_data: Vec<u8> = bincode::serialize(&save)?;
-                contents.extend(pixel_data);
+    /// Saves every generated chunk that changed since it was last saved, without unloading anything.
+    ///
+    /// Returns the number of chunks written.
+    pub fn save_modified_chunks(&mut self) -> Result<usize, Box<dyn std::error::Error>> {
+        let keys: Vec<_> = self
+            .manager
+            .kv_iter()
+            .filter(|(_, c)| {
+                c.needs_save() && matches!(c.state(), ChunkState::Cached | ChunkState::Active)
+            })
+            .map(|(k, _)| k)
+            .collect();
 
-                let r = std::fs::write(&chunk_path, contents);
-                if r.is_err() {
-                    log::error!(
-                        "Chunk save failed @ {},{} -> {:?}",
-                        chunk.chunk_x(),
-                        chunk.chunk_y(),
-                        chunk_path
-                    );
-                }
-                r?;
-            }
+        for &key in &keys {
+            self.save_chunk(key)?;
         }
 
-        Ok(())
+        Ok(keys.len())
     }
 
     pub fn unload_all_chunks(
@@ -1113,12 +1146,18 @@ impl<C: Chunk> ChunkHandler<C> {
         Ok(())
     }
 
+    /// Saves every loaded chunk, blocking until the files are written.
     pub fn save_all_chunks(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         #[allow(clippy::for_kv_map)] // want ? to work
         let keys = self.manager.keys();
         for i in keys {
             self.save_chunk(i)?;
         }
+
+        if let Some(io) = &self.io {
+            io.flush();
+        }
+
         Ok(())
     }
 
@@ -1149,13 +1188,139 @@ impl<C: Chunk> ChunkHandler<C> {
         }
     }
 
+    /// Excludes a loaded chunk from (or returns it to) the simulation pass in [`Self::tick`].
+    ///
+    /// Frozen chunks stay loaded and rendered, and still accept direct edits.
+    /// Unfreezing marks the whole chunk dirty so anything that was left mid-motion resumes.
+    /// Returns `false` if the chunk isn't loaded.
+    pub fn set_frozen(&mut self, chunk_x: i32, chunk_y: i32, frozen: bool) -> bool {
+        let Some(ch) = self.manager.chunk_at_mut((chunk_x, chunk_y)) else {
+            return false;
+        };
+
+        if ch.frozen() && !frozen {
+            ch.set_dirty_rect(Some(Rect::new_wh(0, 0, CHUNK_SIZE, CHUNK_SIZE)));
+        }
+        ch.set_frozen(frozen);
+
+        true
+    }
+
+    /// Loaded chunks in [`ChunkState::Active`].
+    pub fn active_chunks(&self) -> impl Iterator<Item = &chunksystem::Chunk<C>> {
+        self.manager
+            .chunks_iter()
+            .filter(|c| c.state() == ChunkState::Active)
+    }
+
+    /// Loaded chunks in [`ChunkState::Active`].
+    pub fn active_chunks_mut(&mut self) -> impl Iterator<Item = &mut chunksystem::Chunk<C>> {
+        self.manager
+            .chunks_iter_mut()
+            .filter(|c| c.state() == ChunkState::Active)
+    }
+
+    /// The 3x3 block of chunks centered on `(chunk_x, chunk_y)`, or [`None`] for unloaded ones.
+    ///
+    /// Row-major from the top left, so index `4` is the center chunk and
+    ///   `(chunk_x + dx, chunk_y + dy)` is at index `(dy + 1) * 3 + (dx + 1)`.
+    pub fn get_neighbors(&self, chunk_x: i32, chunk_y: i32) -> [Option<&chunksystem::Chunk<C>>; 9] {
+        std::array::from_fn(|i| {
+            let dx = (i % 3) as i32 - 1;
+            let dy = (i / 3) as i32 - 1;
+            self.manager.chunk_at((chunk_x + dx, chunk_y + dy))
+        })
+    }
+
+    /// Sets every pixel in `rect` (`x2`/`y2` exclusive) using `placer`.
+    ///
+    /// If `outline` is true, only the one pixel wide border is set. If `background` is true, the
+    ///   background layer is set instead of the foreground. Unloaded pixels are skipped.
+    pub fn fill_rect(
+        &mut self,
+        rect: Rect<i64>,
+        placer: &dyn MaterialPlacerSampler,
+        outline: bool,
+        background: bool,
+    ) {
+        for y in rect.range_tb() {
+            for x in rect.range_lr() {
+                if outline
+                    && x != rect.left()
+                    && x != rect.right() - 1
+                    && y != rect.top()
+                    && y != rect.bottom() - 1
+                {
+                    continue;
+                }
+
+                let _ = if background {
+                    self.set_background(x, y, placer.pixel(x, y))
+                } else {
+                    self.set_pixel(x, y, placer.pixel(x, y))
+                };
+            }
+        }
+    }
+
+    /// Draws a `thickness` pixel wide line from `start` to `end` (both inclusive) using `placer`.
+    ///
+    /// If `background` is true, the line is drawn on the background layer. Unloaded pixels are skipped.
+    pub fn draw_line(
+        &mut self,
+        start: (i64, i64),
+        end: (i64, i64),
+        thickness: u16,
+        placer: &dyn MaterialPlacerSampler,
+        background: bool,
+    ) {
+        let thickness = i64::from(thickness.max(1));
+        let offset = -(thickness - 1) / 2;
+
+        // bresenham
+        let (mut x, mut y) = start;
+        let dx = (end.0 - x).abs();
+        let dy = -(end.1 - y).abs();
+        let step_x = if x < end.0 { 1 } else { -1 };
+        let step_y = if y < end.1 { 1 } else { -1 };
+        let mut err = dx + dy;
+
+        loop {
+            self.fill_rect(
+                Rect::new_wh(x + offset, y + offset, thickness, thickness),
+                placer,
+                false,
+                background,
+            );
+
+            if x == end.0 && y == end.1 {
+                break;
+            }
+
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += step_x;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += step_y;
+            }
+        }
+    }
+
     #[allow(clippy::unnecessary_wraps)]
     #[profiling::function]
+    /// Saves the chunk (see [`ChunkHandler::save_chunk`]) and frees its physics body.
+    ///
+    /// The chunk itself is removed from the manager by the caller.
     fn unload_chunk(
         &mut self,
         index: ChunkKey,
         physics: &mut Physics,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        self.save_chunk(index)?;
+
         let chunk = self.manager.chunk_at_mut(index).unwrap();
         if let Some(ChunkRigidBodyState::Active(handle)) = chunk.rigidbody() {
             physics.remove_rigidbody(*handle);
@@ -1165,9 +1330,32 @@ impl<C: Chunk> ChunkHandler<C> {
         Ok(())
     }
 
+    /// Removes each chunk in `keys` whose entry in `keep_map` is `false`, recycling its buffers.
+    fn remove_chunks(&mut self, keys: &[ChunkKey], keep_map: &[bool]) {
+        for (key, _) in keys.iter().zip(keep_map).filter(|(_, keep)| !**keep) {
+            if let Some(io) = &mut self.io {
+                io.forget(*key);
+            }
+            if let Some(chunk) = unsafe { self.manager.raw_mut() }.remove(key) {
+                self.recycle_chunk(chunk.data);
+            }
+        }
+    }
+
+    /// Returns the buffers of a removed chunk to [`ChunkHandler::buffer_pool`].
+    fn recycle_chunk(&mut self, mut chunk: C) {
+        if let Some(pixels) = chunk.pixels_mut().take() {
+            self.buffer_pool.give_pixels(pixels);
+        }
+        if let Some(background) = chunk.background_pixels_mut().take() {
+            self.buffer_pool.give_pixels(background);
+        }
+        self.buffer_pool.give_buffers(chunk.into_buffers());
+    }
+
     #[profiling::function]
     fn load_chunk(&mut self, chunk_x: i32, chunk_y: i32) -> &mut C {
-        let chunk = Chunk::new_empty(chunk_x, chunk_y);
+        let chunk = Chunk::new_with_buffers(chunk_x, chunk_y, self.buffer_pool.take_buffers());
         let i = (chunk_x, chunk_y);
         self.manager.insert(i, chunk);
         self.manager.chunk_at_mut(i).unwrap()
@@ -1209,4 +1397,33 @@ impl<C: Chunk> ChunkHandler<C> {
     pub fn get_unload_zone(&self, center: (f64, f64)) -> Rect<i32> {
         self.get_zone(center, CHUNK_SIZE * 15)
     }
+
+    /// Fraction (`0.0..=1.0`) of the chunks in the active zone around `center` that are done generating,
+    ///   ie. [`ChunkState::Cached`] or [`ChunkState::Active`].
+    ///
+    /// Useful for showing a loading bar while the area around the player generates.
+    pub fn generation_progress(&self, center: (f64, f64)) -> f32 {
+        let zone = self.get_active_zone(center);
+
+        let mut total = 0_u32;
+        let mut done = 0_u32;
+        for px in zone.range_lr().step_by(CHUNK_SIZE.into()) {
+            for py in zone.range_tb().step_by(CHUNK_SIZE.into()) {
+                total += 1;
+                let chunk_pos = pixel_to_chunk_pos(px.into(), py.into());
+                if matches!(
+                    self.manager.chunk_at(chunk_pos).map(|c| c.state()),
+                    Some(ChunkState::Cached | ChunkState::Active)
+                ) {
+                    done += 1;
+                }
+            }
+        }
+
+        if total == 0 {
+            1.0
+        } else {
+            done as f32 / total as f32
+        }
+    }
 }
@@ -0,0 +1,350 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    thread::JoinHandle,
+};
+
+use chunksystem::ChunkKey;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    material::{color::Color, MaterialInstance},
+    region::RegionFile,
+};
+
+/// Version of [`ChunkSaveFormat`], stored before each chunk's payload.
+///
+/// Bump it whenever the format changes; chunks saved with another version fail to load.
+pub const CHUNK_SAVE_VERSION: u32 = 1;
+
+/// On-disk format of a single chunk, stored bincode encoded in a [`RegionFile`]
+///   after [`CHUNK_SAVE_VERSION`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkSaveFormat {
+    pub pixels: Vec<MaterialInstance>,
+    pub colors: Vec<Color>,
+}
+
+enum ChunkIoRequest {
+    Save(ChunkKey, ChunkSaveFormat),
+    Load(ChunkKey),
+    /// Answered once every request sent before it has been handled.
+    Flush(Sender<()>),
+}
+
+type ChunkLoadResult = (ChunkKey, Result<Option<ChunkSaveFormat>, String>);
+
+/// Region files opened by the worker thread, keyed by region position.
+type OpenRegions = HashMap<(i32, i32), RegionFile>;
+
+/// Where a chunk is in the [`ChunkIoWorker`]'s loading process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkLoadStatus {
+    /// Not requested yet, see [`ChunkIoWorker::queue_load`].
+    Unknown,
+    /// Requested but not received yet.
+    Pending,
+    /// There is no save file for the chunk, so it needs to be generated.
+    Missing,
+}
+
+/// Reads and writes chunk files on a dedicated thread so disk latency never stalls the tick.
+///
+/// Requests are handled in the order they're sent, so a load queued after a save of the same
+///   chunk sees the saved data.
+/// Finished loads are collected with [`ChunkIoWorker::poll_loads`].
+pub struct ChunkIoWorker {
+    requests: Option<Sender<ChunkIoRequest>>,
+    results: Receiver<ChunkLoadResult>,
+    thread: Option<JoinHandle<()>>,
+    pending: HashSet<ChunkKey>,
+    missing: HashSet<ChunkKey>,
+}
+
+impl ChunkIoWorker {
+    /// Starts the worker thread, storing chunks in `root` (created if needed).
+    pub fn new(root: PathBuf) -> Self {
+        let (request_tx, request_rx) = mpsc::channel();
+        let (result_tx, result_rx) = mpsc::channel();
+
+        let thread = std::thread::Builder::new()
+            .name("chunk io".to_owned())
+            .spawn(move || {
+                profiling::register_thread!("chunk io");
+                Self::run(&root, &request_rx, &result_tx);
+            })
+            .expect("Failed to spawn chunk io thread");
+
+        Self {
+            requests: Some(request_tx),
+            results: result_rx,
+            thread: Some(thread),
+            pending: HashSet::new(),
+            missing: HashSet::new(),
+        }
+    }
+
+    /// Max number of region files the worker thread keeps open at once.
+    const MAX_OPEN_REGIONS: usize = 16;
+
+    fn run(root: &Path, requests: &Receiver<ChunkIoRequest>, results: &Sender<ChunkLoadResult>) {
+        let mut regions = OpenRegions::new();
+
+        // the loop ends when the ChunkIoWorker is dropped
+        while let Ok(request) = requests.recv() {
+            match request {
+                ChunkIoRequest::Save(key, save) => {
+                    profiling::scope!("save");
+                    if let Err(e) = Self::write(root, &mut regions, key, &save) {
+                        log::error!("Chunk save failed @ {},{}: {}", key.0, key.1, e);
+                    }
+                },
+                ChunkIoRequest::Load(key) => {
+                    profiling::scope!("load");
+                    let _ = results.send((key, Self::read(root, &mut regions, key)));
+                },
+                ChunkIoRequest::Flush(done) => {
+                    let _ = done.send(());
+                },
+            }
+        }
+    }
+
+    /// Gets the region file at `region_pos`, opening (and creating) it if needed.
+    fn region<'r>(
+        root: &Path,
+        regions: &'r mut OpenRegions,
+        region_pos: (i32, i32),
+    ) -> Result<&'r mut RegionFile, String> {
+        if !regions.contains_key(&region_pos) {
+            if !root.exists() {
+                std::fs::create_dir_all(root)
+                    .map_err(|e| format!("Failed to create chunk directory @ {root:?}: {e}"))?;
+            }
+
+            if regions.len() >= Self::MAX_OPEN_REGIONS {
+                regions.clear();
+            }
+
+            let region = RegionFile::open(RegionFile::path(root, region_pos.0, region_pos.1))?;
+            regions.insert(region_pos, region);
+        }
+
+        Ok(regions.get_mut(&region_pos).unwrap())
+    }
+
+    fn write(
+        root: &Path,
+        regions: &mut OpenRegions,
+        (chunk_x, chunk_y): ChunkKey,
+        save: &ChunkSaveFormat,
+    ) -> Result<(), String> {
+        let (region_pos, (local_x, local_y)) = RegionFile::region_pos(chunk_x, chunk_y);
+        let mut contents = CHUNK_SAVE_VERSION.to_le_bytes().to_vec();
+        bincode::serialize_into(&mut contents, save).map_err(|e| e.to_string())?;
+        Self::region(root, regions, region_pos)?.write_chunk(local_x, local_y, &contents)
+    }
+
+    fn read(
+        root: &Path,
+        regions: &mut OpenRegions,
+        (chunk_x, chunk_y): ChunkKey,
+    ) -> Result<Option<ChunkSaveFormat>, String> {
+        let (region_pos, (local_x, local_y)) = RegionFile::region_pos(chunk_x, chunk_y);
+
+        // don't create region files just to find out they're empty
+        if !regions.contains_key(&region_pos)
+            && !RegionFile::path(root, region_pos.0, region_pos.1).exists()
+        {
+            return Ok(None);
+        }
+
+        let Some(data) = Self::region(root, regions, region_pos)?.read_chunk(local_x, local_y)?
+        else {
+            return Ok(None);
+        };
+        Self::decode(&data)
+            .map(Some)
+            .map_err(|e| format!("chunk {chunk_x},{chunk_y}: {e}"))
+    }
+
+    /// Decodes a payload written by [`ChunkIoWorker::write`], checking its version first.
+    fn decode(data: &[u8]) -> Result<ChunkSaveFormat, String> {
+        let Some((version, payload)) = data
+            .get(..4)
+            .map(|v| (u32::from_le_bytes(v.try_into().unwrap()), &data[4..]))
+        else {
+            return Err("missing save version".to_owned());
+        };
+
+        if version != CHUNK_SAVE_VERSION {
+            return Err(format!(
+                "unsupported save version {version} (expected {CHUNK_SAVE_VERSION})"
+            ));
+        }
+
+        bincode::deserialize(payload).map_err(|e| e.to_string())
+    }
+
+    fn send(&self, request: ChunkIoRequest) {
+        if let Some(requests) = &self.requests {
+            if requests.send(request).is_err() {
+                log::error!("Chunk io thread is gone");
+            }
+        }
+    }
+
+    /// Queues writing `save` for the chunk at `key`.
+    pub fn save(&mut self, key: ChunkKey, save: ChunkSaveFormat) {
+        // it's on disk now, so it needs to be loaded instead of generated next time
+        self.missing.remove(&key);
+        self.send(ChunkIoRequest::Save(key, save));
+    }
+
+    /// Queues reading the chunk at `key`. Returns `false` if it was already requested.
+    pub fn queue_load(&mut self, key: ChunkKey) -> bool {
+        if self.status(key) != ChunkLoadStatus::Unknown {
+            return false;
+        }
+
+        self.pending.insert(key);
+        self.send(ChunkIoRequest::Load(key));
+        true
+    }
+
+    pub fn status(&self, key: ChunkKey) -> ChunkLoadStatus {
+        if self.pending.contains(&key) {
+            ChunkLoadStatus::Pending
+        } else if self.missing.contains(&key) {
+            ChunkLoadStatus::Missing
+        } else {
+            ChunkLoadStatus::Unknown
+        }
+    }
+
+    /// Resets the chunk's status to [`ChunkLoadStatus::Unknown`], eg. when it's unloaded.
+    ///
+    /// Pending loads still arrive from [`ChunkIoWorker::poll_loads`].
+    pub fn forget(&mut self, key: ChunkKey) {
+        self.missing.remove(&key);
+    }
+
+    /// Takes the loads that finished since the last call, without blocking.
+    ///
+    /// Chunks without a save file aren't returned, their status becomes [`ChunkLoadStatus::Missing`].
+    pub fn poll_loads(&mut self) -> Vec<(ChunkKey, Result<ChunkSaveFormat, String>)> {
+        let mut out = vec![];
+        while let Ok((key, result)) = self.results.try_recv() {
+            self.pending.remove(&key);
+            match result {
+                Ok(Some(save)) => out.push((key, Ok(save))),
+                Ok(None) => {
+                    self.missing.insert(key);
+                },
+                Err(e) => out.push((key, Err(e))),
+            }
+        }
+        out
+    }
+
+    /// Blocks until every request sent so far has been handled.
+    pub fn flush(&self) {
+        let (tx, rx) = mpsc::channel();
+        self.send(ChunkIoRequest::Flush(tx));
+        let _ = rx.recv();
+    }
+}
+
+impl Drop for ChunkIoWorker {
+    fn drop(&mut self) {
+        // closing the channel stops the thread once it's done with what's queued
+        self.requests = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::game::common::world::{
+        material::{color::Color, MaterialInstance},
+        CHUNK_AREA,
+    };
+
+    use super::{ChunkIoWorker, ChunkLoadStatus, ChunkSaveFormat, CHUNK_SAVE_VERSION};
+
+    fn wait_for_loads(
+        worker: &mut ChunkIoWorker,
+    ) -> Vec<((i32, i32), Result<ChunkSaveFormat, String>)> {
+        let start = Instant::now();
+        loop {
+            let loads = worker.poll_loads();
+            if !loads.is_empty() || start.elapsed() > Duration::from_secs(5) {
+                return loads;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn queued_load_resolves() {
+        let root = std::env::temp_dir().join(format!("fs_chunk_io_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+
+        let mut worker = ChunkIoWorker::new(root.clone());
+
+        // nothing on disk yet
+        assert!(worker.queue_load((1, 2)));
+        assert!(!worker.queue_load((1, 2)));
+        assert_eq!(worker.status((1, 2)), ChunkLoadStatus::Pending);
+        worker.flush();
+        assert!(worker.poll_loads().is_empty());
+        assert_eq!(worker.status((1, 2)), ChunkLoadStatus::Missing);
+
+        let mut pixels = vec![MaterialInstance::air(); CHUNK_AREA];
+        pixels[5].color = Color::rgb(1, 2, 3);
+        let colors = vec![Color::rgb(4, 5, 6); CHUNK_AREA];
+        worker.save(
+            (1, 2),
+            ChunkSaveFormat { pixels: pixels.clone(), colors: colors.clone() },
+        );
+        assert_eq!(worker.status((1, 2)), ChunkLoadStatus::Unknown);
+
+        assert!(worker.queue_load((1, 2)));
+        let loads = wait_for_loads(&mut worker);
+        assert_eq!(loads.len(), 1);
+
+        let (key, result) = &loads[0];
+        assert_eq!(*key, (1, 2));
+        let save = result.as_ref().unwrap();
+        assert_eq!(save.pixels, pixels);
+        assert_eq!(save.colors, colors);
+        assert_eq!(worker.status((1, 2)), ChunkLoadStatus::Unknown);
+
+        drop(worker);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn save_version() {
+        let save = ChunkSaveFormat {
+            pixels: vec![MaterialInstance::air(); 4],
+            colors: vec![Color::rgb(1, 2, 3); 4],
+        };
+        let mut data = CHUNK_SAVE_VERSION.to_le_bytes().to_vec();
+        bincode::serialize_into(&mut data, &save).unwrap();
+
+        let decoded = ChunkIoWorker::decode(&data).unwrap();
+        assert_eq!(decoded.pixels, save.pixels);
+        assert_eq!(decoded.colors, save.colors);
+
+        // payloads from other versions are rejected
+        data[..4].copy_from_slice(&(CHUNK_SAVE_VERSION + 1).to_le_bytes());
+        assert!(ChunkIoWorker::decode(&data).is_err());
+        assert!(ChunkIoWorker::decode(&[1, 0]).is_err());
+    }
+}
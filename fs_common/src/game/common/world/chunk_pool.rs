@@ -0,0 +1,150 @@
+use super::{
+    material::{color::Color, MaterialInstance},
+    CHUNK_AREA,
+};
+
+/// The per-chunk buffers a [`super::Chunk`] is constructed from.
+///
+/// See [`super::Chunk::new_with_buffers`] and [`super::Chunk::into_buffers`].
+pub struct ChunkBuffers {
+    pub colors: Box<[Color; CHUNK_AREA]>,
+    pub lights: Box<[[f32; 4]; CHUNK_AREA]>,
+    pub background_colors: Box<[Color; CHUNK_AREA]>,
+}
+
+impl Default for ChunkBuffers {
+    fn default() -> Self {
+        Self {
+            colors: Box::new([Color::TRANSPARENT; CHUNK_AREA]),
+            lights: Box::new([[0.0; 4]; CHUNK_AREA]),
+            background_colors: Box::new([Color::TRANSPARENT; CHUNK_AREA]),
+        }
+    }
+}
+
+/// Recycles the large per-chunk buffers of unloaded chunks so newly loaded chunks can reuse them
+///   instead of allocating.
+///
+/// Each kind of buffer is kept up to `capacity` times; anything returned past that is dropped.
+/// Buffers are reset to their empty values when taken out.
+pub struct ChunkBufferPool {
+    pixels: Vec<Box<[MaterialInstance; CHUNK_AREA]>>,
+    colors: Vec<Box<[Color; CHUNK_AREA]>>,
+    lights: Vec<Box<[[f32; 4]; CHUNK_AREA]>>,
+    pub capacity: usize,
+}
+
+impl ChunkBufferPool {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            pixels: Vec::new(),
+            colors: Vec::new(),
+            lights: Vec::new(),
+            capacity,
+        }
+    }
+
+    /// Takes a pixel buffer filled with air, allocating one if the pool is empty.
+    pub fn take_pixels(&mut self) -> Box<[MaterialInstance; CHUNK_AREA]> {
+        if let Some(mut pixels) = self.pixels.pop() {
+            pixels.fill(MaterialInstance::air());
+            pixels
+        } else {
+            Box::new([(); CHUNK_AREA].map(|_| MaterialInstance::air()))
+        }
+    }
+
+    /// Takes a transparent color buffer, allocating one if the pool is empty.
+    pub fn take_colors(&mut self) -> Box<[Color; CHUNK_AREA]> {
+        if let Some(mut colors) = self.colors.pop() {
+            colors.fill(Color::TRANSPARENT);
+            colors
+        } else {
+            Box::new([Color::TRANSPARENT; CHUNK_AREA])
+        }
+    }
+
+    /// Takes an unlit light buffer, allocating one if the pool is empty.
+    pub fn take_lights(&mut self) -> Box<[[f32; 4]; CHUNK_AREA]> {
+        if let Some(mut lights) = self.lights.pop() {
+            lights.fill([0.0; 4]);
+            lights
+        } else {
+            Box::new([[0.0; 4]; CHUNK_AREA])
+        }
+    }
+
+    pub fn take_buffers(&mut self) -> ChunkBuffers {
+        ChunkBuffers {
+            colors: self.take_colors(),
+            lights: self.take_lights(),
+            background_colors: self.take_colors(),
+        }
+    }
+
+    pub fn give_pixels(&mut self, pixels: Box<[MaterialInstance; CHUNK_AREA]>) {
+        if self.pixels.len() < self.capacity {
+            self.pixels.push(pixels);
+        }
+    }
+
+    pub fn give_colors(&mut self, colors: Box<[Color; CHUNK_AREA]>) {
+        if self.colors.len() < self.capacity {
+            self.colors.push(colors);
+        }
+    }
+
+    pub fn give_lights(&mut self, lights: Box<[[f32; 4]; CHUNK_AREA]>) {
+        if self.lights.len() < self.capacity {
+            self.lights.push(lights);
+        }
+    }
+
+    pub fn give_buffers(&mut self, buffers: ChunkBuffers) {
+        self.give_colors(buffers.colors);
+        self.give_lights(buffers.lights);
+        self.give_colors(buffers.background_colors);
+    }
+
+    /// Number of pooled (pixel, color, light) buffers.
+    pub fn pooled(&self) -> (usize, usize, usize) {
+        (self.pixels.len(), self.colors.len(), self.lights.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::common::world::material::{color::Color, MaterialInstance};
+
+    use super::{ChunkBufferPool, ChunkBuffers};
+
+    #[test]
+    fn reuse_and_reset() {
+        let mut pool = ChunkBufferPool::new(4);
+
+        let mut colors = pool.take_colors();
+        colors[5] = Color::rgb(1, 2, 3);
+        let ptr = colors.as_ptr();
+        pool.give_colors(colors);
+        assert_eq!(pool.pooled(), (0, 1, 0));
+
+        let colors = pool.take_colors();
+        assert_eq!(colors.as_ptr(), ptr);
+        assert_eq!(colors[5], Color::TRANSPARENT);
+        assert_eq!(pool.pooled(), (0, 0, 0));
+
+        let pixels = pool.take_pixels();
+        assert_eq!(pixels[0], MaterialInstance::air());
+    }
+
+    #[test]
+    fn bounded() {
+        let mut pool = ChunkBufferPool::new(2);
+
+        for _ in 0..5 {
+            pool.give_buffers(ChunkBuffers::default());
+        }
+
+        assert_eq!(pool.pooled(), (0, 2, 2));
+    }
+}
@@ -80,12 +80,25 @@ impl Camera {
             .with(AutoTarget {
                 target,
                 offset: (0.0, 0.0),
-                style: TargetStyle::Locked,
+                style: TargetStyle::EaseOut(15.0),
+                lead: 4.0,
+                enabled: true,
             })
             .build();
 
         camera
     }
+
+    /// Attaches or detaches every camera from its [`AutoTarget`], eg. to let the camera move freely.
+    pub fn set_follow<C: Chunk>(world: &mut World<C>, follow: bool) {
+        let (camera_storage, mut target_storage) = world
+            .ecs
+            .system_data::<(ReadStorage<Camera>, WriteStorage<AutoTarget>)>();
+
+        for (_, target) in (&camera_storage, &mut target_storage).join() {
+            target.enabled = follow;
+        }
+    }
 }
 
 impl Component for Camera {
@@ -114,6 +127,10 @@ pub struct AutoTarget {
     pub target: Target,
     pub offset: (f64, f64),
     pub style: TargetStyle,
+    /// How many ticks of the target's velocity to look ahead by.
+    pub lead: f64,
+    /// If false, the entity stays where it is instead of following the target.
+    pub enabled: bool,
 }
 
 impl AutoTarget {
@@ -161,7 +178,18 @@ impl<'a> System<'a> for UpdateAutoTargets {
         let (entities, delta_time, target, mut pos_storage, mut vel_storage) = data;
 
         (&entities, &target).join().for_each(|(entity, at)| {
-            if let Some(target_pos) = at.get_target_pos(&pos_storage) {
+            if !at.enabled {
+                return;
+            }
+
+            let lead = at
+                .get_target_vel(&vel_storage)
+                .map_or((0.0, 0.0), |v| (v.x * at.lead, v.y * at.lead));
+
+            if let Some(mut target_pos) = at.get_target_pos(&pos_storage) {
+                target_pos.x += lead.0;
+                target_pos.y += lead.1;
+
                 let pos = pos_storage
                     .get_mut(entity)
                     .expect("AutoTarget missing Position");
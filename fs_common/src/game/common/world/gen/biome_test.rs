@@ -46,7 +46,7 @@ impl<C: Chunk + 'static> BiomeTestGenerator<C> {
     pub fn new() -> Self {
         let mut populators = PopulatorList::new();
 
-        populators.add(CavePopulator);
+        populators.add(CavePopulator::default());
         populators.add(SpawnPopulator);
 
         populators.add(PlaceAbovePopulator {
@@ -143,33 +143,42 @@ impl<C: Chunk + 'static> BiomeTestGenerator<C> {
             //     .placement(RandomOffset::chunk())
             //     .placement(MaterialMatch::physics(PhysicsType::Solid))
             //     .placement(BiomeMatch::only("main")),
-            PlacedFeature::new(Blob::new(
-                placer::SMOOTH_DIRT.clone(),
-                Arc::new(|rng| rng.gen_range(16..64)),
-                Arc::new(|m| m.physics == PhysicsType::Solid),
-                false,
-            ))
+            PlacedFeature::new(
+                "dirt_blob",
+                Blob::new(
+                    placer::SMOOTH_DIRT.clone(),
+                    Arc::new(|rng| rng.gen_range(16..64)),
+                    Arc::new(|m| m.physics == PhysicsType::Solid),
+                    false,
+                ),
+            )
             .placement(Chance(0.25))
             .placement(Count::range(0..=2))
             .placement(RandomOffset::chunk())
             .placement(MaterialMatch::material(material::SMOOTH_STONE.clone())),
-            PlacedFeature::new(Blob::new(
-                placer::TEST_PLACER_2.clone(),
-                Arc::new(|rng| rng.gen_range(10..32)),
-                Arc::new(|m| m.physics == PhysicsType::Solid),
-                true,
-            ))
+            PlacedFeature::new(
+                "test_blob",
+                Blob::new(
+                    placer::TEST_PLACER_2.clone(),
+                    Arc::new(|rng| rng.gen_range(10..32)),
+                    Arc::new(|m| m.physics == PhysicsType::Solid),
+                    true,
+                ),
+            )
             .placement(Chance(0.5))
             .placement(Count::range(0..=2))
             .placement(RandomOffset::chunk())
             .placement(MaterialMatch::physics(PhysicsType::Solid))
             .placement(BiomeMatch::only("main")),
-            PlacedFeature::new(ConfiguredStructureFeature::new("yellow_thing".into()))
-                .placement(Count::range(0..=2))
-                .placement(RandomOffset::chunk())
-                .placement(MaterialMatch::physics(PhysicsType::Solid))
-                .placement(BiomeMatch::only("yellow")),
-            PlacedFeature::new(ConfiguredStructureFeature::new("torch".into()))
+            PlacedFeature::new(
+                "yellow_thing",
+                ConfiguredStructureFeature::new("yellow_thing".into()),
+            )
+            .placement(Count::range(0..=2))
+            .placement(RandomOffset::chunk())
+            .placement(MaterialMatch::physics(PhysicsType::Solid))
+            .placement(BiomeMatch::only("yellow")),
+            PlacedFeature::new("torch", ConfiguredStructureFeature::new("torch".into()))
                 .placement(Chance(0.5))
                 .placement(Spread {
                     count: 3,
@@ -184,7 +193,7 @@ impl<C: Chunk + 'static> BiomeTestGenerator<C> {
                     x: 0..1,
                     y: -10..0,
                 }),
-            PlacedFeature::new(TestStructure),
+            PlacedFeature::new("test_structure", TestStructure),
         ];
 
         Self { populators, features }
@@ -56,7 +56,7 @@ impl<C: Chunk> ConfiguredFeature<C> for Blob {
         rng: &mut dyn rand::RngCore,
         registries: &Registries,
         _ecs: &mut specs::World,
-    ) {
+    ) -> bool {
         let (chunk_x, chunk_y) = chunks.center_chunk();
         let chunk_pixel_x = chunk_x * i32::from(CHUNK_SIZE);
         let chunk_pixel_y = chunk_y * i32::from(CHUNK_SIZE);
@@ -84,6 +84,7 @@ impl<C: Chunk> ConfiguredFeature<C> for Blob {
         .with_seed(seed)
         .generate();
 
+        let mut placed = false;
         for dx in -i32::from(radius_x)..=i32::from(radius_x) {
             for dy in -i32::from(radius_y)..=i32::from(radius_y) {
                 let n = noise.0[((dx + i32::from(radius_x))
@@ -115,10 +116,12 @@ impl<C: Chunk> ConfiguredFeature<C> for Blob {
                             );
 
                             chunks.set(x, y, mat).unwrap();
+                            placed = true;
                         }
                     }
                 }
             }
         }
+        placed
     }
 }
@@ -35,7 +35,7 @@ impl<C: Chunk> ConfiguredFeature<C> for ConfiguredStructureFeature {
         _rng: &mut dyn RngCore,
         registries: &Registries,
         ecs: &mut specs::World,
-    ) {
+    ) -> bool {
         let (cx, cy) = chunks.center_chunk();
         let x = i64::from(cx * i32::from(CHUNK_SIZE)) + i64::from(pos.0);
         let y = i64::from(cy * i32::from(CHUNK_SIZE)) + i64::from(pos.1);
@@ -50,5 +50,6 @@ impl<C: Chunk> ConfiguredFeature<C> for ConfiguredStructureFeature {
             y,
             ConfiguredStructurePlaceContext { ecs, world_seed: world_seed as _ },
         );
+        true
     }
 }
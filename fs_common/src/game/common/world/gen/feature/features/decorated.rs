@@ -0,0 +1,62 @@
+use std::fmt::Debug;
+
+use rand::RngCore;
+
+use crate::game::common::{
+    world::{
+        gen::{feature::ConfiguredFeature, populator::ChunkContext},
+        Chunk,
+    },
+    Registries,
+};
+
+/// Places a base feature, then each decorator at the same position, in order.
+///
+/// Counts as placed if the base feature was, whatever the decorators do.
+///
+/// Everything shares the same `rng`, so the decorators continue its sequence after the base.
+pub struct DecoratedFeature<C: Chunk> {
+    base: Box<dyn ConfiguredFeature<C> + Send + Sync>,
+    decorators: Vec<Box<dyn ConfiguredFeature<C> + Send + Sync>>,
+}
+
+impl<C: Chunk> DecoratedFeature<C> {
+    pub fn new(base: impl ConfiguredFeature<C> + Send + Sync + 'static) -> Self {
+        Self { base: Box::new(base), decorators: vec![] }
+    }
+
+    #[must_use]
+    pub fn decorator(mut self, feature: impl ConfiguredFeature<C> + Send + Sync + 'static) -> Self {
+        self.decorators.push(Box::new(feature));
+        self
+    }
+}
+
+impl<C: Chunk> Debug for DecoratedFeature<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DecoratedFeature")
+            .field("base", &self.base)
+            .field("decorators", &self.decorators)
+            .finish()
+    }
+}
+
+impl<C: Chunk> ConfiguredFeature<C> for DecoratedFeature<C> {
+    fn try_place(
+        &self,
+        chunks: &mut ChunkContext<1, C>,
+        pos: (i32, i32),
+        world_seed: i32,
+        rng: &mut dyn RngCore,
+        registries: &Registries,
+        ecs: &mut specs::World,
+    ) -> bool {
+        let placed = self
+            .base
+            .try_place(chunks, pos, world_seed, rng, registries, ecs);
+        for decorator in &self.decorators {
+            decorator.try_place(chunks, pos, world_seed, rng, registries, ecs);
+        }
+        placed
+    }
+}
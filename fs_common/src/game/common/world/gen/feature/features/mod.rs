@@ -1,4 +1,7 @@
 pub mod blob;
 pub mod configured_structure;
+pub mod decorated;
 pub mod simple;
+pub mod template;
 pub mod test_structure;
+pub mod weighted;
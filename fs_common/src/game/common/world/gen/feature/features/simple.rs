@@ -30,7 +30,7 @@ impl<C: Chunk> ConfiguredFeature<C> for SinglePixel {
         _rng: &mut dyn RngCore,
         registries: &Registries,
         _ecs: &mut specs::World,
-    ) {
+    ) -> bool {
         let (cx, cy) = chunks.center_chunk();
         let m = registries
             .material_placers
@@ -41,6 +41,6 @@ impl<C: Chunk> ConfiguredFeature<C> for SinglePixel {
                 i64::from(cx * i32::from(CHUNK_SIZE)) + i64::from(pos.0),
                 i64::from(cy * i32::from(CHUNK_SIZE)) + i64::from(pos.1),
             );
-        let _: Result<(), _> = chunks.set(pos.0, pos.1, m);
+        chunks.set(pos.0, pos.1, m).is_ok()
     }
 }
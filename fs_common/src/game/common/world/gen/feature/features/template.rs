@@ -0,0 +1,73 @@
+use std::path::Path;
+
+use asefile::AsepriteFile;
+use rand::RngCore;
+
+use crate::game::common::{
+    world::{
+        gen::{feature::ConfiguredFeature, populator::ChunkContext},
+        material::buf::MaterialBuf,
+        Chunk,
+    },
+    Registries,
+};
+
+/// Stamps a fixed [`MaterialBuf`] at the placement position.
+///
+/// Air in the template leaves the terrain under it alone (see [`ChunkContext::paste_masked`]), and
+///   anything past the neighboring chunks is cut off.
+#[derive(Debug)]
+pub struct TemplateFeature {
+    template: MaterialBuf,
+    /// Point in the template that lands on the placement position.
+    anchor: (u16, u16),
+}
+
+impl TemplateFeature {
+    /// Placed with its top left corner on the placement position.
+    pub fn new(template: MaterialBuf) -> Self {
+        Self { template, anchor: (0, 0) }
+    }
+
+    pub fn load_ase(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let ase = AsepriteFile::read_file(path)
+            .map_err(|e| format!("Failed to load template @ {path:?}: {e}"))?;
+        Ok(Self::new(MaterialBuf::load_from_ase(&ase)))
+    }
+
+    #[must_use]
+    pub fn with_anchor(self, x: u16, y: u16) -> Self {
+        Self { anchor: (x, y), ..self }
+    }
+
+    /// Places the template centered on the placement position.
+    #[must_use]
+    pub fn centered(self) -> Self {
+        let anchor = (self.template.width / 2, self.template.height / 2);
+        Self { anchor, ..self }
+    }
+
+    pub fn template(&self) -> &MaterialBuf {
+        &self.template
+    }
+}
+
+impl<C: Chunk> ConfiguredFeature<C> for TemplateFeature {
+    fn try_place(
+        &self,
+        chunks: &mut ChunkContext<1, C>,
+        pos: (i32, i32),
+        _seed: i32,
+        _rng: &mut dyn RngCore,
+        _registries: &Registries,
+        _ecs: &mut specs::World,
+    ) -> bool {
+        chunks.paste_masked(
+            &self.template,
+            pos.0 - i32::from(self.anchor.0),
+            pos.1 - i32::from(self.anchor.1),
+        );
+        true
+    }
+}
@@ -24,11 +24,12 @@ impl<C: Chunk> ConfiguredFeature<C> for TestStructure {
         _rng: &mut dyn rand::RngCore,
         registries: &Registries,
         ecs: &mut specs::World,
-    ) {
+    ) -> bool {
         let (cx, cy) = chunks.center_chunk();
         let x = i64::from(cx * i32::from(CHUNK_SIZE)) + i64::from(pos.0);
         let y = i64::from(cy * i32::from(CHUNK_SIZE)) + i64::from(pos.1);
 
+        let mut placed = false;
         for (_, v) in &registries.structure_sets {
             if v.should_generate_at((cx, cy), world_seed as _, registries, true) {
                 let configured_structure = registries
@@ -41,7 +42,9 @@ impl<C: Chunk> ConfiguredFeature<C> for TestStructure {
                     y,
                     ConfiguredStructurePlaceContext { ecs, world_seed: world_seed as _ },
                 );
+                placed = true;
             }
         }
+        placed
     }
 }
@@ -0,0 +1,84 @@
+use std::fmt::Debug;
+
+use rand::{Rng, RngCore};
+
+use crate::game::common::{
+    world::{
+        gen::{feature::ConfiguredFeature, populator::ChunkContext},
+        Chunk,
+    },
+    Registries,
+};
+
+/// Places one of its features, picked at random with a chance proportional to its weight.
+///
+/// Declines if there are no features or all of their weights are zero.
+pub struct WeightedFeature<C: Chunk> {
+    features: Vec<(u32, Box<dyn ConfiguredFeature<C> + Send + Sync>)>,
+}
+
+impl<C: Chunk> WeightedFeature<C> {
+    pub fn new() -> Self {
+        Self { features: vec![] }
+    }
+
+    #[must_use]
+    pub fn with(
+        mut self,
+        weight: u32,
+        feature: impl ConfiguredFeature<C> + Send + Sync + 'static,
+    ) -> Self {
+        self.features.push((weight, Box::new(feature)));
+        self
+    }
+
+    /// Picks a feature using `rng`, or [`None`] if the total weight is zero.
+    fn choose(&self, rng: &mut dyn RngCore) -> Option<&(dyn ConfiguredFeature<C> + Send + Sync)> {
+        let total: u64 = self.features.iter().map(|(w, _)| u64::from(*w)).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut roll = rng.gen_range(0..total);
+        for (weight, feature) in &self.features {
+            let weight = u64::from(*weight);
+            if roll < weight {
+                return Some(feature.as_ref());
+            }
+            roll -= weight;
+        }
+
+        unreachable!()
+    }
+}
+
+impl<C: Chunk> Default for WeightedFeature<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Chunk> Debug for WeightedFeature<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WeightedFeature")
+            .field("features", &self.features)
+            .finish()
+    }
+}
+
+impl<C: Chunk> ConfiguredFeature<C> for WeightedFeature<C> {
+    fn try_place(
+        &self,
+        chunks: &mut ChunkContext<1, C>,
+        pos: (i32, i32),
+        world_seed: i32,
+        rng: &mut dyn RngCore,
+        registries: &Registries,
+        ecs: &mut specs::World,
+    ) -> bool {
+        let Some(feature) = self.choose(rng) else {
+            return false;
+        };
+        feature.try_place(chunks, pos, world_seed, rng, registries, ecs)
+    }
+}
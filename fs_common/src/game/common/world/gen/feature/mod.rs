@@ -3,7 +3,7 @@ pub mod placement_mods;
 
 use std::fmt::Debug;
 
-use rand::RngCore;
+use rand::{rngs::StdRng, RngCore, SeedableRng};
 
 use crate::game::common::{world::Chunk, Registries};
 
@@ -12,6 +12,9 @@ use super::populator::ChunkContext;
 pub type ProviderFn<T> = dyn Fn(&mut dyn rand::RngCore) -> T + Send + Sync;
 
 pub trait ConfiguredFeature<C: Chunk>: Debug {
+    /// Places the feature at `pos` (relative to the center chunk of `chunks`).
+    ///
+    /// Returns `false` if the feature decided not to place anything there.
     fn try_place(
         &self,
         chunks: &mut ChunkContext<1, C>,
@@ -20,18 +23,31 @@ pub trait ConfiguredFeature<C: Chunk>: Debug {
         rng: &mut dyn RngCore,
         registries: &Registries,
         ecs: &mut specs::World,
-    );
+    ) -> bool;
 }
 
 #[derive(Debug)]
 pub struct PlacedFeature<C: Chunk> {
+    /// Salts the feature's randomness, so it must be unique among a generator's features.
+    id: String,
     feature: Box<dyn ConfiguredFeature<C> + Send + Sync>,
     placement_mods: Vec<Box<dyn PlacementModifier<C> + Send + Sync>>,
 }
 
 impl<C: Chunk> PlacedFeature<C> {
-    pub fn new(feature: impl ConfiguredFeature<C> + Send + Sync + 'static) -> Self {
-        Self { feature: Box::new(feature), placement_mods: vec![] }
+    pub fn new(
+        id: impl Into<String>,
+        feature: impl ConfiguredFeature<C> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            feature: Box::new(feature),
+            placement_mods: vec![],
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
     }
 
     #[must_use]
@@ -43,29 +59,75 @@ impl<C: Chunk> PlacedFeature<C> {
         self
     }
 
+    /// Runs the placement modifiers for the center chunk of `chunks` and places the feature at each result.
+    ///
+    /// Returns the number of placements that succeeded (see [`ConfiguredFeature::try_place`]).
+    ///
+    /// The placement modifiers share an RNG seeded from `(seed, chunk, id)`, and each placement gets its
+    ///   own seeded from `(seed, chunk, pos, id)`, so the result doesn't depend on what other features
+    ///   generated before this one.
     pub fn generate(
         &self,
         chunks: &mut ChunkContext<1, C>,
         seed: i32,
-        rng: &mut dyn RngCore,
         registries: &Registries,
         ecs: &mut specs::World,
-    ) {
+    ) -> usize {
+        let chunk = chunks.center_chunk();
+
+        let mut rng = self.rng(seed, chunk, None);
         let mut positions = vec![(0, 0)];
         for m in &self.placement_mods {
             positions = positions
                 .into_iter()
-                .flat_map(|p| m.process(chunks, p, seed, rng, registries))
+                .flat_map(|p| m.process(chunks, p, seed, &mut rng, registries))
                 .collect();
         }
 
-        for pos in positions {
-            self.feature
-                .try_place(chunks, pos, seed, rng, registries, ecs);
-        }
+        positions
+            .into_iter()
+            .filter(|&pos| {
+                let mut rng = self.rng(seed, chunk, Some(pos));
+                self.feature
+                    .try_place(chunks, pos, seed, &mut rng, registries, ecs)
+            })
+            .count()
+    }
+
+    fn rng(&self, seed: i32, chunk: (i32, i32), pos: Option<(i32, i32)>) -> StdRng {
+        StdRng::seed_from_u64(feature_seed(seed, chunk, pos, &self.id))
     }
 }
 
+/// Hashes `(seed, chunk, pos, id)` into the seed for a feature's RNG.
+///
+/// This has to give the same result everywhere so worlds generate the same for everyone, which
+///   `DefaultHasher` doesn't guarantee across Rust versions.
+fn feature_seed(seed: i32, chunk: (i32, i32), pos: Option<(i32, i32)>, id: &str) -> u64 {
+    // the leading 0/1 keeps `None` and `Some((0, 0))` apart
+    let pos = pos.map_or([0, 0, 0], |(x, y)| {
+        [1, u64::from(x as u32), u64::from(y as u32)]
+    });
+
+    [
+        u64::from(seed as u32),
+        u64::from(chunk.0 as u32),
+        u64::from(chunk.1 as u32),
+    ]
+    .into_iter()
+    .chain(pos)
+    .chain(id.bytes().map(u64::from))
+    .fold(0, |hash, v| splitmix64(hash ^ v))
+}
+
+/// One step of the SplitMix64 generator, used as a fixed 64 bit mixing function.
+const fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
 pub trait PlacementModifier<C: Chunk>: Debug {
     fn process(
         &self,
@@ -76,3 +138,28 @@ pub trait PlacementModifier<C: Chunk>: Debug {
         registries: &Registries,
     ) -> Vec<(i32, i32)>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::feature_seed;
+
+    #[test]
+    fn feature_seed_is_stable() {
+        // fixed values, so any change to the hash (which would change every world) fails here
+        assert_eq!(
+            feature_seed(1234, (-2, 3), Some((10, -20)), "test"),
+            0xF543_C986_CB67_1ED8
+        );
+        assert_eq!(
+            feature_seed(1234, (-2, 3), None, "test"),
+            0xFA9C_AA88_1B7C_4F38
+        );
+
+        let base = feature_seed(1234, (-2, 3), Some((0, 0)), "test");
+        assert_ne!(base, feature_seed(1234, (-2, 3), None, "test"));
+        assert_ne!(base, feature_seed(1235, (-2, 3), Some((0, 0)), "test"));
+        assert_ne!(base, feature_seed(1234, (3, -2), Some((0, 0)), "test"));
+        assert_ne!(base, feature_seed(1234, (-2, 3), Some((0, 1)), "test"));
+        assert_ne!(base, feature_seed(1234, (-2, 3), Some((0, 0)), "test2"));
+    }
+}
@@ -13,6 +13,8 @@ use crate::game::common::{
     Registries,
 };
 
+/// Repeats the incoming position, so the modifiers after it (eg. a
+///   [`RandomOffset`](super::random_offset::RandomOffset)) scatter each copy separately.
 pub struct Count {
     func: Arc<ProviderFn<u16>>,
 }
@@ -22,6 +24,10 @@ impl Count {
         Self { func }
     }
 
+    pub fn fixed(count: u16) -> Self {
+        Self::new(Arc::new(move |_| count))
+    }
+
     pub fn range(range: impl SampleRange<u16> + Send + Sync + Clone + 'static) -> Self {
         Self::new(Arc::new(move |rng| rng.gen_range(range.clone())))
     }
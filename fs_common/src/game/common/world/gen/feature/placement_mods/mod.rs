@@ -3,6 +3,8 @@ pub mod chance;
 pub mod count;
 pub mod material_match;
 pub mod material_match_range;
+pub mod noise_threshold;
 pub mod on_ground;
 pub mod random_offset;
 pub mod spread;
+pub mod surface;
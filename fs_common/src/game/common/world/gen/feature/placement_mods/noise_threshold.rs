@@ -0,0 +1,43 @@
+use bracket_noise::prelude::{FastNoise, NoiseType};
+
+use crate::game::common::{
+    world::{
+        gen::{feature::PlacementModifier, populator::ChunkContext},
+        Chunk, CHUNK_SIZE,
+    },
+    Registries,
+};
+
+/// Only keeps positions where a value noise field, seeded from the world seed, is above
+///   `threshold`, so features end up in clusters instead of evenly spread out.
+///
+/// `scale` is roughly the size of the clusters in pixels. The noise is in `-1.0..=1.0`.
+#[derive(Debug)]
+pub struct NoiseThreshold {
+    pub scale: f64,
+    pub threshold: f64,
+}
+
+impl<C: Chunk> PlacementModifier<C> for NoiseThreshold {
+    fn process(
+        &self,
+        chunks: &mut ChunkContext<1, C>,
+        pos: (i32, i32),
+        seed: i32,
+        _rng: &mut dyn rand::RngCore,
+        _registries: &Registries,
+    ) -> Vec<(i32, i32)> {
+        let world_x = i64::from(chunks.center_chunk().0) * i64::from(CHUNK_SIZE) + i64::from(pos.0);
+        let world_y = i64::from(chunks.center_chunk().1) * i64::from(CHUNK_SIZE) + i64::from(pos.1);
+
+        let mut noise = FastNoise::seeded(seed as _);
+        noise.set_noise_type(NoiseType::Value);
+        noise.set_frequency((1.0 / self.scale) as f32);
+
+        if f64::from(noise.get_noise(world_x as f32, world_y as f32)) > self.threshold {
+            vec![pos]
+        } else {
+            vec![]
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use crate::game::common::{
+    world::{
+        gen::{feature::PlacementModifier, populator::ChunkContext},
+        material::PhysicsType,
+        Chunk,
+    },
+    Registries,
+};
+
+/// Moves the position down to the first surface below it: the pixel right above a solid one
+///   that isn't solid itself.
+///
+/// If the position starts inside solid ground, the ground is skipped first. Nothing is placed if
+///   there's no surface before the bottom of the [`ChunkContext`].
+#[derive(Debug)]
+pub struct SurfacePlacement;
+
+impl<C: Chunk> PlacementModifier<C> for SurfacePlacement {
+    fn process(
+        &self,
+        chunks: &mut ChunkContext<1, C>,
+        pos: (i32, i32),
+        _seed: i32,
+        _rng: &mut dyn rand::RngCore,
+        _registries: &Registries,
+    ) -> Vec<(i32, i32)> {
+        let (x, mut y) = pos;
+        let mut above_is_open = false;
+
+        while ChunkContext::<1, C>::contains(x, y) {
+            let solid = chunks.get(x, y).unwrap().physics == PhysicsType::Solid;
+            if solid && above_is_open {
+                return vec![(x, y - 1)];
+            }
+
+            above_is_open = !solid;
+            y += 1;
+        }
+
+        vec![]
+    }
+}
@@ -2,13 +2,37 @@ use bracket_noise::prelude::{FastNoise, NoiseType};
 use simdnoise::NoiseBuilder;
 
 use crate::game::common::{
-    world::{material::MaterialInstance, Chunk, CHUNK_SIZE},
+    world::{
+        material::{MaterialInstance, PhysicsType},
+        Chunk, CHUNK_SIZE,
+    },
     Registries,
 };
 
 use super::{ChunkContext, Populator};
 
-pub struct CavePopulator;
+/// Carves caves along the ridges of seeded noise, turning solid and sand pixels into air.
+///
+/// The ridges are continuous, so the caves they make are long connected tunnels.
+pub struct CavePopulator {
+    /// Frequency of the ridge noise, higher means smaller and closer together caves.
+    pub frequency: f32,
+    /// How close (`0.0..=1.0`) to a ridge a pixel has to be to become air, higher means thinner
+    ///   caves.
+    pub threshold: f32,
+    /// Like [`CavePopulator::threshold`], but for clearing the background.
+    pub background_threshold: f32,
+}
+
+impl Default for CavePopulator {
+    fn default() -> Self {
+        Self {
+            frequency: 0.0005,
+            threshold: 0.8,
+            background_threshold: 0.925,
+        }
+    }
+}
 
 impl<C: Chunk> Populator<0, C> for CavePopulator {
     #[profiling::function]
@@ -43,7 +67,7 @@ impl<C: Chunk> Populator<0, C> for CavePopulator {
             let mut noise_ridge = FastNoise::seeded(seed as _);
             noise_ridge.set_noise_type(NoiseType::Simplex);
             noise_ridge.set_fractal_octaves(1);
-            noise_ridge.set_frequency(0.0005);
+            noise_ridge.set_frequency(self.frequency);
 
             {
                 profiling::scope!("loop");
@@ -60,11 +84,16 @@ impl<C: Chunk> Populator<0, C> for CavePopulator {
                         let nv = noise_ridge.get_noise(cofs_x + t_x, cofs_y + t_y);
 
                         let f = 1.0 - (nv.abs()).clamp(0.0, 1.0);
-                        if f > 0.8 {
+                        if f > self.threshold
+                            && matches!(
+                                chunks.get(x, y).unwrap().physics,
+                                PhysicsType::Solid | PhysicsType::Sand
+                            )
+                        {
                             chunks.set(x, y, MaterialInstance::air()).unwrap();
                         }
 
-                        if f > 0.925 {
+                        if f > self.background_threshold {
                             chunks
                                 .set_background(x, y, MaterialInstance::air())
                                 .unwrap();
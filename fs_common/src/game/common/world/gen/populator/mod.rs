@@ -4,11 +4,16 @@ pub mod place_above;
 pub mod spawn;
 pub mod stalactite;
 pub mod test;
+pub mod vein;
 
 use std::usize;
 
 use crate::game::common::{
-    world::{chunk_index::ChunkLocalPosition, material::MaterialInstance, Chunk, CHUNK_SIZE},
+    world::{
+        chunk_index::ChunkLocalPosition,
+        material::{buf::MaterialBuf, MaterialInstance},
+        Chunk, CHUNK_SIZE,
+    },
     Registries,
 };
 
@@ -17,6 +22,17 @@ pub trait Populator<const S: u8, C: Chunk> {
     fn populate(&self, chunks: &mut ChunkContext<S, C>, seed: i32, registries: &Registries);
 }
 
+/// The chunk being populated along with the `S` chunks around it on each side.
+///
+/// Pixel coordinates are relative to the top left of the center chunk, so (0, 0)..(`CHUNK_SIZE`,
+///   `CHUNK_SIZE`) is the center chunk and the context covers `-S * CHUNK_SIZE..(S + 1) * CHUNK_SIZE`
+///   on both axes (see [`ChunkContext::contains`]).
+/// Negative coordinates land in the chunks to the left or above, eg. (-1, 0) is the rightmost
+///   column of the chunk to the left, and coordinates past `CHUNK_SIZE` in the chunks to the right
+///   or below.
+///
+/// Coordinates outside of the context are not checked by [`ChunkContext::get`] and
+///   [`ChunkContext::set`], use [`ChunkContext::contains`] first if they can go that far.
 // where S=0 means 1x1, S=1 means 3x3, etc
 // when generic_const_exprs gets stablized eventually, could use [&mut dyn Chunk; (S * 2 + 1) * (S * 2 + 1)]
 pub struct ChunkContext<'a, 'b, const S: u8, C: Chunk>(&'a mut [&'b mut C]);
@@ -132,6 +148,25 @@ impl<'a, 'b, const S: u8, C: Chunk> ChunkContext<'a, 'b, S, C> {
         }
     }
 
+    /// Whether (`x`, `y`) (relative to the center chunk) is inside this context.
+    #[inline]
+    pub fn contains(x: i32, y: i32) -> bool {
+        let range =
+            -i32::from(S) * i32::from(CHUNK_SIZE)..(i32::from(S) + 1) * i32::from(CHUNK_SIZE);
+        range.contains(&x) && range.contains(&y)
+    }
+
+    /// Pastes `buf` with its top left corner at (`x`, `y`) using [`MaterialBuf::iter_masked`], so air
+    ///   in `buf` doesn't erase anything. Pixels outside of this context are skipped.
+    pub fn paste_masked(&mut self, buf: &MaterialBuf, x: i32, y: i32) {
+        for (dx, dy, m) in buf.iter_masked() {
+            let (px, py) = (x + i32::from(dx), y + i32::from(dy));
+            if Self::contains(px, py) {
+                let _: Result<(), _> = self.set(px, py, m.clone());
+            }
+        }
+    }
+
     #[inline]
     pub fn get_background(
         &self,
@@ -0,0 +1,59 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::game::common::{
+    world::{
+        material::{self, MaterialInstance},
+        Chunk, CHUNK_SIZE,
+    },
+    Registries,
+};
+
+use super::{ChunkContext, Populator};
+
+/// Carves a long, winding vein of `material` through solid ground, starting in the center chunk.
+///
+/// Veins can run up to three chunks away from where they start, so this uses a 7x7
+///   [`ChunkContext`] and needs the generator to have at least 3 generation stages.
+pub struct VeinPopulator {
+    pub material: MaterialInstance,
+    /// Number of steps (about one pixel each) the vein takes.
+    pub length: u16,
+    /// Radius of the vein in pixels.
+    pub radius: u8,
+}
+
+impl<C: Chunk> Populator<3, C> for VeinPopulator {
+    #[profiling::function]
+    fn populate(&self, chunks: &mut ChunkContext<3, C>, seed: i32, _registries: &Registries) {
+        let (chunk_x, chunk_y) = chunks.center_chunk();
+        let mut rng = StdRng::seed_from_u64(
+            (seed as u64) ^ (u64::from(chunk_x as u32) << 32) ^ u64::from(chunk_y as u32),
+        );
+
+        let mut x = rng.gen_range(0.0..f32::from(CHUNK_SIZE));
+        let mut y = rng.gen_range(0.0..f32::from(CHUNK_SIZE));
+        let mut angle = rng.gen_range(0.0..std::f32::consts::TAU);
+
+        let radius = i32::from(self.radius);
+        for _ in 0..self.length {
+            for dx in -radius..=radius {
+                for dy in -radius..=radius {
+                    let (px, py) = (x.floor() as i32 + dx, y.floor() as i32 + dy);
+                    if dx * dx + dy * dy > radius * radius
+                        || !ChunkContext::<3, C>::contains(px, py)
+                    {
+                        continue;
+                    }
+
+                    if chunks.get(px, py).unwrap().material_id != *material::AIR {
+                        chunks.set(px, py, self.material.clone()).unwrap();
+                    }
+                }
+            }
+
+            angle += rng.gen_range(-0.1..=0.1);
+            x += angle.cos();
+            y += angle.sin();
+        }
+    }
+}
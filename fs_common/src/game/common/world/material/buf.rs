@@ -1,6 +1,8 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, path::Path};
 
 use asefile::AsepriteFile;
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
 
 use crate::game::common::{
     registry::RegistryID,
@@ -22,6 +24,17 @@ pub struct MaterialBuf {
 #[derive(Debug)]
 pub struct OutOfBoundsError;
 
+/// File format used by [`MaterialBuf::save`], bincode encoded.
+///
+/// The materials are stored as `(count, material)` runs in row-major order, since structures
+///   tend to have large areas of the same material.
+#[derive(Serialize, Deserialize)]
+struct MaterialBufFile {
+    width: u16,
+    height: u16,
+    runs: Vec<(u32, MaterialInstance)>,
+}
+
 impl MaterialBuf {
     pub fn new(width: u16, height: u16, materials: Vec<MaterialInstance>) -> Result<Self, String> {
         if materials.len() == (width as usize * height as usize) {
@@ -115,6 +128,93 @@ impl MaterialBuf {
         buf
     }
 
+    /// Loads an image file, mapping each pixel's color to a material with `id_from_color`.
+    pub fn from_image(
+        path: impl AsRef<Path>,
+        id_from_color: impl Fn(Color) -> MaterialInstance,
+    ) -> Result<Self, String> {
+        let path = path.as_ref();
+        let img = image::open(path)
+            .map_err(|e| format!("Failed to load image @ {path:?}: {e}"))?
+            .into_rgba8();
+
+        let (Ok(width), Ok(height)) = (u16::try_from(img.width()), u16::try_from(img.height()))
+        else {
+            return Err(format!(
+                "Image @ {path:?} is too large: {}x{}",
+                img.width(),
+                img.height()
+            ));
+        };
+
+        let materials = img
+            .pixels()
+            .map(|p| id_from_color(Color::rgba(p.0[0], p.0[1], p.0[2], p.0[3])))
+            .collect();
+
+        Self::new(width, height, materials)
+    }
+
+    /// Saves the buffer as an image file (format chosen by the extension), coloring each pixel with `color_of`.
+    pub fn to_image(
+        &self,
+        path: impl AsRef<Path>,
+        color_of: impl Fn(&MaterialInstance) -> Color,
+    ) -> Result<(), String> {
+        let path = path.as_ref();
+        let data = self
+            .materials
+            .iter()
+            .flat_map(|m| {
+                let c = color_of(m);
+                [c.r, c.g, c.b, c.a]
+            })
+            .collect();
+
+        RgbaImage::from_raw(u32::from(self.width), u32::from(self.height), data)
+            .ok_or_else(|| "Image buffer size mismatch".to_string())?
+            .save(path)
+            .map_err(|e| format!("Failed to save image @ {path:?}: {e}"))
+    }
+
+    /// Saves the buffer to `path`, see [`MaterialBuf::load`].
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let mut runs: Vec<(u32, MaterialInstance)> = vec![];
+        for m in &self.materials {
+            match runs.last_mut() {
+                Some((count, last)) if last == m => *count += 1,
+                _ => runs.push((1, m.clone())),
+            }
+        }
+
+        let file = MaterialBufFile { width: self.width, height: self.height, runs };
+        let data = bincode::serialize(&file).map_err(|e| e.to_string())?;
+        std::fs::write(path, data).map_err(|e| format!("Failed to save @ {path:?}: {e}"))
+    }
+
+    /// Loads a buffer saved with [`MaterialBuf::save`].
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data = std::fs::read(path).map_err(|e| format!("Failed to load @ {path:?}: {e}"))?;
+        let file: MaterialBufFile =
+            bincode::deserialize(&data).map_err(|e| format!("Invalid file @ {path:?}: {e}"))?;
+
+        // check before expanding the runs so a bad file can't allocate a huge Vec
+        let len: u64 = file.runs.iter().map(|(count, _)| u64::from(*count)).sum();
+        let expected = u64::from(file.width) * u64::from(file.height);
+        if len != expected {
+            return Err(format!(
+                "Invalid file @ {path:?}: has {len} materials, expected {}x{}={expected}",
+                file.width, file.height
+            ));
+        }
+
+        let mut materials = Vec::with_capacity(expected as usize);
+        for (count, m) in file.runs {
+            materials.resize(materials.len() + count as usize, m);
+        }
+        Self::new(file.width, file.height, materials)
+    }
+
     pub fn copy<C: Chunk + Send>(
         chunk_handler: &ChunkHandler<C>,
         x: impl Into<i64>,
@@ -189,6 +289,53 @@ impl MaterialBuf {
         Ok(())
     }
 
+    /// Like [`MaterialBuf::paste`], but cells of the buffer where `skip` returns true leave the
+    ///   pixel under them untouched, so irregular shapes don't erase what's around them.
+    ///
+    /// Pass [`MaterialBuf::is_masked`] to skip air and structure void.
+    pub fn paste_masked(
+        &self,
+        chunk_handler: &mut dyn FSChunkAccess,
+        x: impl Into<i64>,
+        y: impl Into<i64>,
+        skip: impl Fn(&MaterialInstance) -> bool,
+    ) -> Result<(), String> {
+        let x = x.into();
+        let y = y.into();
+
+        for (i, m) in self.materials.iter().enumerate() {
+            if skip(m) {
+                continue;
+            }
+
+            let dx = (i % self.width as usize) as i64;
+            let dy = (i / self.width as usize) as i64;
+            chunk_handler.set_pixel(x + dx, y + dy, m.clone())?;
+        }
+
+        Ok(())
+    }
+
+    /// Whether `m` is left out of masked pastes by default: air and structure void.
+    pub fn is_masked(m: &MaterialInstance) -> bool {
+        m.material_id == *super::AIR || m.material_id == *super::STRUCTURE_VOID
+    }
+
+    /// The cells that aren't [`MaterialBuf::is_masked`], as `(x, y, material)`.
+    pub fn iter_masked(&self) -> impl Iterator<Item = (u16, u16, &MaterialInstance)> {
+        self.materials
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| !Self::is_masked(m))
+            .map(|(i, m)| {
+                (
+                    (i % self.width as usize) as u16,
+                    (i / self.width as usize) as u16,
+                    m,
+                )
+            })
+    }
+
     pub fn get(&self, x: u16, y: u16) -> Result<MaterialInstance, OutOfBoundsError> {
         if x < self.width && y < self.height {
             Ok(self.materials[x as usize + y as usize * self.width as usize].clone())
@@ -203,6 +350,34 @@ impl MaterialBuf {
         }
     }
 
+    /// The cells where `other` differs from `self`, as `(x, y, other's material)` in row-major order.
+    ///
+    /// Applying the result to a copy of `self` (with [`MaterialBuf::set`]) gives `other`.
+    /// Returns an error if the dimensions don't match.
+    pub fn diff(&self, other: &MaterialBuf) -> Result<Vec<(u16, u16, MaterialInstance)>, String> {
+        if self.width != other.width || self.height != other.height {
+            return Err(format!(
+                "Dimension mismatch: {}x{} vs {}x{}",
+                self.width, self.height, other.width, other.height
+            ));
+        }
+
+        Ok(self
+            .materials
+            .iter()
+            .zip(&other.materials)
+            .enumerate()
+            .filter(|(_, (a, b))| a != b)
+            .map(|(i, (_, b))| {
+                (
+                    (i % self.width as usize) as u16,
+                    (i / self.width as usize) as u16,
+                    b.clone(),
+                )
+            })
+            .collect())
+    }
+
     #[must_use]
     pub fn rotated(&self, angle: AngleDiff) -> Self {
         let (new_w, new_h) = match angle {
@@ -235,6 +410,44 @@ impl MaterialBuf {
 
         new
     }
+
+    /// Rotated 90 degrees clockwise, so the width and height swap.
+    #[must_use]
+    pub fn rotate_cw(&self) -> Self {
+        self.rotated(AngleDiff::Clockwise90)
+    }
+
+    /// Rotated 90 degrees counterclockwise, so the width and height swap.
+    #[must_use]
+    pub fn rotate_ccw(&self) -> Self {
+        self.rotated(AngleDiff::CounterClockwise90)
+    }
+
+    /// Mirrored left to right.
+    #[must_use]
+    pub fn flip_horizontal(&self) -> Self {
+        let materials = self
+            .materials
+            .chunks(usize::from(self.width).max(1))
+            .flat_map(|row| row.iter().rev().cloned())
+            .collect();
+
+        Self { width: self.width, height: self.height, materials }
+    }
+
+    /// Mirrored top to bottom.
+    #[must_use]
+    pub fn flip_vertical(&self) -> Self {
+        let materials = self
+            .materials
+            .chunks(usize::from(self.width).max(1))
+            .rev()
+            .flatten()
+            .cloned()
+            .collect();
+
+        Self { width: self.width, height: self.height, materials }
+    }
 }
 
 impl Debug for MaterialBuf {
@@ -297,3 +510,146 @@ impl MaterialRect {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::game::common::world::material::{
+        color::Color, MaterialInstance, PhysicsType, STRUCTURE_VOID, TEST,
+    };
+
+    use crate::game::common::world::gen::structure::AngleDiff;
+
+    use super::MaterialBuf;
+
+    #[test]
+    fn diff() {
+        let a = MaterialBuf::of_air(3, 2);
+        assert_eq!(a.diff(&a.clone()), Ok(vec![]));
+
+        let mat = TEST.instance(PhysicsType::Solid, Color::RED);
+        let b = MaterialBuf::new(3, 2, vec![mat.clone(); 6]).unwrap();
+        let diff = a.diff(&b).unwrap();
+        assert_eq!(diff.len(), 6);
+        assert_eq!(diff[0], (0, 0, mat.clone()));
+        assert_eq!(diff[4], (1, 1, mat.clone()));
+
+        let mut patched = a.clone();
+        for (x, y, m) in diff {
+            patched.set(x, y, m);
+        }
+        assert_eq!(patched, b);
+
+        assert!(a.diff(&MaterialBuf::of_air(2, 3)).is_err());
+        assert_eq!(b.diff(&a).unwrap()[5], (2, 1, MaterialInstance::air()));
+    }
+
+    #[test]
+    fn iter_masked() {
+        let mut buf = MaterialBuf::of_air(3, 3);
+        let mat = TEST.instance(PhysicsType::Solid, Color::RED);
+        buf.set(2, 1, mat.clone());
+        let void = STRUCTURE_VOID.instance(PhysicsType::Air, Color::TRANSPARENT);
+        buf.set(0, 2, void);
+
+        let masked: Vec<_> = buf.iter_masked().collect();
+        assert_eq!(masked, [(2, 1, &mat)]);
+    }
+
+    #[test]
+    fn image_round_trip() {
+        let path = std::env::temp_dir().join(format!("fs_material_buf_{}.png", std::process::id()));
+
+        let mut buf = MaterialBuf::of_air(4, 3);
+        buf.set(1, 2, TEST.instance(PhysicsType::Solid, Color::RED));
+        buf.set(3, 0, TEST.instance(PhysicsType::Sand, Color::BLUE));
+
+        buf.to_image(&path, |m| m.color).unwrap();
+        let loaded = MaterialBuf::from_image(&path, |c| {
+            if c.a == 0 {
+                MaterialInstance::air()
+            } else {
+                TEST.instance(PhysicsType::Solid, c)
+            }
+        });
+        let _ignore = std::fs::remove_file(&path);
+        let loaded = loaded.unwrap();
+
+        assert_eq!((loaded.width, loaded.height), (4, 3));
+        assert_eq!(loaded.get(0, 0).unwrap(), MaterialInstance::air());
+        assert_eq!(loaded.get(1, 2).unwrap().color, Color::RED);
+        assert_eq!(loaded.get(3, 0).unwrap().color, Color::BLUE);
+
+        let missing = MaterialBuf::from_image(path.with_extension("missing.png"), |_| {
+            MaterialInstance::air()
+        });
+        assert!(missing.is_err());
+    }
+
+    /// A `width`x`height` buffer where each cell's red channel is its index.
+    fn numbered(width: u16, height: u16) -> MaterialBuf {
+        let materials = (0..width * height)
+            .map(|i| TEST.instance(PhysicsType::Solid, Color::rgb(i as u8, 0, 0)))
+            .collect();
+        MaterialBuf::new(width, height, materials).unwrap()
+    }
+
+    fn cells(buf: &MaterialBuf) -> (u16, u16, Vec<u8>) {
+        (
+            buf.width,
+            buf.height,
+            buf.materials.iter().map(|m| m.color.r).collect(),
+        )
+    }
+
+    #[test]
+    fn rotate_and_flip() {
+        // 0 1 2
+        // 3 4 5
+        let buf = numbered(3, 2);
+
+        assert_eq!(cells(&buf.rotate_cw()), (2, 3, vec![3, 0, 4, 1, 5, 2]));
+        assert_eq!(cells(&buf.rotate_ccw()), (2, 3, vec![2, 5, 1, 4, 0, 3]));
+        assert_eq!(
+            cells(&buf.flip_horizontal()),
+            (3, 2, vec![2, 1, 0, 5, 4, 3])
+        );
+        assert_eq!(cells(&buf.flip_vertical()), (3, 2, vec![3, 4, 5, 0, 1, 2]));
+
+        assert_eq!(buf.rotate_cw().rotate_ccw(), buf);
+        assert_eq!(buf.flip_horizontal().flip_horizontal(), buf);
+        assert_eq!(
+            buf.flip_horizontal().flip_vertical(),
+            buf.rotated(AngleDiff::Angle180)
+        );
+    }
+
+    #[test]
+    fn save_load() {
+        let path = std::env::temp_dir().join(format!("fs_material_buf_{}.bin", std::process::id()));
+
+        let mut buf = MaterialBuf::of_air(64, 32);
+        for x in 10..50 {
+            for y in 20..32 {
+                buf.set(x, y, TEST.instance(PhysicsType::Solid, Color::RED));
+            }
+        }
+        buf.set(0, 0, TEST.instance(PhysicsType::Sand, Color::BLUE));
+
+        buf.save(&path).unwrap();
+        let size = std::fs::metadata(&path).unwrap().len();
+        let loaded = MaterialBuf::load(&path);
+
+        // a file with the wrong number of materials is rejected
+        let mut truncated = MaterialBuf::of_air(4, 4);
+        truncated.height = 5;
+        truncated.save(&path).unwrap();
+        let bad = MaterialBuf::load(&path);
+        let _ignore = std::fs::remove_file(&path);
+
+        assert_eq!(loaded.unwrap(), buf);
+        let naive = bincode::serialize(&buf.materials).unwrap().len();
+        assert!(size * 10 < naive as u64, "{size} vs {naive}");
+
+        assert!(bad.unwrap_err().contains("expected 4x5=20"));
+    }
+}
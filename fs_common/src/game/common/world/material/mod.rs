@@ -1,6 +1,7 @@
 pub mod buf;
 pub mod color;
 pub mod placer;
+pub mod properties;
 
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
@@ -19,6 +20,9 @@ pub enum PhysicsType {
     Object,
 }
 
+/// Temperature (in °C) new [`MaterialInstance`]s start at.
+pub const AMBIENT_TEMPERATURE: f32 = 20.0;
+
 #[derive(Debug)]
 pub struct Material {
     pub display_name: String,
@@ -30,6 +34,18 @@ pub struct MaterialInstance {
     pub physics: PhysicsType,
     pub color: Color,
     pub light: [f32; 3],
+    /// In °C. Only changes while `Settings::simulate_heat` is on.
+    ///
+    /// Not saved or synced, so the on-disk and network formats are the same with or without the
+    ///   `heat` feature. Loaded pixels start at [`AMBIENT_TEMPERATURE`].
+    #[cfg(feature = "heat")]
+    #[serde(skip, default = "ambient_temperature")]
+    pub temperature: f32,
+}
+
+#[cfg(feature = "heat")]
+fn ambient_temperature() -> f32 {
+    AMBIENT_TEMPERATURE
 }
 
 impl MaterialInstance {
@@ -43,6 +59,12 @@ impl MaterialInstance {
         Self { light, ..self }
     }
 
+    #[cfg(feature = "heat")]
+    #[must_use]
+    pub fn with_temperature(self, temperature: f32) -> Self {
+        Self { temperature, ..self }
+    }
+
     /// Any instance that returns false will be skipped when simulating
     #[inline(always)] // this function is very hot
     pub fn dynamic(&self) -> bool {
@@ -64,6 +86,8 @@ impl RegistryID<Material> {
             physics,
             color,
             light: [0.0; 3],
+            #[cfg(feature = "heat")]
+            temperature: AMBIENT_TEMPERATURE,
         }
     }
 }
@@ -79,6 +103,11 @@ pub static FADED_COBBLE_DIRT: Lazy<RegistryID<Material>> = Lazy::new(|| "faded_c
 pub static SMOOTH_STONE: Lazy<RegistryID<Material>> = Lazy::new(|| "smooth_stone".into());
 pub static SMOOTH_DIRT: Lazy<RegistryID<Material>> = Lazy::new(|| "smooth_dirt".into());
 
+pub static ACID: Lazy<RegistryID<Material>> = Lazy::new(|| "acid".into());
+pub static GLASS: Lazy<RegistryID<Material>> = Lazy::new(|| "glass".into());
+pub static WATER: Lazy<RegistryID<Material>> = Lazy::new(|| "water".into());
+pub static STEAM: Lazy<RegistryID<Material>> = Lazy::new(|| "steam".into());
+
 pub static STRUCTURE_VOID: Lazy<RegistryID<Material>> = Lazy::new(|| "structure_void".into());
 
 pub type MaterialRegistry = Registry<Material>;
@@ -112,6 +141,19 @@ pub fn init_material_types() -> MaterialRegistry {
         SMOOTH_DIRT.clone(),
         Material { display_name: "Dirt".to_string() },
     );
+    registry.register(ACID.clone(), Material { display_name: "Acid".to_string() });
+    registry.register(
+        GLASS.clone(),
+        Material { display_name: "Glass".to_string() },
+    );
+    registry.register(
+        WATER.clone(),
+        Material { display_name: "Water".to_string() },
+    );
+    registry.register(
+        STEAM.clone(),
+        Material { display_name: "Steam".to_string() },
+    );
     registry.register(
         STRUCTURE_VOID.clone(),
         Material { display_name: "Structure Void".to_string() },
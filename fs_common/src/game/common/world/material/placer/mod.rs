@@ -34,6 +34,8 @@ impl<F: Fn() -> MaterialInstance + Sync> MaterialPlacerSampler for F {
 #[derive(Debug)]
 pub struct MaterialPlacerMeta {
     pub display_name: String,
+    /// Used to group placers in the draw UI palette.
+    pub category: String,
 }
 
 pub struct MaterialPlacer {
@@ -51,6 +53,10 @@ pub static AIR_PLACER: Lazy<RegistryID<MaterialPlacer>> = Lazy::new(|| "air".int
 pub static TEST_PLACER_1: Lazy<RegistryID<MaterialPlacer>> = Lazy::new(|| "test_placer_1".into());
 pub static TEST_PLACER_2: Lazy<RegistryID<MaterialPlacer>> = Lazy::new(|| "test_placer_2".into());
 pub static TEST_GRASS: Lazy<RegistryID<MaterialPlacer>> = Lazy::new(|| "test_grass".into());
+pub static ACID: Lazy<RegistryID<MaterialPlacer>> = Lazy::new(|| "acid".into());
+pub static GLASS: Lazy<RegistryID<MaterialPlacer>> = Lazy::new(|| "glass".into());
+pub static WATER: Lazy<RegistryID<MaterialPlacer>> = Lazy::new(|| "water".into());
+pub static STEAM: Lazy<RegistryID<MaterialPlacer>> = Lazy::new(|| "steam".into());
 
 pub static COBBLE_STONE: Lazy<RegistryID<MaterialPlacer>> = Lazy::new(|| "cobble_stone".into());
 pub static COBBLE_DIRT: Lazy<RegistryID<MaterialPlacer>> = Lazy::new(|| "cobble_dirt".into());
@@ -98,15 +104,71 @@ pub fn init_material_placers(file_helper: &FileHelper) -> MaterialPlacerRegistry
     registry.register(
         AIR_PLACER.clone(),
         MaterialPlacer {
-            meta: MaterialPlacerMeta { display_name: "Air".to_string() },
+            meta: MaterialPlacerMeta {
+                display_name: "Air".to_string(),
+                category: "Basic".to_string(),
+            },
             sampler: Box::new(MaterialInstance::air) as Box<dyn MaterialPlacerSampler + Send>,
         },
     );
 
+    registry.register(
+        ACID.clone(),
+        MaterialPlacer {
+            meta: MaterialPlacerMeta {
+                display_name: "Acid".to_string(),
+                category: "Basic".to_string(),
+            },
+            sampler: Box::new(super::ACID.instance(PhysicsType::Liquid, Color::rgb(96, 220, 32))),
+        },
+    );
+
+    registry.register(
+        GLASS.clone(),
+        MaterialPlacer {
+            meta: MaterialPlacerMeta {
+                display_name: "Glass".to_string(),
+                category: "Basic".to_string(),
+            },
+            sampler: Box::new(
+                super::GLASS.instance(PhysicsType::Solid, Color::rgba(200, 230, 255, 160)),
+            ),
+        },
+    );
+
+    registry.register(
+        WATER.clone(),
+        MaterialPlacer {
+            meta: MaterialPlacerMeta {
+                display_name: "Water".to_string(),
+                category: "Basic".to_string(),
+            },
+            sampler: Box::new(
+                super::WATER.instance(PhysicsType::Liquid, Color::rgba(32, 96, 220, 200)),
+            ),
+        },
+    );
+
+    registry.register(
+        STEAM.clone(),
+        MaterialPlacer {
+            meta: MaterialPlacerMeta {
+                display_name: "Steam".to_string(),
+                category: "Basic".to_string(),
+            },
+            sampler: Box::new(
+                super::STEAM.instance(PhysicsType::Gas, Color::rgba(220, 220, 230, 120)),
+            ),
+        },
+    );
+
     registry.register(
         TEST_PLACER_1.clone(),
         MaterialPlacer {
-            meta: MaterialPlacerMeta { display_name: "Test 1".to_string() },
+            meta: MaterialPlacerMeta {
+                display_name: "Test 1".to_string(),
+                category: "Test".to_string(),
+            },
             sampler: Box::new(super::TEST.instance(PhysicsType::Solid, Color::GRAY)),
         },
     );
@@ -114,7 +176,10 @@ pub fn init_material_placers(file_helper: &FileHelper) -> MaterialPlacerRegistry
     registry.register(
         TEST_PLACER_2.clone(),
         MaterialPlacer {
-            meta: MaterialPlacerMeta { display_name: "Test 2".to_string() },
+            meta: MaterialPlacerMeta {
+                display_name: "Test 2".to_string(),
+                category: "Test".to_string(),
+            },
             sampler: Box::new(
                 TexturedPlacer::new(
                     super::TEST.clone(),
@@ -129,7 +194,10 @@ pub fn init_material_placers(file_helper: &FileHelper) -> MaterialPlacerRegistry
     registry.register(
         TEST_GRASS.clone(),
         MaterialPlacer {
-            meta: MaterialPlacerMeta { display_name: "Test Grass".to_string() },
+            meta: MaterialPlacerMeta {
+                display_name: "Test Grass".to_string(),
+                category: "Test".to_string(),
+            },
             sampler: Box::new(super::TEST.instance(PhysicsType::Solid, Color::rgb(0, 127, 0))),
         },
     );
@@ -137,7 +205,10 @@ pub fn init_material_placers(file_helper: &FileHelper) -> MaterialPlacerRegistry
     registry.register_basic_textured(
         COBBLE_STONE.clone(),
         super::COBBLE_STONE.clone(),
-        MaterialPlacerMeta { display_name: "Cobblestone".to_string() },
+        MaterialPlacerMeta {
+            display_name: "Cobblestone".to_string(),
+            category: "Terrain".to_string(),
+        },
         PhysicsType::Solid,
         "cobble_stone_128x",
         file_helper,
@@ -146,7 +217,10 @@ pub fn init_material_placers(file_helper: &FileHelper) -> MaterialPlacerRegistry
     registry.register_basic_textured(
         COBBLE_DIRT.clone(),
         super::COBBLE_DIRT.clone(),
-        MaterialPlacerMeta { display_name: "Cobbledirt".to_string() },
+        MaterialPlacerMeta {
+            display_name: "Cobbledirt".to_string(),
+            category: "Terrain".to_string(),
+        },
         PhysicsType::Solid,
         "cobble_dirt_128x",
         file_helper,
@@ -155,7 +229,10 @@ pub fn init_material_placers(file_helper: &FileHelper) -> MaterialPlacerRegistry
     registry.register_basic_textured(
         FADED_COBBLE_STONE.clone(),
         super::FADED_COBBLE_STONE.clone(),
-        MaterialPlacerMeta { display_name: "Faded Cobblestone".to_string() },
+        MaterialPlacerMeta {
+            display_name: "Faded Cobblestone".to_string(),
+            category: "Terrain".to_string(),
+        },
         PhysicsType::Solid,
         "flat_cobble_stone_128x",
         file_helper,
@@ -164,7 +241,10 @@ pub fn init_material_placers(file_helper: &FileHelper) -> MaterialPlacerRegistry
     registry.register_basic_textured(
         FADED_COBBLE_DIRT.clone(),
         super::FADED_COBBLE_DIRT.clone(),
-        MaterialPlacerMeta { display_name: "Faded Cobbledirt".to_string() },
+        MaterialPlacerMeta {
+            display_name: "Faded Cobbledirt".to_string(),
+            category: "Terrain".to_string(),
+        },
         PhysicsType::Solid,
         "flat_cobble_dirt_128x",
         file_helper,
@@ -173,7 +253,10 @@ pub fn init_material_placers(file_helper: &FileHelper) -> MaterialPlacerRegistry
     registry.register_basic_textured(
         SMOOTH_STONE.clone(),
         super::SMOOTH_STONE.clone(),
-        MaterialPlacerMeta { display_name: "Smooth Stone".to_string() },
+        MaterialPlacerMeta {
+            display_name: "Smooth Stone".to_string(),
+            category: "Terrain".to_string(),
+        },
         PhysicsType::Solid,
         "smooth_stone_128x",
         file_helper,
@@ -182,7 +265,10 @@ pub fn init_material_placers(file_helper: &FileHelper) -> MaterialPlacerRegistry
     registry.register_basic_textured(
         SMOOTH_DIRT.clone(),
         super::SMOOTH_DIRT.clone(),
-        MaterialPlacerMeta { display_name: "Dirt".to_string() },
+        MaterialPlacerMeta {
+            display_name: "Dirt".to_string(),
+            category: "Terrain".to_string(),
+        },
         PhysicsType::Solid,
         "smooth_dirt_128x",
         file_helper,
@@ -194,7 +280,10 @@ pub fn init_material_placers(file_helper: &FileHelper) -> MaterialPlacerRegistry
         registry.register_basic_textured(
             format!("test_{color}"),
             super::COBBLE_STONE.clone(),
-            MaterialPlacerMeta { display_name: format!("Test {color}") },
+            MaterialPlacerMeta {
+                display_name: format!("Test {color}"),
+                category: "Test".to_string(),
+            },
             PhysicsType::Solid,
             format!("test_{color}"),
             file_helper,
@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+
+use crate::game::common::registry::RegistryID;
+
+use super::{
+    color::Color, Material, MaterialInstance, PhysicsType, ACID, AIR, COBBLE_DIRT, COBBLE_STONE,
+    GLASS, SMOOTH_DIRT, SMOOTH_STONE, STEAM, WATER,
+};
+
+/// Scalar properties of a material used by the dynamic simulation systems.
+///
+/// Build with [`MaterialProperties::default`] and the `with_*` methods.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaterialProperties {
+    /// Relative weight; denser materials sink through less dense ones.
+    pub density: f32,
+    /// Chance (`0.0..=1.0`) of catching fire when next to fire.
+    pub flammability: f32,
+    /// Fraction (`0.0..=1.0`) of the temperature difference exchanged with neighbors per tick.
+    pub thermal_conductivity: f32,
+    /// Amount of light emitted.
+    pub emission: f32,
+    /// Explosion strength needed to destroy a pixel of this material.
+    pub toughness: f32,
+    /// Whether acid can dissolve this material. Only applies to solid and sand pixels.
+    pub corrodable: bool,
+    /// Chance (`0.0..=1.0`) per tick of dissolving a neighboring corrodable pixel.
+    ///
+    /// Liquids with a strength above zero behave as acid.
+    pub acid_strength: f32,
+}
+
+impl MaterialProperties {
+    pub const DEFAULT: Self = Self {
+        density: 1.0,
+        flammability: 0.0,
+        thermal_conductivity: 0.1,
+        emission: 0.0,
+        toughness: 1.0,
+        corrodable: true,
+        acid_strength: 0.0,
+    };
+
+    #[must_use]
+    pub fn with_density(self, density: f32) -> Self {
+        Self { density, ..self }
+    }
+
+    #[must_use]
+    pub fn with_flammability(self, flammability: f32) -> Self {
+        Self { flammability, ..self }
+    }
+
+    #[must_use]
+    pub fn with_thermal_conductivity(self, thermal_conductivity: f32) -> Self {
+        Self { thermal_conductivity, ..self }
+    }
+
+    #[must_use]
+    pub fn with_emission(self, emission: f32) -> Self {
+        Self { emission, ..self }
+    }
+
+    #[must_use]
+    pub fn with_toughness(self, toughness: f32) -> Self {
+        Self { toughness, ..self }
+    }
+
+    #[must_use]
+    pub fn with_corrodable(self, corrodable: bool) -> Self {
+        Self { corrodable, ..self }
+    }
+
+    #[must_use]
+    pub fn with_acid_strength(self, acid_strength: f32) -> Self {
+        Self { acid_strength, ..self }
+    }
+}
+
+impl Default for MaterialProperties {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// A material turning into another one when its temperature crosses a threshold, eg. water boiling.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhaseTransition {
+    /// Threshold in °C.
+    pub temperature: f32,
+    /// If `true`, the transition happens above [`PhaseTransition::temperature`], otherwise below it.
+    pub above: bool,
+    /// What the pixel is replaced with. It keeps its own temperature.
+    pub into: MaterialInstance,
+}
+
+impl PhaseTransition {
+    pub fn above(temperature: f32, into: MaterialInstance) -> Self {
+        Self { temperature, above: true, into }
+    }
+
+    pub fn below(temperature: f32, into: MaterialInstance) -> Self {
+        Self { temperature, above: false, into }
+    }
+
+    /// Whether a pixel at `temperature` goes through this transition.
+    #[inline]
+    pub fn applies(&self, temperature: f32) -> bool {
+        if self.above {
+            temperature > self.temperature
+        } else {
+            temperature < self.temperature
+        }
+    }
+}
+
+/// [`MaterialProperties`] for each material, keyed by material id.
+///
+/// Lookups for unregistered materials fall back to [`MaterialProperties::DEFAULT`].
+pub struct MaterialPropertiesRegistry {
+    map: HashMap<RegistryID<Material>, MaterialProperties, ahash::RandomState>,
+    transitions: HashMap<RegistryID<Material>, Vec<PhaseTransition>, ahash::RandomState>,
+}
+
+impl MaterialPropertiesRegistry {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            map: HashMap::default(),
+            transitions: HashMap::default(),
+        }
+    }
+
+    pub fn register(&mut self, material_id: RegistryID<Material>, properties: MaterialProperties) {
+        self.map.insert(material_id, properties);
+    }
+
+    /// Adds a phase transition for `material_id`. The first registered one that applies wins.
+    pub fn register_transition(
+        &mut self,
+        material_id: RegistryID<Material>,
+        transition: PhaseTransition,
+    ) {
+        self.transitions
+            .entry(material_id)
+            .or_default()
+            .push(transition);
+    }
+
+    /// Phase transitions registered for `material_id`, in registration order.
+    #[inline]
+    pub fn transitions(&self, material_id: &RegistryID<Material>) -> &[PhaseTransition] {
+        self.transitions.get(material_id).map_or(&[], Vec::as_slice)
+    }
+
+    /// The phase transition a pixel of `material_id` at `temperature` should go through, if any.
+    #[inline]
+    pub fn transition(
+        &self,
+        material_id: &RegistryID<Material>,
+        temperature: f32,
+    ) -> Option<&PhaseTransition> {
+        self.transitions(material_id)
+            .iter()
+            .find(|t| t.applies(temperature))
+    }
+
+    /// Properties registered for `material_id`, if any.
+    #[inline]
+    pub fn get(&self, material_id: &RegistryID<Material>) -> Option<&MaterialProperties> {
+        self.map.get(material_id)
+    }
+
+    /// Properties for `material_id`, or [`MaterialProperties::DEFAULT`] if it isn't registered.
+    #[inline]
+    pub fn get_or_default(&self, material_id: &RegistryID<Material>) -> &MaterialProperties {
+        self.map
+            .get(material_id)
+            .unwrap_or(&MaterialProperties::DEFAULT)
+    }
+
+    #[inline]
+    pub fn density(&self, material_id: &RegistryID<Material>) -> f32 {
+        self.get_or_default(material_id).density
+    }
+
+    #[inline]
+    pub fn flammability(&self, material_id: &RegistryID<Material>) -> f32 {
+        self.get_or_default(material_id).flammability
+    }
+
+    #[inline]
+    pub fn thermal_conductivity(&self, material_id: &RegistryID<Material>) -> f32 {
+        self.get_or_default(material_id).thermal_conductivity
+    }
+
+    #[inline]
+    pub fn emission(&self, material_id: &RegistryID<Material>) -> f32 {
+        self.get_or_default(material_id).emission
+    }
+
+    #[inline]
+    pub fn toughness(&self, material_id: &RegistryID<Material>) -> f32 {
+        self.get_or_default(material_id).toughness
+    }
+
+    #[inline]
+    pub fn corrodable(&self, material_id: &RegistryID<Material>) -> bool {
+        self.get_or_default(material_id).corrodable
+    }
+
+    #[inline]
+    pub fn acid_strength(&self, material_id: &RegistryID<Material>) -> f32 {
+        self.get_or_default(material_id).acid_strength
+    }
+}
+
+pub fn init_material_properties() -> MaterialPropertiesRegistry {
+    let mut registry = MaterialPropertiesRegistry::new();
+
+    registry.register(
+        AIR.clone(),
+        MaterialProperties::default()
+            .with_density(0.0)
+            .with_thermal_conductivity(0.02),
+    );
+    registry.register(
+        COBBLE_STONE.clone(),
+        MaterialProperties::default()
+            .with_density(2.5)
+            .with_thermal_conductivity(0.3)
+            .with_toughness(3.0),
+    );
+    registry.register(
+        SMOOTH_STONE.clone(),
+        MaterialProperties::default()
+            .with_density(2.5)
+            .with_thermal_conductivity(0.3)
+            .with_toughness(4.0),
+    );
+    registry.register(
+        COBBLE_DIRT.clone(),
+        MaterialProperties::default()
+            .with_density(1.5)
+            .with_toughness(0.5),
+    );
+    registry.register(
+        SMOOTH_DIRT.clone(),
+        MaterialProperties::default()
+            .with_density(1.5)
+            .with_toughness(0.8),
+    );
+    registry.register(
+        ACID.clone(),
+        MaterialProperties::default()
+            .with_density(1.2)
+            .with_corrodable(false)
+            .with_acid_strength(0.1),
+    );
+    registry.register(
+        GLASS.clone(),
+        MaterialProperties::default()
+            .with_density(2.5)
+            .with_toughness(0.5)
+            .with_corrodable(false),
+    );
+    registry.register(
+        WATER.clone(),
+        MaterialProperties::default().with_thermal_conductivity(0.5),
+    );
+    registry.register(
+        STEAM.clone(),
+        MaterialProperties::default()
+            .with_density(0.05)
+            .with_thermal_conductivity(0.2),
+    );
+
+    // condensing a bit below the boiling point keeps pixels near it from flickering between the two
+    registry.register_transition(
+        WATER.clone(),
+        PhaseTransition::above(
+            100.0,
+            STEAM.instance(PhysicsType::Gas, Color::rgba(220, 220, 230, 120)),
+        ),
+    );
+    registry.register_transition(
+        STEAM.clone(),
+        PhaseTransition::below(
+            90.0,
+            WATER.instance(PhysicsType::Liquid, Color::rgba(32, 96, 220, 200)),
+        ),
+    );
+
+    registry
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::common::world::material::{
+        ACID, AIR, GLASS, SMOOTH_STONE, STEAM, TEST, WATER,
+    };
+
+    use super::{init_material_properties, MaterialProperties};
+
+    #[test]
+    fn fallback_to_default() {
+        let registry = init_material_properties();
+
+        assert!(registry.get(&TEST).is_none());
+        assert_eq!(registry.get_or_default(&TEST), &MaterialProperties::DEFAULT);
+        assert_eq!(
+            registry.get_or_default(&"not_a_material".into()),
+            &MaterialProperties::DEFAULT
+        );
+
+        assert!(registry.density(&AIR) < MaterialProperties::DEFAULT.density);
+    }
+
+    #[test]
+    fn acid() {
+        let registry = init_material_properties();
+
+        assert!(registry.acid_strength(&ACID) > 0.0);
+        assert!(!registry.corrodable(&GLASS));
+        assert!(registry.corrodable(&SMOOTH_STONE));
+        // unregistered materials are corrodable but not acidic
+        assert!(registry.corrodable(&TEST));
+        assert!(registry.acid_strength(&TEST) <= 0.0);
+    }
+
+    #[test]
+    fn phase_transitions() {
+        let registry = init_material_properties();
+
+        assert!(registry.transition(&WATER, 50.0).is_none());
+        assert_eq!(
+            registry.transition(&WATER, 150.0).unwrap().into.material_id,
+            *STEAM
+        );
+        // no flickering right around the boiling point
+        assert!(registry.transition(&STEAM, 95.0).is_none());
+        assert_eq!(
+            registry.transition(&STEAM, 50.0).unwrap().into.material_id,
+            *WATER
+        );
+
+        assert!(registry.transitions(&TEST).is_empty());
+    }
+}
@@ -1,5 +1,9 @@
+use std::cmp::Ordering;
+
 use mint::Point2;
 
+use crate::game::common::Rect;
+
 use super::material::{MaterialInstance, PhysicsType};
 
 pub type Tri = ((f64, f64), (f64, f64), (f64, f64));
@@ -9,6 +13,9 @@ pub type Poly = Vec<VPoint>;
 pub type Loop = Vec<Poly>;
 pub type Mesh = Vec<Loop>;
 
+/// Default Douglas-Peucker tolerance (in pixels) used when simplifying meshes.
+pub const DEFAULT_SIMPLIFY_EPSILON: f64 = 1.0;
+
 pub fn pixels_to_valuemap(pixels: &[MaterialInstance]) -> Vec<f64> {
     pixels
         .iter()
@@ -26,8 +33,9 @@ pub fn generate_mesh_only_simplified(
     values: &[f64],
     width: u32,
     height: u32,
+    epsilon: f64,
 ) -> Result<Mesh, String> {
-    generate_mesh_with_simplified(values, width, height).map(|t| t.1)
+    generate_mesh_with_simplified(values, width, height, epsilon).map(|t| t.1)
 }
 
 #[allow(dead_code)]
@@ -36,14 +44,130 @@ pub fn generate_mesh_only_unsimplified(
     width: u32,
     height: u32,
 ) -> Result<Mesh, String> {
-    generate_mesh_with_simplified(values, width, height).map(|t| t.0)
+    generate_mesh_with_simplified(values, width, height, DEFAULT_SIMPLIFY_EPSILON).map(|t| t.0)
 }
 
+/// Returns the mesh and a copy simplified with a Douglas-Peucker tolerance of `epsilon` pixels.
+///
+/// Higher `epsilon` means fewer triangles but a less accurate shape.
 pub fn generate_mesh_with_simplified(
     values: &[f64],
     width: u32,
     height: u32,
+    epsilon: f64,
 ) -> Result<(Mesh, Mesh), String> {
+    check_dimensions(values, width, height)?;
+
+    let rings = contour_rings(values, width, height)?
+        .iter()
+        .filter_map(|ring| finish_ring(ring, (0.0, 0.0), width, height, epsilon))
+        .collect();
+
+    Ok(group_rings(rings))
+}
+
+/// How far (in pixels) the area remeshed by [`update_mesh_with_simplified`] extends past the changes.
+const UPDATE_MARGIN: i32 = 2;
+
+/// Like [`generate_mesh_with_simplified`], but only regenerates the rings near `dirty` (in pixels),
+///   keeping the rest of `old`.
+///
+/// `old` must have been generated from `values` as they were before the pixels in `dirty` changed.
+/// Returns `Ok(None)` if the rings near `dirty` cover most of the value map, in which case a full
+///   regeneration is cheaper.
+#[allow(clippy::cast_possible_wrap)]
+pub fn update_mesh_with_simplified(
+    old: (&Mesh, &Mesh),
+    values: &[f64],
+    width: u32,
+    height: u32,
+    dirty: Rect<i32>,
+    epsilon: f64,
+) -> Result<Option<(Mesh, Mesh)>, String> {
+    check_dimensions(values, width, height)?;
+
+    let bounds = Rect::new(0, 0, width as i32, height as i32);
+    // a pixel is a corner of the marching squares cells around it
+    let Some(changed) = clip(dirty.inflated(1), bounds) else {
+        return Ok(Some((old.0.clone(), old.1.clone())));
+    };
+
+    // rings touching the changes (see `touches`) need to be far enough inside the region to not be cut
+    let mut region = clip(changed.inflated(2 * UPDATE_MARGIN + 1), bounds).unwrap();
+    loop {
+        let (region_w, region_h) = (region.width() as u32, region.height() as u32);
+        if u64::from(region_w * region_h) * 4 >= u64::from(width * height) * 3 {
+            return Ok(None);
+        }
+
+        let region_values: Vec<f64> = region
+            .range_tb()
+            .flat_map(|y| {
+                let row = (y * bounds.x2) as usize;
+                values[row + region.x1 as usize..row + region.x2 as usize]
+                    .iter()
+                    .copied()
+            })
+            .collect();
+        let offset = (f64::from(region.x1), f64::from(region.y1));
+
+        let mut rings = vec![];
+        let mut grow: Option<Rect<i32>> = None;
+        for raw in contour_rings(&region_values, region_w, region_h)? {
+            // outside of the region counts as empty, so rings reaching its edges may be cut off
+            let (min_x, min_y, max_x, max_y) = raw_bounds(&raw);
+            let margin = f64::from(UPDATE_MARGIN);
+            let cut = (region.x1 > bounds.x1 && min_x < margin)
+                || (region.y1 > bounds.y1 && min_y < margin)
+                || (region.x2 < bounds.x2 && max_x > f64::from(region_w) - margin)
+                || (region.y2 < bounds.y2 && max_y > f64::from(region_h) - margin);
+            if cut {
+                let rect = Rect::new(
+                    region.x1 + min_x.floor() as i32,
+                    region.y1 + min_y.floor() as i32,
+                    region.x1 + max_x.ceil() as i32,
+                    region.y1 + max_y.ceil() as i32,
+                );
+                // checked before `finish_ring` since a piece too small to keep can still be part of a
+                //   ring near the changes
+                if clip(rect, changed.inflated(2 * UPDATE_MARGIN)).is_some() {
+                    grow = Some(grow.map_or(rect, |g| g.union(rect)));
+                }
+                continue;
+            }
+
+            if let Some(ring) = finish_ring(&raw, offset, width, height, epsilon) {
+                if touches(&ring.points, changed) {
+                    rings.push(ring);
+                }
+            }
+        }
+
+        if let Some(grow) = grow {
+            region = clip(region.union(grow.inflated(UPDATE_MARGIN)), bounds).unwrap();
+            continue;
+        }
+
+        // the rest of the rings didn't change
+        for (a_loop, simplified_loop) in old.0.iter().zip(old.1) {
+            // see `group_rings`
+            let exterior = a_loop.len() - 1;
+            for (i, (points, simplified)) in a_loop.iter().zip(simplified_loop).enumerate() {
+                if !touches(points, changed) {
+                    rings.push(Ring {
+                        exterior: i == exterior,
+                        points: points.clone(),
+                        simplified: simplified.clone(),
+                    });
+                }
+            }
+        }
+
+        return Ok(Some(group_rings(rings)));
+    }
+}
+
+fn check_dimensions(values: &[f64], width: u32, height: u32) -> Result<(), String> {
     if values.len() as u32 != width * height {
         return Err(format!(
             "generate_mesh failed: Dimension mismatch (w*h = {}*{} = {}, but values.len() = {})",
@@ -54,79 +178,238 @@ pub fn generate_mesh_with_simplified(
         ));
     }
 
-    let c = contour::ContourBuilder::new(width, height, true);
+    Ok(())
+}
 
-    let contours = c.contours(values, &[1.0]);
+/// A ring of a mesh, with its simplified copy.
+struct Ring {
+    /// Whether this is the outside of a shape rather than a hole in one.
+    exterior: bool,
+    points: Poly,
+    simplified: Poly,
+}
 
-    let feat = contours.map(|vf| {
-        let mp = &vf[0].geometry().0;
-        let v: (Mesh, Mesh) = mp
-            .iter()
-            .map(|pt| {
-                return pt
-                    .interiors()
-                    .iter()
-                    .chain(std::iter::once(pt.exterior()))
-                    .map(|ln| {
-                        let pts: Vec<Point2<_>> =
-                            ln.0.iter()
-                                .map(|pt| {
-                                    let mut x = pt.x;
-                                    let mut y = pt.y;
-
-                                    // this extra manipulation helps seal the seams on chunk edges during the later mesh simplification
-
-                                    if (y == 0.0 || (y - f64::from(height)).abs() < f64::EPSILON)
-                                        && (x - 0.5).abs() < f64::EPSILON
-                                    {
-                                        x = 0.0;
-                                    }
-
-                                    if (x == 0.0 || (x - f64::from(width)).abs() < f64::EPSILON)
-                                        && (y - 0.5).abs() < f64::EPSILON
-                                    {
-                                        y = 0.0;
-                                    }
-
-                                    if (y == 0.0 || (y - f64::from(height)).abs() < f64::EPSILON)
-                                        && (x - (f64::from(width) - 0.5)).abs() < f64::EPSILON
-                                    {
-                                        x = f64::from(width);
-                                    }
-
-                                    if (x == 0.0 || (x - f64::from(width)).abs() < f64::EPSILON)
-                                        && (y - (f64::from(height) - 0.5)).abs() < f64::EPSILON
-                                    {
-                                        y = f64::from(height);
-                                    }
-
-                                    x = x.round() - 0.5;
-                                    y = y.round() - 0.5;
-
-                                    Point2 { x, y }
-                                })
-                                .collect();
-
-                        let keep = ramer_douglas_peucker::rdp(&pts, 1.0);
-
-                        let p1: Poly = pts.iter().map(|p| vec![p.x, p.y]).collect();
-                        let p2: Poly = pts
-                            .iter()
-                            .enumerate()
-                            .filter(|(i, &_p)| keep.contains(i))
-                            .map(|(_, p)| vec![p.x, p.y])
-                            .collect();
-                        (p1, p2)
-                    })
-                    .filter(|(norm, simple)| norm.len() > 2 && simple.len() > 2)
-                    .unzip();
-            })
-            .filter(|p: &(Loop, Loop)| !p.0.is_empty() && !p.1.is_empty())
-            .unzip();
-        v
-    });
+/// Runs marching squares over `values`, returning the rings around the solid (`1.0`) areas.
+fn contour_rings(values: &[f64], width: u32, height: u32) -> Result<Vec<Vec<Point2<f64>>>, String> {
+    let rings = contour::contour_rings(values, 1.0, width, height).map_err(|e| e.to_string())?;
+
+    // same as `ContourBuilder`'s smoothing
+    let smooth = |x: f64, y: f64| {
+        let (xt, yt) = (x.trunc() as u32, y.trunc() as u32);
+        let mut pt = Point2 { x, y };
+        let ix = (yt * width + xt) as usize;
+        if let Some(&v1) = values.get(ix) {
+            if x > 0.0 && x < f64::from(width) && (f64::from(xt) - x).abs() < f64::EPSILON {
+                let v0 = values[ix - 1];
+                pt.x = x + (1.0 - v0) / (v1 - v0) - 0.5;
+            }
+            if y > 0.0 && y < f64::from(height) && (f64::from(yt) - y).abs() < f64::EPSILON {
+                let v0 = values[ix - width as usize];
+                pt.y = y + (1.0 - v0) / (v1 - v0) - 0.5;
+            }
+        }
+        pt
+    };
 
-    feat.map_err(|e| e.to_string())
+    Ok(rings
+        .iter()
+        .map(|ring| ring.iter().map(|pt| smooth(pt.x, pt.y)).collect())
+        .collect())
+}
+
+/// Moves `ring` by `offset` onto the pixel grid of a `width`x`height` value map and simplifies it.
+///
+/// Returns [`None`] if too little is left of it.
+fn finish_ring(
+    ring: &[Point2<f64>],
+    offset: (f64, f64),
+    width: u32,
+    height: u32,
+    epsilon: f64,
+) -> Option<Ring> {
+    let pts: Vec<Point2<_>> = ring
+        .iter()
+        .map(|pt| {
+            let mut x = pt.x + offset.0;
+            let mut y = pt.y + offset.1;
+
+            // this extra manipulation helps seal the seams on chunk edges during the later mesh simplification
+
+            if (y == 0.0 || (y - f64::from(height)).abs() < f64::EPSILON)
+                && (x - 0.5).abs() < f64::EPSILON
+            {
+                x = 0.0;
+            }
+
+            if (x == 0.0 || (x - f64::from(width)).abs() < f64::EPSILON)
+                && (y - 0.5).abs() < f64::EPSILON
+            {
+                y = 0.0;
+            }
+
+            if (y == 0.0 || (y - f64::from(height)).abs() < f64::EPSILON)
+                && (x - (f64::from(width) - 0.5)).abs() < f64::EPSILON
+            {
+                x = f64::from(width);
+            }
+
+            if (x == 0.0 || (x - f64::from(width)).abs() < f64::EPSILON)
+                && (y - (f64::from(height) - 0.5)).abs() < f64::EPSILON
+            {
+                y = f64::from(height);
+            }
+
+            x = x.round() - 0.5;
+            y = y.round() - 0.5;
+
+            Point2 { x, y }
+        })
+        .collect();
+
+    let keep = ramer_douglas_peucker::rdp(&pts, epsilon);
+
+    let points: Poly = pts.iter().map(|p| vec![p.x, p.y]).collect();
+    let simplified: Poly = pts
+        .iter()
+        .enumerate()
+        .filter(|(i, &_p)| keep.contains(i))
+        .map(|(_, p)| vec![p.x, p.y])
+        .collect();
+
+    (points.len() > 2 && simplified.len() > 2).then(|| Ring {
+        // marching squares winds the outsides of shapes one way and holes the other
+        exterior: ring_area(ring) > 0.0,
+        points,
+        simplified,
+    })
+}
+
+/// Groups `rings` into a mesh, each loop being the holes of a shape followed by its outside.
+///
+/// The grouping and order only depend on the rings themselves, so a mesh patched by
+///   [`update_mesh_with_simplified`] is the same as a fully regenerated one.
+fn group_rings(rings: Vec<Ring>) -> (Mesh, Mesh) {
+    let by_points = |a: &Ring, b: &Ring| a.points.partial_cmp(&b.points).unwrap_or(Ordering::Equal);
+
+    let (mut exteriors, holes): (Vec<Ring>, Vec<Ring>) =
+        rings.into_iter().partition(|r| r.exterior);
+    exteriors.sort_by(by_points);
+
+    let areas: Vec<f64> = exteriors
+        .iter()
+        .map(|r| {
+            let pts: Vec<Point2<f64>> = r
+                .points
+                .iter()
+                .map(|p| Point2 { x: p[0], y: p[1] })
+                .collect();
+            ring_area(&pts).abs()
+        })
+        .collect();
+
+    let mut shape_holes: Vec<Vec<Ring>> = exteriors.iter().map(|_| vec![]).collect();
+    for hole in holes {
+        // a hole belongs to the innermost shape around it, holes without one are dropped
+        let parent = (0..exteriors.len())
+            .filter(|&i| ring_contains(&exteriors[i].points, &hole.points))
+            .min_by(|&a, &b| areas[a].partial_cmp(&areas[b]).unwrap_or(Ordering::Equal));
+        if let Some(i) = parent {
+            shape_holes[i].push(hole);
+        }
+    }
+
+    exteriors
+        .into_iter()
+        .zip(shape_holes)
+        .map(|(exterior, mut holes)| {
+            holes.sort_by(by_points);
+            let (mut a_loop, mut simplified_loop): (Loop, Loop) =
+                holes.into_iter().map(|r| (r.points, r.simplified)).unzip();
+            a_loop.push(exterior.points);
+            simplified_loop.push(exterior.simplified);
+            (a_loop, simplified_loop)
+        })
+        .unzip()
+}
+
+/// Twice the signed area of `ring`, positive for the outsides of shapes.
+fn ring_area(ring: &[Point2<f64>]) -> f64 {
+    let n = ring.len() - 1;
+    let mut area = ring[n - 1].y * ring[0].x - ring[n - 1].x * ring[0].y;
+    for i in 1..=n {
+        area += ring[i - 1].y * ring[i].x - ring[i - 1].x * ring[i].y;
+    }
+    area
+}
+
+/// Whether `ring` is inside of `outer`, going by its first point that isn't on `outer`.
+#[allow(clippy::ptr_arg)]
+fn ring_contains(outer: &Poly, ring: &Poly) -> bool {
+    for pt in ring {
+        let (x, y) = (pt[0], pt[1]);
+
+        let mut inside = false;
+        let mut on_edge = false;
+        for (a, b) in outer.iter().zip(outer.iter().cycle().skip(outer.len() - 1)) {
+            let cross = (b[0] - a[0]) * (y - a[1]) - (x - a[0]) * (b[1] - a[1]);
+            if cross.abs() < f64::EPSILON
+                && x >= a[0].min(b[0])
+                && x <= a[0].max(b[0])
+                && y >= a[1].min(b[1])
+                && y <= a[1].max(b[1])
+            {
+                on_edge = true;
+                break;
+            }
+            if (a[1] > y) != (b[1] > y) && x < (b[0] - a[0]) * (y - a[1]) / (b[1] - a[1]) + a[0] {
+                inside = !inside;
+            }
+        }
+
+        if !on_edge {
+            return inside;
+        }
+    }
+
+    true
+}
+
+/// `(min_x, min_y, max_x, max_y)` of the points in `ring`.
+fn raw_bounds(ring: &[Point2<f64>]) -> (f64, f64, f64, f64) {
+    ring.iter().fold(
+        (f64::MAX, f64::MAX, f64::MIN, f64::MIN),
+        |(min_x, min_y, max_x, max_y), pt| {
+            (
+                min_x.min(pt.x),
+                min_y.min(pt.y),
+                max_x.max(pt.x),
+                max_y.max(pt.y),
+            )
+        },
+    )
+}
+
+/// Whether any point of `ring` is near the pixels in `rect`.
+#[allow(clippy::ptr_arg)]
+fn touches(ring: &Poly, rect: Rect<i32>) -> bool {
+    let rect = rect.inflated(UPDATE_MARGIN);
+    ring.iter().any(|pt| {
+        pt[0] >= f64::from(rect.x1)
+            && pt[0] <= f64::from(rect.x2)
+            && pt[1] >= f64::from(rect.y1)
+            && pt[1] <= f64::from(rect.y2)
+    })
+}
+
+/// The part of `rect` inside of `bounds`, or [`None`] if they don't overlap.
+fn clip(rect: Rect<i32>, bounds: Rect<i32>) -> Option<Rect<i32>> {
+    let clipped = Rect::new(
+        rect.x1.max(bounds.x1),
+        rect.y1.max(bounds.y1),
+        rect.x2.min(bounds.x2),
+        rect.y2.min(bounds.y2),
+    );
+    (clipped.x1 < clipped.x2 && clipped.y1 < clipped.y2).then_some(clipped)
 }
 
 /// return type:<br>
@@ -160,3 +443,103 @@ pub fn triangulate(mesh: &Mesh) -> Vec<Vec<Tri>> {
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use crate::game::common::Rect;
+
+    use super::{generate_mesh_with_simplified, update_mesh_with_simplified};
+
+    const SIZE: i32 = 100;
+    const SIZE_U32: u32 = SIZE as u32;
+
+    /// Solid circles scattered over an empty map.
+    fn blobs(rng: &mut StdRng) -> Vec<f64> {
+        let mut values = vec![0.0; (SIZE * SIZE) as usize];
+        for _ in 0..30 {
+            let (cx, cy) = (rng.gen_range(0..SIZE), rng.gen_range(0..SIZE));
+            let r: i32 = rng.gen_range(2..20);
+            for y in 0..SIZE {
+                for x in 0..SIZE {
+                    if (x - cx).pow(2) + (y - cy).pow(2) < r * r {
+                        values[(x + y * SIZE) as usize] = 1.0;
+                    }
+                }
+            }
+        }
+        values
+    }
+
+    #[test]
+    fn update_matches_full() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut values = blobs(&mut rng);
+        let mut mesh = generate_mesh_with_simplified(&values, SIZE_U32, SIZE_U32, 1.0).unwrap();
+
+        let mut patched = 0;
+        for _ in 0..100 {
+            // dig or fill a small spot, sometimes hanging off the edge
+            let dirty = Rect::new_wh(
+                rng.gen_range(-3..SIZE),
+                rng.gen_range(-3..SIZE),
+                rng.gen_range(1..10),
+                rng.gen_range(1..10),
+            );
+            let fill = if rng.gen_bool(0.5) { 1.0 } else { 0.0 };
+            for y in dirty.range_tb().filter(|y| (0..SIZE).contains(y)) {
+                for x in dirty.range_lr().filter(|x| (0..SIZE).contains(x)) {
+                    if rng.gen_bool(0.7) {
+                        values[(x + y * SIZE) as usize] = fill;
+                    }
+                }
+            }
+
+            let full = generate_mesh_with_simplified(&values, SIZE_U32, SIZE_U32, 1.0).unwrap();
+            let updated = update_mesh_with_simplified(
+                (&mesh.0, &mesh.1),
+                &values,
+                SIZE_U32,
+                SIZE_U32,
+                dirty,
+                1.0,
+            )
+            .unwrap();
+            if let Some(updated) = updated {
+                assert_eq!(updated, full);
+                patched += 1;
+            }
+            mesh = full;
+        }
+
+        // most small edits shouldn't need a full regeneration
+        assert!(patched > 50, "only {patched} edits were patched");
+    }
+
+    #[test]
+    fn update_falls_back() {
+        let values = blobs(&mut StdRng::seed_from_u64(3));
+        let mesh = generate_mesh_with_simplified(&values, SIZE_U32, SIZE_U32, 1.0).unwrap();
+
+        let most = Rect::new(0, 0, SIZE, SIZE - 10);
+        assert_eq!(
+            update_mesh_with_simplified((&mesh.0, &mesh.1), &values, SIZE_U32, SIZE_U32, most, 1.0),
+            Ok(None)
+        );
+
+        // nothing changed on the map
+        let outside = Rect::new_wh(-20, -20, 5, 5);
+        assert_eq!(
+            update_mesh_with_simplified(
+                (&mesh.0, &mesh.1),
+                &values,
+                SIZE_U32,
+                SIZE_U32,
+                outside,
+                1.0
+            ),
+            Ok(Some(mesh))
+        );
+    }
+}
@@ -13,8 +13,11 @@ pub mod chunk_access;
 pub mod chunk_data;
 pub mod chunk_handler;
 pub mod chunk_index;
+pub mod chunk_io;
+pub mod chunk_pool;
 pub mod gen;
 pub mod physics;
+pub mod region;
 pub mod tile_entity;
 
 pub use chunk::*;
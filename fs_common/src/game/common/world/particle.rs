@@ -1,12 +1,14 @@
-use std::{collections::HashMap, hash::BuildHasherDefault, sync::Arc};
+use std::{collections::HashMap, sync::Arc};
+
+use chunksystem::ChunkKey;
 
 use super::{
     chunk_access::FSChunkAccess, entity::Hitbox, material::MaterialInstance, Position, TickTime,
     Velocity,
 };
 use crate::game::common::world::{
-    chunk_index, chunk_update_order, material::PhysicsType, pixel_to_chunk_pos,
-    pixel_to_chunk_pos_with_chunk_size, ChunkState, PassThroughHasherU32,
+    chunk_update_order, material::PhysicsType, pixel_to_chunk_pos,
+    pixel_to_chunk_pos_with_chunk_size, ChunkState,
 };
 
 use itertools::Itertools;
@@ -17,13 +19,18 @@ use specs::{Entities, Join, Read, ReadStorage, System, Write};
 
 const PARTICLE_CHUNK_SIZE: u16 = 64;
 
+/// Default value for [`ParticleSystem::max_particles`].
+pub const DEFAULT_MAX_PARTICLES: usize = 200_000;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Particle {
     pub material: MaterialInstance,
     pub pos: Position,
     pub vel: Velocity,
     pub in_object_state: InObjectState,
-    pub chunk_cache: (u32, u8), // (chunk index, chunk update order) TODO: make chunk update order a type?
+    pub chunk_cache: (ChunkKey, u8), // (particle chunk, chunk update order) TODO: make chunk update order a type?
+    /// Number of ticks left before the particle disappears, or `None` to live until it lands.
+    pub lifetime: Option<u32>,
 }
 
 impl Particle {
@@ -32,15 +39,19 @@ impl Particle {
             pixel_to_chunk_pos_with_chunk_size(pos.x as i64, pos.y as i64, PARTICLE_CHUNK_SIZE);
         Self {
             material,
-            chunk_cache: (
-                chunk_index(chunk_x, chunk_y),
-                chunk_update_order(chunk_x, chunk_y),
-            ),
+            chunk_cache: ((chunk_x, chunk_y), chunk_update_order(chunk_x, chunk_y)),
             pos,
             vel,
             in_object_state: InObjectState::FirstFrame,
+            lifetime: None,
         }
     }
+
+    #[must_use]
+    pub fn with_lifetime(mut self, lifetime: u32) -> Self {
+        self.lifetime = Some(lifetime);
+        self
+    }
 }
 
 // #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -76,10 +87,108 @@ pub enum InObjectState {
 //     type Storage = NullStorage<Self>;
 // }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+/// What [`ParticleSystem::spawn`] and [`ParticleSystem::spawn_many`] do once [`ParticleSystem::max_particles`] is reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParticleOverflowPolicy {
+    /// Don't spawn the new particles.
+    #[default]
+    RejectNew,
+    /// Remove existing particles to make room, active ones first.
+    ///
+    /// Active particles are regrouped every tick, so this is only roughly the oldest.
+    DropOldest,
+}
+
+impl ParticleOverflowPolicy {
+    pub fn values() -> &'static [Self] {
+        &[Self::RejectNew, Self::DropOldest]
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ParticleSystem {
     pub active: Vec<Particle>,
     pub sleeping: Vec<Particle>,
+    /// Cap on the number of particles (active + sleeping) for [`ParticleSystem::spawn`] and [`ParticleSystem::spawn_many`].
+    ///
+    /// Particles displaced from the world by the simulation ignore this, since dropping them would delete material.
+    #[serde(skip, default = "default_max_particles")]
+    pub max_particles: usize,
+    #[serde(skip)]
+    pub overflow_policy: ParticleOverflowPolicy,
+}
+
+fn default_max_particles() -> usize {
+    DEFAULT_MAX_PARTICLES
+}
+
+impl Default for ParticleSystem {
+    fn default() -> Self {
+        Self {
+            active: Vec::new(),
+            sleeping: Vec::new(),
+            max_particles: DEFAULT_MAX_PARTICLES,
+            overflow_policy: ParticleOverflowPolicy::default(),
+        }
+    }
+}
+
+impl ParticleSystem {
+    /// Total number of particles, active and sleeping.
+    pub fn len(&self) -> usize {
+        self.active.len() + self.sleeping.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.active.is_empty() && self.sleeping.is_empty()
+    }
+
+    /// Spawns a particle that disappears after `lifetime` ticks (if it hasn't landed already).
+    ///
+    /// Returns `false` if the particle wasn't spawned because [`ParticleSystem::max_particles`] was reached
+    ///   and the [`ParticleSystem::overflow_policy`] is [`ParticleOverflowPolicy::RejectNew`].
+    pub fn spawn(
+        &mut self,
+        material: MaterialInstance,
+        pos: Position,
+        vel: Velocity,
+        lifetime: u32,
+    ) -> bool {
+        self.spawn_many([Particle::new(material, pos, vel).with_lifetime(lifetime)]) == 1
+    }
+
+    /// Spawns `particles`, following the [`ParticleSystem::overflow_policy`] for any past [`ParticleSystem::max_particles`].
+    ///
+    /// With [`ParticleOverflowPolicy::RejectNew`] the ones that don't fit are dropped.
+    /// With [`ParticleOverflowPolicy::DropOldest`] existing particles are removed to make room, and if
+    ///   there are more new particles than the cap only the last ones are kept.
+    /// Returns the number of particles spawned.
+    pub fn spawn_many(&mut self, particles: impl IntoIterator<Item = Particle>) -> usize {
+        match self.overflow_policy {
+            ParticleOverflowPolicy::RejectNew => {
+                let room = self.max_particles.saturating_sub(self.len());
+                let before = self.active.len();
+                self.active.extend(particles.into_iter().take(room));
+                self.active.len() - before
+            },
+            ParticleOverflowPolicy::DropOldest => {
+                let mut particles: Vec<_> = particles.into_iter().collect();
+                if particles.len() > self.max_particles {
+                    particles.drain(..particles.len() - self.max_particles);
+                }
+
+                let overflow = (self.len() + particles.len()).saturating_sub(self.max_particles);
+                let from_active = overflow.min(self.active.len());
+                self.active.drain(..from_active);
+                let from_sleeping = (overflow - from_active).min(self.sleeping.len());
+                self.sleeping.drain(..from_sleeping);
+
+                let spawned = particles.len();
+                self.active.append(&mut particles);
+                spawned
+            },
+        }
+    }
 }
 
 pub struct UpdateParticles<'a, H: FSChunkAccess + Send + Sync> {
@@ -168,10 +277,10 @@ impl<H: FSChunkAccess + Send + Sync> UpdateParticles<'_, H> {
         let parts: Vec<_> = {
             profiling::scope!("sort+group");
             let mut maps = [
-                HashMap::<u32, Vec<Particle>, BuildHasherDefault<PassThroughHasherU32>>::default(),
-                HashMap::<u32, Vec<Particle>, BuildHasherDefault<PassThroughHasherU32>>::default(),
-                HashMap::<u32, Vec<Particle>, BuildHasherDefault<PassThroughHasherU32>>::default(),
-                HashMap::<u32, Vec<Particle>, BuildHasherDefault<PassThroughHasherU32>>::default(),
+                HashMap::<ChunkKey, Vec<Particle>, ahash::RandomState>::default(),
+                HashMap::<ChunkKey, Vec<Particle>, ahash::RandomState>::default(),
+                HashMap::<ChunkKey, Vec<Particle>, ahash::RandomState>::default(),
+                HashMap::<ChunkKey, Vec<Particle>, ahash::RandomState>::default(),
             ];
             for p in system.active.drain(..) {
                 // safety: p.chunk_cache.1 is a chunk order number, assumed to be 0..=3
@@ -191,6 +300,13 @@ impl<H: FSChunkAccess + Send + Sync> UpdateParticles<'_, H> {
                 profiling::scope!("chunk");
 
                 chunk_px.retain_mut(|part| {
+                    if let Some(lifetime) = &mut part.lifetime {
+                        if *lifetime == 0 {
+                            return false;
+                        }
+                        *lifetime -= 1;
+                    }
+
                     // TODO: this reborrow is UB
                     let unsafe_async_chunk_handler =
                         unsafe { &mut **((async_chunk_handler.clone()).value) };
@@ -202,10 +318,7 @@ impl<H: FSChunkAccess + Send + Sync> UpdateParticles<'_, H> {
                         part.pos.y as i64,
                         PARTICLE_CHUNK_SIZE,
                     );
-                    part.chunk_cache = (
-                        chunk_index(chunk_x, chunk_y),
-                        chunk_update_order(chunk_x, chunk_y),
-                    );
+                    part.chunk_cache = ((chunk_x, chunk_y), chunk_update_order(chunk_x, chunk_y));
                     res
                 });
 
@@ -345,3 +458,58 @@ impl<H: FSChunkAccess + Send + Sync> UpdateParticles<'_, H> {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::game::common::world::{material::MaterialInstance, Position, Velocity};
+
+    use super::{Particle, ParticleOverflowPolicy, ParticleSystem};
+
+    fn particle(x: f64) -> Particle {
+        Particle::new(
+            MaterialInstance::air(),
+            Position { x, y: 0.0 },
+            Velocity { x: 0.0, y: 0.0 },
+        )
+    }
+
+    #[test]
+    fn reject_new() {
+        let mut system = ParticleSystem { max_particles: 3, ..ParticleSystem::default() };
+
+        assert_eq!(system.spawn_many((0..5).map(|i| particle(f64::from(i)))), 3);
+        assert_eq!(system.len(), 3);
+        assert!(!system.spawn(
+            MaterialInstance::air(),
+            Position { x: 0.0, y: 0.0 },
+            Velocity { x: 0.0, y: 0.0 },
+            10
+        ));
+        assert_eq!(system.len(), 3);
+    }
+
+    #[test]
+    fn drop_oldest() {
+        let mut system = ParticleSystem {
+            max_particles: 3,
+            overflow_policy: ParticleOverflowPolicy::DropOldest,
+            ..ParticleSystem::default()
+        };
+
+        assert_eq!(system.spawn_many((0..2).map(|i| particle(f64::from(i)))), 2);
+        system.sleeping.push(particle(10.0));
+        assert_eq!(system.spawn_many((2..4).map(|i| particle(f64::from(i)))), 2);
+
+        // the two oldest active particles made room, the sleeping one is untouched
+        assert_eq!(system.len(), 3);
+        let xs: Vec<_> = system.active.iter().map(|p| p.pos.x as i32).collect();
+        assert_eq!(xs, [2, 3]);
+        assert_eq!(system.sleeping.len(), 1);
+
+        // more than fits at once keeps only the newest
+        assert_eq!(system.spawn_many((4..9).map(|i| particle(f64::from(i)))), 3);
+        let xs: Vec<_> = system.active.iter().map(|p| p.pos.x as i32).collect();
+        assert_eq!(xs, [6, 7, 8]);
+        assert!(system.sleeping.is_empty());
+    }
+}
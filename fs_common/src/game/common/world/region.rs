@@ -0,0 +1,231 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+
+/// Width and height of a region, in chunks.
+pub const REGION_SIZE: i32 = 32;
+
+const MAGIC: &[u8; 4] = b"FSRG";
+const VERSION: u32 = 1;
+const ENTRY_COUNT: usize = (REGION_SIZE * REGION_SIZE) as usize;
+/// `offset: u64` + `length: u32`
+const ENTRY_SIZE: usize = 12;
+const HEADER_SIZE: usize = MAGIC.len() + 4 + ENTRY_COUNT * ENTRY_SIZE;
+
+/// Location of a chunk's payload in a region file. `length == 0` means the chunk isn't stored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct RegionEntry {
+    offset: u64,
+    length: u32,
+}
+
+/// A file storing the chunks of a [`REGION_SIZE`]x[`REGION_SIZE`] block.
+///
+/// The file starts with a header indexing where each chunk's zlib compressed payload is,
+///   so reading a chunk is a single seek + read.
+/// Payloads that grow past their old slot are appended to the end of the file; the old slot is left unused.
+pub struct RegionFile {
+    file: File,
+    index: Vec<RegionEntry>,
+}
+
+impl RegionFile {
+    /// Opens the region file at `path`, creating it if it doesn't exist.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let exists = path.exists();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(path)
+            .map_err(|e| format!("Failed to open region file @ {path:?}: {e}"))?;
+
+        let index = if exists {
+            Self::read_header(&mut file).map_err(|e| format!("Bad region file @ {path:?}: {e}"))?
+        } else {
+            let index = vec![RegionEntry::default(); ENTRY_COUNT];
+            Self::write_header(&mut file, &index).map_err(|e| e.to_string())?;
+            index
+        };
+
+        Ok(Self { file, index })
+    }
+
+    /// Returns the region containing the chunk, and the chunk's position within that region.
+    pub fn region_pos(chunk_x: i32, chunk_y: i32) -> ((i32, i32), (u8, u8)) {
+        (
+            (
+                chunk_x.div_euclid(REGION_SIZE),
+                chunk_y.div_euclid(REGION_SIZE),
+            ),
+            (
+                chunk_x.rem_euclid(REGION_SIZE) as u8,
+                chunk_y.rem_euclid(REGION_SIZE) as u8,
+            ),
+        )
+    }
+
+    /// Path of the region file for region (`region_x`, `region_y`) inside `root`.
+    pub fn path(root: impl AsRef<Path>, region_x: i32, region_y: i32) -> PathBuf {
+        root.as_ref()
+            .join(format!("r_{region_x}_{region_y}.region"))
+    }
+
+    pub fn has_chunk(&self, local_x: u8, local_y: u8) -> bool {
+        Self::entry_index(local_x, local_y).map_or(false, |i| self.index[i].length > 0)
+    }
+
+    /// Reads and decompresses a chunk's payload. Returns `Ok(None)` if the chunk isn't stored.
+    pub fn read_chunk(&mut self, local_x: u8, local_y: u8) -> Result<Option<Vec<u8>>, String> {
+        let entry = self.index[Self::entry_index(local_x, local_y)?];
+        if entry.length == 0 {
+            return Ok(None);
+        }
+
+        let mut compressed = vec![0; entry.length as usize];
+        self.file
+            .seek(SeekFrom::Start(entry.offset))
+            .and_then(|_| self.file.read_exact(&mut compressed))
+            .map_err(|e| e.to_string())?;
+
+        let mut data = Vec::new();
+        ZlibDecoder::new(compressed.as_slice())
+            .read_to_end(&mut data)
+            .map_err(|e| e.to_string())?;
+
+        Ok(Some(data))
+    }
+
+    /// Compresses and stores a chunk's payload, replacing any previous one.
+    pub fn write_chunk(&mut self, local_x: u8, local_y: u8, data: &[u8]) -> Result<(), String> {
+        let i = Self::entry_index(local_x, local_y)?;
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::fast());
+        encoder.write_all(data).map_err(|e| e.to_string())?;
+        let compressed = encoder.finish().map_err(|e| e.to_string())?;
+        let length = u32::try_from(compressed.len()).map_err(|e| e.to_string())?;
+
+        let old = self.index[i];
+        let offset = if old.length > 0 && length <= old.length {
+            old.offset
+        } else {
+            self.file
+                .seek(SeekFrom::End(0))
+                .map_err(|e| e.to_string())?
+        };
+
+        self.file
+            .seek(SeekFrom::Start(offset))
+            .and_then(|_| self.file.write_all(&compressed))
+            .map_err(|e| e.to_string())?;
+
+        let entry = RegionEntry { offset, length };
+        self.file
+            .seek(SeekFrom::Start((MAGIC.len() + 4 + i * ENTRY_SIZE) as u64))
+            .and_then(|_| self.file.write_all(&Self::encode_entry(entry)))
+            .and_then(|_| self.file.flush())
+            .map_err(|e| e.to_string())?;
+        self.index[i] = entry;
+
+        Ok(())
+    }
+
+    fn entry_index(local_x: u8, local_y: u8) -> Result<usize, String> {
+        if i32::from(local_x) < REGION_SIZE && i32::from(local_y) < REGION_SIZE {
+            Ok(local_x as usize + local_y as usize * REGION_SIZE as usize)
+        } else {
+            Err(format!(
+                "Chunk position {local_x},{local_y} is outside of a {REGION_SIZE}x{REGION_SIZE} region"
+            ))
+        }
+    }
+
+    fn encode_entry(entry: RegionEntry) -> [u8; ENTRY_SIZE] {
+        let mut buf = [0; ENTRY_SIZE];
+        buf[..8].copy_from_slice(&entry.offset.to_le_bytes());
+        buf[8..].copy_from_slice(&entry.length.to_le_bytes());
+        buf
+    }
+
+    fn read_header(file: &mut File) -> Result<Vec<RegionEntry>, String> {
+        let mut header = vec![0; HEADER_SIZE];
+        file.seek(SeekFrom::Start(0))
+            .and_then(|_| file.read_exact(&mut header))
+            .map_err(|e| e.to_string())?;
+
+        if &header[..MAGIC.len()] != MAGIC {
+            return Err("missing magic number".into());
+        }
+
+        let version = u32::from_le_bytes(header[MAGIC.len()..MAGIC.len() + 4].try_into().unwrap());
+        if version != VERSION {
+            return Err(format!(
+                "unsupported version {version} (expected {VERSION})"
+            ));
+        }
+
+        Ok(header[MAGIC.len() + 4..]
+            .chunks_exact(ENTRY_SIZE)
+            .map(|e| RegionEntry {
+                offset: u64::from_le_bytes(e[..8].try_into().unwrap()),
+                length: u32::from_le_bytes(e[8..].try_into().unwrap()),
+            })
+            .collect())
+    }
+
+    fn write_header(file: &mut File, index: &[RegionEntry]) -> std::io::Result<()> {
+        let mut header = Vec::with_capacity(HEADER_SIZE);
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&VERSION.to_le_bytes());
+        for entry in index {
+            header.extend_from_slice(&Self::encode_entry(*entry));
+        }
+
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(&header)?;
+        file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn region_round_trip() {
+        let path =
+            std::env::temp_dir().join(format!("fs_region_test_{}.region", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let small = vec![1_u8; 100];
+        let big: Vec<u8> = (0..10_000).map(|i| (i * 7 % 251) as u8).collect();
+
+        {
+            let mut region = RegionFile::open(&path).unwrap();
+            assert!(!region.has_chunk(3, 4));
+            assert_eq!(region.read_chunk(3, 4).unwrap(), None);
+
+            region.write_chunk(3, 4, &small).unwrap();
+            region.write_chunk(31, 31, &small).unwrap();
+            // grows past its old slot
+            region.write_chunk(3, 4, &big).unwrap();
+
+            assert!(region.write_chunk(32, 0, &small).is_err());
+        }
+
+        let mut region = RegionFile::open(&path).unwrap();
+        assert!(region.has_chunk(3, 4));
+        assert_eq!(region.read_chunk(3, 4).unwrap(), Some(big));
+        assert_eq!(region.read_chunk(31, 31).unwrap(), Some(small));
+        assert_eq!(region.read_chunk(0, 0).unwrap(), None);
+
+        assert_eq!(RegionFile::region_pos(-1, 33), ((-1, 1), (31, 1)));
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -150,8 +150,12 @@ impl FSRigidBody {
         position: (f32, f32),
     ) -> Result<Vec<FSRigidBody>, String> {
         let values = mesh::pixels_to_valuemap(pixels);
-        let mesh =
-            mesh::generate_mesh_only_simplified(&values, u32::from(width), u32::from(height))?;
+        let mesh = mesh::generate_mesh_only_simplified(
+            &values,
+            u32::from(width),
+            u32::from(height),
+            mesh::DEFAULT_SIMPLIFY_EPSILON,
+        )?;
 
         let loops = mesh::triangulate(&mesh);
 
@@ -233,6 +237,7 @@ impl FSRigidBody {
             &values,
             u32::from(self.width),
             u32::from(self.height),
+            mesh::DEFAULT_SIMPLIFY_EPSILON,
         )?;
 
         let loops = mesh::triangulate(&mesh);
@@ -21,6 +21,13 @@ use super::{
     Chunk, Position, Velocity,
 };
 
+/// Chance of an acid pixel being used up each time it dissolves something.
+const ACID_DEPLETION_CHANCE: f32 = 0.25;
+
+/// Temperature changes (in °C) smaller than this don't keep a pixel's chunk dirty.
+#[cfg(feature = "heat")]
+const HEAT_EPSILON: f32 = 0.01;
+
 pub struct Simulator {}
 
 trait SimulationHelper {
@@ -87,6 +94,17 @@ impl SimulationHelperChunk<'_, '_> {
         self.max_y[ch] = self.max_y[ch].max(ch_y);
     }
 
+    /// Marks the pixel as changed without writing to it, so it is simulated again next tick.
+    #[inline]
+    fn touch_local(&mut self, x: i32, y: i32) {
+        let (ch, _, ch_x, ch_y) = Self::local_to_indices(x, y);
+
+        self.min_x[ch] = self.min_x[ch].min(ch_x);
+        self.min_y[ch] = self.min_y[ch].min(ch_y);
+        self.max_x[ch] = self.max_x[ch].max(ch_x);
+        self.max_y[ch] = self.max_y[ch].max(ch_y);
+    }
+
     #[inline]
     unsafe fn set_pixel_from_index_unchecked(
         &mut self,
@@ -451,6 +469,7 @@ impl Simulator {
         chunk_data: &mut [SimulatorChunkContext; 9],
         particles: &mut Vec<Particle>,
         registries: Arc<Registries>,
+        simulate_heat: bool,
     ) {
         const CENTER_CHUNK: usize = 4;
 
@@ -484,7 +503,8 @@ impl Simulator {
                 y: i32,
                 helper: &mut SimulationHelperChunk,
                 rng: &Rng,
-                _registries: &Registries,
+                registries: &Registries,
+                simulate_heat: bool,
             ) {
                 // Safety: x and y are assumed to be within the chunk
 
@@ -504,6 +524,25 @@ impl Simulator {
                             helper.set_pixel_local_unchecked(x, y, mat);
                         }
                     }
+                } else if cur.physics == PhysicsType::Liquid {
+                    let strength = registries
+                        .material_properties
+                        .acid_strength(&cur.material_id);
+                    if strength > 0.0 {
+                        Simulator::simulate_acid(
+                            x,
+                            y,
+                            &cur.clone(),
+                            strength,
+                            helper,
+                            rng,
+                            registries,
+                        );
+                    }
+                }
+
+                if simulate_heat {
+                    Simulator::simulate_heat(x, y, helper, registries);
                 }
             }
 
@@ -512,14 +551,14 @@ impl Simulator {
                 for y in my_dirty_rect.range_tb().rev() {
                     for x in my_dirty_rect.range_lr() {
                         // Safety: dirty rects are always within the chunk
-                        process(x, y, &mut helper, &rng, &registries);
+                        process(x, y, &mut helper, &rng, &registries, simulate_heat);
                     }
                 }
             } else {
                 for y in my_dirty_rect.range_tb().rev() {
                     for x in my_dirty_rect.range_lr().rev() {
                         // Safety: dirty rects are always within the chunk
-                        process(x, y, &mut helper, &rng, &registries);
+                        process(x, y, &mut helper, &rng, &registries, simulate_heat);
                     }
                 }
             }
@@ -645,6 +684,96 @@ impl Simulator {
         rigidbodies.append(&mut new_rb);
     }
 
+    /// Dissolves neighbors of the acid pixel at `x`, `y` that are corrodable, each with a `strength` chance.
+    ///
+    /// The acid is used up after dissolving something with a [`ACID_DEPLETION_CHANCE`] chance.
+    /// As long as it is touching something it could still dissolve, the acid keeps its chunk dirty.
+    fn simulate_acid(
+        x: i32,
+        y: i32,
+        cur: &MaterialInstance,
+        strength: f32,
+        helper: &mut impl SimulationHelper,
+        rng: &fastrand::Rng,
+        registries: &Registries,
+    ) {
+        let mut touching_corrodable = false;
+        for (dx, dy) in [(0, 1), (-1, 0), (1, 0), (0, -1)] {
+            let other = helper.pixel_local(x + dx, y + dy);
+            if !matches!(other.physics, PhysicsType::Solid | PhysicsType::Sand)
+                || !registries
+                    .material_properties
+                    .corrodable(&other.material_id)
+            {
+                continue;
+            }
+
+            if rng.f32() < strength {
+                helper.set_all_local(x + dx, y + dy, MaterialInstance::air());
+                if rng.f32() < ACID_DEPLETION_CHANCE {
+                    helper.set_all_local(x, y, MaterialInstance::air());
+                    return;
+                }
+            }
+            touching_corrodable = true;
+        }
+
+        if touching_corrodable {
+            helper.set_pixel_local(x, y, cur.clone());
+        }
+    }
+
+    /// Moves the temperature of the pixel at `x`, `y` towards the average of its neighbors, by its
+    ///   material's thermal conductivity, then applies its phase transition for the new temperature.
+    ///
+    /// While its temperature is changing, neighbors it differs from are kept dirty too, so the heat
+    ///   can spread past the dirty rect.
+    #[cfg(feature = "heat")]
+    fn simulate_heat(x: i32, y: i32, helper: &mut SimulationHelperChunk, registries: &Registries) {
+        const NEIGHBORS: [(i32, i32); 4] = [(0, 1), (-1, 0), (1, 0), (0, -1)];
+
+        let cur = helper.pixel_local(x, y);
+        let neighbor_avg = NEIGHBORS
+            .iter()
+            .map(|(dx, dy)| helper.pixel_local(x + dx, y + dy).temperature)
+            .sum::<f32>()
+            / 4.0;
+        let conductivity = registries
+            .material_properties
+            .thermal_conductivity(&cur.material_id);
+        let temperature = cur.temperature + (neighbor_avg - cur.temperature) * conductivity;
+
+        if let Some(transition) = registries
+            .material_properties
+            .transition(&cur.material_id, temperature)
+        {
+            let mat = transition.into.clone().with_temperature(temperature);
+            helper.set_all_local(x, y, mat);
+        } else if (temperature - cur.temperature).abs() > HEAT_EPSILON {
+            let mat = MaterialInstance { temperature, ..cur.clone() };
+            helper.set_pixel_local(x, y, mat);
+        } else {
+            return;
+        }
+
+        for (dx, dy) in NEIGHBORS {
+            if (helper.pixel_local(x + dx, y + dy).temperature - temperature).abs() > HEAT_EPSILON {
+                helper.touch_local(x + dx, y + dy);
+            }
+        }
+    }
+
+    /// Pixels have no temperature without the `heat` feature.
+    #[cfg(not(feature = "heat"))]
+    #[inline]
+    fn simulate_heat(
+        _x: i32,
+        _y: i32,
+        _helper: &mut SimulationHelperChunk,
+        _registries: &Registries,
+    ) {
+    }
+
     #[allow(clippy::inline_always)]
     #[inline(always)] // speeds up simulate_chunk by ~35%
     fn simulate_pixel(
@@ -738,3 +867,194 @@ impl Simulator {
         new_mat
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::UnsafeCell, sync::Arc};
+
+    use crate::game::common::{
+        world::{
+            material::{
+                self,
+                color::Color,
+                properties::{init_material_properties, MaterialProperties},
+                MaterialInstance, PhysicsType,
+            },
+            CHUNK_AREA, CHUNK_SIZE,
+        },
+        Rect, Registries,
+    };
+
+    #[cfg(feature = "heat")]
+    use crate::game::common::world::material::AMBIENT_TEMPERATURE;
+
+    use super::{Simulator, SimulatorChunkContext};
+
+    fn cells<T>(init: impl Fn() -> T) -> Box<[UnsafeCell<T>; CHUNK_AREA]> {
+        (0..CHUNK_AREA)
+            .map(|_| UnsafeCell::new(init()))
+            .collect::<Vec<_>>()
+            .into_boxed_slice()
+            .try_into()
+            .unwrap()
+    }
+
+    struct TestChunk {
+        pixels: Box<[UnsafeCell<MaterialInstance>; CHUNK_AREA]>,
+        colors: Box<[UnsafeCell<Color>; CHUNK_AREA]>,
+        lights: Box<[UnsafeCell<[f32; 4]>; CHUNK_AREA]>,
+    }
+
+    impl TestChunk {
+        fn new() -> Self {
+            Self {
+                pixels: cells(MaterialInstance::air),
+                colors: cells(|| Color::TRANSPARENT),
+                lights: cells(|| [0.0; 4]),
+            }
+        }
+
+        fn pixel(&mut self, x: i32, y: i32) -> &mut MaterialInstance {
+            self.pixels[(x + y * i32::from(CHUNK_SIZE)) as usize].get_mut()
+        }
+    }
+
+    /// Simulates the center of `chunks` for `ticks` ticks, starting with `dirty_rect` dirty.
+    fn simulate(
+        chunks: &[TestChunk],
+        mut dirty_rect: Option<Rect<i32>>,
+        ticks: u32,
+        registries: &Arc<Registries>,
+        simulate_heat: bool,
+    ) {
+        for _ in 0..ticks {
+            let mut chunk_data: [SimulatorChunkContext; 9] = chunks
+                .iter()
+                .enumerate()
+                .map(|(i, c)| SimulatorChunkContext {
+                    pixels: &c.pixels,
+                    colors: &c.colors,
+                    lights: &c.lights,
+                    dirty: false,
+                    dirty_rect: if i == 4 { dirty_rect } else { None },
+                })
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap();
+
+            let mut particles = vec![];
+            Simulator::simulate_chunk(
+                0,
+                0,
+                &mut chunk_data,
+                &mut particles,
+                registries.clone(),
+                simulate_heat,
+            );
+            dirty_rect = chunk_data[4].dirty_rect;
+        }
+    }
+
+    /// Registries with a heat source that never cools down, and chunks with a 10x10 block of
+    ///   water surrounded by it in the center chunk.
+    fn heated_water() -> (Arc<Registries>, Vec<TestChunk>, MaterialInstance) {
+        let mut registries = Registries::empty();
+        registries.material_properties = init_material_properties();
+        registries.material_properties.register(
+            material::TEST.clone(),
+            MaterialProperties::default().with_thermal_conductivity(0.0),
+        );
+
+        let mut chunks: Vec<TestChunk> = (0..9).map(|_| TestChunk::new()).collect();
+
+        let water = material::WATER.instance(PhysicsType::Liquid, Color::BLUE);
+        let heater = material::TEST.instance(PhysicsType::Solid, Color::RED);
+        #[cfg(feature = "heat")]
+        let heater = heater.with_temperature(1000.0);
+        for y in 44..56 {
+            for x in 44..56 {
+                let inside = (45..55).contains(&x) && (45..55).contains(&y);
+                *chunks[4].pixel(x, y) = if inside {
+                    water.clone()
+                } else {
+                    heater.clone()
+                };
+            }
+        }
+
+        (Arc::new(registries), chunks, water)
+    }
+
+    /// Simulates a pixel of acid resting on `other` for `ticks` ticks, and returns what's left of both.
+    fn acid_on(other: &MaterialInstance, ticks: u32) -> (MaterialInstance, MaterialInstance) {
+        let mut registries = Registries::empty();
+        registries.material_properties = init_material_properties();
+        let registries = Arc::new(registries);
+
+        let mut chunks: Vec<TestChunk> = (0..9).map(|_| TestChunk::new()).collect();
+        *chunks[4].pixel(50, 50) = material::ACID.instance(PhysicsType::Liquid, Color::GREEN);
+        *chunks[4].pixel(50, 51) = other.clone();
+
+        // restarted every tick, so the acid is looked at even when it doesn't change anything
+        let dirty_rect = Some(Rect::new_wh(49, 49, 3, 3));
+        for _ in 0..ticks {
+            simulate(&chunks, dirty_rect, 1, &registries, false);
+        }
+
+        let acid = chunks[4].pixel(50, 50).clone();
+        (acid, chunks[4].pixel(50, 51).clone())
+    }
+
+    #[test]
+    fn acid_dissolves_stone() {
+        let stone = material::SMOOTH_STONE.instance(PhysicsType::Solid, Color::GRAY);
+        // at a 0.1 chance per tick, this fails about once in a billion runs
+        let (_, left) = acid_on(&stone, 200);
+        assert_eq!(left, MaterialInstance::air());
+    }
+
+    #[test]
+    fn acid_leaves_glass() {
+        let glass = material::GLASS.instance(PhysicsType::Solid, Color::WHITE);
+        let (acid, left) = acid_on(&glass, 200);
+        assert_eq!(left, glass);
+        assert_eq!(acid.material_id, *material::ACID);
+    }
+
+    #[test]
+    fn heat_off() {
+        let (registries, mut chunks, water) = heated_water();
+        let dirty_rect = Some(Rect::new_wh(44, 44, 12, 12));
+
+        simulate(&chunks, dirty_rect, 10, &registries, false);
+        // without the feature, the setting does nothing
+        #[cfg(not(feature = "heat"))]
+        simulate(&chunks, dirty_rect, 10, &registries, true);
+
+        for y in 45..55 {
+            for x in 45..55 {
+                assert_eq!(*chunks[4].pixel(x, y), water);
+            }
+        }
+    }
+
+    #[cfg(feature = "heat")]
+    #[test]
+    fn water_boils() {
+        let (registries, mut chunks, _) = heated_water();
+        let dirty_rect = Some(Rect::new_wh(44, 44, 12, 12));
+
+        simulate(&chunks, dirty_rect, 500, &registries, true);
+        for y in 45..55 {
+            for x in 45..55 {
+                let px = chunks[4].pixel(x, y);
+                assert_eq!(px.material_id, *material::STEAM, "{x}, {y}");
+                assert_eq!(px.physics, PhysicsType::Gas);
+                assert!(px.temperature > 100.0);
+            }
+        }
+        assert!((chunks[4].pixel(44, 44).temperature - 1000.0).abs() < f32::EPSILON);
+        // the surrounding air warmed up a bit too
+        assert!(chunks[4].pixel(43, 50).temperature > AMBIENT_TEMPERATURE);
+    }
+}
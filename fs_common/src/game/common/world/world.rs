@@ -3,7 +3,7 @@ use std::{
     hash::{Hash, Hasher},
     path::PathBuf,
     sync::Arc,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use crate::game::common::{
@@ -14,12 +14,14 @@ use crate::game::common::{
 use chunksystem::ChunkQuery;
 use rapier2d::{
     na::{Point2, Vector2},
-    prelude::{ColliderBuilder, InteractionGroups, RigidBodyBuilder, RigidBodyType},
+    prelude::{
+        ColliderBuilder, InteractionGroups, RigidBodyBuilder, RigidBodyHandle, RigidBodyType,
+    },
 };
 // use salva2d::{integrations::rapier::ColliderSampling, object::Boundary};
 use specs::{
     saveload::{SimpleMarker, SimpleMarkerAllocator},
-    Join, Read, ReadStorage, RunNow, WorldExt,
+    Builder, Join, Read, ReadStorage, RunNow, WorldExt,
 };
 
 use super::{
@@ -31,7 +33,8 @@ use super::{
         UpdatePhysicsEntities,
     },
     gen::{biome_test::BiomeTestGenerator, structure::StructureNode},
-    material::{self, color::Color, MaterialInstance, PhysicsType},
+    material::{self, buf::MaterialBuf, color::Color, MaterialInstance, PhysicsType},
+    mesh,
     particle::{Particle, ParticleSystem, UpdateParticles},
     physics::Physics,
     rigidbody::FSRigidBody,
@@ -42,6 +45,11 @@ use super::{
     Velocity, CHUNK_SIZE,
 };
 
+/// Speed (in pixels per tick) of explosion debris per unit of explosion strength.
+const EXPLOSION_DEBRIS_SPEED: f64 = 1.0;
+/// Impulse applied to rigidbodies per unit of explosion strength.
+const EXPLOSION_RIGIDBODY_IMPULSE: f32 = 0.05;
+
 #[derive(Debug)]
 pub enum WorldNetworkMode {
     Local,
@@ -56,6 +64,7 @@ pub struct World<C: Chunk> {
     pub rigidbodies: Vec<FSRigidBody>,
     pub physics: Physics,
     pub seed: i32,
+    pub last_autosave: Instant,
 }
 
 pub fn ecs() -> specs::World {
@@ -143,6 +152,7 @@ impl<C: Chunk + Send + Sync + 'static> World<C> {
                     .hash(&mut h);
                 h.finish() as i32
             }),
+            last_autosave: Instant::now(),
         };
 
         // sample rigidbodies
@@ -390,6 +400,192 @@ impl<C: Chunk + Send + Sync + 'static> World<C> {
 
         None
     }
+
+    /// Cuts the solid pixels in the given region out of the world and turns them into a rigidbody.
+    ///
+    /// Non-solid pixels in the region are left where they are.
+    /// Returns `None` (leaving the world unchanged) if the region isn't fully loaded or has nothing to detach.
+    pub fn detach_rigidbody(&mut self, x: i64, y: i64, w: u16, h: u16) -> Option<RigidBodyHandle> {
+        // `MaterialBuf::cut` stops partway through if it hits an unloaded pixel
+        let loaded = (0..h).all(|dy| {
+            (0..w).all(|dx| {
+                self.chunk_handler
+                    .is_pixel_loaded(x + i64::from(dx), y + i64::from(dy))
+            })
+        });
+        if !loaded {
+            return None;
+        }
+
+        let mut buf = MaterialBuf::cut(&mut self.chunk_handler, x, y, w, h).ok()?;
+
+        // put back everything that isn't part of the body
+        for dy in 0..h {
+            for dx in 0..w {
+                let mat = buf.get(dx, dy).ok()?;
+                if mat.physics != PhysicsType::Solid {
+                    let _ignore =
+                        self.chunk_handler
+                            .set_pixel(x + i64::from(dx), y + i64::from(dy), mat);
+                    buf.set(dx, dy, MaterialInstance::air());
+                }
+            }
+        }
+
+        let values = mesh::pixels_to_valuemap(&buf.materials);
+        let tris: Vec<_> = mesh::generate_mesh_with_simplified(
+            &values,
+            w.into(),
+            h.into(),
+            mesh::DEFAULT_SIMPLIFY_EPSILON,
+        )
+        .map(|(_, simplified)| mesh::triangulate(&simplified))
+        .unwrap_or_default()
+        .into_iter()
+        .flatten()
+        .collect();
+
+        let rb = if tris.is_empty() {
+            None
+        } else {
+            FSRigidBody::from_tris(
+                tris,
+                buf.materials.clone(),
+                w,
+                h,
+                &mut self.physics,
+                (x as f32 / PHYSICS_SCALE, y as f32 / PHYSICS_SCALE),
+            )
+            .ok()
+        };
+
+        let Some(rb) = rb else {
+            // restore the pixels we cut out
+            for dy in 0..h {
+                for dx in 0..w {
+                    let mat = buf.get(dx, dy).ok()?;
+                    if mat.physics == PhysicsType::Solid {
+                        let _ignore =
+                            self.chunk_handler
+                                .set_pixel(x + i64::from(dx), y + i64::from(dy), mat);
+                    }
+                }
+            }
+            return None;
+        };
+
+        let handle = rb.body;
+        self.rigidbodies.push(rb);
+        handle
+    }
+
+    /// Blows a hole in the terrain centered on (`x`, `y`).
+    ///
+    /// Every pixel within `radius` is hit with `power`, falling off linearly to zero at the edge, and is
+    ///   destroyed if that is at least its material's toughness (see [`material::properties::MaterialProperties`]).
+    /// Destroyed pixels are thrown outward as particles (subject to [`ParticleSystem::max_particles`]),
+    ///   and active particles and rigidbodies in range are pushed away. Rigidbody pixels are never destroyed.
+    /// Uses no randomness, so the same world state and arguments always give the same result.
+    /// Returns the number of pixels destroyed.
+    pub fn explode(
+        &mut self,
+        x: i64,
+        y: i64,
+        radius: f64,
+        power: f32,
+        registries: &Registries,
+    ) -> usize {
+        if radius <= 0.0 || power <= 0.0 {
+            return 0;
+        }
+
+        // strength of the explosion at an offset from the center, scaled by the direction away from it
+        let push = |dx: f64, dy: f64| {
+            let dist = dx.hypot(dy);
+            if dist > radius {
+                return None;
+            }
+            let strength = f64::from(power) * (1.0 - dist / radius);
+            if dist < f64::EPSILON {
+                // straight up from the center
+                Some((strength, 0.0, -strength))
+            } else {
+                Some((strength, dx / dist * strength, dy / dist * strength))
+            }
+        };
+
+        let r = radius.ceil() as i64;
+        let mut debris = Vec::new();
+        for dy in -r..=r {
+            for dx in -r..=r {
+                let Some((strength, push_x, push_y)) = push(dx as f64, dy as f64) else {
+                    continue;
+                };
+
+                let (px, py) = (x + dx, y + dy);
+                let Ok(mat) = self.chunk_handler.pixel(px, py) else {
+                    continue;
+                };
+                if matches!(mat.physics, PhysicsType::Air | PhysicsType::Object)
+                    || (strength as f32)
+                        < registries.material_properties.toughness(&mat.material_id)
+                {
+                    continue;
+                }
+
+                let mat = mat.clone();
+                if self
+                    .chunk_handler
+                    .set_pixel(px, py, MaterialInstance::air())
+                    .is_ok()
+                {
+                    debris.push(Particle::new(
+                        mat,
+                        Position { x: px as f64 + 0.5, y: py as f64 + 0.5 },
+                        Velocity {
+                            x: push_x * EXPLOSION_DEBRIS_SPEED,
+                            y: push_y * EXPLOSION_DEBRIS_SPEED,
+                        },
+                    ));
+                }
+            }
+        }
+        let destroyed = debris.len();
+
+        {
+            let mut particle_system = self.ecs.write_resource::<ParticleSystem>();
+            for part in &mut particle_system.active {
+                if let Some((_, push_x, push_y)) =
+                    push(part.pos.x - x as f64, part.pos.y - y as f64)
+                {
+                    part.vel.x += push_x * EXPLOSION_DEBRIS_SPEED;
+                    part.vel.y += push_y * EXPLOSION_DEBRIS_SPEED;
+                }
+            }
+            particle_system.spawn_many(debris);
+        }
+
+        for rb in &self.rigidbodies {
+            if let Some(body) = rb.get_body_mut(&mut self.physics) {
+                let center = body.center_of_mass();
+                let (cx, cy) = (
+                    f64::from(center.x * PHYSICS_SCALE),
+                    f64::from(center.y * PHYSICS_SCALE),
+                );
+                if let Some((_, push_x, push_y)) = push(cx - x as f64, cy - y as f64) {
+                    body.apply_impulse(
+                        Vector2::new(
+                            push_x as f32 * EXPLOSION_RIGIDBODY_IMPULSE,
+                            push_y as f32 * EXPLOSION_RIGIDBODY_IMPULSE,
+                        ),
+                        true,
+                    );
+                }
+            }
+        }
+
+        destroyed
+    }
 }
 
 impl<C: Chunk + SidedChunk + Send + Sync + 'static> World<C>
@@ -405,6 +601,11 @@ where
         file_helper: &FileHelper,
     ) {
         *self.ecs.write_resource::<TickTime>() = TickTime(tick_time);
+        {
+            let mut particle_system = self.ecs.write_resource::<ParticleSystem>();
+            particle_system.max_particles = settings.max_particles;
+            particle_system.overflow_policy = settings.particle_overflow_policy;
+        }
 
         {
             profiling::scope!("fill rigidbodies");
@@ -791,6 +992,25 @@ where
         {
             profiling::scope!("update chunk collision");
             for c in self.chunk_handler.manager.chunks_iter_mut() {
+                // edited chunks get their collision rebuilt from the updated mesh below
+                match c.update_mesh_with_epsilon(settings.mesh_simplify_epsilon) {
+                    Ok(true) => {
+                        if let Some(ChunkRigidBodyState::Active(handle)) = c.rigidbody() {
+                            self.physics.remove_rigidbody(*handle);
+                        }
+                        c.set_rigidbody(None);
+                    },
+                    Ok(false) => {},
+                    Err(e) => {
+                        log::error!(
+                            "Chunk mesh update failed @ {},{}: {}",
+                            c.chunk_x(),
+                            c.chunk_y(),
+                            e
+                        );
+                    },
+                }
+
                 if c.rigidbody().is_none() {
                     // if let Some(tr) = c.get_tris() {
                     //     let mut body_def = BodyDef::default();
@@ -960,6 +1180,19 @@ where
             }
         }
 
+        if settings.autosave
+            && self.path.is_some()
+            && self.last_autosave.elapsed()
+                >= Duration::from_secs(settings.autosave_interval.into())
+        {
+            profiling::scope!("autosave");
+            self.last_autosave = Instant::now();
+            match self.chunk_handler.save_modified_chunks() {
+                Ok(n) => log::info!("Autosaved {n} chunks."),
+                Err(e) => log::error!("Autosave failed: {e}"),
+            }
+        }
+
         // match self.net_mode {
         //     WorldNetworkMode::Local => {
         //         self.chunk_handler.tick(tick_time, loaders, settings);
@@ -967,6 +1200,91 @@ where
         //     WorldNetworkMode::Remote => {},
         // }
     }
+
+    /// Runs [`ChunkHandler::tick`] `ticks` times without rendering, physics or entity updates.
+    ///
+    /// A temporary [`Loader`] is placed at each of `loaders` for the duration of the call.
+    /// Intended for benchmarks and regression tests of the simulator.
+    pub fn step_n(
+        &mut self,
+        ticks: u32,
+        loaders: &[(f64, f64)],
+        settings: &Settings,
+        registries: &Arc<Registries>,
+        file_helper: &FileHelper,
+    ) -> TickStats {
+        let loader_entities: Vec<_> = loaders
+            .iter()
+            .map(|&(x, y)| {
+                self.ecs
+                    .create_entity()
+                    .with(Position { x, y })
+                    .with(Loader)
+                    .build()
+            })
+            .collect();
+
+        let start_tick = self.ecs.read_resource::<TickTime>().0;
+        let mut stats = TickStats::default();
+
+        for i in 0..ticks {
+            let tick_time = start_tick.wrapping_add(i);
+            *self.ecs.write_resource::<TickTime>() = TickTime(tick_time);
+
+            let start = Instant::now();
+            self.chunk_handler.tick(ChunkTickContext {
+                tick_time,
+                settings,
+                world: &mut self.ecs,
+                physics: &mut self.physics,
+                registries,
+                seed: self.seed,
+                file_helper,
+            });
+            let elapsed = start.elapsed();
+
+            stats.ticks += 1;
+            stats.total_time += elapsed;
+            stats.max_tick_time = stats.max_tick_time.max(elapsed);
+        }
+
+        *self.ecs.write_resource::<TickTime>() = TickTime(start_tick.wrapping_add(ticks));
+
+        // ok to fail since it just means a tick already deleted the loader
+        let _ignore = self.ecs.delete_entities(&loader_entities);
+        self.ecs.maintain();
+
+        stats.loaded_chunks = self.chunk_handler.manager.len();
+        stats.active_chunks = self.chunk_handler.active_chunks().count();
+
+        stats
+    }
+}
+
+/// Aggregate results of [`World::step_n`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TickStats {
+    /// Number of ticks that were run.
+    pub ticks: u32,
+    /// Total time spent ticking.
+    pub total_time: Duration,
+    /// Time taken by the slowest tick.
+    pub max_tick_time: Duration,
+    /// Number of loaded chunks after the last tick.
+    pub loaded_chunks: usize,
+    /// Number of [`super::ChunkState::Active`] chunks after the last tick.
+    pub active_chunks: usize,
+}
+
+impl TickStats {
+    /// Average time per tick, or zero if no ticks were run.
+    pub fn mean_tick_time(&self) -> Duration {
+        if self.ticks == 0 {
+            Duration::ZERO
+        } else {
+            self.total_time / self.ticks
+        }
+    }
 }
 
 #[inline]
@@ -18,6 +18,8 @@ pub struct WorldMeta {
     pub last_played_version: String,
     pub save_format: String,
     pub last_played_time: toml::value::Datetime,
+    #[serde(default)]
+    pub seed: Option<i32>,
 }
 
 impl<C: Chunk + Send + Sync + 'static> World<C> {
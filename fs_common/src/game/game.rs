@@ -40,10 +40,11 @@ pub struct FPSCounter {
 }
 
 impl<C: Chunk + Send + Sync + 'static> GameData<C> {
+    /// Creates the game with a new world, generated with `seed` or a random one if it's [`None`].
     #[profiling::function]
-    pub fn new(file_helper: FileHelper, build_data: BuildData) -> Self {
+    pub fn new(file_helper: FileHelper, build_data: BuildData, seed: Option<i32>) -> Self {
         GameData {
-            world: Some(World::create(None, Some(3))), // TODO: non constant seed
+            world: Some(World::create(None, seed)),
             tick_time: 0,
             frame_count: 0,
             fps_counter: FPSCounter {
@@ -107,7 +107,7 @@ pub fn main() -> Result<(), String> {
 
         let res = std::panic::catch_unwind(move || {
             println!("Starting server...");
-            let mut game: ServerGame = ServerGame::new(file_helper, build_data);
+            let mut game: ServerGame = ServerGame::new(file_helper, build_data, cl_args.seed);
 
             if let Some(w) = &mut game.0.world {
                 Player::create_and_add(w);
@@ -195,14 +195,14 @@ pub fn main() -> Result<(), String> {
 
         info!("Finished init.");
 
-        let mut game: ClientGame = ClientGame::new(file_helper, build_data);
+        let mut game: ClientGame = ClientGame::new(file_helper, build_data, cl_args.seed);
 
         if let Some(w) = &mut game.data.world {
             let player = Player::create_and_add(w);
 
             Camera::create_and_add(w, Target::Entity(player));
 
-            game.client.world = Some(ClientWorld { local_entity: Some(player) });
+            game.client.world = Some(ClientWorld::new(Some(player)));
         };
 
         info!("Starting main loop...");
@@ -2,8 +2,8 @@ use clap::error::ContextKind;
 use crossterm::event::{poll, read, Event, KeyCode, KeyEvent, KeyModifiers};
 use log::{debug, error, info, warn};
 use std::{
-    io::{Read, Write},
-    net::{SocketAddr, TcpListener, TcpStream},
+    collections::{HashMap, HashSet},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
     ops::Add,
     time::{Duration, Instant},
 };
@@ -17,23 +17,119 @@ use tui::{
 };
 use tui_logger::{TuiLoggerSmartWidget, TuiWidgetState};
 
-use super::world::ServerChunk;
+use super::{
+    sync::{ChunkRequests, ChunkSyncQueue},
+    world::{ServerChunk, ServerChunkHandlerExt},
+};
+use chunksystem::{ChunkKey, ChunkQuery};
 use fs_common::game::{
     common::{
-        cli::{CLArgs, CLSubcommand},
+        cli::CLArgs,
         commands::CommandHandler,
-        networking::{Packet, PacketType},
-        world::{Chunk, ChunkState, CHUNK_AREA},
-        FileHelper,
+        networking::{check_handshake, send_packet, EntitySync, Packet, PacketReader, PacketType},
+        world::{material::MaterialInstance, Chunk, ChunkState, Loader, Position},
+        FileHelper, Rect,
     },
     BuildData, GameData,
 };
+use specs::{Builder, WorldExt};
+
+/// Ticks between [`PacketType::EntitySyncPacket`]s.
+const ENTITY_SYNC_INTERVAL: u32 = 2;
+
+/// How long a new connection has to send its [`PacketType::HelloPacket`].
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What a client told us about itself in its [`PacketType::HelloPacket`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientInfo {
+    pub name: String,
+    pub protocol_version: u32,
+}
+
+/// A new connection that hasn't finished its handshake yet.
+///
+/// The stream is nonblocking, so a client that connects and never says hello can't stall the
+///   server while we wait for it.
+pub struct PendingHandshake {
+    stream: TcpStream,
+    addr: SocketAddr,
+    reader: PacketReader,
+    since: Instant,
+}
+
+impl PendingHandshake {
+    pub fn new(stream: TcpStream, addr: SocketAddr) -> std::io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        Ok(Self {
+            stream,
+            addr,
+            reader: PacketReader::new(),
+            since: Instant::now(),
+        })
+    }
+
+    /// Checks for the client's [`PacketType::HelloPacket`] without blocking, answering it with a
+    ///   [`PacketType::WelcomePacket`] if it's accepted.
+    ///
+    /// Returns [`None`] while still waiting, or the reason the client should be refused, which
+    ///   includes taking longer than [`HANDSHAKE_TIMEOUT`] (as of `now`).
+    pub fn poll(&mut self, tick_speed: u16, now: Instant) -> Option<Result<ClientInfo, String>> {
+        let packet = match self.reader.read(&mut self.stream) {
+            Ok(Some(packet)) => packet,
+            Ok(None) if now.saturating_duration_since(self.since) > HANDSHAKE_TIMEOUT => {
+                return Some(Err("Timed out waiting for handshake".to_owned()));
+            },
+            Ok(None) => return None,
+            Err(e) => return Some(Err(format!("Failed to read handshake: {e}"))),
+        };
+
+        let info = match packet.packet_type {
+            PacketType::HelloPacket { protocol_version, chunk_size, client_name } => {
+                if let Err(reason) = check_handshake(protocol_version, chunk_size) {
+                    return Some(Err(reason));
+                }
+                ClientInfo { name: client_name, protocol_version }
+            },
+            other => {
+                return Some(Err(format!("Expected HelloPacket, got {}", other.name())));
+            },
+        };
+
+        Some(
+            self.send(&Packet::welcome(tick_speed))
+                .map(|()| info)
+                .map_err(|e| e.to_string()),
+        )
+    }
+
+    /// Tells the client why it's refused and closes the connection.
+    pub fn refuse(mut self, reason: String) {
+        let packet = Packet {
+            packet_type: PacketType::DisconnectPacket { reason },
+        };
+        let _ = self.send(&packet);
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+
+    /// The accepted connection, along with anything the client sent after its hello.
+    pub fn into_parts(self) -> (TcpStream, SocketAddr, PacketReader) {
+        (self.stream, self.addr, self.reader)
+    }
+
+    fn send(&mut self, packet: &Packet) -> std::io::Result<()> {
+        self.stream.set_nonblocking(false)?;
+        let result = send_packet(&mut self.stream, packet);
+        self.stream.set_nonblocking(true)?;
+        result.map(|_| ())
+    }
+}
 
 pub struct ServerGame(pub GameData<ServerChunk>);
 
 impl ServerGame {
-    pub fn new(file_helper: FileHelper, build_data: BuildData) -> Self {
-        Self(GameData::new(file_helper, build_data))
+    pub fn new(file_helper: FileHelper, build_data: BuildData, seed: Option<i32>) -> Self {
+        Self(GameData::new(file_helper, build_data, seed))
     }
 
     #[profiling::function]
@@ -60,16 +156,26 @@ impl ServerGame {
 
         term.clear().unwrap();
 
-        let CLSubcommand::Server { port } = args.subcommand.as_ref().unwrap();
-        let net_listener =
-            TcpListener::bind(format!("127.0.0.1:{port}")).map_err(|e| e.to_string())?;
+        let bind_addr = args.subcommand.as_ref().unwrap().bind_addr();
+        let net_listener = TcpListener::bind(bind_addr)
+            .map_err(|e| format!("Failed to bind server to {bind_addr}: {e}"))?;
         net_listener
             .set_nonblocking(true)
             .map_err(|e| e.to_string())?;
 
-        info!(target: "", "Server listening on port {}...", port);
+        info!(target: "", "Server listening on {}...", bind_addr);
 
+        let mut handshakes: Vec<PendingHandshake> = Vec::new();
         let mut connections: Vec<(TcpStream, SocketAddr)> = Vec::new();
+        let mut chunk_requests: HashMap<SocketAddr, ChunkRequests> = HashMap::new();
+        // keep the chunks around each client loaded
+        let mut client_loaders: HashMap<SocketAddr, specs::Entity> = HashMap::new();
+        let mut pixel_edits: Vec<(i64, i64, MaterialInstance)> = Vec::new();
+        let mut sync_queues: HashMap<SocketAddr, ChunkSyncQueue> = HashMap::new();
+        let mut packet_readers: HashMap<SocketAddr, PacketReader> = HashMap::new();
+        let mut clients: HashMap<SocketAddr, ClientInfo> = HashMap::new();
+        // entity ids included in the last EntitySyncPacket
+        let mut synced_entities: HashSet<u32> = HashSet::new();
 
         let mut prev_tick_time = std::time::Instant::now();
         let mut prev_tick_physics_time = std::time::Instant::now();
@@ -89,151 +195,263 @@ impl ServerGame {
         let mut command_handler = CommandHandler::new();
 
         'mainLoop: loop {
-            if let Ok((mut stream, addr)) = net_listener.accept() {
+            if let Ok((stream, addr)) = net_listener.accept() {
                 info!("Incoming Connection: {}", addr.to_string());
-                stream.set_nonblocking(false).unwrap();
-                if let Some(w) = &self.0.world {
-                    for ci in unsafe { w.chunk_handler.manager.raw().iter() } {
-                        // println!("Writing SyncChunkPacket");
-                        let (chunk_x, chunk_y) = *ci.0;
-                        let packet = Packet {
-                            packet_type: PacketType::SyncChunkPacket {
-                                chunk_x,
-                                chunk_y,
-                                pixels: ci.1.pixels().as_ref().unwrap().to_vec(),
-                                colors: ci.1.colors().to_vec(),
-                            },
-                        };
-                        // let buf = serde_json::to_string(&packet).unwrap().into_bytes();
-                        // let size_buf = serde_json::to_string(&(buf.len() as u32)).unwrap().into_bytes();
-                        let buf = bincode::serialize(&packet).unwrap();
-                        let size_buf = bincode::serialize(&(buf.len() as u32)).unwrap();
-                        stream.write_all(&size_buf).unwrap();
-                        stream.flush().unwrap();
-                        stream.write_all(&buf).unwrap();
-                        stream.flush().unwrap();
-
-                        // println!("Wrote SyncChunkPacket");
-                    }
+                match PendingHandshake::new(stream, addr) {
+                    Ok(handshake) => handshakes.push(handshake),
+                    Err(e) => warn!("Failed to set up connection from {}: {}", addr, e),
                 }
-                stream.set_nonblocking(true).unwrap();
-                connections.push((stream, addr));
             }
 
-            for c in &mut connections {
-                let mut buf = [0; 4];
-                if c.0.read_exact(&mut buf).is_ok() {
-                    let size: u32 = bincode::deserialize(&buf).unwrap();
-                    debug!("Incoming packet, size = {}.", size);
-
-                    let mut buf = Vec::with_capacity(size as usize);
-
-                    debug!("read_to_end...");
-                    match std::io::Read::by_ref(&mut c.0)
-                        .take(u64::from(size))
-                        .read_to_end(&mut buf)
-                    {
-                        Ok(_) => {
-                            debug!("Read {} bytes.", buf.len());
-                            let p: Packet =
-                                bincode::deserialize(&buf).expect("Failed to deserialize packet.");
-                            debug!(
-                                "Recieved packet from {:?}: {:?}",
-                                c.1,
-                                match p.packet_type {
-                                    PacketType::SyncChunkPacket { .. } => "SyncChunkPacket",
-                                    PacketType::SyncLiquidFunPacket { .. } => "SyncLiquidFunPacket",
-                                }
-                            );
+            let now = Instant::now();
+            for mut handshake in std::mem::take(&mut handshakes) {
+                match handshake.poll(self.0.settings.tick_speed, now) {
+                    None => handshakes.push(handshake),
+                    Some(Ok(info)) => {
+                        let (stream, addr, reader) = handshake.into_parts();
+                        info!("{} joined as \"{}\"", addr.to_string(), info.name);
+                        connections.push((stream, addr));
+                        sync_queues.insert(addr, ChunkSyncQueue::new());
+                        chunk_requests.insert(addr, ChunkRequests::new());
+                        packet_readers.insert(addr, reader);
+                        clients.insert(addr, info);
+                    },
+                    Some(Err(reason)) => {
+                        warn!(
+                            "Refusing connection from {}: {}",
+                            handshake.addr.to_string(),
+                            reason
+                        );
+                        handshake.refuse(reason);
+                    },
+                }
+            }
+
+            let tick_time = self.0.tick_time;
+            let mut moved_clients = vec![];
+            let mut dropped_loaders = vec![];
+            connections.retain_mut(|c| {
+                let reader = packet_readers.entry(c.1).or_default();
+                loop {
+                    match reader.read(&mut c.0) {
+                        Ok(Some(p)) => {
+                            debug!("Recieved packet from {:?}: {:?}", c.1, p.packet_type.name());
+
+                            match p.packet_type {
+                                PacketType::ClientPositionPacket { x, y } => {
+                                    moved_clients.push((c.1, Position { x, y }));
+                                },
+                                PacketType::RequestChunkPacket { chunk_x, chunk_y } => {
+                                    let requests = chunk_requests.entry(c.1).or_default();
+                                    if !requests.request((chunk_x, chunk_y), tick_time) {
+                                        debug!(
+                                            "Ignoring chunk request from {}, it has {} waiting",
+                                            c.1,
+                                            requests.pending()
+                                        );
+                                    }
+                                },
+                                PacketType::ResyncChunkPacket { chunk_x, chunk_y } => {
+                                    if let Some(queue) = sync_queues.get_mut(&c.1) {
+                                        queue.forget((chunk_x, chunk_y));
+                                    }
+                                    chunk_requests
+                                        .entry(c.1)
+                                        .or_default()
+                                        .request((chunk_x, chunk_y), tick_time);
+                                },
+                                PacketType::SetPixelsPacket { edits } => {
+                                    pixel_edits.extend(edits);
+                                },
+                                _ => {},
+                            }
                         },
+                        // the rest of the packet (if any) is read next time
+                        Ok(None) => return true,
                         Err(e) => {
-                            // TODO: this needs to be handled correctly like in client::game
-                            //         since when read_to_end fails, it can still have read some of the bytes
-                            panic!("read_to_end failed: {e}");
+                            warn!("Dropping connection from {}: {}", c.1, e);
+                            let _ = c.0.shutdown(Shutdown::Both);
+                            sync_queues.remove(&c.1);
+                            packet_readers.remove(&c.1);
+                            clients.remove(&c.1);
+                            chunk_requests.remove(&c.1);
+                            dropped_loaders.extend(client_loaders.remove(&c.1));
+                            return false;
                         },
                     }
                 }
+            });
+
+            if !pixel_edits.is_empty() {
+                if let Some(w) = &mut self.0.world {
+                    let count = pixel_edits.len();
+                    let applied = w
+                        .chunk_handler
+                        .apply_edits(std::mem::take(&mut pixel_edits));
+                    if applied < count {
+                        debug!(
+                            "Ignored {} pixel edits outside of loaded chunks",
+                            count - applied
+                        );
+                    }
+                } else {
+                    pixel_edits.clear();
+                }
+            }
+
+            if let Some(w) = &mut self.0.world {
+                // stop syncing chunks the clients moved away from
+                for (addr, pos) in &moved_clients {
+                    if let Some(queue) = sync_queues.get_mut(addr) {
+                        let view = w.chunk_handler.get_unload_zone((pos.x, pos.y));
+                        queue.set_view((pos.x, pos.y), view);
+                    }
+                }
+
+                Self::sync_client_loaders(
+                    &mut w.ecs,
+                    moved_clients,
+                    &mut client_loaders,
+                    dropped_loaders,
+                );
+            }
+
+            // answer chunk requests for chunks we have, the rest are loaded by the clients' loaders
+            if let Some(w) = &mut self.0.world {
+                for c in &mut connections {
+                    let Some(requests) = chunk_requests.get_mut(&c.1) else {
+                        continue;
+                    };
+
+                    let expired = requests.expire(self.0.tick_time);
+                    if expired > 0 {
+                        debug!(
+                            "Dropped {} chunk requests from {} that didn't load in time",
+                            expired, c.1
+                        );
+                    }
+
+                    requests.answer(|key| {
+                        let Some(ch) = w.chunk_handler.manager.chunk_at(key) else {
+                            return false;
+                        };
+                        if !matches!(ch.state(), ChunkState::Cached | ChunkState::Active) {
+                            return false;
+                        }
+                        let Some(pixels) = ch.pixels() else {
+                            return false;
+                        };
+
+                        let packet = Packet {
+                            packet_type: PacketType::SyncChunkPacket {
+                                chunk_x: key.0,
+                                chunk_y: key.1,
+                                pixels: pixels.to_vec(),
+                                colors: ch.colors().to_vec(),
+                            },
+                        };
+
+                        c.0.set_nonblocking(false).unwrap();
+                        if let Err(e) = send_packet(&mut c.0, &packet) {
+                            warn!("Failed to send chunk {:?} to {}: {}", key, c.1, e);
+                        } else if let Some(queue) = sync_queues.get_mut(&c.1) {
+                            queue.mark_known(key);
+                        }
+                        c.0.set_nonblocking(true).unwrap();
+                        true
+                    });
+                }
             }
 
             let now = std::time::Instant::now();
 
             // tick
 
-            let can_tick = self.0.settings.tick;
+            // while paused, only tick if a step was requested
+            let stepping = !self.0.settings.tick && self.0.settings.tick_steps > 0;
+            let can_tick = self.0.settings.tick || stepping;
 
-            if do_tick_next && can_tick {
+            // the loop keeps its pace while paused so the console stays responsive
+            if do_tick_next {
                 if now.saturating_duration_since(prev_tick_time).as_millis() > 500 {
-                    warn!(target: "", "50+ ms behind, skipping some ticks to catch up...");
+                    if can_tick {
+                        warn!(target: "", "50+ ms behind, skipping some ticks to catch up...");
+                    }
                     prev_tick_time = now;
                 } else {
                     prev_tick_time = prev_tick_time.add(Duration::from_nanos(
                         1_000_000_000 / u64::from(self.0.settings.tick_speed),
                     ));
                 }
+            }
+
+            if do_tick_next && can_tick {
+                if stepping {
+                    self.0.settings.tick_steps -= 1;
+                }
                 let st = Instant::now();
                 self.tick();
 
-                if self.0.tick_time % 4 == 0 {
-                    if let Some(w) = &self.0.world {
-                        let mut n = 0;
-                        for ci in unsafe { w.chunk_handler.manager.raw().iter() } {
-                            n += 1;
-                            if ci.1.state() == ChunkState::Active
-                                && ci.1.dirty
-                                && n % (self.0.tick_time / 4) % 4 == 0
-                            {
-                                for c in &mut connections {
-                                    // println!("Writing SyncChunkPacket");
-                                    let (chunk_x, chunk_y) = *ci.0;
-                                    let pixels_vec = ci.1.pixels().as_ref().unwrap().to_vec();
-                                    let colors_vec = ci.1.colors().to_vec();
-
-                                    assert!(
-                                        pixels_vec.len() == CHUNK_AREA,
-                                        "Almost sent wrong size pixels Vec: {} (expected {})",
-                                        pixels_vec.len(),
-                                        CHUNK_AREA
-                                    );
-
-                                    if colors_vec.len() != CHUNK_AREA * 4 {
-                                        panic!(
-                                            "Almost sent wrong size colors Vec: {} (expected {})",
-                                            colors_vec.len(),
-                                            CHUNK_AREA * 4
-                                        );
-                                    }
-
-                                    let packet = Packet {
-                                        packet_type: PacketType::SyncChunkPacket {
-                                            chunk_x,
-                                            chunk_y,
-                                            pixels: pixels_vec,
-                                            colors: colors_vec,
-                                        },
-                                    };
-                                    // let buf = serde_json::to_string(&packet).unwrap().into_bytes();
-                                    // let size_buf = serde_json::to_string(&(buf.len() as u32)).unwrap().into_bytes();
-                                    let buf = bincode::serialize(&packet).unwrap();
-                                    let size_buf = bincode::serialize(&(buf.len() as u32)).unwrap();
-
-                                    c.0.set_nonblocking(false).unwrap();
-                                    c.0.write_all(&size_buf).unwrap();
-                                    c.0.flush().unwrap();
-                                    c.0.write_all(&buf).unwrap();
-                                    c.0.flush().unwrap();
-                                    c.0.set_nonblocking(true).unwrap();
-
-                                    // println!("Wrote SyncChunkPacket");
-                                }
+                if let Some(w) = &mut self.0.world {
+                    let dirty: Vec<(ChunkKey, Option<Rect<u16>>)> = w
+                        .chunk_handler
+                        .manager
+                        .kv_iter_mut()
+                        .filter(|(_, ch)| ch.state() == ChunkState::Active && ch.dirty)
+                        .map(|(key, ch)| {
+                            ch.dirty = false;
+                            (key, ch.sync_rect.take())
+                        })
+                        .collect();
+
+                    for c in &mut connections {
+                        let Some(queue) = sync_queues.get_mut(&c.1) else {
+                            continue;
+                        };
+                        queue.queue_dirty(dirty.iter().copied());
+
+                        c.0.set_nonblocking(false).unwrap();
+                        queue.send_within_budget(self.0.settings.chunk_sync_budget, |key, rect| {
+                            let ch = w.chunk_handler.manager.chunk_at(key)?;
+                            let pixels = ch.pixels()?;
+
+                            let delta = rect.and_then(|rect| {
+                                PacketType::chunk_delta(key.0, key.1, pixels, rect)
+                            });
+                            let packet = Packet {
+                                packet_type: delta.unwrap_or_else(|| PacketType::SyncChunkPacket {
+                                    chunk_x: key.0,
+                                    chunk_y: key.1,
+                                    pixels: pixels.to_vec(),
+                                    colors: ch.colors().to_vec(),
+                                }),
+                            };
+
+                            match send_packet(&mut c.0, &packet) {
+                                Ok(size) => Some(size),
+                                Err(e) => {
+                                    warn!("Failed to sync chunk {:?} to {}: {}", key, c.1, e);
+                                    None
+                                },
                             }
-                        }
+                        });
+                        c.0.set_nonblocking(true).unwrap();
                     }
+                }
 
-                    // TODO: come up with a good way to merge this loop with the one right above
-                    if let Some(w) = &mut self.0.world {
-                        for ci in unsafe { w.chunk_handler.manager.raw_mut().iter_mut() } {
-                            if ci.1.state() == ChunkState::Active && ci.1.dirty {
-                                ci.1.dirty = false;
+                if self.0.tick_time % ENTITY_SYNC_INTERVAL == 0 {
+                    if let Some(w) = &self.0.world {
+                        let entities = EntitySync::collect(&w.ecs, &mut synced_entities);
+                        if !entities.is_empty() {
+                            let packet = Packet {
+                                packet_type: PacketType::EntitySyncPacket { entities },
+                            };
+                            for c in &mut connections {
+                                c.0.set_nonblocking(false).unwrap();
+                                if let Err(e) = send_packet(&mut c.0, &packet) {
+                                    warn!("Failed to sync entities to {}: {}", c.1, e);
+                                }
+                                c.0.set_nonblocking(true).unwrap();
                             }
                         }
                     }
@@ -243,6 +461,10 @@ impl ServerGame {
                 self.0.fps_counter.tick_times[self.0.fps_counter.tick_times.len() - 1] =
                     Instant::now().saturating_duration_since(st).as_nanos() as f32;
 
+                self.0.fps_counter.ticks += 1;
+            }
+
+            if do_tick_next {
                 if poll(Duration::from_millis(1)).unwrap() {
                     let event = read().unwrap();
 
@@ -262,6 +484,17 @@ impl ServerGame {
                                         Ok(m) => {
                                             if m.subcommand_matches("shutdown").is_some() {
                                                 break 'mainLoop;
+                                            } else if m.subcommand_matches("pause").is_some() {
+                                                self.0.settings.tick = false;
+                                                info!(target: "", "Simulation paused");
+                                            } else if m.subcommand_matches("resume").is_some() {
+                                                self.0.settings.tick = true;
+                                                self.0.settings.tick_steps = 0;
+                                                info!(target: "", "Simulation resumed");
+                                            } else if let Some(m) = m.subcommand_matches("step") {
+                                                let ticks =
+                                                    m.get_one::<u32>("ticks").copied().unwrap_or(1);
+                                                self.0.settings.tick_steps += ticks;
                                             }
                                         },
                                         Err(e)
@@ -299,12 +532,9 @@ impl ServerGame {
                     .unwrap();
                 term.draw(|f| self.draw_terminal(f, &input, &mut tui_widget_state))
                     .unwrap();
-
-                self.0.fps_counter.ticks += 1;
             }
-            do_tick_next = can_tick
-                && now.saturating_duration_since(prev_tick_time).as_nanos()
-                    > 1_000_000_000 / u128::from(self.0.settings.tick_speed); // intended is 30 ticks per second
+            do_tick_next = now.saturating_duration_since(prev_tick_time).as_nanos()
+                > 1_000_000_000 / u128::from(self.0.settings.tick_speed); // intended is 30 ticks per second
 
             // tick liquidfun
 
@@ -446,6 +676,33 @@ impl ServerGame {
         Ok(())
     }
 
+    /// Moves the [`Loader`]s of the clients in `moved` to their new positions, creating them if
+    ///   needed, and deletes the loaders in `dropped`.
+    fn sync_client_loaders(
+        ecs: &mut specs::World,
+        moved: Vec<(SocketAddr, Position)>,
+        loaders: &mut HashMap<SocketAddr, specs::Entity>,
+        dropped: Vec<specs::Entity>,
+    ) {
+        for entity in dropped {
+            let _ = ecs.delete_entity(entity);
+        }
+
+        for (addr, position) in moved {
+            match loaders.get(&addr) {
+                Some(&entity) if ecs.is_alive(entity) => {
+                    ecs.write_storage::<Position>()
+                        .insert(entity, position)
+                        .expect("Failed to move client loader");
+                },
+                _ => {
+                    let entity = ecs.create_entity().with(position).with(Loader).build();
+                    loaders.insert(addr, entity);
+                },
+            }
+        }
+    }
+
     #[profiling::function]
     fn tick(&mut self) {
         self.0.tick_time += 1;
@@ -604,3 +861,158 @@ impl ServerGame {
         frame.render_widget(paragraph, main_chunks[1]);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        net::{TcpListener, TcpStream},
+        time::{Duration, Instant},
+    };
+
+    use fs_common::game::common::networking::{
+        read_packet, send_packet, Packet, PacketType, PROTOCOL_VERSION,
+    };
+    use fs_common::game::common::world::{self, Loader, Position, CHUNK_SIZE};
+    use specs::WorldExt;
+
+    use super::{ClientInfo, PendingHandshake, ServerGame, HANDSHAKE_TIMEOUT};
+
+    /// A client socket and the server's pending handshake for it.
+    fn connect() -> (TcpStream, PendingHandshake) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, addr) = listener.accept().unwrap();
+
+        (client, PendingHandshake::new(server, addr).unwrap())
+    }
+
+    /// Sends `hello` from a client socket and runs the server side of the handshake on it.
+    fn handshake(hello: &Packet) -> (Result<ClientInfo, String>, Packet) {
+        let (mut client, mut pending) = connect();
+
+        send_packet(&mut client, hello).unwrap();
+        let start = Instant::now();
+        let result = loop {
+            if let Some(result) = pending.poll(20, Instant::now()) {
+                break result;
+            }
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "hello never arrived"
+            );
+            std::thread::sleep(Duration::from_millis(1));
+        };
+        if let Err(reason) = &result {
+            // what the server loop does with refused clients
+            pending.refuse(reason.clone());
+        }
+
+        (result, read_packet(&mut client).unwrap())
+    }
+
+    #[test]
+    fn handshake_accepted() {
+        let (result, reply) = handshake(&Packet::hello("alice"));
+
+        assert_eq!(
+            result.unwrap(),
+            ClientInfo {
+                name: "alice".to_owned(),
+                protocol_version: PROTOCOL_VERSION,
+            }
+        );
+        assert!(matches!(
+            reply.packet_type,
+            PacketType::WelcomePacket {
+                protocol_version: PROTOCOL_VERSION,
+                chunk_size: CHUNK_SIZE,
+                server_tick_speed: 20,
+            }
+        ));
+    }
+
+    #[test]
+    fn handshake_version_mismatch() {
+        let hello = Packet {
+            packet_type: PacketType::HelloPacket {
+                protocol_version: PROTOCOL_VERSION + 1,
+                chunk_size: CHUNK_SIZE,
+                client_name: "bob".to_owned(),
+            },
+        };
+        let (result, reply) = handshake(&hello);
+
+        let reason = result.unwrap_err();
+        assert!(reason.starts_with("Protocol version mismatch"), "{reason}");
+        let PacketType::DisconnectPacket { reason: sent } = reply.packet_type else {
+            panic!(
+                "expected a DisconnectPacket, got {}",
+                reply.packet_type.name()
+            );
+        };
+        assert_eq!(sent, reason);
+    }
+
+    #[test]
+    fn handshake_timeout() {
+        let (mut client, mut pending) = connect();
+
+        // a silent client doesn't block the server
+        let start = Instant::now();
+        assert!(pending.poll(20, start).is_none());
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        let result = pending.poll(20, start + HANDSHAKE_TIMEOUT + Duration::from_secs(1));
+        let Some(Err(reason)) = result else {
+            panic!("expected the handshake to time out, got {result:?}");
+        };
+        pending.refuse(reason);
+
+        let reply = read_packet(&mut client).unwrap();
+        assert!(matches!(
+            reply.packet_type,
+            PacketType::DisconnectPacket { .. }
+        ));
+    }
+
+    #[test]
+    fn client_loaders() {
+        let mut ecs = world::ecs();
+        let mut loaders = HashMap::new();
+        let alice = "127.0.0.1:1000".parse().unwrap();
+        let bob = "127.0.0.1:1001".parse().unwrap();
+
+        ServerGame::sync_client_loaders(
+            &mut ecs,
+            vec![
+                (alice, Position { x: 1.0, y: 2.0 }),
+                (bob, Position { x: 0.0, y: 0.0 }),
+            ],
+            &mut loaders,
+            vec![],
+        );
+        assert_eq!(loaders.len(), 2);
+        assert!(ecs.read_storage::<Loader>().contains(loaders[&alice]));
+
+        // moving reuses the loader
+        let alice_loader = loaders[&alice];
+        ServerGame::sync_client_loaders(
+            &mut ecs,
+            vec![(alice, Position { x: 5.0, y: 6.0 })],
+            &mut loaders,
+            vec![],
+        );
+        assert_eq!(loaders[&alice], alice_loader);
+        assert_eq!(
+            ecs.read_storage::<Position>().get(alice_loader),
+            Some(&Position { x: 5.0, y: 6.0 })
+        );
+
+        let bob_loader = loaders.remove(&bob).unwrap();
+        ServerGame::sync_client_loaders(&mut ecs, vec![], &mut loaders, vec![bob_loader]);
+        ecs.maintain();
+        assert!(!ecs.is_alive(bob_loader));
+        assert!(ecs.is_alive(alice_loader));
+    }
+}
@@ -1,4 +1,6 @@
 mod game;
 pub use game::*;
 
+pub mod sync;
+
 pub mod world;
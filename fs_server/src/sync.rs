@@ -0,0 +1,356 @@
+use std::collections::{hash_map::Entry, HashMap, HashSet, VecDeque};
+
+use chunksystem::ChunkKey;
+use fs_common::game::common::{world::CHUNK_SIZE, Rect};
+
+/// Most chunk requests a client can have waiting at once, a bit more than a load zone's worth.
+///
+/// Requests past this are ignored until some of the waiting ones are answered or expire.
+pub const MAX_CHUNK_REQUESTS: usize = 1024;
+
+/// Ticks a chunk request can wait for its chunk to load before it's dropped.
+pub const CHUNK_REQUEST_TIMEOUT: u32 = 600;
+
+/// Chunks one client has asked for that haven't been sent to it yet.
+///
+/// The client asks for each chunk once when it enters its load zone, and again if it leaves and
+///   comes back, so dropping a request that can't be answered (eg. because the client moved away
+///   before the chunk loaded) is safe.
+#[derive(Debug, Default)]
+pub struct ChunkRequests {
+    /// Tick each chunk was requested on.
+    requested: HashMap<ChunkKey, u32>,
+}
+
+impl ChunkRequests {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a request made on tick `tick`. Requesting a chunk again doesn't restart its timeout.
+    ///
+    /// Returns `false` if the request was ignored because [`MAX_CHUNK_REQUESTS`] are waiting.
+    pub fn request(&mut self, key: ChunkKey, tick: u32) -> bool {
+        if self.requested.contains_key(&key) {
+            return true;
+        }
+        if self.requested.len() >= MAX_CHUNK_REQUESTS {
+            return false;
+        }
+
+        self.requested.insert(key, tick);
+        true
+    }
+
+    /// Drops requests made more than [`CHUNK_REQUEST_TIMEOUT`] ticks before `tick`, returning how
+    ///   many were dropped.
+    pub fn expire(&mut self, tick: u32) -> usize {
+        let before = self.requested.len();
+        self.requested
+            .retain(|_, &mut at| tick.wrapping_sub(at) <= CHUNK_REQUEST_TIMEOUT);
+        before - self.requested.len()
+    }
+
+    /// Calls `answer` for every waiting request, dropping the ones it returns `true` for.
+    pub fn answer(&mut self, mut answer: impl FnMut(ChunkKey) -> bool) {
+        self.requested.retain(|&key, _| !answer(key));
+    }
+
+    /// Number of requests waiting.
+    pub fn pending(&self) -> usize {
+        self.requested.len()
+    }
+}
+
+/// Dirty chunks waiting to be sent to one client.
+///
+/// Only chunks the client has been sent before (see [`ChunkSyncQueue::mark_known`]) and hasn't
+///   moved away from since (see [`ChunkSyncQueue::set_view`]) are queued.
+/// Each batch of newly dirty chunks is queued nearest to the client first, behind anything still
+///   waiting from earlier batches, so far away chunks can't be starved by nearby ones that keep
+///   changing.
+///
+/// Each queued chunk keeps the part of it that changed since it was last sent, [`None`] meaning
+///   all of it.
+#[derive(Debug, Default)]
+pub struct ChunkSyncQueue {
+    known: HashSet<ChunkKey>,
+    /// The client's position in chunk coordinates, see [`ChunkSyncQueue::set_view`].
+    focus: Option<(f64, f64)>,
+    queued: HashMap<ChunkKey, Option<Rect<u16>>>,
+    pending: VecDeque<ChunkKey>,
+}
+
+impl ChunkSyncQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Marks a chunk as sent to the client, so it receives updates for it from now on.
+    pub fn mark_known(&mut self, key: ChunkKey) {
+        self.known.insert(key);
+    }
+
+    /// Forgets that the client has a chunk (eg. because it dropped it), so it isn't sent updates
+    ///   for it until it's marked as known again.
+    pub fn forget(&mut self, key: ChunkKey) {
+        self.known.remove(&key);
+        if self.queued.remove(&key).is_some() {
+            self.pending.retain(|&k| k != key);
+        }
+    }
+
+    /// Updates the client's position `pos` and `view`, the part of the world it keeps loaded (both
+    ///   in pixels).
+    ///
+    /// Chunks are queued nearest to `pos` first, and the ones outside of `view` are forgotten (see
+    ///   [`ChunkSyncQueue::forget`]), since the client drops them too.
+    pub fn set_view(&mut self, pos: (f64, f64), view: Rect<i32>) {
+        // offset so chunk keys are compared by their centers
+        let size = f64::from(CHUNK_SIZE);
+        self.focus = Some((pos.0 / size - 0.5, pos.1 / size - 0.5));
+
+        self.known.retain(|&(cx, cy)| {
+            Rect::new_wh(
+                cx * i32::from(CHUNK_SIZE),
+                cy * i32::from(CHUNK_SIZE),
+                CHUNK_SIZE,
+                CHUNK_SIZE,
+            )
+            .intersects(&view)
+        });
+        let known = &self.known;
+        self.queued.retain(|key, _| known.contains(key));
+        self.pending.retain(|key| known.contains(key));
+    }
+
+    /// The client's position in chunk coordinates, if known.
+    pub fn focus(&self) -> Option<(f64, f64)> {
+        self.focus
+    }
+
+    /// Queues the chunks in `dirty` that the client knows about, along with the part of each that
+    ///   changed ([`None`] for all of it).
+    ///
+    /// Chunks that are queued already keep their place, and their changed part grows to include
+    ///   the new one.
+    pub fn queue_dirty(&mut self, dirty: impl IntoIterator<Item = (ChunkKey, Option<Rect<u16>>)>) {
+        let mut batch = vec![];
+        for (key, rect) in dirty {
+            if !self.known.contains(&key) {
+                continue;
+            }
+
+            match self.queued.entry(key) {
+                Entry::Occupied(mut e) => {
+                    let merged = match (*e.get(), rect) {
+                        (Some(a), Some(b)) => Some(a.union(b)),
+                        _ => None,
+                    };
+                    e.insert(merged);
+                },
+                Entry::Vacant(e) => {
+                    e.insert(rect);
+                    batch.push(key);
+                },
+            }
+        }
+
+        if let Some((fx, fy)) = self.focus() {
+            let dist = |&(cx, cy): &ChunkKey| {
+                let dx = f64::from(cx) - fx;
+                let dy = f64::from(cy) - fy;
+                dx * dx + dy * dy
+            };
+            batch.sort_by(|a, b| dist(a).total_cmp(&dist(b)));
+        }
+
+        self.pending.extend(batch);
+    }
+
+    /// Sends queued chunks in order until `budget` bytes have been sent, returning the bytes sent.
+    ///
+    /// `send` gets the part of the chunk that changed ([`None`] for all of it) and returns the size
+    ///   of the packet it sent, or [`None`] if the chunk couldn't be sent (eg. it was unloaded),
+    ///   which drops it from the queue.
+    /// The last chunk sent may go over the budget, so at least one is sent per call.
+    pub fn send_within_budget(
+        &mut self,
+        budget: usize,
+        mut send: impl FnMut(ChunkKey, Option<Rect<u16>>) -> Option<usize>,
+    ) -> usize {
+        let mut sent = 0;
+
+        while sent < budget {
+            let Some(key) = self.pending.pop_front() else {
+                break;
+            };
+            let rect = self.queued.remove(&key).flatten();
+
+            if let Some(bytes) = send(key, rect) {
+                sent += bytes;
+            }
+        }
+
+        sent
+    }
+
+    /// Number of chunks waiting to be sent.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fs_common::game::common::Rect;
+
+    use super::{ChunkRequests, ChunkSyncQueue, CHUNK_REQUEST_TIMEOUT, MAX_CHUNK_REQUESTS};
+
+    #[test]
+    fn nearest_first_within_budget() {
+        let mut queue = ChunkSyncQueue::new();
+        for x in -2..=2 {
+            queue.mark_known((x, 0));
+        }
+        queue.set_view((50.0, 50.0), Rect::new(-1000, -1000, 1000, 1000));
+
+        // (5, 0) isn't known by the client
+        queue.queue_dirty([(2, 0), (5, 0), (0, 0), (-1, 0)].map(|key| (key, None)));
+        assert_eq!(queue.pending(), 3);
+
+        let mut sent = vec![];
+        let bytes = queue.send_within_budget(150, |key, _| {
+            sent.push(key);
+            Some(100)
+        });
+        assert_eq!(bytes, 200);
+        assert_eq!(sent, vec![(0, 0), (-1, 0)]);
+
+        // older chunks go before the new batch, and already queued ones aren't duplicated
+        queue.queue_dirty([(1, 0), (2, 0)].map(|key| (key, None)));
+        assert_eq!(queue.pending(), 2);
+
+        sent.clear();
+        queue.send_within_budget(1000, |key, _| {
+            sent.push(key);
+            Some(100)
+        });
+        assert_eq!(sent, vec![(2, 0), (1, 0)]);
+        assert_eq!(queue.pending(), 0);
+    }
+
+    #[test]
+    fn changed_rects_merge() {
+        let mut queue = ChunkSyncQueue::new();
+        queue.mark_known((0, 0));
+        queue.mark_known((1, 0));
+
+        queue.queue_dirty([
+            ((0, 0), Some(Rect::new_wh(1_u16, 1_u16, 2_u16, 2_u16))),
+            ((1, 0), Some(Rect::new_wh(0_u16, 0_u16, 1_u16, 1_u16))),
+        ]);
+        queue.queue_dirty([
+            ((0, 0), Some(Rect::new_wh(5_u16, 5_u16, 1_u16, 1_u16))),
+            ((1, 0), None),
+        ]);
+        assert_eq!(queue.pending(), 2);
+
+        let mut sent = vec![];
+        queue.send_within_budget(1000, |key, rect| {
+            sent.push((key, rect.map(|r| (r.x1, r.y1, r.x2, r.y2))));
+            Some(1)
+        });
+        assert_eq!(sent, vec![((0, 0), Some((1, 1, 6, 6))), ((1, 0), None)]);
+
+        // sending clears the changed part
+        queue.queue_dirty([((0, 0), Some(Rect::new_wh(8_u16, 8_u16, 1_u16, 1_u16)))]);
+        sent.clear();
+        queue.send_within_budget(1000, |key, rect| {
+            sent.push((key, rect.map(|r| (r.x1, r.y1, r.x2, r.y2))));
+            Some(1)
+        });
+        assert_eq!(sent, vec![((0, 0), Some((8, 8, 9, 9)))]);
+    }
+
+    #[test]
+    fn view() {
+        let mut queue = ChunkSyncQueue::new();
+        assert_eq!(queue.focus(), None);
+        for x in -5..=5 {
+            queue.mark_known((x, 0));
+        }
+        queue.queue_dirty([(-5, 0), (5, 0)].map(|key| (key, None)));
+
+        // the client moved right, so the leftmost chunks are out of view
+        queue.set_view((250.0, 50.0), Rect::new(-150, -250, 750, 350));
+        assert!(queue.focus().is_some());
+        assert_eq!(queue.pending(), 1);
+
+        // (-3, 0) isn't synced anymore, and the rest go nearest to the new position first
+        queue.queue_dirty([(-3, 0), (-2, 0), (1, 0), (4, 0)].map(|key| (key, None)));
+        let mut sent = vec![];
+        queue.send_within_budget(1000, |key, _| {
+            sent.push(key);
+            Some(1)
+        });
+        assert_eq!(sent, vec![(5, 0), (1, 0), (4, 0), (-2, 0)]);
+    }
+
+    #[test]
+    fn forget() {
+        let mut queue = ChunkSyncQueue::new();
+        queue.mark_known((0, 0));
+        queue.mark_known((1, 0));
+        queue.queue_dirty([(0, 0), (1, 0)].map(|key| (key, None)));
+
+        queue.forget((0, 0));
+        assert_eq!(queue.pending(), 1);
+        queue.queue_dirty([((0, 0), None)]);
+        assert_eq!(queue.pending(), 1);
+
+        let mut sent = vec![];
+        queue.send_within_budget(1000, |key, _| {
+            sent.push(key);
+            Some(1)
+        });
+        assert_eq!(sent, vec![(1, 0)]);
+
+        // until it's sent again
+        queue.mark_known((0, 0));
+        queue.queue_dirty([((0, 0), None)]);
+        assert_eq!(queue.pending(), 1);
+    }
+
+    #[test]
+    fn chunk_requests() {
+        let mut requests = ChunkRequests::new();
+
+        assert!(requests.request((0, 0), 10));
+        // duplicates don't take up space or restart the timeout
+        assert!(requests.request((0, 0), 500));
+        assert_eq!(requests.pending(), 1);
+
+        for x in 1..MAX_CHUNK_REQUESTS as i32 {
+            assert!(requests.request((x, 0), 20));
+        }
+        assert!(!requests.request((-1, 0), 20));
+        assert_eq!(requests.pending(), MAX_CHUNK_REQUESTS);
+
+        // answered requests are dropped, the rest keep waiting
+        let mut answered = vec![];
+        requests.answer(|key| {
+            answered.push(key);
+            key.0 % 2 == 1
+        });
+        assert_eq!(answered.len(), MAX_CHUNK_REQUESTS);
+        assert_eq!(requests.pending(), MAX_CHUNK_REQUESTS / 2);
+        assert!(requests.request((-1, 0), 20));
+
+        assert_eq!(requests.expire(10 + CHUNK_REQUEST_TIMEOUT), 0);
+        assert_eq!(requests.expire(11 + CHUNK_REQUEST_TIMEOUT), 1);
+        let waiting = requests.pending();
+        assert_eq!(requests.expire(21 + CHUNK_REQUEST_TIMEOUT), waiting);
+        assert_eq!(requests.pending(), 0);
+    }
+}
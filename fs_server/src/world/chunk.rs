@@ -1,10 +1,13 @@
+use chunksystem::ChunkQuery;
 use fs_common::game::common::world::chunk_data::CommonChunkData;
 use fs_common::game::common::world::chunk_data::SidedChunkData;
+use fs_common::game::common::world::chunk_handler::ChunkHandler;
 use fs_common::game::common::world::chunk_index::ChunkLocalIndex;
 use fs_common::game::common::world::chunk_index::ChunkLocalPosition;
+use fs_common::game::common::world::chunk_pool::ChunkBuffers;
 use fs_common::game::common::world::material::color::Color;
 use fs_common::game::common::world::material::MaterialInstance;
-use fs_common::game::common::world::mesh;
+use fs_common::game::common::world::pixel_to_chunk;
 use fs_common::game::common::world::tile_entity::TileEntity;
 use fs_common::game::common::world::tile_entity::TileEntityCommon;
 use fs_common::game::common::world::tile_entity::TileEntitySided;
@@ -22,6 +25,10 @@ pub struct ServerChunk {
     pub light_data: Box<[[f32; 4]; CHUNK_AREA]>,
     pub background_data: Box<[Color; CHUNK_AREA]>,
     pub dirty: bool,
+    /// Part of the chunk changed since it was last synced, or [`None`] if all of it needs syncing.
+    ///
+    /// Only meaningful while `dirty` is set.
+    pub sync_rect: Option<Rect<u16>>,
 }
 
 impl SidedChunkData for ServerChunk {
@@ -36,13 +43,22 @@ impl TileEntitySided for TileEntityServer {
 }
 
 impl Chunk for ServerChunk {
-    fn new_empty(chunk_x: i32, chunk_y: i32) -> Self {
+    fn new_with_buffers(chunk_x: i32, chunk_y: i32, buffers: ChunkBuffers) -> Self {
         Self {
             data: CommonChunkData::new(chunk_x, chunk_y),
-            color_data: Box::new([Color::TRANSPARENT; CHUNK_AREA]),
-            light_data: Box::new([[0.0; 4]; CHUNK_AREA]),
-            background_data: Box::new([Color::TRANSPARENT; CHUNK_AREA]),
+            color_data: buffers.colors,
+            light_data: buffers.lights,
+            background_data: buffers.background_colors,
             dirty: true,
+            sync_rect: None,
+        }
+    }
+
+    fn into_buffers(self) -> ChunkBuffers {
+        ChunkBuffers {
+            colors: self.color_data,
+            lights: self.light_data,
+            background_colors: self.background_data,
         }
     }
 
@@ -70,6 +86,22 @@ impl Chunk for ServerChunk {
         self.data.dirty_rect = rect;
     }
 
+    fn needs_save(&self) -> bool {
+        self.data.needs_save
+    }
+
+    fn set_needs_save(&mut self, needs_save: bool) {
+        self.data.needs_save = needs_save;
+    }
+
+    fn frozen(&self) -> bool {
+        self.data.frozen
+    }
+
+    fn set_frozen(&mut self, frozen: bool) {
+        self.data.frozen = frozen;
+    }
+
     fn refresh(&mut self) {}
 
     fn set_pixel(&mut self, pos: ChunkLocalPosition, mat: MaterialInstance) -> Result<(), String> {
@@ -116,7 +148,8 @@ impl Chunk for ServerChunk {
         let i: ChunkLocalIndex = pos.into();
 
         self.color_data[i] = color;
-        self.dirty = true;
+
+        self.mark_synced_dirty(Rect::new_wh(pos.x(), pos.y(), 1_u16, 1_u16));
     }
 
     fn color(&self, pos: ChunkLocalPosition) -> Color {
@@ -136,8 +169,8 @@ impl Chunk for ServerChunk {
         &self.data.pixels
     }
 
-    fn set_pixel_colors(&mut self, colors: Box<[Color; CHUNK_AREA]>) {
-        self.color_data = colors;
+    fn set_pixel_colors(&mut self, colors: Box<[Color; CHUNK_AREA]>) -> Box<[Color; CHUNK_AREA]> {
+        std::mem::replace(&mut self.color_data, colors)
     }
 
     fn colors_mut(&mut self) -> &mut [Color; CHUNK_AREA] {
@@ -160,8 +193,11 @@ impl Chunk for ServerChunk {
         &self.data.background
     }
 
-    fn set_background_pixel_colors(&mut self, colors: Box<[Color; CHUNK_AREA]>) {
-        self.background_data = colors;
+    fn set_background_pixel_colors(
+        &mut self,
+        colors: Box<[Color; CHUNK_AREA]>,
+    ) -> Box<[Color; CHUNK_AREA]> {
+        std::mem::replace(&mut self.background_data, colors)
     }
 
     fn background_colors_mut(&mut self) -> &mut [Color; CHUNK_AREA] {
@@ -174,21 +210,26 @@ impl Chunk for ServerChunk {
 
     fn mark_dirty(&mut self) {
         self.dirty = true;
+        self.sync_rect = None;
     }
 
-    fn generate_mesh(&mut self) -> Result<(), String> {
-        if self.data.pixels.is_none() {
-            return Err("generate_mesh failed: self.data.pixels is None".to_owned());
+    fn mark_dirty_rect(&mut self, rect: Rect<i32>) {
+        let size = i32::from(CHUNK_SIZE);
+        let (x1, y1) = (rect.x1.clamp(0, size), rect.y1.clamp(0, size));
+        let (x2, y2) = (rect.x2.clamp(0, size), rect.y2.clamp(0, size));
+        if x1 >= x2 || y1 >= y2 {
+            return;
         }
 
-        let vs: Vec<f64> = mesh::pixels_to_valuemap(self.data.pixels.as_ref().unwrap().as_ref());
-
-        let generated =
-            mesh::generate_mesh_only_simplified(&vs, u32::from(CHUNK_SIZE), u32::from(CHUNK_SIZE));
+        self.mark_synced_dirty(Rect::new(x1 as u16, y1 as u16, x2 as u16, y2 as u16));
+    }
 
-        self.data.mesh_simplified = generated.ok();
+    fn generate_mesh_with_epsilon(&mut self, epsilon: f64) -> Result<(), String> {
+        self.data.generate_mesh(epsilon)
+    }
 
-        Ok(())
+    fn update_mesh_with_epsilon(&mut self, epsilon: f64) -> Result<bool, String> {
+        self.data.update_mesh(epsilon)
     }
 
     fn mesh_loops(&self) -> &Option<Vec<Vec<Vec<Vec<f64>>>>> {
@@ -269,3 +310,50 @@ impl SidedChunk for ServerChunk {
         &mut self.data.tile_entities
     }
 }
+
+impl ServerChunk {
+    /// Marks `rect` as needing to be synced, on top of whatever already does.
+    fn mark_synced_dirty(&mut self, rect: Rect<u16>) {
+        self.sync_rect = match self.sync_rect {
+            Some(cur) => Some(cur.union(rect)),
+            // already waiting on a full sync
+            None if self.dirty => None,
+            None => Some(rect),
+        };
+        self.dirty = true;
+    }
+}
+
+pub trait ServerChunkHandlerExt {
+    /// Applies the edits from a client's [`PacketType::SetPixelsPacket`], skipping any outside of
+    ///   loaded and generated chunks. Returns the number of edits applied.
+    ///
+    /// The edited chunks are synced to clients like any other change.
+    ///
+    /// [`PacketType::SetPixelsPacket`]: fs_common::game::common::networking::PacketType::SetPixelsPacket
+    fn apply_edits(&mut self, edits: Vec<(i64, i64, MaterialInstance)>) -> usize;
+}
+
+impl ServerChunkHandlerExt for ChunkHandler<ServerChunk> {
+    fn apply_edits(&mut self, edits: Vec<(i64, i64, MaterialInstance)>) -> usize {
+        let mut applied = 0;
+        for (world_x, world_y, mat) in edits {
+            let (key, pos) = pixel_to_chunk(world_x, world_y);
+            let Some(chunk) = self.manager.chunk_at_mut(key) else {
+                continue;
+            };
+            // still being generated, the edit would be overwritten
+            if !matches!(chunk.state(), ChunkState::Cached | ChunkState::Active) {
+                continue;
+            }
+
+            let color = mat.color;
+            if chunk.set_pixel(pos, mat).is_ok() {
+                // set_pixel doesn't mark the chunk for syncing, set_color does
+                chunk.set_color(pos, color);
+                applied += 1;
+            }
+        }
+        applied
+    }
+}
@@ -8,19 +8,43 @@ mod tests {
     use fs_common::game::common::world::chunk_access::FSChunkAccess;
     use fs_common::game::common::world::chunk_handler::{ChunkHandler, ChunkTickContext};
     use fs_common::game::common::world::physics::Physics;
-    use fs_common::game::common::world::{self, Loader, Position};
+    use fs_common::game::common::world::{self, Loader, Position, TickStats, World};
     use fs_common::game::common::Settings;
     use fs_common::game::common::{FileHelper, Registries};
 
     use fs_common::game::common::world::gen::TestGenerator;
+    use fs_common::game::common::world::material::MaterialInstance;
+    use fs_common::game::common::world::particle::ParticleSystem;
     use specs::{Builder, WorldExt};
 
     use crate::world::ServerChunk;
 
+    /// A world with nothing loaded, using [`TestGenerator`] and seed 2.
+    fn test_world() -> World<ServerChunk> {
+        let mut w = World::create(None, Some(2));
+        w.chunk_handler = ChunkHandler::new(TestGenerator::new(), None);
+        w
+    }
+
+    fn file_helper() -> FileHelper {
+        FileHelper::new("../gamedir/".into(), "../gamedir/assets/".into())
+    }
+
+    /// Steps `w` for 20 ticks with a loader at (110, -120), which loads the chunks around the origin.
+    fn step_until_loaded(w: &mut World<ServerChunk>) -> TickStats {
+        w.step_n(
+            20,
+            &[(110.0, -120.0)],
+            &Settings::default(),
+            &std::sync::Arc::new(Registries::empty()),
+            &file_helper(),
+        )
+    }
+
     #[test]
     fn chunk_loading() {
         let registries = std::sync::Arc::new(Registries::empty());
-        let file_helper = FileHelper::new("../gamedir/".into(), "../gamedir/assets/".into());
+        let file_helper = file_helper();
 
         let mut ch: ChunkHandler<ServerChunk> =
             ChunkHandler::<ServerChunk>::new(TestGenerator::new(), None);
@@ -125,6 +149,225 @@ mod tests {
         assert!(!ch.is_chunk_loaded((-3, 2)));
     }
 
+    #[test]
+    fn nearest_chunks_load_first() {
+        let registries = std::sync::Arc::new(Registries::empty());
+        let file_helper = file_helper();
+
+        let mut ch: ChunkHandler<ServerChunk> =
+            ChunkHandler::<ServerChunk>::new(TestGenerator::new(), None);
+        let mut ecs = world::ecs();
+        let mut phys = Physics::new();
+
+        ecs.create_entity()
+            .with(Position { x: 110.0, y: -120.0 })
+            .with(Loader)
+            .build();
+
+        // far one first
+        assert!(ch.queue_load_chunk(8, 8));
+        assert!(ch.queue_load_chunk(1, -1));
+        assert!(!ch.queue_load_chunk(8, 8));
+
+        ch.tick(ChunkTickContext {
+            tick_time: 0,
+            settings: &Settings::default(),
+            world: &mut ecs,
+            physics: &mut phys,
+            registries: &registries,
+            seed: 2,
+            file_helper: &file_helper,
+        });
+
+        // only the nearest chunks in the load zone fit in one tick
+        assert!(ch.is_chunk_loaded((1, -1)));
+        assert!(!ch.is_chunk_loaded((8, 8)));
+        assert!(ch.load_queue.contains(&(8, 8)));
+    }
+
+    #[test]
+    fn skip_out_of_range_queued_chunks() {
+        let registries = std::sync::Arc::new(Registries::empty());
+        let file_helper = file_helper();
+
+        let mut ch: ChunkHandler<ServerChunk> =
+            ChunkHandler::<ServerChunk>::new(TestGenerator::new(), None);
+        let mut ecs = world::ecs();
+        let mut phys = Physics::new();
+
+        // queued while the loader is next to it
+        let loader = ecs
+            .create_entity()
+            .with(Position { x: 10050.0, y: 10050.0 })
+            .with(Loader)
+            .build();
+        assert!(ch.queue_load_chunk(100, 100));
+
+        // then the loader moves away before it gets loaded
+        *ecs.write_storage::<Position>().get_mut(loader).unwrap() =
+            Position { x: 110.0, y: -120.0 };
+
+        for _ in 0..100 {
+            ch.tick(ChunkTickContext {
+                tick_time: 0,
+                settings: &Settings::default(),
+                world: &mut ecs,
+                physics: &mut phys,
+                registries: &registries,
+                seed: 2,
+                file_helper: &file_helper,
+            });
+            if ch.load_queue.is_empty() {
+                break;
+            }
+        }
+
+        assert!(ch.load_queue.is_empty());
+        assert!(ch.is_chunk_loaded((0, 0)));
+        assert!(!ch.is_chunk_loaded((100, 100)));
+    }
+
+    #[test]
+    fn seeded_generation() {
+        use fs_common::game::common::world::{
+            gen::{GenBuffers, GenContext, WorldGenerator},
+            material::color::Color,
+            CHUNK_AREA,
+        };
+
+        let registries = Registries::empty();
+        let generator = TestGenerator::<ServerChunk>::new();
+
+        let generate = |seed: i32| {
+            let mut pixels: Box<[MaterialInstance; CHUNK_AREA]> =
+                vec![MaterialInstance::air(); CHUNK_AREA]
+                    .try_into()
+                    .unwrap();
+            let mut colors: Box<[Color; CHUNK_AREA]> =
+                vec![Color::TRANSPARENT; CHUNK_AREA].try_into().unwrap();
+            let mut background = pixels.clone();
+            let mut background_colors = colors.clone();
+
+            generator.generate(
+                (3, -2),
+                GenBuffers::new(
+                    &mut pixels,
+                    &mut colors,
+                    &mut background,
+                    &mut background_colors,
+                ),
+                GenContext { seed, registries: &registries },
+            );
+            pixels
+        };
+
+        assert_eq!(generate(5), generate(5));
+        assert_ne!(generate(5), generate(6));
+    }
+
+    #[test]
+    fn state_change_callback() {
+        use fs_common::game::common::world::ChunkState;
+        use std::sync::{Arc, Mutex};
+
+        let mut w = test_world();
+
+        let transitions = Arc::new(Mutex::new(vec![]));
+        let record = transitions.clone();
+        w.chunk_handler
+            .set_state_change_callback(move |chunk_x, chunk_y, old, new| {
+                if (chunk_x, chunk_y) == (0, 0) {
+                    record.lock().unwrap().push((old, new));
+                }
+            });
+
+        step_until_loaded(&mut w);
+
+        let transitions = transitions.lock().unwrap();
+        assert_eq!(
+            transitions[0],
+            (ChunkState::NotGenerated, ChunkState::Generating(0))
+        );
+        for pair in transitions.windows(2) {
+            // each transition starts where the last one ended
+            assert_eq!(pair[0].1, pair[1].0);
+        }
+
+        let mut states: Vec<_> = transitions
+            .iter()
+            .map(|(_, new)| match new {
+                ChunkState::NotGenerated => "NotGenerated",
+                ChunkState::Generating(_) => "Generating",
+                ChunkState::Cached => "Cached",
+                ChunkState::Active => "Active",
+            })
+            .collect();
+        states.dedup();
+        assert_eq!(states, vec!["Generating", "Cached", "Active"]);
+    }
+
+    #[test]
+    fn active_chunks() {
+        use fs_common::game::common::world::{Chunk, ChunkState};
+
+        let mut w = test_world();
+        step_until_loaded(&mut w);
+        assert!(w.chunk_handler.manager.len() > 2);
+
+        for ch in w.chunk_handler.manager.chunks_iter_mut() {
+            ch.set_state(ChunkState::Cached);
+        }
+        assert_eq!(w.chunk_handler.active_chunks().count(), 0);
+
+        for key in [(0, 0), (1, -1)] {
+            w.chunk_handler
+                .chunk_at_mut(key)
+                .unwrap()
+                .set_state(ChunkState::Active);
+        }
+
+        let mut active: Vec<_> = w
+            .chunk_handler
+            .active_chunks()
+            .map(|ch| (ch.chunk_x(), ch.chunk_y()))
+            .collect();
+        active.sort_unstable();
+        assert_eq!(active, vec![(0, 0), (1, -1)]);
+
+        for ch in w.chunk_handler.active_chunks_mut() {
+            ch.set_frozen(true);
+        }
+        assert!(w.chunk_handler.chunk_at((0, 0)).unwrap().frozen());
+        assert!(w.chunk_handler.chunk_at((1, -1)).unwrap().frozen());
+        assert!(!w.chunk_handler.chunk_at((1, 0)).unwrap().frozen());
+    }
+
+    #[test]
+    fn get_neighbors() {
+        use fs_common::game::common::world::Chunk;
+
+        let mut w = test_world();
+        step_until_loaded(&mut w);
+
+        let neighbors = w.chunk_handler.get_neighbors(1, -1);
+        let pos = |i: usize| {
+            let ch = neighbors[i].unwrap();
+            (ch.chunk_x(), ch.chunk_y())
+        };
+        assert_eq!(pos(4), (1, -1));
+        assert_eq!(pos(0), (0, -2));
+        assert_eq!(pos(2), (2, -2));
+        assert_eq!(pos(6), (0, 0));
+        assert_eq!(pos(8), (2, 0));
+        assert_eq!(pos(5), (2, -1));
+
+        assert!(w
+            .chunk_handler
+            .get_neighbors(500, 500)
+            .iter()
+            .all(Option::is_none));
+    }
+
     #[test]
     fn zones() {
         let ch: ChunkHandler<ServerChunk> =
@@ -140,4 +383,1028 @@ mod tests {
         assert!(active.width() < load.width() && active.height() < load.height());
         assert!(load.width() < unload.width() && load.height() < unload.height());
     }
+
+    #[test]
+    fn step_n() {
+        let registries = std::sync::Arc::new(Registries::empty());
+        let file_helper = file_helper();
+
+        let mut w = test_world();
+
+        let stats = step_until_loaded(&mut w);
+
+        assert_eq!(stats.ticks, 20);
+        assert!(stats.loaded_chunks > 0);
+        assert!(stats.max_tick_time <= stats.total_time);
+        assert!(w.chunk_handler.is_chunk_loaded((0, 0)));
+
+        // the temporary loader should be gone, so everything unloads
+        //   (active chunks are cached on one even tick and unloaded on the next)
+        let stats = w.step_n(4, &[], &Settings::default(), &registries, &file_helper);
+
+        assert_eq!(stats.ticks, 4);
+        assert_eq!(stats.loaded_chunks, 0);
+    }
+
+    #[test]
+    fn gen_threads() {
+        let mut w: World<ServerChunk> = World::create(None, Some(2));
+        w.chunk_handler = ChunkHandler::new_with_gen_threads(TestGenerator::new(), None, 2);
+        assert_eq!(w.chunk_handler.gen_pool.current_num_threads(), 2);
+
+        let stats = step_until_loaded(&mut w);
+
+        assert_eq!(stats.ticks, 20);
+        assert!(w.chunk_handler.is_chunk_loaded((0, 0)));
+    }
+
+    #[test]
+    fn frozen() {
+        let mut w = test_world();
+
+        assert!(!w.chunk_handler.set_frozen(0, 0, true));
+
+        step_until_loaded(&mut w);
+
+        assert!(w.chunk_handler.set_frozen(0, 0, true));
+        assert!(w.chunk_handler.chunk_at_dyn((0, 0)).unwrap().frozen());
+
+        // frozen chunks still accept edits
+        let mat = world::material::TEST.instance(
+            world::material::PhysicsType::Sand,
+            world::material::color::Color::rgb(1, 2, 3),
+        );
+        assert!(w.chunk_handler.set_pixel(10, 10, mat.clone()).is_ok());
+        assert_eq!(w.chunk_handler.pixel(10, 10), Ok(&mat));
+
+        // unfreezing should wake the chunk back up
+        assert!(w.chunk_handler.set_frozen(0, 0, false));
+        let chunk = w.chunk_handler.chunk_at_dyn((0, 0)).unwrap();
+        assert!(!chunk.frozen());
+        assert!(chunk.dirty_rect().is_some());
+    }
+
+    #[test]
+    fn unloaded_chunks_persist() {
+        let registries = std::sync::Arc::new(Registries::empty());
+        let file_helper = file_helper();
+
+        let dir = std::env::temp_dir().join(format!("fs_persist_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut w = test_world();
+        w.chunk_handler.set_save_dir(dir.clone());
+
+        step_until_loaded(&mut w);
+
+        let mat = world::material::TEST.instance(
+            world::material::PhysicsType::Solid,
+            world::material::color::Color::rgb(1, 2, 3),
+        );
+        assert!(w.chunk_handler.set_pixel(10, 10, mat.clone()).is_ok());
+
+        // unloading saves the edit
+        w.step_n(4, &[], &Settings::default(), &registries, &file_helper);
+        assert!(!w.chunk_handler.is_chunk_loaded((0, 0)));
+
+        // loads finish in the background, so give them some time
+        for _ in 0..100 {
+            w.step_n(
+                1,
+                &[(110.0, -120.0)],
+                &Settings::default(),
+                &registries,
+                &file_helper,
+            );
+            if w.chunk_handler.pixel(10, 10) == Ok(&mat) {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        assert_eq!(w.chunk_handler.pixel(10, 10), Ok(&mat));
+
+        drop(w);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn chunk_at_mut() {
+        use fs_common::game::common::{world::Chunk, Rect};
+
+        let mut w = test_world();
+        step_until_loaded(&mut w);
+
+        assert!(w.chunk_handler.chunk_at_mut((500, 500)).is_none());
+
+        w.chunk_handler
+            .chunk_at_mut((0, 0))
+            .unwrap()
+            .set_dirty_rect(Some(Rect::new(1, 2, 30, 40)));
+        let rect = w.chunk_handler.chunk_at((0, 0)).unwrap().dirty_rect();
+        assert_eq!(rect.map(|r| (r.x1, r.y1, r.x2, r.y2)), Some((1, 2, 30, 40)));
+
+        w.chunk_handler
+            .chunk_at_mut_dyn((0, 0))
+            .unwrap()
+            .set_dirty_rect(None);
+        assert!(w
+            .chunk_handler
+            .chunk_at_dyn((0, 0))
+            .unwrap()
+            .dirty_rect()
+            .is_none());
+    }
+
+    #[test]
+    fn raycast() {
+        let mut w = test_world();
+        step_until_loaded(&mut w);
+
+        for y in 0..50 {
+            for x in 0..50 {
+                assert!(w
+                    .chunk_handler
+                    .set_pixel(x, y, MaterialInstance::air())
+                    .is_ok());
+            }
+        }
+
+        // fully empty
+        assert!(w.chunk_handler.raycast(0, 0, 49, 49).is_none());
+        assert!(w.chunk_handler.raycast(49, 0, 0, 49).is_none());
+
+        let mat = world::material::TEST.instance(
+            world::material::PhysicsType::Solid,
+            world::material::color::Color::rgb(1, 2, 3),
+        );
+        assert!(w.chunk_handler.set_pixel(30, 10, mat.clone()).is_ok());
+        assert!(w.chunk_handler.set_pixel(40, 10, mat.clone()).is_ok());
+        assert!(w.chunk_handler.set_pixel(20, 20, mat.clone()).is_ok());
+
+        // horizontal, from both sides
+        assert_eq!(w.chunk_handler.raycast(0, 10, 49, 10), Some((30, 10, &mat)));
+        assert_eq!(w.chunk_handler.raycast(49, 10, 0, 10), Some((40, 10, &mat)));
+        assert!(w.chunk_handler.raycast(0, 10, 29, 10).is_none());
+
+        // diagonal
+        assert_eq!(w.chunk_handler.raycast(5, 5, 45, 45), Some((20, 20, &mat)));
+        assert_eq!(w.chunk_handler.raycast(45, 45, 0, 0), Some((20, 20, &mat)));
+
+        // stops at the first unloaded chunk, even with a hit further along
+        assert!(w.chunk_handler.raycast(100_000, 10, 0, 10).is_none());
+    }
+
+    #[test]
+    fn flood_fill() {
+        let mut w = test_world();
+        step_until_loaded(&mut w);
+
+        let solid = world::material::TEST.instance(
+            world::material::PhysicsType::Solid,
+            world::material::color::Color::rgb(1, 2, 3),
+        );
+        let fill = world::material::TEST.instance(
+            world::material::PhysicsType::Sand,
+            world::material::color::Color::rgb(4, 5, 6),
+        );
+
+        // a 19x19 pocket of air inside a solid box, across the boundary of chunks (0, 0) and (1, 0)
+        for y in 10..=30 {
+            for x in 90..=110 {
+                let edge = x == 90 || x == 110 || y == 10 || y == 30;
+                let mat = if edge {
+                    solid.clone()
+                } else {
+                    MaterialInstance::air()
+                };
+                assert!(w.chunk_handler.set_pixel(x, y, mat).is_ok());
+            }
+        }
+
+        let is_air = |mat: &MaterialInstance| mat.physics == world::material::PhysicsType::Air;
+        assert_eq!(
+            w.chunk_handler.flood_fill(100, 20, fill.clone(), is_air),
+            19 * 19
+        );
+
+        for y in 10..=30 {
+            for x in 90..=110 {
+                let edge = x == 90 || x == 110 || y == 10 || y == 30;
+                let expected = if edge { &solid } else { &fill };
+                assert_eq!(w.chunk_handler.pixel(x, y), Ok(expected));
+            }
+        }
+
+        // nothing matches anymore
+        assert_eq!(w.chunk_handler.flood_fill(100, 20, fill.clone(), is_air), 0);
+
+        let is_fill = |mat: &MaterialInstance| mat == &fill;
+        assert_eq!(
+            w.chunk_handler
+                .flood_fill_max(100, 20, MaterialInstance::air(), 10, is_fill),
+            10
+        );
+    }
+
+    #[test]
+    fn set_rect() {
+        use fs_common::game::common::world::Chunk;
+
+        let mut w = test_world();
+        step_until_loaded(&mut w);
+
+        let mat = world::material::TEST.instance(
+            world::material::PhysicsType::Solid,
+            world::material::color::Color::rgb(1, 2, 3),
+        );
+
+        w.chunk_handler
+            .chunk_at_mut((0, 0))
+            .unwrap()
+            .set_dirty_rect(None);
+
+        // covers chunks (0, 0) and (1, 0)
+        assert!(w
+            .chunk_handler
+            .set_rect(0, 20, 200, 10, mat.clone())
+            .is_ok());
+        // only the filled part of the chunk is dirty
+        let rect = w.chunk_handler.chunk_at((0, 0)).unwrap().dirty_rect();
+        assert_eq!(
+            rect.map(|r| (r.x1, r.y1, r.x2, r.y2)),
+            Some((0, 20, 100, 30))
+        );
+        for y in 20..30 {
+            for x in 0..200 {
+                assert_eq!(w.chunk_handler.pixel(x, y), Ok(&mat));
+            }
+        }
+        for x in 0..200 {
+            assert_ne!(w.chunk_handler.pixel(x, 19), Ok(&mat));
+            assert_ne!(w.chunk_handler.pixel(x, 30), Ok(&mat));
+        }
+        assert_ne!(w.chunk_handler.pixel(-1, 20), Ok(&mat));
+        assert_ne!(w.chunk_handler.pixel(200, 20), Ok(&mat));
+
+        assert!(w.chunk_handler.set_rect(100_000, 0, 10, 10, mat).is_err());
+    }
+
+    #[test]
+    fn explode() {
+        let registries = std::sync::Arc::new(Registries::empty());
+
+        let mut w = test_world();
+
+        step_until_loaded(&mut w);
+
+        let mat = world::material::TEST.instance(
+            world::material::PhysicsType::Solid,
+            world::material::color::Color::rgb(1, 2, 3),
+        );
+        for y in 0..5 {
+            for x in 0..5 {
+                assert!(w.chunk_handler.set_pixel(x, y, mat.clone()).is_ok());
+            }
+        }
+
+        // too weak to break anything (default toughness is 1.0)
+        assert_eq!(w.explode(2, 2, 4.0, 0.5, &registries), 0);
+        assert_eq!(w.chunk_handler.pixel(2, 2), Ok(&mat));
+
+        let particles_before = w.ecs.read_resource::<ParticleSystem>().len();
+        let destroyed = w.explode(2, 2, 4.0, 10.0, &registries);
+
+        assert!(destroyed >= 25);
+        assert_eq!(
+            w.ecs.read_resource::<ParticleSystem>().len(),
+            particles_before + destroyed
+        );
+        for y in 0..5 {
+            for x in 0..5 {
+                assert_eq!(w.chunk_handler.pixel(x, y), Ok(&MaterialInstance::air()));
+            }
+        }
+    }
+
+    #[test]
+    fn client_edits() {
+        use fs_common::game::common::networking::{Packet, PacketType};
+        use fs_common::game::common::world::{Chunk, ChunkState};
+
+        use crate::world::ServerChunkHandlerExt;
+
+        let mut w = test_world();
+        step_until_loaded(&mut w);
+        // as if everything had been synced
+        for ch in w.chunk_handler.manager.chunks_iter_mut() {
+            ch.dirty = false;
+            ch.sync_rect = None;
+        }
+
+        let mat = world::material::TEST.instance(
+            world::material::PhysicsType::Solid,
+            world::material::color::Color::rgb(1, 2, 3),
+        );
+        let packet = Packet {
+            packet_type: PacketType::SetPixelsPacket {
+                edits: vec![(10, 10, mat.clone()), (100_000, 100_000, mat.clone())],
+            },
+        };
+        let packet = Packet::decode(&packet.encode().unwrap()).unwrap();
+        let PacketType::SetPixelsPacket { edits } = packet.packet_type else {
+            panic!("expected a SetPixelsPacket");
+        };
+
+        // the second edit isn't in a loaded chunk
+        assert!(!w.chunk_handler.is_pixel_loaded(100_000, 100_000));
+        assert_eq!(w.chunk_handler.apply_edits(edits), 1);
+        assert_eq!(w.chunk_handler.pixel(10, 10), Ok(&mat));
+
+        // only the edited pixel needs syncing
+        let chunk = w.chunk_handler.manager.chunk_at((0, 0)).unwrap();
+        assert!(chunk.dirty);
+        let rect = chunk.sync_rect.unwrap();
+        assert_eq!((rect.x1, rect.y1, rect.x2, rect.y2), (10, 10, 11, 11));
+
+        // or to chunks that are still generating
+        w.chunk_handler
+            .manager
+            .chunk_at_mut((0, 0))
+            .unwrap()
+            .set_state(ChunkState::Generating(0));
+        assert_eq!(w.chunk_handler.apply_edits(vec![(20, 20, mat.clone())]), 0);
+        assert_ne!(w.chunk_handler.pixel(20, 20), Ok(&mat));
+    }
+
+    #[test]
+    fn simulated_changes_sync_as_delta() {
+        use fs_common::game::common::networking::PacketType;
+        use fs_common::game::common::world::{Chunk, CHUNK_AREA};
+
+        let mut w = test_world();
+        step_until_loaded(&mut w);
+        // as if everything had been synced and settled
+        for ch in w.chunk_handler.manager.chunks_iter_mut() {
+            ch.dirty = false;
+            ch.sync_rect = None;
+            ch.set_dirty_rect(None);
+        }
+        w.chunk_handler
+            .manager
+            .chunk_at_mut((0, 0))
+            .unwrap()
+            .data
+            .pixels = Some(
+            vec![MaterialInstance::air(); CHUNK_AREA]
+                .try_into()
+                .unwrap(),
+        );
+
+        // a grain of sand just above a floor, so it only falls a pixel
+        let solid = world::material::TEST.instance(
+            world::material::PhysicsType::Solid,
+            world::material::color::Color::rgb(1, 2, 3),
+        );
+        let sand = world::material::TEST.instance(
+            world::material::PhysicsType::Sand,
+            world::material::color::Color::rgb(4, 5, 6),
+        );
+        for x in 30..=40 {
+            assert!(w.chunk_handler.set_pixel(x, 12, solid.clone()).is_ok());
+        }
+        assert!(w.chunk_handler.set_pixel(35, 10, sand).is_ok());
+        w.step_n(
+            1,
+            &[(110.0, -120.0)],
+            &Settings::default(),
+            &std::sync::Arc::new(Registries::empty()),
+            &file_helper(),
+        );
+        assert_eq!(w.chunk_handler.pixel(35, 10), Ok(&MaterialInstance::air()));
+
+        // only the area around the falling sand needs syncing
+        let chunk = w.chunk_handler.manager.chunk_at((0, 0)).unwrap();
+        assert!(chunk.dirty);
+        let rect = chunk.sync_rect.unwrap();
+        assert!(rect.contains_point((35_u16, 10_u16)));
+        assert!(rect.width() <= 3 && rect.height() <= 3);
+        assert!(matches!(
+            PacketType::chunk_delta(0, 0, chunk.pixels().unwrap(), rect),
+            Some(PacketType::ChunkDeltaPacket { .. })
+        ));
+    }
+
+    #[test]
+    fn material_buf_copy_cut() {
+        use fs_common::game::common::world::material::{
+            buf::MaterialBuf, color::Color, PhysicsType, TEST,
+        };
+        use fs_common::game::common::world::{Chunk, CHUNK_AREA};
+
+        // two chunks side by side, so the region crosses a chunk border
+        let mut ch: ChunkHandler<ServerChunk> = ChunkHandler::new(TestGenerator::new(), None);
+        for key in [(-1, 0), (0, 0)] {
+            let mut chunk: ServerChunk = Chunk::new_empty(key.0, key.1);
+            chunk.data.pixels = Some(
+                vec![MaterialInstance::air(); CHUNK_AREA]
+                    .try_into()
+                    .unwrap(),
+            );
+            ch.manager.insert(key, chunk);
+        }
+
+        let red = TEST.instance(PhysicsType::Solid, Color::RED);
+        let blue = TEST.instance(PhysicsType::Sand, Color::BLUE);
+        ch.set_pixel(-1, 0, red.clone()).unwrap();
+        ch.set_pixel(1, 1, blue.clone()).unwrap();
+
+        let buf = MaterialBuf::copy(&ch, -1, 0, 3_u16, 2_u16).unwrap();
+        assert_eq!((buf.width, buf.height), (3, 2));
+        assert_eq!(buf.materials.len(), 6);
+        assert_eq!(buf.get(0, 0).unwrap(), red);
+        assert_eq!(buf.get(2, 1).unwrap(), blue);
+        assert_eq!(buf.get(1, 0).unwrap(), MaterialInstance::air());
+
+        let cut = MaterialBuf::cut(&mut ch, -1, 0, 3_u16, 2_u16).unwrap();
+        assert_eq!(cut, buf);
+        assert_eq!(ch.pixel(-1, 0), Ok(&MaterialInstance::air()));
+        assert_eq!(ch.pixel(1, 1), Ok(&MaterialInstance::air()));
+
+        // (0, -1) isn't loaded
+        assert!(MaterialBuf::copy(&ch, 0, -1, 3_u16, 2_u16).is_err());
+    }
+
+    #[test]
+    fn material_buf_paste_masked() {
+        use fs_common::game::common::world::material::{
+            buf::MaterialBuf, color::Color, PhysicsType, TEST,
+        };
+        use fs_common::game::common::world::{Chunk, CHUNK_AREA};
+
+        let solid = TEST.instance(PhysicsType::Solid, Color::BLUE);
+        let mut ch: ChunkHandler<ServerChunk> = ChunkHandler::new(TestGenerator::new(), None);
+        let mut chunk: ServerChunk = Chunk::new_empty(0, 0);
+        chunk.data.pixels = Some(vec![solid.clone(); CHUNK_AREA].try_into().unwrap());
+        ch.manager.insert((0, 0), chunk);
+
+        // a plus shape with air in the corners
+        let red = TEST.instance(PhysicsType::Sand, Color::RED);
+        let mut buf = MaterialBuf::of_air(3, 3);
+        for (x, y) in [(1, 0), (0, 1), (1, 1), (2, 1), (1, 2)] {
+            buf.set(x, y, red.clone());
+        }
+
+        buf.paste_masked(&mut ch, 10, 10, MaterialBuf::is_masked)
+            .unwrap();
+        assert_eq!(ch.pixel(10, 10), Ok(&solid));
+        assert_eq!(ch.pixel(12, 12), Ok(&solid));
+        assert_eq!(ch.pixel(11, 10), Ok(&red));
+        assert_eq!(ch.pixel(11, 11), Ok(&red));
+
+        // any predicate works, this one only pastes the air
+        buf.paste_masked(&mut ch, 20, 20, |m| m.physics == PhysicsType::Sand)
+            .unwrap();
+        assert_eq!(ch.pixel(20, 20), Ok(&MaterialInstance::air()));
+        assert_eq!(ch.pixel(21, 21), Ok(&solid));
+
+        // paste still overwrites everything
+        buf.paste(&mut ch, 30, 30).unwrap();
+        assert_eq!(ch.pixel(30, 30), Ok(&MaterialInstance::air()));
+    }
+
+    /// The chunks up to `range` away from (0, 0) filled with air, in the order [`ChunkContext`]
+    ///   expects.
+    ///
+    /// [`ChunkContext`]: fs_common::game::common::world::gen::populator::ChunkContext
+    fn air_chunks_around_origin(range: i32) -> Vec<ServerChunk> {
+        use fs_common::game::common::world::{Chunk, CHUNK_AREA};
+
+        let width = range * 2 + 1;
+        (0..width * width)
+            .map(|i| {
+                let mut chunk: ServerChunk = Chunk::new_empty(i % width - range, i / width - range);
+                chunk.data.pixels = Some(
+                    vec![MaterialInstance::air(); CHUNK_AREA]
+                        .try_into()
+                        .unwrap(),
+                );
+                chunk
+            })
+            .collect()
+    }
+
+    /// Runs `f` with a [`ChunkContext`] over [`air_chunks_around_origin`]`(1)` and empty registries.
+    ///
+    /// [`ChunkContext`]: fs_common::game::common::world::gen::populator::ChunkContext
+    fn with_air_context<R>(
+        f: impl FnOnce(
+            &mut fs_common::game::common::world::gen::populator::ChunkContext<1, ServerChunk>,
+            &Registries,
+        ) -> R,
+    ) -> R {
+        use fs_common::game::common::world::gen::populator::ChunkContext;
+
+        let registries = Registries::empty();
+        let mut chunks = air_chunks_around_origin(1);
+        let mut refs: Vec<&mut ServerChunk> = chunks.iter_mut().collect();
+        let mut ctx = ChunkContext::<1, ServerChunk>::new(&mut refs).unwrap();
+        f(&mut ctx, &registries)
+    }
+
+    #[test]
+    fn count_placement() {
+        use fs_common::game::common::world::gen::feature::placement_mods::{
+            count::Count, random_offset::RandomOffset,
+        };
+        use fs_common::game::common::world::gen::feature::PlacementModifier;
+        use rand::SeedableRng;
+
+        with_air_context(|ctx, registries| {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+
+            let counted = Count::fixed(5).process(ctx, (10, 20), 0, &mut rng, registries);
+            assert_eq!(counted, vec![(10, 20); 5]);
+
+            let offset = RandomOffset::new(0..4, 0..4);
+            let scattered: Vec<_> = counted
+                .into_iter()
+                .flat_map(|pos| offset.process(ctx, pos, 0, &mut rng, registries))
+                .collect();
+            assert_eq!(scattered.len(), 5);
+            assert!(scattered
+                .iter()
+                .all(|&(x, y)| (10..14).contains(&x) && (20..24).contains(&y)));
+
+            let ranged = Count::range(2..=3).process(ctx, (0, 0), 0, &mut rng, registries);
+            assert!((2..=3).contains(&ranged.len()));
+        });
+    }
+
+    #[test]
+    fn random_offset_deterministic() {
+        use fs_common::game::common::world::gen::feature::placement_mods::random_offset::RandomOffset;
+        use fs_common::game::common::world::gen::feature::PlacementModifier;
+        use rand::SeedableRng;
+
+        with_air_context(|ctx, registries| {
+            let offset = RandomOffset::new(-8..8, 0..50);
+            let mut run = |seed| {
+                let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+                (0..10)
+                    .flat_map(|_| offset.process(ctx, (20, 30), 0, &mut rng, registries))
+                    .collect::<Vec<_>>()
+            };
+
+            let a = run(5);
+            assert_eq!(a.len(), 10);
+            assert!(a
+                .iter()
+                .all(|&(x, y)| (12..28).contains(&x) && (30..80).contains(&y)));
+
+            // the same seed gives the same offsets, another one doesn't
+            assert_eq!(run(5), a);
+            assert_ne!(run(6), a);
+        });
+    }
+
+    #[test]
+    fn surface_placement() {
+        use fs_common::game::common::world::gen::feature::placement_mods::surface::SurfacePlacement;
+        use fs_common::game::common::world::gen::feature::PlacementModifier;
+        use fs_common::game::common::world::material::{color::Color, PhysicsType, TEST};
+        use rand::SeedableRng;
+
+        with_air_context(|ctx, registries| {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+
+            // ground from y = 60 down in columns 10 and 30, plus an overhang at y = 5..8 in column 30
+            let solid = TEST.instance(PhysicsType::Solid, Color::RED);
+            for y in (60..200).chain(5..8) {
+                ctx.set(30, y, solid.clone()).unwrap();
+            }
+            for y in 60..200 {
+                ctx.set(10, y, solid.clone()).unwrap();
+            }
+
+            let mut surface = |pos| SurfacePlacement.process(ctx, pos, 0, &mut rng, registries);
+
+            assert_eq!(surface((10, 0)), vec![(10, 59)]);
+            assert_eq!(surface((10, -100)), vec![(10, 59)]);
+            assert_eq!(surface((30, 0)), vec![(30, 4)]);
+            // starting inside the overhang finds the ground under it
+            assert_eq!(surface((30, 6)), vec![(30, 59)]);
+            // nothing solid in the column, or solid down to the bottom of the context
+            assert_eq!(surface((20, 0)), vec![]);
+            assert_eq!(surface((10, 100)), vec![]);
+        });
+    }
+
+    #[test]
+    fn noise_threshold() {
+        use fs_common::game::common::world::gen::feature::placement_mods::noise_threshold::NoiseThreshold;
+        use fs_common::game::common::world::gen::feature::PlacementModifier;
+        use rand::SeedableRng;
+
+        with_air_context(|ctx, registries| {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+
+            let mut passing = |threshold, seed| {
+                let modifier = NoiseThreshold { scale: 16.0, threshold };
+                (0..100)
+                    .step_by(4)
+                    .flat_map(|x| (0..100).step_by(4).map(move |y| (x, y)))
+                    .filter(|&pos| {
+                        !modifier
+                            .process(ctx, pos, seed, &mut rng, registries)
+                            .is_empty()
+                    })
+                    .collect::<Vec<_>>()
+            };
+
+            let some = passing(0.2, 3);
+            assert!(!some.is_empty());
+            assert_eq!(passing(0.2, 3), some);
+            assert_ne!(passing(0.2, 4), some);
+
+            // everything that passes a threshold passes a lower one too
+            let more = passing(-0.2, 3);
+            assert!(more.len() > some.len());
+            assert!(some.iter().all(|pos| more.contains(pos)));
+        });
+    }
+
+    #[test]
+    fn placed_feature_counts_successes() {
+        use fs_common::game::common::world::gen::feature::placement_mods::count::Count;
+        use fs_common::game::common::world::gen::feature::{ConfiguredFeature, PlacedFeature};
+        use fs_common::game::common::world::gen::populator::ChunkContext;
+
+        /// Places nothing, but says it did if `.0` is set.
+        #[derive(Debug)]
+        struct Fixed(bool);
+
+        impl ConfiguredFeature<ServerChunk> for Fixed {
+            fn try_place(
+                &self,
+                _chunks: &mut ChunkContext<1, ServerChunk>,
+                _pos: (i32, i32),
+                _world_seed: i32,
+                _rng: &mut dyn rand::RngCore,
+                _registries: &Registries,
+                _ecs: &mut specs::World,
+            ) -> bool {
+                self.0
+            }
+        }
+
+        let mut ecs = world::ecs();
+        with_air_context(|ctx, registries| {
+            let declines = PlacedFeature::new("declines", Fixed(false)).placement(Count::fixed(4));
+            assert_eq!(declines.generate(ctx, 0, registries, &mut ecs), 0);
+
+            let accepts = PlacedFeature::new("accepts", Fixed(true)).placement(Count::fixed(4));
+            assert_eq!(accepts.generate(ctx, 0, registries, &mut ecs), 4);
+        });
+    }
+
+    #[test]
+    fn weighted_feature() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        use fs_common::game::common::world::gen::feature::features::weighted::WeightedFeature;
+        use fs_common::game::common::world::gen::feature::ConfiguredFeature;
+        use fs_common::game::common::world::gen::populator::ChunkContext;
+        use rand::SeedableRng;
+
+        /// Counts how many times it was picked.
+        #[derive(Debug)]
+        struct Counter(Arc<AtomicUsize>);
+
+        impl ConfiguredFeature<ServerChunk> for Counter {
+            fn try_place(
+                &self,
+                _chunks: &mut ChunkContext<1, ServerChunk>,
+                _pos: (i32, i32),
+                _world_seed: i32,
+                _rng: &mut dyn rand::RngCore,
+                _registries: &Registries,
+                _ecs: &mut specs::World,
+            ) -> bool {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                true
+            }
+        }
+
+        let mut ecs = world::ecs();
+        with_air_context(|ctx, registries| {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+
+            let (a, b) = (Arc::default(), Arc::default());
+            let weighted = WeightedFeature::new()
+                .with(1, Counter(Arc::clone(&a)))
+                .with(0, Counter(Arc::default()))
+                .with(3, Counter(Arc::clone(&b)));
+            for _ in 0..4000 {
+                assert!(weighted.try_place(ctx, (0, 0), 0, &mut rng, registries, &mut ecs));
+            }
+
+            let (a, b) = (a.load(Ordering::Relaxed), b.load(Ordering::Relaxed));
+            assert_eq!(a + b, 4000);
+            assert!((800..1200).contains(&a), "{a} vs {b}");
+
+            // nothing to pick from
+            let empty = WeightedFeature::new().with(0, Counter(Arc::default()));
+            assert!(!empty.try_place(ctx, (0, 0), 0, &mut rng, registries, &mut ecs));
+            assert!(!WeightedFeature::new().try_place(
+                ctx,
+                (0, 0),
+                0,
+                &mut rng,
+                registries,
+                &mut ecs
+            ));
+        });
+    }
+
+    #[test]
+    fn decorated_feature() {
+        use std::sync::{Arc, Mutex};
+
+        use fs_common::game::common::world::gen::feature::features::decorated::DecoratedFeature;
+        use fs_common::game::common::world::gen::feature::{
+            ConfiguredFeature, PlacedFeature, PlacementModifier,
+        };
+        use fs_common::game::common::world::gen::populator::ChunkContext;
+        use rand::SeedableRng;
+
+        type Log = Arc<Mutex<Vec<(&'static str, (i32, i32))>>>;
+
+        /// Logs where it was placed, and says it was placed if `placed` is set.
+        #[derive(Debug)]
+        struct Recorder {
+            name: &'static str,
+            placed: bool,
+            log: Log,
+        }
+
+        impl ConfiguredFeature<ServerChunk> for Recorder {
+            fn try_place(
+                &self,
+                _chunks: &mut ChunkContext<1, ServerChunk>,
+                pos: (i32, i32),
+                _world_seed: i32,
+                _rng: &mut dyn rand::RngCore,
+                _registries: &Registries,
+                _ecs: &mut specs::World,
+            ) -> bool {
+                self.log.lock().unwrap().push((self.name, pos));
+                self.placed
+            }
+        }
+
+        /// Logs the incoming position and moves it by `(10, 20)`.
+        #[derive(Debug)]
+        struct Shift(Log);
+
+        impl PlacementModifier<ServerChunk> for Shift {
+            fn process(
+                &self,
+                _chunks: &mut ChunkContext<1, ServerChunk>,
+                pos: (i32, i32),
+                _seed: i32,
+                _rng: &mut dyn rand::RngCore,
+                _registries: &Registries,
+            ) -> Vec<(i32, i32)> {
+                self.0.lock().unwrap().push(("shift", pos));
+                vec![(pos.0 + 10, pos.1 + 20)]
+            }
+        }
+
+        let log = Log::default();
+        let recorder = |name, placed| Recorder { name, placed, log: Arc::clone(&log) };
+        let take = || std::mem::take(&mut *log.lock().unwrap());
+
+        let mut ecs = world::ecs();
+        with_air_context(|ctx, registries| {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+
+            // the base first, then each decorator in order, all at the same position
+            let declined = DecoratedFeature::new(recorder("base", false))
+                .decorator(recorder("moss", true))
+                .decorator(recorder("vines", true));
+            assert!(!declined.try_place(ctx, (5, 6), 0, &mut rng, registries, &mut ecs));
+            assert_eq!(
+                take(),
+                [("base", (5, 6)), ("moss", (5, 6)), ("vines", (5, 6))]
+            );
+
+            // only the base decides whether it counts as placed
+            let placed =
+                DecoratedFeature::new(recorder("base", true)).decorator(recorder("moss", false));
+            assert!(placed.try_place(ctx, (5, 6), 0, &mut rng, registries, &mut ecs));
+            assert_eq!(take(), [("base", (5, 6)), ("moss", (5, 6))]);
+
+            // as a placed feature, the placement modifiers run first and the whole thing goes where they say
+            let decorated =
+                DecoratedFeature::new(recorder("base", true)).decorator(recorder("moss", true));
+            let placed =
+                PlacedFeature::new("decorated", decorated).placement(Shift(Arc::clone(&log)));
+            assert_eq!(placed.generate(ctx, 0, registries, &mut ecs), 1);
+            assert_eq!(
+                take(),
+                [("shift", (0, 0)), ("base", (10, 20)), ("moss", (10, 20))]
+            );
+        });
+    }
+
+    #[test]
+    fn template_feature() {
+        use fs_common::game::common::world::gen::feature::features::template::TemplateFeature;
+        use fs_common::game::common::world::gen::feature::ConfiguredFeature;
+        use fs_common::game::common::world::material::{
+            buf::MaterialBuf, color::Color, MaterialInstance, PhysicsType, STRUCTURE_VOID, TEST,
+        };
+        use fs_common::game::common::world::CHUNK_SIZE;
+        use rand::SeedableRng;
+
+        let brick = TEST.instance(PhysicsType::Solid, Color::RED);
+        let ground = TEST.instance(PhysicsType::Solid, Color::GREEN);
+        let void = STRUCTURE_VOID.instance(PhysicsType::Air, Color::BLACK);
+        let air = MaterialInstance::air();
+
+        // brick, air, brick
+        // void, brick, brick
+        let template = MaterialBuf::new(
+            3,
+            2,
+            vec![
+                brick.clone(),
+                air.clone(),
+                brick.clone(),
+                void,
+                brick.clone(),
+                brick.clone(),
+            ],
+        )
+        .unwrap();
+
+        let mut ecs = world::ecs();
+        with_air_context(|ctx, registries| {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+
+            for x in -2..3 {
+                for y in 10..12 {
+                    ctx.set(x, y, ground.clone()).unwrap();
+                }
+            }
+
+            // centered on (0, 11), so it spans (-1, 10) to (1, 11) across the chunk border
+            let centered = TemplateFeature::new(template.clone()).centered();
+            assert!(centered.try_place(ctx, (0, 11), 0, &mut rng, registries, &mut ecs));
+
+            // air and structure void in the template keep the ground under them
+            let expected = [
+                [&ground, &brick, &ground, &brick, &ground],
+                [&ground, &ground, &brick, &brick, &ground],
+            ];
+            for (y, row) in (10..).zip(expected) {
+                for (x, m) in (-2..).zip(row) {
+                    assert_eq!(ctx.get(x, y).unwrap(), m, "at ({x}, {y})");
+                }
+            }
+
+            // corner aligned on the last column of the context, the rest is cut off
+            let edge = 2 * i32::from(CHUNK_SIZE) - 1;
+            let corner = TemplateFeature::new(template);
+            assert!(corner.try_place(ctx, (edge, 0), 0, &mut rng, registries, &mut ecs));
+            assert_eq!(ctx.get(edge, 0).unwrap(), &brick);
+            assert_eq!(ctx.get(edge, 1).unwrap(), &air);
+        });
+    }
+
+    #[test]
+    fn chunk_context_crosses_chunks() {
+        use fs_common::game::common::world::{
+            gen::populator::{vein::VeinPopulator, ChunkContext, Populator},
+            material::{self, color::Color, PhysicsType},
+            Chunk, CHUNK_SIZE,
+        };
+
+        let cs = i32::from(CHUNK_SIZE);
+        let stone = material::TEST.instance(PhysicsType::Solid, Color::rgb(80, 80, 80));
+
+        // a bar from the chunk to the left through the center chunk into the one to the right
+        let mut chunks = air_chunks_around_origin(1);
+        let mut refs: Vec<&mut ServerChunk> = chunks.iter_mut().collect();
+        let mut ctx = ChunkContext::<1, ServerChunk>::new(&mut refs).unwrap();
+        for x in -10..cs + 10 {
+            ctx.set(x, -1, stone.clone()).unwrap();
+        }
+        assert_eq!(ctx.get(-1, -1), Ok(&stone));
+        assert_eq!(ctx.get(cs, -1), Ok(&stone));
+        assert_eq!(ctx.get(-11, -1), Ok(&MaterialInstance::air()));
+        drop(refs);
+
+        // (-1, -1) is the bottom right corner of the chunk up and to the left
+        let last = usize::from(CHUNK_SIZE) - 1;
+        let pixel = |chunk: &ServerChunk, x: usize, y: usize| {
+            chunk.pixels().as_ref().unwrap()[x + y * usize::from(CHUNK_SIZE)].clone()
+        };
+        assert_eq!(pixel(&chunks[0], last, last), stone);
+        assert_eq!(pixel(&chunks[0], last - 10, last), MaterialInstance::air());
+        assert_eq!(pixel(&chunks[1], 0, last), stone);
+        assert_eq!(pixel(&chunks[2], 9, last), stone);
+        assert_eq!(pixel(&chunks[2], 10, last), MaterialInstance::air());
+        for chunk in &chunks[3..] {
+            assert!(chunk
+                .pixels()
+                .as_ref()
+                .unwrap()
+                .iter()
+                .all(|m| m.material_id == *material::AIR));
+        }
+
+        // a 7x7 context, with the vein starting in the center chunk and wandering into others
+        let mut chunks = air_chunks_around_origin(3);
+        for chunk in &mut chunks {
+            chunk.data.pixels.as_mut().unwrap().fill(stone.clone());
+        }
+        let mut refs: Vec<&mut ServerChunk> = chunks.iter_mut().collect();
+        let mut ctx = ChunkContext::<3, ServerChunk>::new(&mut refs).unwrap();
+        let vein = material::TEST.instance(PhysicsType::Solid, Color::ROSE);
+        VeinPopulator {
+            material: vein.clone(),
+            length: CHUNK_SIZE * 2,
+            radius: 2,
+        }
+        .populate(&mut ctx, 5, &Registries::empty());
+        drop(refs);
+
+        let veined = chunks
+            .iter()
+            .filter(|c| c.pixels().as_ref().unwrap().iter().any(|m| *m == vein))
+            .map(|c| (c.chunk_x(), c.chunk_y()))
+            .collect::<Vec<_>>();
+        assert!(veined.contains(&(0, 0)), "{veined:?}");
+        assert!(veined.len() > 1, "{veined:?}");
+    }
+
+    #[test]
+    fn cave_populator() {
+        use fs_common::game::common::world::{
+            gen::populator::{cave::CavePopulator, ChunkContext, Populator},
+            material::{self, color::Color, PhysicsType},
+            CHUNK_SIZE,
+        };
+
+        let stone = material::TEST.instance(PhysicsType::Solid, Color::rgb(80, 80, 80));
+        let caves = CavePopulator { frequency: 0.02, ..CavePopulator::default() };
+
+        let size = usize::from(CHUNK_SIZE);
+        let mut air = 0;
+        let mut largest_cave = 0;
+        for mut chunk in air_chunks_around_origin(1) {
+            chunk.data.pixels.as_mut().unwrap().fill(stone.clone());
+            let mut refs = [&mut chunk];
+            let mut ctx = ChunkContext::<0, ServerChunk>::new(&mut refs).unwrap();
+            caves.populate(&mut ctx, 3, &Registries::empty());
+
+            let pixels = chunk.data.pixels.as_ref().unwrap();
+            let mut seen = vec![false; pixels.len()];
+            for start in 0..pixels.len() {
+                if seen[start] || pixels[start] != MaterialInstance::air() {
+                    continue;
+                }
+
+                // flood fill the cave
+                let mut cave = 0;
+                let mut stack = vec![start];
+                seen[start] = true;
+                while let Some(i) = stack.pop() {
+                    cave += 1;
+                    let (x, y) = (i % size, i / size);
+                    let neighbors = [
+                        (x > 0).then(|| i - 1),
+                        (x + 1 < size).then(|| i + 1),
+                        (y > 0).then(|| i - size),
+                        (y + 1 < size).then(|| i + size),
+                    ];
+                    for n in neighbors.into_iter().flatten() {
+                        if !seen[n] && pixels[n] == MaterialInstance::air() {
+                            seen[n] = true;
+                            stack.push(n);
+                        }
+                    }
+                }
+
+                air += cave;
+                largest_cave = largest_cave.max(cave);
+            }
+        }
+
+        let fraction = air as f64 / (9 * size * size) as f64;
+        assert!((0.05..0.6).contains(&fraction), "{fraction}");
+        assert!(largest_cave > 200, "{largest_cave}");
+    }
 }
@@ -0,0 +1,12 @@
+#version 140
+
+in vec2 tex_c;
+in vec2 world_pos;
+out vec4 color;
+
+uniform sampler2D tex_bg;
+
+void main() {
+    vec4 bg_color = texture(tex_bg, tex_c);
+    color = vec4(bg_color.rgb * vec3(0.67), bg_color.a);
+}
\ No newline at end of file
@@ -0,0 +1,11 @@
+#version 140
+
+in vec2 tex_c;
+out vec4 color;
+
+uniform sampler2D tex;
+uniform vec4 col;
+
+void main() {
+    color = texture(tex, tex_c) * col;
+}
\ No newline at end of file